@@ -1,5 +1,5 @@
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
-use microdrop::audio::AudioProcessor;
+use microdrop::audio::{AudioProcessor, ProcessorPool};
 use std::time::Duration;
 
 // Generate synthetic audio data for benchmarking
@@ -197,6 +197,31 @@ fn bench_memory_allocation_patterns(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_processor_pool(c: &mut Criterion) {
+    let mut group = c.benchmark_group("processor_pool");
+
+    // A short request, where building the resampler dominates
+    let samples = generate_mixed_content(48000, 2000, 2);
+    group.throughput(Throughput::Elements(samples.len() as u64));
+
+    group.bench_function("fresh_48kHz_stereo_2s", |b| {
+        b.iter(|| {
+            let mut processor = AudioProcessor::new(48000, 2).unwrap();
+            let _ = black_box(processor.process(black_box(&samples)));
+        });
+    });
+
+    let pool = ProcessorPool::default();
+    group.bench_function("pooled_48kHz_stereo_2s", |b| {
+        b.iter(|| {
+            let mut processor = pool.checkout(48000, 2).unwrap();
+            let _ = black_box(processor.process(black_box(&samples)));
+        });
+    });
+
+    group.finish();
+}
+
 fn bench_extreme_edge_cases(c: &mut Criterion) {
     let mut group = c.benchmark_group("edge_cases");
 
@@ -238,6 +263,7 @@ criterion_group!(
     bench_resampling_operations,
     bench_full_audio_processing_pipeline,
     bench_memory_allocation_patterns,
+    bench_processor_pool,
     bench_extreme_edge_cases
 );
 criterion_main!(benches);
\ No newline at end of file
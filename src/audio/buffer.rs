@@ -0,0 +1,392 @@
+//! Sample buffer shared between the capture callback and the audio engine.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use super::{rms, SpillWriter};
+
+/// What the capture buffer does once a recording reaches its capacity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackpressurePolicy {
+    /// Discard the oldest samples, keeping the end of the recording. The whole
+    /// capacity is reserved up front as a ring, so the capture callback never
+    /// allocates; spilling hands the ring to the writer and starts a new one.
+    DropOldest,
+    /// Discard incoming samples, keeping the start of the recording
+    DropNewest,
+    /// Allocate as audio arrives, so short recordings stay small, then drop
+    /// the oldest samples once the capacity is reached
+    #[default]
+    Grow,
+}
+
+/// Samples collected by the capture callback.
+///
+/// Until recording officially starts only the most recent `preroll_capacity`
+/// samples are kept, so speech that begins right as recording is triggered
+/// isn't clipped. The pre-roll is prepended to the recording when it is taken.
+///
+/// The recording itself is unbounded unless [`set_limit`](Self::set_limit)
+/// caps it, in which case a [`BackpressurePolicy`] decides what is dropped.
+///
+/// With a [voice trigger](Self::set_voice_trigger), starting only arms the
+/// buffer: it keeps filling the pre-roll until a chunk is loud enough, so the
+/// onset of speech is kept.
+///
+/// With [spilling](Self::set_spill) on, each time the recording reaches the
+/// threshold everything kept so far is handed to a [`SpillWriter`], and
+/// [`take`](Self::take) only returns what came after. The hand-off swaps the
+/// buffers out rather than copying them, so the capture callback stays quick.
+#[derive(Debug, Default)]
+pub struct CaptureBuffer {
+    preroll: VecDeque<f32>,
+    preroll_capacity: usize,
+    recorded: VecDeque<f32>,
+    recording: bool,
+    capacity: Option<usize>,
+    policy: BackpressurePolicy,
+    dropped: usize,
+    trigger: Option<f32>,
+    armed: bool,
+    /// Recorded samples that trigger a spill, and the writer spilled to
+    spill: Option<(usize, SpillWriter)>,
+}
+
+impl CaptureBuffer {
+    /// Create a buffer retaining up to `preroll_capacity` interleaved samples before recording
+    pub fn new(preroll_capacity: usize) -> Self {
+        Self {
+            preroll: VecDeque::with_capacity(preroll_capacity),
+            preroll_capacity,
+            recorded: VecDeque::new(),
+            recording: false,
+            capacity: None,
+            policy: BackpressurePolicy::default(),
+            dropped: 0,
+            trigger: None,
+            armed: false,
+            spill: None,
+        }
+    }
+
+    /// Once started, wait to record until a chunk's RMS level reaches `threshold`
+    pub fn set_voice_trigger(&mut self, threshold: Option<f32>) {
+        self.trigger = threshold;
+    }
+
+    /// Cap the recording at `capacity` interleaved samples, which should be a
+    /// whole number of frames so dropping never splits one
+    pub fn set_limit(&mut self, capacity: usize, policy: BackpressurePolicy) {
+        self.capacity = Some(capacity);
+        self.policy = policy;
+        if policy == BackpressurePolicy::DropOldest {
+            self.recorded
+                .reserve_exact(capacity.saturating_sub(self.recorded.len()));
+        }
+    }
+
+    /// Move the recording to `writer` each time `after` interleaved samples
+    /// have been recorded, which should be fewer than the limit. The writer is
+    /// created beforehand so the capture callback never touches the disk.
+    pub fn set_spill(&mut self, after: usize, writer: SpillWriter) {
+        self.spill = Some((after.max(1), writer));
+    }
+
+    /// Append samples delivered by the capture callback
+    pub fn push(&mut self, data: &[f32]) {
+        if self.armed && self.trigger.is_some_and(|threshold| rms(data) >= threshold) {
+            self.armed = false;
+            self.recording = true;
+        }
+        if self.recording {
+            self.record(data);
+            self.spill_if_full();
+        } else if self.preroll_capacity > 0 {
+            self.preroll.extend(data);
+            let excess = self.preroll.len().saturating_sub(self.preroll_capacity);
+            self.preroll.drain(..excess);
+        }
+    }
+
+    fn record(&mut self, data: &[f32]) {
+        let Some(capacity) = self.capacity else {
+            self.recorded.extend(data);
+            return;
+        };
+
+        match self.policy {
+            BackpressurePolicy::DropNewest => {
+                let room = capacity.saturating_sub(self.recorded.len());
+                let kept = data.len().min(room);
+                self.recorded.extend(&data[..kept]);
+                self.dropped += data.len() - kept;
+            }
+            BackpressurePolicy::DropOldest | BackpressurePolicy::Grow => {
+                // Make room before appending, so a full ring is never outgrown
+                let skipped = data.len().saturating_sub(capacity);
+                let data = &data[skipped..];
+                let excess = (self.recorded.len() + data.len()).saturating_sub(capacity);
+                self.recorded.drain(..excess);
+                self.recorded.extend(data);
+                self.dropped += skipped + excess;
+                // The pre-roll led into samples that are gone now
+                if skipped + excess > 0 {
+                    self.preroll.clear();
+                }
+            }
+        }
+    }
+
+    /// Hand everything kept so far to the spill file once the recording reaches the threshold
+    fn spill_if_full(&mut self) {
+        let Some((after, ref mut writer)) = self.spill else {
+            return;
+        };
+        if self.recorded.len() < after {
+            return;
+        }
+        if !self.preroll.is_empty() {
+            writer.send(std::mem::take(&mut self.preroll));
+        }
+        writer.send(std::mem::take(&mut self.recorded));
+    }
+
+    /// The writer holding the start of the last recording, if it was spilled.
+    /// Call after [`take`](Self::take), which returns the rest. A writer that
+    /// was never spilled to is dropped, removing its file.
+    pub fn take_spill(&mut self) -> Option<SpillWriter> {
+        self.spill
+            .take()
+            .map(|(_, writer)| writer)
+            .filter(|writer| writer.sent() > 0)
+    }
+
+    /// Samples discarded because the recording was full, since the last [`take`](Self::take)
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Begin keeping every sample instead of only the pre-roll window, or with
+    /// a voice trigger, once input is loud enough
+    pub fn start_recording(&mut self) {
+        if self.trigger.is_some() {
+            self.armed = true;
+        } else {
+            self.recording = true;
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// Drain the pre-roll followed by the recorded samples. Nothing is
+    /// returned if a voice trigger never fired.
+    pub fn take(&mut self) -> Vec<f32> {
+        if std::mem::take(&mut self.armed) {
+            self.preroll.clear();
+        }
+        let mut samples: Vec<f32> = self.preroll.drain(..).collect();
+        samples.extend(self.recorded.drain(..));
+        self.recording = false;
+        self.dropped = 0;
+        samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preroll_keeps_most_recent_samples() {
+        let mut buffer = CaptureBuffer::new(4);
+        buffer.push(&[1.0, 2.0, 3.0]);
+        buffer.push(&[4.0, 5.0, 6.0]);
+
+        assert_eq!(buffer.take(), vec![3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn test_preroll_is_prepended_to_recording() {
+        let mut buffer = CaptureBuffer::new(2);
+        buffer.push(&[1.0, 2.0, 3.0]);
+        buffer.start_recording();
+        buffer.push(&[4.0, 5.0]);
+        buffer.push(&[6.0]);
+
+        assert_eq!(buffer.take(), vec![2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert!(!buffer.is_recording());
+    }
+
+    #[test]
+    fn test_disabled_preroll_discards_samples_before_recording() {
+        let mut buffer = CaptureBuffer::new(0);
+        buffer.push(&[1.0, 2.0, 3.0]);
+        buffer.start_recording();
+        buffer.push(&[4.0]);
+
+        assert_eq!(buffer.take(), vec![4.0]);
+    }
+
+    #[test]
+    fn test_voice_trigger_starts_recording_with_onset() {
+        let mut buffer = CaptureBuffer::new(2);
+        buffer.set_voice_trigger(Some(0.5));
+        buffer.start_recording();
+        buffer.push(&[0.1, 0.0, 0.1]);
+        assert!(!buffer.is_recording());
+
+        // The quiet lead-in before the loud chunk is kept as pre-roll
+        buffer.push(&[0.9, -0.9]);
+        assert!(buffer.is_recording());
+        buffer.push(&[0.0]);
+        assert_eq!(buffer.take(), vec![0.0, 0.1, 0.9, -0.9, 0.0]);
+    }
+
+    #[test]
+    fn test_spill_moves_recording_to_disk() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut buffer = CaptureBuffer::new(2);
+        buffer.set_spill(4, SpillWriter::create(dir.path()).unwrap());
+        buffer.push(&[1.0, 2.0]);
+        buffer.start_recording();
+        buffer.push(&[3.0, 4.0]);
+
+        // Reaching the threshold spills the pre-roll and the recording so far
+        buffer.push(&[5.0, 6.0]);
+        buffer.push(&[7.0]);
+        assert_eq!(buffer.take(), vec![7.0]);
+        let spilled = buffer.take_spill().unwrap().finish().unwrap();
+        assert_eq!(
+            spilled.read(0..spilled.len()).unwrap(),
+            vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]
+        );
+    }
+
+    #[test]
+    fn test_unused_spill_is_discarded() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut buffer = CaptureBuffer::new(0);
+        buffer.set_spill(4, SpillWriter::create(dir.path()).unwrap());
+        buffer.start_recording();
+        buffer.push(&[1.0, 2.0]);
+
+        assert_eq!(buffer.take(), vec![1.0, 2.0]);
+        assert!(buffer.take_spill().is_none());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_untriggered_voice_trigger_takes_nothing() {
+        let mut buffer = CaptureBuffer::new(2);
+        buffer.set_voice_trigger(Some(0.5));
+        buffer.start_recording();
+        buffer.push(&[0.1, 0.0, 0.1]);
+
+        assert!(buffer.take().is_empty());
+        // The next start arms the trigger again
+        buffer.start_recording();
+        buffer.push(&[0.6]);
+        assert_eq!(buffer.take(), vec![0.6]);
+    }
+
+    #[test]
+    fn test_take_empties_buffer() {
+        let mut buffer = CaptureBuffer::new(2);
+        buffer.push(&[1.0, 2.0]);
+        buffer.start_recording();
+        buffer.push(&[3.0]);
+        buffer.take();
+
+        assert!(buffer.take().is_empty());
+    }
+
+    fn full_buffer(policy: BackpressurePolicy) -> CaptureBuffer {
+        let mut buffer = CaptureBuffer::new(0);
+        buffer.set_limit(4, policy);
+        buffer.start_recording();
+        buffer.push(&[1.0, 2.0, 3.0]);
+        buffer.push(&[4.0, 5.0, 6.0]);
+        buffer
+    }
+
+    #[test]
+    fn test_drop_oldest_keeps_end_of_recording() {
+        let mut buffer = full_buffer(BackpressurePolicy::DropOldest);
+        assert_eq!(buffer.dropped(), 2);
+        assert_eq!(buffer.take(), vec![3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(buffer.dropped(), 0);
+    }
+
+    #[test]
+    fn test_drop_oldest_records_into_a_reserved_ring() {
+        let mut buffer = CaptureBuffer::new(0);
+        buffer.set_limit(1000, BackpressurePolicy::DropOldest);
+        let reserved = buffer.recorded.capacity();
+        assert!(reserved >= 1000);
+
+        buffer.start_recording();
+        for _ in 0..25 {
+            buffer.push(&[0.5; 128]);
+        }
+        assert_eq!(buffer.recorded.len(), 1000);
+        assert_eq!(buffer.recorded.capacity(), reserved);
+        // A single chunk bigger than the ring keeps only its end
+        buffer.push(&(0..1500).map(|i| i as f32).collect::<Vec<_>>());
+        assert_eq!(buffer.recorded.capacity(), reserved);
+        assert_eq!(buffer.recorded.front(), Some(&500.0));
+    }
+
+    #[test]
+    fn test_dropping_oldest_discards_preroll() {
+        let mut buffer = CaptureBuffer::new(2);
+        buffer.set_limit(4, BackpressurePolicy::DropOldest);
+        buffer.push(&[-1.0, -2.0]);
+        buffer.start_recording();
+        buffer.push(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+
+        assert_eq!(buffer.take(), vec![2.0, 3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn test_drop_newest_keeps_start_of_recording() {
+        let mut buffer = full_buffer(BackpressurePolicy::DropNewest);
+        buffer.push(&[7.0]);
+        assert_eq!(buffer.dropped(), 3);
+        assert_eq!(buffer.take(), vec![1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_grow_allocates_as_audio_arrives_up_to_cap() {
+        let mut buffer = CaptureBuffer::new(0);
+        buffer.set_limit(100_000, BackpressurePolicy::Grow);
+        // Nothing is reserved for a recording that hasn't happened yet
+        assert!(buffer.recorded.capacity() < 100_000);
+
+        buffer.start_recording();
+        buffer.push(&[0.5; 1000]);
+        let small = buffer.recorded.capacity();
+        assert!((1000..100_000).contains(&small), "{}", small);
+
+        for _ in 0..150 {
+            buffer.push(&[0.5; 1000]);
+        }
+        // Grown to the cap, then the oldest samples give way
+        assert_eq!(buffer.recorded.len(), 100_000);
+        assert!(buffer.recorded.capacity() >= 100_000);
+        assert_eq!(buffer.dropped(), 51_000);
+    }
+
+    #[test]
+    fn test_unlimited_buffer_never_drops() {
+        let mut buffer = CaptureBuffer::new(0);
+        buffer.start_recording();
+        buffer.push(&[0.0; 1000]);
+
+        assert_eq!(buffer.dropped(), 0);
+        assert_eq!(buffer.take().len(), 1000);
+    }
+}
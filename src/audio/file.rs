@@ -0,0 +1,659 @@
+//! Audio file input: WAV, compressed formats via symphonia, and headerless PCM;
+//! and WAV output for saved recordings.
+
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use symphonia::core::audio::SampleBuffer;
+use opus_decoder::OpusDecoder;
+use symphonia::core::codecs::{CodecParameters, DecoderOptions, CODEC_TYPE_NULL, CODEC_TYPE_OPUS};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tracing::{debug, instrument, warn};
+
+use crate::{MicrodropError, Result};
+
+/// Decoded interleaved samples together with their native format.
+#[derive(Debug, Clone)]
+pub struct DecodedAudio {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// Explicit format for input files, overriding whatever the header says.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InputFormat {
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+}
+
+impl InputFormat {
+    /// Whether nothing is overridden
+    pub fn is_empty(&self) -> bool {
+        self.sample_rate.is_none() && self.channels.is_none()
+    }
+}
+
+/// Read an audio file into interleaved `f32` samples.
+///
+/// WAV, FLAC, MP3 and Ogg (FLAC or Vorbis) files are decoded using the format
+/// found in the file unless `format` overrides the sample rate or channel count.
+/// Files without a recognised header are read as raw 16-bit little-endian PCM,
+/// which requires an explicit sample rate.
+#[instrument(
+    name = "decode_input",
+    level = "debug",
+    skip_all,
+    fields(path = %path.as_ref().display())
+)]
+pub fn read_audio_file<P: AsRef<Path>>(path: P, format: InputFormat) -> Result<DecodedAudio> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|e| {
+        MicrodropError::Audio(format!("Failed to read input file {}: {}", path.display(), e))
+    })?;
+
+    let mut audio = if bytes.starts_with(b"RIFF") {
+        decode_wav(&bytes)?
+    } else if is_compressed(&bytes, format.is_empty()) {
+        decode_compressed(bytes, path)?
+    } else {
+        let sample_rate = format.sample_rate.ok_or_else(|| {
+            MicrodropError::Audio(format!(
+                "Unrecognized audio format for {}. Use --input-sample-rate and --input-channels to read headerless PCM.",
+                path.display()
+            ))
+        })?;
+        DecodedAudio {
+            samples: decode_pcm_s16le(&bytes),
+            sample_rate,
+            channels: format.channels.unwrap_or(1),
+        }
+    };
+
+    if let Some(sample_rate) = format.sample_rate {
+        audio.sample_rate = sample_rate;
+    }
+    if let Some(channels) = format.channels {
+        audio.channels = channels;
+    }
+
+    if audio.sample_rate == 0 || audio.channels == 0 {
+        return Err(MicrodropError::Audio(
+            "Input sample rate and channel count must be greater than zero".to_string(),
+        ));
+    }
+
+    if audio.samples.len() % audio.channels as usize != 0 {
+        return Err(MicrodropError::Audio(format!(
+            "Input has {} samples, which is not a multiple of the channel count {}",
+            audio.samples.len(),
+            audio.channels
+        )));
+    }
+
+    debug!(
+        "Read {} samples from {} ({}Hz {}ch)",
+        audio.samples.len(),
+        path.display(),
+        audio.sample_rate,
+        audio.channels
+    );
+    Ok(audio)
+}
+
+/// `fmt` chunk format tags
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Encoding and bit depth declared by a WAV file's `fmt` chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WavEncoding {
+    format_tag: u16,
+    bits_per_sample: u16,
+}
+
+impl WavEncoding {
+    /// Find the `fmt` chunk in a RIFF/WAVE file, resolving extensible formats
+    /// to their subformat
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.get(8..12)? != b"WAVE" {
+            return None;
+        }
+        let mut offset = 12;
+        while let Some(header) = bytes.get(offset..offset + 8) {
+            let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+            let body = offset + 8;
+            if &header[..4] == b"fmt " {
+                let fmt = bytes.get(body..body + size.min(bytes.len() - body))?;
+                let u16_at = |i: usize| Some(u16::from_le_bytes([*fmt.get(i)?, *fmt.get(i + 1)?]));
+                let mut format_tag = u16_at(0)?;
+                if format_tag == WAVE_FORMAT_EXTENSIBLE {
+                    // The subformat GUID starts with the plain format tag
+                    format_tag = u16_at(24)?;
+                }
+                return Some(Self {
+                    format_tag,
+                    bits_per_sample: u16_at(14)?,
+                });
+            }
+            // Chunks are padded to an even length
+            offset = body.checked_add(size)?.checked_add(size % 2)?;
+        }
+        None
+    }
+
+    fn name(&self) -> String {
+        match self.format_tag {
+            WAVE_FORMAT_PCM => "PCM".to_string(),
+            WAVE_FORMAT_IEEE_FLOAT => "IEEE float".to_string(),
+            0x0002 => "Microsoft ADPCM".to_string(),
+            0x0006 => "A-law".to_string(),
+            0x0007 => "mu-law".to_string(),
+            0x0011 => "IMA ADPCM".to_string(),
+            0x0055 => "MPEG Layer III".to_string(),
+            tag => format!("format tag {:#06x}", tag),
+        }
+    }
+
+    /// Reject anything other than 8/16/24/32-bit PCM and 32-bit float
+    fn validate(&self) -> Result<()> {
+        let bits = self.bits_per_sample;
+        let supported = match self.format_tag {
+            WAVE_FORMAT_PCM => matches!(bits, 8 | 16 | 24 | 32),
+            WAVE_FORMAT_IEEE_FLOAT => bits == 32,
+            _ => false,
+        };
+        if supported {
+            return Ok(());
+        }
+        Err(MicrodropError::Audio(format!(
+            "Unsupported WAV encoding: {}-bit {}. Only 8, 16, 24 or 32-bit PCM and 32-bit float WAV files can be read; convert the file first (e.g. `ffmpeg -i in.wav -c:a pcm_s16le out.wav`).",
+            bits,
+            self.name()
+        )))
+    }
+}
+
+/// Decode an in-memory WAV file in whatever sample format its header declares.
+///
+/// 8, 16, 24 and 32-bit integer PCM are scaled by their bit depth and 32-bit
+/// float is read as is, so every format yields samples in `[-1.0, 1.0]`.
+/// Compressed subformats such as ADPCM are rejected with an error naming them.
+pub fn decode_wav(bytes: &[u8]) -> Result<DecodedAudio> {
+    if let Some(encoding) = WavEncoding::parse(bytes) {
+        encoding.validate()?;
+    }
+
+    let reader = WavReader::new(Cursor::new(bytes))
+        .map_err(|e| MicrodropError::Audio(format!("Failed to parse WAV header: {}", e)))?;
+    let spec = reader.spec();
+
+    let samples = match spec.sample_format {
+        SampleFormat::Float => reader
+            .into_samples::<f32>()
+            .collect::<std::result::Result<Vec<_>, _>>(),
+        SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<std::result::Result<Vec<_>, _>>()
+        }
+    }
+    .map_err(|e| MicrodropError::Audio(format!("Failed to decode WAV samples: {}", e)))?;
+
+    Ok(DecodedAudio {
+        samples,
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+    })
+}
+
+/// Write interleaved `samples` to `path` as a 32-bit float WAV file.
+///
+/// More than two channels get a `WAVE_FORMAT_EXTENSIBLE` header, so
+/// multi-channel captures open with their channel count intact.
+pub fn write_wav_file<P: AsRef<Path>>(
+    path: P,
+    samples: &[f32],
+    sample_rate: u32,
+    channels: u16,
+) -> Result<()> {
+    let path = path.as_ref();
+    let failed = |e: hound::Error| {
+        MicrodropError::Audio(format!("Failed to write {}: {}", path.display(), e))
+    };
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+
+    let mut writer = WavWriter::create(path, spec).map_err(failed)?;
+    for &sample in samples {
+        writer.write_sample(sample).map_err(failed)?;
+    }
+    writer.finalize().map_err(failed)
+}
+
+/// Whether `bytes` start with a container symphonia can demux. A bare MPEG
+/// frame has no magic string, and headerless PCM can start with the same
+/// bytes, so it is only looked for when `sniff_mpeg` is set.
+fn is_compressed(bytes: &[u8], sniff_mpeg: bool) -> bool {
+    bytes.starts_with(b"fLaC")
+        || bytes.starts_with(b"OggS")
+        || bytes.starts_with(b"ID3")
+        || (sniff_mpeg && is_mpeg_frame_header(bytes))
+}
+
+/// Whether `bytes` start with a valid MPEG audio frame header: the sync word
+/// followed by a known version, layer, bitrate and sample rate
+fn is_mpeg_frame_header(bytes: &[u8]) -> bool {
+    let [0xFF, second, third, ..] = *bytes else {
+        return false;
+    };
+    let sync = second & 0xE0 == 0xE0;
+    let version = (second >> 3) & 0b11;
+    let layer = (second >> 1) & 0b11;
+    let bitrate = third >> 4;
+    let sample_rate = (third >> 2) & 0b11;
+    sync && version != 0b01 && layer != 0b00 && bitrate != 0b1111 && sample_rate != 0b11
+}
+
+fn decode_compressed(bytes: Vec<u8>, path: &Path) -> Result<DecodedAudio> {
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let source = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| {
+            MicrodropError::Audio(format!(
+                "Unsupported audio format for {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+    let mut reader = probed.format;
+
+    let track = reader
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| {
+            MicrodropError::Audio(format!("No audio track found in {}", path.display()))
+        })?;
+    let track_id = track.id;
+    let codec_params = track.codec_params.clone();
+    if codec_params.codec == CODEC_TYPE_OPUS {
+        return decode_opus(reader, track_id, &codec_params, path);
+    }
+
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&codec_params, &DecoderOptions::default())
+        .map_err(|_| {
+            MicrodropError::Audio(format!(
+                "Unsupported audio codec in {}: codec {} can't be decoded",
+                path.display(),
+                codec_params.codec
+            ))
+        })?;
+
+    let mut samples = Vec::new();
+    let mut sample_rate = codec_params.sample_rate.unwrap_or(0);
+    let mut channels = codec_params.channels.map(|c| c.count()).unwrap_or(0);
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(e) => {
+                return Err(MicrodropError::Audio(format!(
+                    "Failed to read {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        match decoder.decode(&packet) {
+            Ok(decoded) => {
+                let spec = *decoded.spec();
+                sample_rate = spec.rate;
+                channels = spec.channels.count();
+
+                let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+                buffer.copy_interleaved_ref(decoded);
+                samples.extend_from_slice(buffer.samples());
+            }
+            // A corrupt packet only loses that packet's audio
+            Err(SymphoniaError::DecodeError(e)) => {
+                warn!("Skipping undecodable packet in {}: {}", path.display(), e)
+            }
+            Err(e) => {
+                return Err(MicrodropError::Audio(format!(
+                    "Failed to decode {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        }
+    }
+
+    Ok(DecodedAudio {
+        samples,
+        sample_rate,
+        channels: channels as u16,
+    })
+}
+
+/// Decode an Ogg Opus track. Symphonia demuxes the pages but has no Opus
+/// decoder, so packets go to `opus-decoder`. Output is always 48 kHz with the
+/// encoder's pre-skip trimmed off the front.
+fn decode_opus(
+    mut reader: Box<dyn FormatReader>,
+    track_id: u32,
+    codec_params: &CodecParameters,
+    path: &Path,
+) -> Result<DecodedAudio> {
+    let channels = codec_params.channels.map(|c| c.count()).unwrap_or(0);
+    let mut decoder = OpusDecoder::new(48_000, channels).map_err(|e| {
+        MicrodropError::Audio(format!(
+            "Unsupported Opus stream in {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    let mut pcm = vec![0.0; OpusDecoder::MAX_FRAME_SIZE_48K * channels];
+    let mut samples = Vec::new();
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(e) => {
+                return Err(MicrodropError::Audio(format!(
+                    "Failed to read {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        match decoder.decode_float(&packet.data, &mut pcm, false) {
+            Ok(frames) => samples.extend_from_slice(&pcm[..frames * channels]),
+            // A corrupt packet only loses that packet's audio
+            Err(e) => warn!("Skipping undecodable packet in {}: {}", path.display(), e),
+        }
+    }
+
+    let pre_skip = codec_params.delay.unwrap_or(0) as usize * channels;
+    samples.drain(..pre_skip.min(samples.len()));
+
+    Ok(DecodedAudio {
+        samples,
+        sample_rate: 48_000,
+        channels: channels as u16,
+    })
+}
+
+fn decode_pcm_s16le(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    fn write_wav(samples: &[i16], sample_rate: u32, channels: u16) -> NamedTempFile {
+        let file = NamedTempFile::new().unwrap();
+        let spec = WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let mut writer = WavWriter::create(file.path(), spec).unwrap();
+        for &sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        file
+    }
+
+    #[test]
+    fn test_read_wav_uses_header_format() {
+        let file = write_wav(&[0, 16384, -16384, 32767], 44100, 2);
+        let audio = read_audio_file(file.path(), InputFormat::default()).unwrap();
+
+        assert_eq!(audio.sample_rate, 44100);
+        assert_eq!(audio.channels, 2);
+        assert_eq!(audio.samples, vec![0.0, 0.5, -0.5, 32767.0 / 32768.0]);
+    }
+
+    #[test]
+    fn test_write_wav_file_round_trips() {
+        let file = NamedTempFile::new().unwrap();
+        let samples = [0.0, 0.25, -0.5, 1.0, -1.0, 0.125, 0.5, -0.25];
+        write_wav_file(file.path(), &samples, 48000, 4).unwrap();
+
+        let audio = read_audio_file(file.path(), InputFormat::default()).unwrap();
+        assert_eq!(audio.sample_rate, 48000);
+        assert_eq!(audio.channels, 4);
+        assert_eq!(audio.samples, samples);
+
+        let err = write_wav_file("/nonexistent/dir/clip.wav", &samples, 16000, 1).unwrap_err();
+        assert!(err.to_string().contains("/nonexistent/dir/clip.wav"));
+    }
+
+    #[test]
+    fn test_override_replaces_wav_header() {
+        let file = write_wav(&[0, 0, 0, 0], 44100, 2);
+        let format = InputFormat {
+            sample_rate: Some(16000),
+            channels: Some(1),
+        };
+        let audio = read_audio_file(file.path(), format).unwrap();
+
+        assert_eq!(audio.sample_rate, 16000);
+        assert_eq!(audio.channels, 1);
+        assert_eq!(audio.samples.len(), 4);
+    }
+
+    #[test]
+    fn test_read_headerless_pcm() {
+        let file = NamedTempFile::new().unwrap();
+        let bytes: Vec<u8> = [0i16, 16384, -16384, -32768]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        fs::write(file.path(), bytes).unwrap();
+
+        let format = InputFormat {
+            sample_rate: Some(8000),
+            channels: Some(2),
+        };
+        let audio = read_audio_file(file.path(), format).unwrap();
+
+        assert_eq!(audio.sample_rate, 8000);
+        assert_eq!(audio.channels, 2);
+        assert_eq!(audio.samples, vec![0.0, 0.5, -0.5, -1.0]);
+    }
+
+    #[test]
+    fn test_headerless_pcm_resembling_mpeg_sync() {
+        let file = NamedTempFile::new().unwrap();
+        // -1 is stored as FF FF, which starts like an MPEG sync word
+        let bytes: Vec<u8> = [-1i16, -20, 0, 16384]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        fs::write(file.path(), &bytes).unwrap();
+        assert!(!is_mpeg_frame_header(&bytes));
+
+        let format = InputFormat {
+            sample_rate: Some(16000),
+            channels: None,
+        };
+        let audio = read_audio_file(file.path(), format).unwrap();
+        assert_eq!(audio.samples.len(), 4);
+        assert_eq!(audio.samples[2..], [0.0, 0.5]);
+
+        // MPEG-1 Layer III, 128 kbps, 44.1 kHz
+        assert!(is_mpeg_frame_header(&[0xFF, 0xFB, 0x90, 0x00]));
+    }
+
+    fn fixture(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/audio")
+            .join(name)
+    }
+
+    #[test]
+    fn test_read_24_bit_wav() {
+        let audio = read_audio_file(fixture("pcm24.wav"), InputFormat::default()).unwrap();
+
+        assert_eq!(audio.sample_rate, 16000);
+        assert_eq!(audio.channels, 1);
+        assert_eq!(
+            audio.samples,
+            vec![0.0, 0.5, -0.5, 8388607.0 / 8388608.0, -1.0]
+        );
+    }
+
+    #[test]
+    fn test_read_float_wav() {
+        let audio = read_audio_file(fixture("float32.wav"), InputFormat::default()).unwrap();
+        assert_eq!(audio.samples, vec![0.0, 0.5, -0.25, 1.0]);
+    }
+
+    #[test]
+    fn test_read_8_and_32_bit_pcm() {
+        for (bits, sample, expected) in [(8, 64, 0.5), (32, -(1 << 30), -0.5)] {
+            let spec = WavSpec {
+                channels: 1,
+                sample_rate: 16000,
+                bits_per_sample: bits,
+                sample_format: SampleFormat::Int,
+            };
+            let mut cursor = Cursor::new(Vec::new());
+            let mut writer = WavWriter::new(&mut cursor, spec).unwrap();
+            writer.write_sample(sample).unwrap();
+            writer.finalize().unwrap();
+
+            let audio = decode_wav(&cursor.into_inner()).unwrap();
+            assert_eq!(audio.samples, vec![expected], "{}-bit PCM", bits);
+        }
+    }
+
+    #[test]
+    fn test_adpcm_wav_is_reported_as_unsupported() {
+        let err = read_audio_file(fixture("adpcm.wav"), InputFormat::default()).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unsupported WAV encoding: 4-bit Microsoft ADPCM"));
+    }
+
+    #[test]
+    fn test_64_bit_float_wav_is_reported_as_unsupported() {
+        let err = read_audio_file(fixture("float64.wav"), InputFormat::default()).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unsupported WAV encoding: 64-bit IEEE float"));
+    }
+
+    /// The 0.1s 440 Hz tone written by tests/fixtures/audio/generate.py
+    fn assert_is_fixture_tone(audio: &DecodedAudio) {
+        assert_eq!(audio.sample_rate, 16000);
+        assert_eq!(audio.channels, 1);
+        assert_eq!(audio.samples.len(), 1600);
+        for (n, &sample) in audio.samples.iter().enumerate() {
+            let phase = 2.0 * std::f32::consts::PI * 440.0 * n as f32 / 16000.0;
+            let expected = (0.5 * 32767.0 * phase.sin()).round() / 32768.0;
+            assert!((sample - expected).abs() < 1e-4, "sample {} differs", n);
+        }
+    }
+
+    #[test]
+    fn test_read_flac() {
+        let audio = read_audio_file(fixture("tone.flac"), InputFormat::default()).unwrap();
+        assert_is_fixture_tone(&audio);
+    }
+
+    #[test]
+    fn test_read_ogg() {
+        let audio = read_audio_file(fixture("tone.ogg"), InputFormat::default()).unwrap();
+        assert_is_fixture_tone(&audio);
+    }
+
+    #[test]
+    fn test_read_mp3() {
+        let audio = read_audio_file(fixture("silence.mp3"), InputFormat::default()).unwrap();
+
+        assert_eq!(audio.sample_rate, 32000);
+        assert_eq!(audio.channels, 1);
+        assert!(!audio.samples.is_empty());
+        assert!(audio.samples.iter().all(|s| s.abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_read_opus_decodes_at_48k() {
+        // One 20 ms packet after a 312-sample pre-skip
+        let audio = read_audio_file(fixture("silence.opus"), InputFormat::default()).unwrap();
+
+        assert_eq!(audio.sample_rate, 48000);
+        assert_eq!(audio.channels, 1);
+        assert_eq!(audio.samples.len(), 960 - 312);
+        assert!(audio.samples.iter().all(|s| s.abs() < 1e-3));
+    }
+
+    #[test]
+    fn test_headerless_pcm_requires_sample_rate() {
+        let file = NamedTempFile::new().unwrap();
+        fs::write(file.path(), [0u8; 16]).unwrap();
+
+        let err = read_audio_file(file.path(), InputFormat::default()).unwrap_err();
+        assert!(err.to_string().contains("--input-sample-rate"));
+    }
+
+    #[test]
+    fn test_sample_count_must_match_channels() {
+        let file = NamedTempFile::new().unwrap();
+        fs::write(file.path(), [0u8; 6]).unwrap(); // 3 samples
+
+        let format = InputFormat {
+            sample_rate: Some(16000),
+            channels: Some(2),
+        };
+        let err = read_audio_file(file.path(), format).unwrap_err();
+        assert!(err.to_string().contains("not a multiple of the channel count 2"));
+    }
+}
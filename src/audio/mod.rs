@@ -1,34 +1,166 @@
 //! Microphone capture and audio preprocessing pipeline.
 
+use std::fmt;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{Device, Host, Stream, StreamConfig};
-use ringbuf::HeapRb;
-use tracing::{debug, error, info};
+use cpal::{BufferSize, Device, Host, Stream, StreamConfig, StreamError, SupportedBufferSize};
+use serde::{Serialize, Serializer};
+use tracing::{debug, error, info, warn};
+use unicode_normalization::UnicodeNormalization;
 
 use crate::{MicrodropError, Result};
 
+pub mod buffer;
+pub mod file;
+pub mod pool;
 pub mod processing;
+pub mod spill;
+pub mod vad;
+pub use buffer::*;
+pub use file::*;
+pub use pool::*;
 pub use processing::*;
-
-const RING_BUFFER_SIZE: usize = 1024 * 1024; // 1MB ring buffer
+pub use spill::*;
+pub use vad::*;
 
 pub struct AudioEngine {
     host: Host,
     device: Option<Device>,
     config: Option<StreamConfig>,
     stream: Option<Stream>,
-    ring_buffer: Option<HeapRb<f32>>,
+    buffer: Arc<Mutex<CaptureBuffer>>,
+    preroll: Duration,
+    buffer_limit: Duration,
+    backpressure: BackpressurePolicy,
+    /// Samples the last capture dropped because the buffer was full
+    dropped_samples: usize,
+    /// First error reported by the running stream, raised by `stop_capture`
+    stream_error: Arc<Mutex<Option<String>>>,
+    /// RMS level that starts the recording, if it waits for speech
+    auto_start: Option<f32>,
+    /// Frames per callback to request from the backend, if not its default
+    buffer_frames: Option<u32>,
+    /// Recording length after which it is moved to disk, if ever
+    spill_after: Option<Duration>,
+    /// Start of the last recording, if it was spilled
+    spilled: Option<SpillFile>,
 }
 
-#[derive(Debug, Clone)]
+/// Longest recording kept in memory unless configured otherwise
+pub const DEFAULT_BUFFER_LIMIT: Duration = Duration::from_secs(30 * 60);
+
+/// RMS level that starts an `--auto-start` recording unless configured otherwise
+pub const DEFAULT_AUTO_START_THRESHOLD: f32 = 0.02;
+
+#[derive(Debug, Clone, Serialize)]
 pub struct AudioStats {
+    #[serde(serialize_with = "serialize_secs")]
     pub duration: Duration,
     pub sample_count: usize,
     pub sample_rate: u32,
     pub channels: u16,
     pub format: String,
+    /// Samples discarded because the capture buffer was full
+    pub dropped_samples: usize,
+    /// Root-mean-square level of the samples (0.0-1.0)
+    pub rms: f32,
+    /// Largest absolute sample value (0.0-1.0)
+    pub peak: f32,
+}
+
+impl AudioStats {
+    /// Stats for interleaved `samples` in the given format
+    pub fn from_samples(samples: &[f32], sample_rate: u32, channels: u16) -> Self {
+        let duration =
+            Duration::from_secs_f64(samples.len() as f64 / (sample_rate as f64 * channels as f64));
+
+        AudioStats {
+            duration,
+            sample_count: samples.len(),
+            sample_rate,
+            channels,
+            format: "f32".to_string(),
+            dropped_samples: 0,
+            rms: rms(samples),
+            peak: samples.iter().fold(0.0, |peak: f32, s| peak.max(s.abs())),
+        }
+    }
+}
+
+impl fmt::Display for AudioStats {
+    /// One line with the duration, format and levels, e.g.
+    /// `1.50s, 48000 Hz, 2 channels, peak -6.0 dBFS, RMS -9.0 dBFS`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.2}s, {} Hz, {} channel{}, peak {:.1} dBFS, RMS {:.1} dBFS",
+            self.duration.as_secs_f64(),
+            self.sample_rate,
+            self.channels,
+            if self.channels == 1 { "" } else { "s" },
+            dbfs(self.peak),
+            dbfs(self.rms)
+        )?;
+        if self.dropped_samples > 0 {
+            write!(f, ", {} samples dropped", self.dropped_samples)?;
+        }
+        Ok(())
+    }
+}
+
+/// Level relative to full scale in decibels; silence is negative infinity
+fn dbfs(level: f32) -> f32 {
+    20.0 * level.log10()
+}
+
+fn serialize_secs<S: Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+/// Source of captured audio for the recording workflow.
+///
+/// Implemented by the cpal-backed [`AudioEngine`] and, for tests, by
+/// [`MockCaptureBackend`] which yields preloaded samples.
+pub trait CaptureBackend {
+    fn select_device(&mut self, device_name: Option<&str>) -> Result<()>;
+    /// Select the input device at `index` in enumeration order (0-based)
+    fn select_device_index(&mut self, index: usize) -> Result<()>;
+    fn configure_stream(&mut self) -> Result<()>;
+    fn start_capture(&mut self) -> Result<()>;
+    fn stop_capture(&mut self) -> Result<Vec<f32>>;
+    /// Describe `samples` using the format negotiated by `configure_stream`.
+    /// Errors if no format has been negotiated, rather than guessing one.
+    fn get_stats(&self, samples: &[f32]) -> Result<AudioStats>;
+    /// Name of the currently selected device, if any
+    fn device_name(&self) -> Option<String>;
+    /// Whether a started capture is keeping audio, rather than still waiting
+    /// for speech to trigger it
+    fn is_recording(&self) -> bool {
+        true
+    }
+    /// The start of the last recording, if it outgrew memory and was spilled
+    /// to disk, in which case `stop_capture` only returned the rest
+    fn take_spilled(&mut self) -> Option<SpillFile> {
+        None
+    }
+
+    /// Record for `duration` and return the captured samples, blocking the
+    /// calling thread. Configures the stream first, selecting the default device
+    /// if none was chosen.
+    fn capture_for(&mut self, duration: Duration) -> Result<Vec<f32>> {
+        if self.device_name().is_none() {
+            self.select_device(None)?;
+        }
+        self.configure_stream()?;
+        self.start_capture()?;
+        std::thread::sleep(duration);
+        self.stop_capture()
+    }
 }
 
 impl Default for AudioEngine {
@@ -45,10 +177,52 @@ impl AudioEngine {
             device: None,
             config: None,
             stream: None,
-            ring_buffer: None,
+            buffer: Arc::new(Mutex::new(CaptureBuffer::default())),
+            preroll: Duration::ZERO,
+            buffer_limit: DEFAULT_BUFFER_LIMIT,
+            backpressure: BackpressurePolicy::default(),
+            dropped_samples: 0,
+            stream_error: Arc::new(Mutex::new(None)),
+            auto_start: None,
+            buffer_frames: None,
+            spill_after: None,
+            spilled: None,
         }
     }
 
+    /// Keep the last `preroll` of audio from before capture officially starts.
+    ///
+    /// When non-zero the stream is opened as soon as it is configured, and the
+    /// retained audio is prepended to the recording on stop.
+    pub fn set_preroll(&mut self, preroll: Duration) {
+        self.preroll = preroll;
+    }
+
+    /// Have `start_capture` wait for the input's RMS level to reach `threshold`
+    /// before recording. Combine with a pre-roll to keep the onset of speech.
+    pub fn set_auto_start(&mut self, threshold: Option<f32>) {
+        self.auto_start = threshold;
+    }
+
+    /// Ask the backend for `frames` per callback instead of its default buffer
+    /// size; smaller buffers lower latency but risk overruns
+    pub fn set_buffer_frames(&mut self, frames: Option<u32>) {
+        self.buffer_frames = frames;
+    }
+
+    /// Move recordings longer than `after` to a temporary file as they grow,
+    /// so only the part since the last spill is held in memory. Should be
+    /// shorter than the buffer limit, which still applies to that part.
+    pub fn set_spill_after(&mut self, after: Option<Duration>) {
+        self.spill_after = after;
+    }
+
+    /// Keep at most `limit` of recorded audio, applying `policy` once it is reached
+    pub fn set_buffer_limit(&mut self, limit: Duration, policy: BackpressurePolicy) {
+        self.buffer_limit = limit;
+        self.backpressure = policy;
+    }
+
     pub fn list_devices(&self) -> Result<Vec<String>> {
         let devices: Result<Vec<String>> = self
             .host
@@ -66,14 +240,15 @@ impl AudioEngine {
     pub fn select_device(&mut self, device_name: Option<&str>) -> Result<()> {
         let device = match device_name {
             Some(name) => {
-                let devices = self.host.input_devices().map_err(|e| {
-                    MicrodropError::Audio(format!("Failed to enumerate devices: {}", e))
-                })?;
-
-                devices
-                    .filter(|d| d.name().map(|n| n == name).unwrap_or(false))
-                    .next()
-                    .ok_or_else(|| MicrodropError::Audio(format!("Audio device '{}' not found. Use 'arecord -l' or system audio settings to see available devices.", name)))?
+                let mut devices = self.input_devices()?;
+
+                let names: Vec<String> = devices
+                    .iter()
+                    .map(|d| d.name().unwrap_or_default())
+                    .collect();
+
+                let index = match_device_name(&names, name)?;
+                devices.swap_remove(index)
             }
             None => self.host.default_input_device().ok_or_else(|| {
                 MicrodropError::Audio("No default input device available. Please check that your microphone is connected and recognized by the system.".to_string())
@@ -89,6 +264,29 @@ impl AudioEngine {
         Ok(())
     }
 
+    /// Select the input device at `index`, in the order [`Self::list_devices`] returns them
+    pub fn select_device_index(&mut self, index: usize) -> Result<()> {
+        let mut devices = self.input_devices()?;
+        check_device_index(index, devices.len())?;
+        let device = devices.swap_remove(index);
+
+        let device_name = device
+            .name()
+            .unwrap_or_else(|_| "Unknown Device".to_string());
+        info!("Selected audio device #{}: {}", index, device_name);
+
+        self.device = Some(device);
+        Ok(())
+    }
+
+    fn input_devices(&self) -> Result<Vec<Device>> {
+        Ok(self
+            .host
+            .input_devices()
+            .map_err(|e| MicrodropError::Audio(format!("Failed to enumerate devices: {}", e)))?
+            .collect())
+    }
+
     pub fn configure_stream(&mut self) -> Result<()> {
         let device = self
             .device
@@ -115,11 +313,110 @@ impl AudioEngine {
         })?;
 
         debug!("Selected audio config: {:?}", config);
-        self.config = Some(config.into());
+        let buffer_size = choose_buffer_size(self.buffer_frames, config.buffer_size());
+        let mut config: StreamConfig = config.into();
+        config.buffer_size = buffer_size;
+        self.config = Some(config);
+
+        if !self.preroll.is_zero() {
+            self.open_stream()?;
+        }
         Ok(())
     }
 
     pub fn start_capture(&mut self) -> Result<()> {
+        if self.stream.is_none() {
+            self.open_stream()?;
+        }
+
+        let spill = self.spill_writer();
+        let mut buffer = self.lock_buffer();
+        if let Some((after, writer)) = spill {
+            buffer.set_spill(after, writer);
+        }
+        buffer.start_recording();
+        drop(buffer);
+        match self.auto_start {
+            Some(threshold) => info!("Audio capture armed, waiting for RMS {}", threshold),
+            None => info!("Audio capture started"),
+        }
+        Ok(())
+    }
+
+    pub fn stop_capture(&mut self) -> Result<Vec<f32>> {
+        if let Some(stream) = self.stream.take() {
+            drop(stream);
+            info!("Audio capture stopped");
+        }
+
+        let (samples, dropped, spill) = {
+            let mut buffer = self.lock_buffer();
+            let dropped = buffer.dropped();
+            (buffer.take(), dropped, buffer.take_spill())
+        };
+        self.dropped_samples = dropped;
+        self.spilled = spill.map(SpillWriter::finish).transpose()?;
+        if let Some(ref spilled) = self.spilled {
+            info!(
+                "Recording outgrew memory; {} samples were spilled to {}",
+                spilled.len(),
+                spilled.path().display()
+            );
+        }
+        if self.dropped_samples > 0 {
+            warn!(
+                "Capture buffer filled up after {:?}: dropped {} samples ({:?} policy)",
+                self.buffer_limit, self.dropped_samples, self.backpressure
+            );
+        }
+
+        let stream_error = self
+            .stream_error
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        if let Some(reason) = stream_error {
+            let spilled = self.spilled.as_ref().map_or(0, SpillFile::len);
+            let captured = self.config.as_ref().map_or(Duration::ZERO, |config| {
+                let frames = (samples.len() + spilled) / config.channels.max(1) as usize;
+                Duration::from_secs_f64(frames as f64 / config.sample_rate.0 as f64)
+            });
+            return Err(MicrodropError::Audio(format!(
+                "Recording failed: {} ({:.1}s of audio was captured before the error)",
+                reason,
+                captured.as_secs_f64()
+            )));
+        }
+
+        debug!("Collected {} samples from capture buffer", samples.len());
+        Ok(samples)
+    }
+
+    /// Record for `duration` from the selected (or default) device; see
+    /// [`CaptureBackend::capture_for`]
+    pub fn capture_for(&mut self, duration: Duration) -> Result<Vec<f32>> {
+        CaptureBackend::capture_for(self, duration)
+    }
+
+    /// Sample rate negotiated by [`configure_stream`](Self::configure_stream)
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.config.as_ref().map(|c| c.sample_rate.0)
+    }
+
+    /// Stats for `samples` in the negotiated capture format, which outlives the stream
+    pub fn get_stats(&self, samples: &[f32]) -> Result<AudioStats> {
+        let config = self.config.as_ref().ok_or_else(|| {
+            MicrodropError::Audio("Capture format unknown: no stream was configured".to_string())
+        })?;
+
+        Ok(AudioStats {
+            dropped_samples: self.dropped_samples,
+            ..AudioStats::from_samples(samples, config.sample_rate.0, config.channels)
+        })
+    }
+
+    /// Open the input stream and start filling the pre-roll
+    fn open_stream(&mut self) -> Result<()> {
         let device = self
             .device
             .as_ref()
@@ -130,66 +427,65 @@ impl AudioEngine {
             .as_ref()
             .ok_or_else(|| MicrodropError::Audio("No configuration set".to_string()))?;
 
-        // For MVP, create a simple ring buffer placeholder
-        let rb = HeapRb::<f32>::new(RING_BUFFER_SIZE);
-        self.ring_buffer = Some(rb);
+        let frames =
+            |duration: Duration| (duration.as_secs_f64() * config.sample_rate.0 as f64) as usize;
+        let mut buffer = CaptureBuffer::new(frames(self.preroll) * config.channels as usize);
+        buffer.set_limit(
+            frames(self.buffer_limit) * config.channels as usize,
+            self.backpressure,
+        );
+        buffer.set_voice_trigger(self.auto_start);
+        *self.lock_buffer() = buffer;
+        *self.stream_error.lock().unwrap_or_else(|e| e.into_inner()) = None;
 
-        // Build stream - simplified approach for MVP
         let stream = self.build_stream(device, config)?;
 
-        // Start the stream
         stream
             .play()
             .map_err(|e| MicrodropError::Audio(format!("Failed to start stream: {}", e)))?;
 
-        info!("Audio capture started");
+        debug!("Audio stream opened with {:?} pre-roll", self.preroll);
         self.stream = Some(stream);
         Ok(())
     }
 
-    pub fn stop_capture(&mut self) -> Result<Vec<f32>> {
-        if let Some(stream) = self.stream.take() {
-            drop(stream);
-            info!("Audio capture stopped");
+    /// Create the file a recording longer than the spill threshold moves to,
+    /// with the threshold in interleaved samples. Done before capture starts
+    /// so the capture callback never touches the file system.
+    fn spill_writer(&self) -> Option<(usize, SpillWriter)> {
+        let after = self.spill_after?;
+        let config = self.config.as_ref()?;
+        let samples =
+            (after.as_secs_f64() * config.sample_rate.0 as f64) as usize * config.channels as usize;
+        match SpillWriter::create(&std::env::temp_dir()) {
+            Ok(writer) => Some((samples, writer)),
+            Err(e) => {
+                warn!("{}; keeping the recording in memory", e);
+                None
+            }
         }
-
-        // For MVP, return empty vec for now - we'll implement proper ring buffer draining later
-        let samples = Vec::new();
-        self.ring_buffer = None;
-
-        debug!("Collected {} samples from ring buffer", samples.len());
-        Ok(samples)
     }
 
-    pub fn get_stats(&self, samples: &[f32]) -> AudioStats {
-        let config = self.config.as_ref();
-        let sample_rate = config.map(|c| c.sample_rate.0).unwrap_or(44100);
-        let channels = config.map(|c| c.channels).unwrap_or(1);
-
-        let duration =
-            Duration::from_secs_f64(samples.len() as f64 / (sample_rate as f64 * channels as f64));
-
-        AudioStats {
-            duration,
-            sample_count: samples.len(),
-            sample_rate,
-            channels,
-            format: "f32".to_string(),
-        }
+    fn lock_buffer(&self) -> std::sync::MutexGuard<'_, CaptureBuffer> {
+        // A panic while holding the lock can't leave the buffer inconsistent
+        self.buffer.lock().unwrap_or_else(|e| e.into_inner())
     }
 
     fn build_stream(&self, device: &Device, config: &StreamConfig) -> Result<Stream> {
-        let err_callback = move |err| {
+        let stream_error = Arc::clone(&self.stream_error);
+        let err_callback = move |err: StreamError| {
             error!("Audio stream error: {}", err);
+            let mut stream_error = stream_error.lock().unwrap_or_else(|e| e.into_inner());
+            // Later errors are usually fallout from the first
+            stream_error.get_or_insert_with(|| describe_stream_error(&err));
         };
 
-        // For MVP, create a simple f32 stream without ring buffer integration
+        let buffer = Arc::clone(&self.buffer);
         let stream = device
             .build_input_stream(
                 config,
                 move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                    // For MVP, just count samples - no actual storage
-                    debug!("Received {} audio samples", data.len());
+                    buffer.lock().unwrap_or_else(|e| e.into_inner()).push(data);
                 },
                 err_callback,
                 None,
@@ -199,3 +495,523 @@ impl AudioEngine {
         Ok(stream)
     }
 }
+
+impl CaptureBackend for AudioEngine {
+    fn select_device(&mut self, device_name: Option<&str>) -> Result<()> {
+        AudioEngine::select_device(self, device_name)
+    }
+
+    fn select_device_index(&mut self, index: usize) -> Result<()> {
+        AudioEngine::select_device_index(self, index)
+    }
+
+    fn configure_stream(&mut self) -> Result<()> {
+        AudioEngine::configure_stream(self)
+    }
+
+    fn start_capture(&mut self) -> Result<()> {
+        AudioEngine::start_capture(self)
+    }
+
+    fn stop_capture(&mut self) -> Result<Vec<f32>> {
+        AudioEngine::stop_capture(self)
+    }
+
+    fn get_stats(&self, samples: &[f32]) -> Result<AudioStats> {
+        AudioEngine::get_stats(self, samples)
+    }
+
+    fn device_name(&self) -> Option<String> {
+        self.device.as_ref().and_then(|d| d.name().ok())
+    }
+
+    fn is_recording(&self) -> bool {
+        self.lock_buffer().is_recording()
+    }
+
+    fn take_spilled(&mut self) -> Option<SpillFile> {
+        self.spilled.take()
+    }
+}
+
+/// User-facing reason for a stream failure
+fn describe_stream_error(err: &StreamError) -> String {
+    match err {
+        StreamError::DeviceNotAvailable => {
+            "the input device was disconnected or became unavailable".to_string()
+        }
+        StreamError::BackendSpecific { err } => {
+            format!("the audio backend reported an error: {}", err.description)
+        }
+    }
+}
+
+/// Root-mean-square level of `samples`, 0.0 for none
+pub fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum: f32 = samples.iter().map(|s| s * s).sum();
+    (sum / samples.len() as f32).sqrt()
+}
+
+/// Find the device matching `query`, preferring an exact name match and falling
+/// back to a case-insensitive substring match. Ambiguous substrings are an error.
+fn match_device_name(names: &[String], query: &str) -> Result<usize> {
+    // Compare in NFC so composed and decomposed accents match
+    if let Some(index) = names.iter().position(|n| n.nfc().eq(query.nfc())) {
+        return Ok(index);
+    }
+
+    let needle = query.nfc().collect::<String>().to_lowercase();
+    let candidates: Vec<usize> = names
+        .iter()
+        .enumerate()
+        .filter(|(_, n)| n.nfc().collect::<String>().to_lowercase().contains(&needle))
+        .map(|(i, _)| i)
+        .collect();
+
+    match candidates.as_slice() {
+        [index] => {
+            debug!("Device '{}' matched by substring: {}", query, names[*index]);
+            Ok(*index)
+        }
+        [] => Err(MicrodropError::DeviceNotFound(query.to_string())),
+        _ => {
+            let matches: Vec<&str> = candidates.iter().map(|&i| names[i].as_str()).collect();
+            Err(MicrodropError::Audio(format!(
+                "Audio device '{}' is ambiguous and matches {} devices: {}. Please use a more specific name.",
+                query,
+                matches.len(),
+                matches.join(", ")
+            )))
+        }
+    }
+}
+
+/// `BufferSize::Fixed(requested)` if the device accepts that many frames,
+/// otherwise the backend default with a warning
+fn choose_buffer_size(requested: Option<u32>, supported: &SupportedBufferSize) -> BufferSize {
+    let Some(frames) = requested else {
+        return BufferSize::Default;
+    };
+    match *supported {
+        SupportedBufferSize::Range { min, max } if (min..=max).contains(&frames) => {
+            BufferSize::Fixed(frames)
+        }
+        SupportedBufferSize::Range { min, max } => {
+            warn!(
+                "Buffer size of {} frames is outside the device's range of {}-{}; using the default",
+                frames, min, max
+            );
+            BufferSize::Default
+        }
+        SupportedBufferSize::Unknown => {
+            warn!(
+                "Device doesn't report which buffer sizes it supports; ignoring the requested {} frames",
+                frames
+            );
+            BufferSize::Default
+        }
+    }
+}
+
+/// Error unless `index` addresses one of `count` devices
+fn check_device_index(index: usize, count: usize) -> Result<()> {
+    if index < count {
+        Ok(())
+    } else {
+        Err(MicrodropError::Audio(format!(
+            "Audio device index {} is out of range: {} input device(s) available",
+            index, count
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device_names() -> Vec<String> {
+        vec![
+            "default".to_string(),
+            "alsa_input.usb-Blue_Microphones_Yeti_Stereo_Microphone-00.analog-stereo".to_string(),
+            "alsa_input.pci-0000_00_1f.3.analog-stereo".to_string(),
+            "HDA Intel PCH: ALC3246 Analog (hw:0,0)".to_string(),
+        ]
+    }
+
+    #[test]
+    fn test_match_device_exact() {
+        let names = device_names();
+        assert_eq!(match_device_name(&names, "default").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_match_device_exact_preferred_over_substring() {
+        let names = vec!["mic".to_string(), "usb mic".to_string()];
+        assert_eq!(match_device_name(&names, "mic").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_match_device_substring_case_insensitive() {
+        let names = device_names();
+        assert_eq!(match_device_name(&names, "yeti").unwrap(), 1);
+        assert_eq!(match_device_name(&names, "HDA INTEL").unwrap(), 3);
+    }
+
+    #[test]
+    fn test_match_device_unicode_normalized() {
+        // "Micrófono" with a precomposed ó, and with o + combining acute accent
+        let composed = "Micr\u{f3}fono USB";
+        let decomposed = "Micro\u{301}fono USB";
+        let names = vec!["default".to_string(), composed.to_string()];
+        assert_eq!(match_device_name(&names, decomposed).unwrap(), 1);
+        assert_eq!(match_device_name(&names, "micro\u{301}fono").unwrap(), 1);
+
+        let names = vec![decomposed.to_string()];
+        assert_eq!(match_device_name(&names, composed).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_match_device_ambiguous() {
+        let names = device_names();
+        let err = match_device_name(&names, "analog-stereo").unwrap_err().to_string();
+        assert!(err.contains("ambiguous"));
+        assert!(err.contains("Yeti"));
+        assert!(err.contains("pci-0000"));
+    }
+
+    #[test]
+    fn test_choose_buffer_size() {
+        let range = SupportedBufferSize::Range { min: 64, max: 4096 };
+        assert_eq!(choose_buffer_size(None, &range), BufferSize::Default);
+        assert_eq!(
+            choose_buffer_size(Some(256), &range),
+            BufferSize::Fixed(256)
+        );
+        assert_eq!(choose_buffer_size(Some(64), &range), BufferSize::Fixed(64));
+        assert_eq!(
+            choose_buffer_size(Some(4096), &range),
+            BufferSize::Fixed(4096)
+        );
+        assert_eq!(choose_buffer_size(Some(32), &range), BufferSize::Default);
+        assert_eq!(choose_buffer_size(Some(8192), &range), BufferSize::Default);
+        assert_eq!(
+            choose_buffer_size(Some(256), &SupportedBufferSize::Unknown),
+            BufferSize::Default
+        );
+    }
+
+    #[test]
+    fn test_match_device_not_found() {
+        let names = device_names();
+        let err = match_device_name(&names, "webcam").unwrap_err().to_string();
+        assert!(err.contains("not found"));
+    }
+
+    #[test]
+    fn test_check_device_index() {
+        assert!(check_device_index(0, 2).is_ok());
+        assert!(check_device_index(1, 2).is_ok());
+
+        let err = check_device_index(2, 2).unwrap_err().to_string();
+        assert!(err.contains("out of range"));
+        assert!(err.contains("2 input device(s) available"));
+        assert!(check_device_index(0, 0).is_err());
+    }
+}
+
+/// Mock capture backend for deterministic testing
+#[cfg(any(test, feature = "mock-capture"))]
+pub struct MockCaptureBackend {
+    samples: Vec<f32>,
+    sample_rate: u32,
+    channels: u16,
+    selected_device: Option<String>,
+    configured: bool,
+    capturing: bool,
+    capture_count: usize,
+    stream_error: Option<String>,
+    unplugged: Vec<String>,
+    devices: Vec<String>,
+    spill_after: Option<usize>,
+    spilled: Option<SpillFile>,
+    silent: bool,
+}
+
+#[cfg(any(test, feature = "mock-capture"))]
+impl MockCaptureBackend {
+    /// Create a backend that yields `samples` (interleaved) on every capture.
+    pub fn new(samples: Vec<f32>, sample_rate: u32, channels: u16) -> Self {
+        Self {
+            samples,
+            sample_rate,
+            channels,
+            selected_device: None,
+            configured: false,
+            capturing: false,
+            capture_count: 0,
+            stream_error: None,
+            unplugged: Vec::new(),
+            devices: Vec::new(),
+            spill_after: None,
+            spilled: None,
+            silent: false,
+        }
+    }
+
+    /// Leave started captures waiting for speech, like a voice trigger that
+    /// never fires, so nothing is recorded
+    pub fn hear_no_speech(&mut self) {
+        self.silent = true;
+    }
+
+    /// Spill captures to disk in blocks of `after` samples as
+    /// [`AudioEngine::set_spill_after`] would, returning only the remainder
+    /// from `stop_capture`
+    pub fn set_spill_after(&mut self, after: Option<usize>) {
+        self.spill_after = after;
+    }
+
+    /// Make the next `stop_capture` fail as if the stream reported `reason`
+    pub fn fail_stream(&mut self, reason: &str) {
+        self.stream_error = Some(reason.to_string());
+    }
+
+    /// Make selecting `name` fail as if the device were disconnected
+    pub fn unplug(&mut self, name: &str) {
+        self.unplugged.push(name.to_string());
+    }
+
+    /// Match requested device names against `names` like a real host would,
+    /// instead of accepting any name
+    pub fn set_devices(&mut self, names: &[&str]) {
+        self.devices = names.iter().map(|name| name.to_string()).collect();
+    }
+
+    pub fn selected_device(&self) -> Option<&str> {
+        self.selected_device.as_deref()
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capturing
+    }
+
+    pub fn capture_count(&self) -> usize {
+        self.capture_count
+    }
+}
+
+#[cfg(any(test, feature = "mock-capture"))]
+impl CaptureBackend for MockCaptureBackend {
+    fn select_device(&mut self, device_name: Option<&str>) -> Result<()> {
+        if let Some(name) = device_name.filter(|name| self.unplugged.iter().any(|u| u == name)) {
+            return Err(MicrodropError::DeviceNotFound(name.to_string()));
+        }
+        self.selected_device = match device_name {
+            Some(name) if !self.devices.is_empty() => {
+                Some(self.devices[match_device_name(&self.devices, name)?].clone())
+            }
+            name => Some(name.unwrap_or("mock").to_string()),
+        };
+        Ok(())
+    }
+
+    fn select_device_index(&mut self, index: usize) -> Result<()> {
+        self.selected_device = Some(format!("mock #{}", index));
+        Ok(())
+    }
+
+    fn configure_stream(&mut self) -> Result<()> {
+        if self.selected_device.is_none() {
+            return Err(MicrodropError::Audio("No device selected".to_string()));
+        }
+        self.configured = true;
+        Ok(())
+    }
+
+    fn start_capture(&mut self) -> Result<()> {
+        if !self.configured {
+            return Err(MicrodropError::Audio("No configuration set".to_string()));
+        }
+        self.capturing = true;
+        self.capture_count += 1;
+        Ok(())
+    }
+
+    fn stop_capture(&mut self) -> Result<Vec<f32>> {
+        if !self.capturing {
+            return Ok(Vec::new());
+        }
+        self.capturing = false;
+        if let Some(reason) = self.stream_error.take() {
+            return Err(MicrodropError::Audio(format!(
+                "Recording failed: {}",
+                reason
+            )));
+        }
+        if self.silent {
+            return Ok(Vec::new());
+        }
+        let mut samples = self.samples.clone();
+        if let Some(after) = self
+            .spill_after
+            .filter(|&after| after > 0 && samples.len() >= after)
+        {
+            let rest = samples.split_off(samples.len() - samples.len() % after);
+            let mut writer = SpillWriter::create(&std::env::temp_dir())?;
+            writer.send(samples.into());
+            self.spilled = Some(writer.finish()?);
+            samples = rest;
+        }
+        Ok(samples)
+    }
+
+    fn get_stats(&self, samples: &[f32]) -> Result<AudioStats> {
+        if !self.configured {
+            return Err(MicrodropError::Audio("No configuration set".to_string()));
+        }
+        Ok(AudioStats::from_samples(
+            samples,
+            self.sample_rate,
+            self.channels,
+        ))
+    }
+
+    fn device_name(&self) -> Option<String> {
+        self.selected_device.clone()
+    }
+
+    fn is_recording(&self) -> bool {
+        !self.silent
+    }
+
+    fn take_spilled(&mut self) -> Option<SpillFile> {
+        self.spilled.take()
+    }
+}
+
+#[cfg(test)]
+mod mock_tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_capture_yields_preloaded_samples() {
+        let samples = vec![0.1, -0.1, 0.2, -0.2];
+        let mut backend = MockCaptureBackend::new(samples.clone(), 48000, 2);
+
+        backend.select_device(Some("test-mic")).unwrap();
+        backend.configure_stream().unwrap();
+        backend.start_capture().unwrap();
+        assert!(backend.is_capturing());
+
+        let captured = backend.stop_capture().unwrap();
+        assert_eq!(captured, samples);
+        assert!(!backend.is_capturing());
+        assert_eq!(backend.selected_device(), Some("test-mic"));
+        assert_eq!(backend.capture_count(), 1);
+    }
+
+    #[test]
+    fn test_mock_capture_requires_configuration() {
+        let mut backend = MockCaptureBackend::new(vec![0.0; 16], 16000, 1);
+        assert!(backend.configure_stream().is_err());
+        assert!(backend.start_capture().is_err());
+    }
+
+    #[test]
+    fn test_mock_capture_stats() {
+        let mut backend = MockCaptureBackend::new(Vec::new(), 48000, 2);
+        backend.select_device(None).unwrap();
+        backend.configure_stream().unwrap();
+        let stats = backend.get_stats(&vec![0.0; 96000]).unwrap();
+
+        assert_eq!(stats.sample_rate, 48000);
+        assert_eq!(stats.channels, 2);
+        assert_eq!(stats.sample_count, 96000);
+        assert_eq!(stats.duration, Duration::from_secs(1));
+        assert_eq!(stats.dropped_samples, 0);
+        assert_eq!(stats.rms, 0.0);
+
+        let stats = backend.get_stats(&[0.5, -0.5, 0.5, -0.5]).unwrap();
+        assert_eq!(stats.rms, 0.5);
+        assert_eq!(stats.peak, 0.5);
+    }
+
+    #[test]
+    fn test_audio_stats_display() {
+        let mut stats = AudioStats::from_samples(&[0.5, -0.5, 0.5, -0.5], 4, 2);
+        assert_eq!(
+            stats.to_string(),
+            "0.50s, 4 Hz, 2 channels, peak -6.0 dBFS, RMS -6.0 dBFS"
+        );
+
+        stats.dropped_samples = 12;
+        assert!(stats.to_string().ends_with(", 12 samples dropped"));
+
+        let silence = AudioStats::from_samples(&[0.0; 16000], 16000, 1);
+        assert_eq!(
+            silence.to_string(),
+            "1.00s, 16000 Hz, 1 channel, peak -inf dBFS, RMS -inf dBFS"
+        );
+
+        let json = serde_json::to_value(&silence).unwrap();
+        assert_eq!(json["duration"], 1.0);
+        assert_eq!(json["sample_rate"], 16000);
+        assert_eq!(json["peak"], 0.0);
+    }
+
+    #[test]
+    fn test_capture_for_runs_a_whole_capture() {
+        let mut backend = MockCaptureBackend::new(vec![0.25; 1600], 16000, 1);
+
+        let samples = backend.capture_for(Duration::from_millis(5)).unwrap();
+        assert_eq!(samples, vec![0.25; 1600]);
+        assert_eq!(backend.selected_device(), Some("mock"));
+        assert_eq!(backend.capture_count(), 1);
+        assert!(!backend.is_capturing());
+
+        // An explicitly selected device is kept
+        backend.select_device(Some("usb-mic")).unwrap();
+        backend.capture_for(Duration::ZERO).unwrap();
+        assert_eq!(backend.selected_device(), Some("usb-mic"));
+        assert_eq!(backend.capture_count(), 2);
+    }
+
+    #[test]
+    fn test_negotiated_format_survives_stop_capture() {
+        let mut backend = MockCaptureBackend::new(vec![0.0; 4800], 48000, 2);
+        assert!(backend.get_stats(&[]).is_err());
+
+        backend.select_device(None).unwrap();
+        backend.configure_stream().unwrap();
+        backend.start_capture().unwrap();
+        let samples = backend.stop_capture().unwrap();
+
+        let stats = backend.get_stats(&samples).unwrap();
+        assert_eq!(stats.sample_rate, 48000);
+        assert_eq!(stats.channels, 2);
+    }
+
+    #[test]
+    fn test_stream_error_fails_stop_capture() {
+        let mut engine = AudioEngine::new();
+        *engine.stream_error.lock().unwrap() =
+            Some(describe_stream_error(&StreamError::DeviceNotAvailable));
+
+        let err = engine.stop_capture().unwrap_err().to_string();
+        assert!(err.contains("Recording failed"));
+        assert!(err.contains("disconnected"));
+
+        // The error is reported once
+        assert!(engine.stop_capture().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_engine_stats_require_negotiated_format() {
+        let engine = AudioEngine::new();
+        let err = engine.get_stats(&[0.0; 16]).unwrap_err().to_string();
+        assert!(err.contains("Capture format unknown"));
+    }
+}
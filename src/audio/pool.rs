@@ -0,0 +1,173 @@
+//! Reusable [`AudioProcessor`]s for callers that convert many recordings,
+//! such as the HTTP server.
+
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+use tracing::debug;
+
+use super::AudioProcessor;
+use crate::Result;
+
+/// Idle processors kept by default
+pub const DEFAULT_POOL_SIZE: usize = 4;
+
+/// A bounded set of idle [`AudioProcessor`]s keyed by input format.
+///
+/// Building a processor allocates the resampler's sinc tables, which costs far
+/// more than converting a short clip. Checking one out reuses an idle processor
+/// for the same sample rate and channel count when there is one, and the
+/// processor comes back to the pool when the [`PooledProcessor`] is dropped.
+pub struct ProcessorPool {
+    idle: Mutex<HashMap<(u32, u16), Vec<AudioProcessor>>>,
+    max_idle: usize,
+}
+
+impl ProcessorPool {
+    /// Create a pool keeping at most `max_idle` processors across all formats;
+    /// 0 builds a fresh processor every time
+    pub fn new(max_idle: usize) -> Self {
+        Self {
+            idle: Mutex::new(HashMap::new()),
+            max_idle,
+        }
+    }
+
+    /// Take a processor for `sample_rate` and `channels`, building one if none is idle
+    pub fn checkout(&self, sample_rate: u32, channels: u16) -> Result<PooledProcessor<'_>> {
+        let key = (sample_rate, channels);
+        let reused = self
+            .idle
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_mut(&key)
+            .and_then(|processors| processors.pop());
+
+        let processor = match reused {
+            Some(mut processor) => {
+                debug!(
+                    "Reusing pooled processor for {}Hz {}ch",
+                    sample_rate, channels
+                );
+                processor.reset();
+                processor
+            }
+            None => AudioProcessor::new(sample_rate, channels)?,
+        };
+
+        Ok(PooledProcessor {
+            pool: self,
+            key,
+            processor: Some(processor),
+        })
+    }
+
+    /// Number of processors waiting to be reused
+    pub fn idle(&self) -> usize {
+        self.idle
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .map(Vec::len)
+            .sum()
+    }
+
+    fn give_back(&self, key: (u32, u16), processor: AudioProcessor) {
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        if idle.values().map(Vec::len).sum::<usize>() < self.max_idle {
+            idle.entry(key).or_default().push(processor);
+        }
+    }
+}
+
+impl Default for ProcessorPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_POOL_SIZE)
+    }
+}
+
+/// An [`AudioProcessor`] checked out of a [`ProcessorPool`], returned on drop
+pub struct PooledProcessor<'a> {
+    pool: &'a ProcessorPool,
+    key: (u32, u16),
+    processor: Option<AudioProcessor>,
+}
+
+impl Deref for PooledProcessor<'_> {
+    type Target = AudioProcessor;
+
+    fn deref(&self) -> &AudioProcessor {
+        self.processor.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledProcessor<'_> {
+    fn deref_mut(&mut self) -> &mut AudioProcessor {
+        self.processor.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledProcessor<'_> {
+    fn drop(&mut self) {
+        if let Some(processor) = self.processor.take() {
+            self.pool.give_back(self.key, processor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_processors_are_reused_per_format() {
+        let pool = ProcessorPool::new(4);
+
+        let mut processor = pool.checkout(48000, 2).unwrap();
+        processor.set_skip_silent_channels(true);
+        assert_eq!(processor.process(&[0.5; 9600]).unwrap().len(), 1600);
+        drop(processor);
+        assert_eq!(pool.idle(), 1);
+
+        // Another format builds its own processor
+        let other = pool.checkout(44100, 1).unwrap();
+        assert_eq!(other.get_input_sample_rate(), 44100);
+        assert_eq!(pool.idle(), 1);
+        drop(other);
+        assert_eq!(pool.idle(), 2);
+
+        // A reused processor starts over, settings included
+        let processor = pool.checkout(48000, 2).unwrap();
+        assert_eq!(pool.idle(), 1);
+        assert_eq!(processor.get_input_sample_rate(), 48000);
+        assert!(!processor.skips_silent_channels());
+    }
+
+    #[test]
+    fn test_pool_keeps_at_most_max_idle() {
+        let pool = ProcessorPool::new(2);
+        let processors: Vec<_> = (0..3).map(|_| pool.checkout(16000, 1).unwrap()).collect();
+        drop(processors);
+        assert_eq!(pool.idle(), 2);
+
+        let unpooled = ProcessorPool::new(0);
+        drop(unpooled.checkout(16000, 1).unwrap());
+        assert_eq!(unpooled.idle(), 0);
+    }
+
+    #[test]
+    fn test_pooled_output_matches_a_fresh_processor() {
+        let samples: Vec<f32> = (0..44100).map(|i| (i as f32 * 0.05).sin() * 0.5).collect();
+        let expected = AudioProcessor::new(44100, 1)
+            .unwrap()
+            .process(&samples)
+            .unwrap();
+
+        let pool = ProcessorPool::default();
+        for _ in 0..2 {
+            let mut processor = pool.checkout(44100, 1).unwrap();
+            assert_eq!(processor.process(&samples).unwrap(), expected);
+        }
+    }
+}
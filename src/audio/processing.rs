@@ -1,18 +1,26 @@
 //! Audio preprocessing utilities for format conversion and resampling.
 
+use std::ops::Range;
+
 use rubato::{
     Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction,
 };
-use tracing::{debug, warn};
+use tracing::{debug, instrument, warn, Span};
 
 use crate::{MicrodropError, Result};
 
-const TARGET_SAMPLE_RATE: u32 = 16000;
+/// Sample rate Whisper expects, and that processed audio is produced at
+pub const TARGET_SAMPLE_RATE: u32 = 16000;
+
+/// RMS level (about -60 dBFS) below which a channel counts as unplugged
+const SILENT_CHANNEL_RMS: f32 = 0.001;
 
 pub struct AudioProcessor {
     resampler: Option<SincFixedIn<f32>>,
     input_sample_rate: u32,
     input_channels: u16,
+    /// Leave silent channels out of the downmix
+    skip_silent_channels: bool,
 }
 
 impl AudioProcessor {
@@ -31,7 +39,7 @@ impl AudioProcessor {
                 2.0, // max_resample_ratio_relative
                 params,
                 1024, // chunk_size
-                input_channels as usize,
+                1,    // resampling runs after the downmix
             )
             .map_err(|e| MicrodropError::Audio(format!("Failed to create resampler: {}", e)))?;
 
@@ -49,9 +57,21 @@ impl AudioProcessor {
             resampler,
             input_sample_rate,
             input_channels,
+            skip_silent_channels: false,
         })
     }
 
+    #[instrument(
+        name = "process_audio",
+        level = "debug",
+        skip_all,
+        fields(
+            input_samples = input.len(),
+            sample_rate = self.input_sample_rate,
+            channels = self.input_channels,
+            output_samples
+        )
+    )]
     pub fn process(&mut self, input: &[f32]) -> Result<Vec<f32>> {
         // Handle empty input early
         if input.is_empty() {
@@ -66,19 +86,15 @@ impl AudioProcessor {
         };
 
         // Step 2: Resample if needed
-        let resampled = if self.resampler.is_some() && !mono_samples.is_empty() {
-            let input_channels = vec![mono_samples];
-            let output_channels = self
-                .resampler
-                .as_mut()
-                .unwrap()
-                .process(&input_channels, None)
-                .map_err(|e| MicrodropError::Audio(format!("Resampling failed: {}", e)))?;
-            output_channels.into_iter().next().unwrap_or_default()
-        } else {
-            mono_samples
+        let input_sample_rate = self.input_sample_rate;
+        let resampled = match self.resampler.as_mut() {
+            Some(resampler) if !mono_samples.is_empty() => {
+                resample(resampler, &mono_samples, input_sample_rate)?
+            }
+            _ => mono_samples,
         };
 
+        Span::current().record("output_samples", resampled.len());
         debug!(
             "Processed {} input samples -> {} output samples",
             input.len(),
@@ -87,18 +103,50 @@ impl AudioProcessor {
         Ok(resampled)
     }
 
-    fn downmix_to_mono(&self, interleaved: &[f32]) -> Vec<f32> {
+    /// Exclude channels that carry only silence or a noise floor across the
+    /// whole buffer from the downmix, so one live mic on a multi-input
+    /// interface isn't diluted by the unplugged ones
+    pub fn set_skip_silent_channels(&mut self, skip: bool) {
+        self.skip_silent_channels = skip;
+    }
+
+    pub fn skips_silent_channels(&self) -> bool {
+        self.skip_silent_channels
+    }
+
+    /// Return to the state `new` left it in, keeping the resampler's
+    /// allocations, so the processor can be reused for another recording
+    pub fn reset(&mut self) {
+        if let Some(resampler) = self.resampler.as_mut() {
+            resampler.reset();
+        }
+        self.skip_silent_channels = false;
+    }
+
+    pub fn downmix_to_mono(&self, interleaved: &[f32]) -> Vec<f32> {
         let channels = self.input_channels as usize;
         let frame_count = interleaved.len() / channels;
         let mut mono = Vec::with_capacity(frame_count);
 
+        let live = if self.skip_silent_channels {
+            live_channels(interleaved, channels)
+        } else {
+            vec![true; channels]
+        };
+        let live_count = live.iter().filter(|&&l| l).count();
+
         for frame_idx in 0..frame_count {
             let start = frame_idx * channels;
             let end = start + channels;
 
             if end <= interleaved.len() {
-                let frame_sum: f32 = interleaved[start..end].iter().sum();
-                mono.push(frame_sum / channels as f32);
+                let frame_sum: f32 = interleaved[start..end]
+                    .iter()
+                    .zip(&live)
+                    .filter(|(_, &l)| l)
+                    .map(|(s, _)| s)
+                    .sum();
+                mono.push(frame_sum / live_count as f32);
             } else {
                 warn!("Incomplete frame at end of audio buffer, skipping");
                 break;
@@ -122,10 +170,161 @@ impl AudioProcessor {
     }
 }
 
+/// Which channels of an interleaved buffer rise above the silence threshold.
+/// If none do, every channel is kept so quiet recordings still mix normally.
+fn live_channels(interleaved: &[f32], channels: usize) -> Vec<bool> {
+    let frame_count = interleaved.len() / channels;
+    let mut live: Vec<bool> = (0..channels)
+        .map(|channel| {
+            let energy: f32 = interleaved
+                .iter()
+                .skip(channel)
+                .step_by(channels)
+                .take(frame_count)
+                .map(|s| s * s)
+                .sum();
+            (energy / frame_count.max(1) as f32).sqrt() >= SILENT_CHANNEL_RMS
+        })
+        .collect();
+
+    let skipped: Vec<usize> = (0..channels).filter(|&c| !live[c]).collect();
+    if skipped.len() == channels {
+        debug!("All {} channels are silent, mixing them all", channels);
+        live = vec![true; channels];
+    } else if !skipped.is_empty() {
+        debug!("Skipping silent channel(s) {:?} in downmix", skipped);
+    }
+    live
+}
+
+/// Select `duration` seconds of processed 16 kHz audio starting `offset`
+/// seconds in, or everything after `offset` when no duration is given.
+pub fn slice_seconds(samples: &[f32], offset: f64, duration: Option<f64>) -> Result<&[f32]> {
+    let range = seconds_range(samples.len(), TARGET_SAMPLE_RATE, offset, duration)?;
+    Ok(&samples[range])
+}
+
+/// The frames of a recording `frames` long at `sample_rate` that
+/// [`slice_seconds`] would select
+pub fn seconds_range(
+    frames: usize,
+    sample_rate: u32,
+    offset: f64,
+    duration: Option<f64>,
+) -> Result<Range<usize>> {
+    let to_index = |seconds: f64| (seconds * sample_rate as f64).round() as usize;
+    let available = frames as f64 / sample_rate as f64;
+
+    let start = to_index(offset);
+    if start >= frames {
+        return Err(MicrodropError::Audio(format!(
+            "Offset {:.2}s is beyond the end of the audio ({:.2}s)",
+            offset, available
+        )));
+    }
+
+    let end = match duration {
+        Some(duration) => {
+            let end = start + to_index(duration);
+            if end > frames {
+                return Err(MicrodropError::Audio(format!(
+                    "Requested {:.2}s from {:.2}s, but the audio is only {:.2}s long",
+                    duration, offset, available
+                )));
+            }
+            end
+        }
+        None => frames,
+    };
+
+    Ok(start..end)
+}
+
+/// Resample a whole mono buffer, feeding every chunk through the resampler,
+/// including a zero-padded final partial chunk and any flushes needed to reach
+/// the full output length.
+///
+/// rubato 0.15's `SincFixedIn` output is already aligned with its input, even
+/// though `output_delay()` reports half the filter length, so nothing is trimmed.
+fn resample(
+    resampler: &mut SincFixedIn<f32>,
+    mono: &[f32],
+    input_sample_rate: u32,
+) -> Result<Vec<f32>> {
+    let map_err =
+        |e: rubato::ResampleError| MicrodropError::Audio(format!("Resampling failed: {}", e));
+
+    let expected_len =
+        (mono.len() as f64 * TARGET_SAMPLE_RATE as f64 / input_sample_rate as f64).round() as usize;
+    let mut output = Vec::with_capacity(expected_len + resampler.output_frames_max());
+
+    let mut chunks = mono.chunks_exact(resampler.input_frames_next());
+    for chunk in chunks.by_ref() {
+        let out = resampler.process(&[chunk], None).map_err(map_err)?;
+        output.extend_from_slice(&out[0]);
+    }
+
+    let remainder = chunks.remainder();
+    if !remainder.is_empty() {
+        let out = resampler
+            .process_partial(Some(&[remainder]), None)
+            .map_err(map_err)?;
+        output.extend_from_slice(&out[0]);
+    }
+
+    // Flush until the end of the input has come out
+    while output.len() < expected_len {
+        let out = resampler
+            .process_partial::<&[f32]>(None, None)
+            .map_err(map_err)?;
+        if out[0].is_empty() {
+            break;
+        }
+        output.extend_from_slice(&out[0]);
+    }
+
+    // The flush should always reach the expected length; say so if the tail was lost
+    let missing = expected_len.saturating_sub(output.len());
+    if missing > 0 {
+        let unprocessed = missing as u64 * input_sample_rate as u64 / TARGET_SAMPLE_RATE as u64;
+        warn!(
+            "Resampler dropped the last ~{} input samples ({} output samples short)",
+            unprocessed, missing
+        );
+    }
+
+    // Each call handles a complete recording, so start the next one fresh
+    resampler.reset();
+
+    output.truncate(expected_len);
+    Ok(output)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_slice_seconds() {
+        let samples: Vec<f32> = (0..48000).map(|i| i as f32).collect();
+
+        let slice = slice_seconds(&samples, 1.0, Some(0.5)).unwrap();
+        assert_eq!(slice.len(), 8000);
+        assert_eq!(slice[0], 16000.0);
+
+        assert_eq!(slice_seconds(&samples, 2.5, None).unwrap().len(), 8000);
+        assert_eq!(slice_seconds(&samples, 0.0, None).unwrap().len(), 48000);
+    }
+
+    #[test]
+    fn test_slice_seconds_out_of_range() {
+        let samples = vec![0.0; 16000];
+
+        assert!(slice_seconds(&samples, 1.0, None).is_err());
+        assert!(slice_seconds(&samples, 0.5, Some(0.75)).is_err());
+        assert!(slice_seconds(&samples, 0.5, Some(0.5)).is_ok());
+    }
+
     #[test]
     fn test_downmix_stereo_to_mono() {
         let processor = AudioProcessor::new(44100, 2).unwrap();
@@ -176,6 +375,57 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_resampling_consumes_whole_buffer() {
+        let mut processor = AudioProcessor::new(48000, 1).unwrap();
+        let input = vec![0.25; 48000 * 2 + 500];
+
+        let output = processor.process(&input).unwrap();
+        assert_eq!(output.len(), 32167);
+
+        // Repeated calls are independent of each other
+        assert_eq!(processor.process(&input).unwrap(), output);
+    }
+
+    #[test]
+    fn test_resampling_is_time_aligned() {
+        let mut processor = AudioProcessor::new(44100, 1).unwrap();
+        let mut input = vec![0.0; 5000];
+        input[2000] = 1.0;
+
+        let output = processor.process(&input).unwrap();
+        let peak = (0..output.len())
+            .max_by(|&a, &b| output[a].total_cmp(&output[b]))
+            .unwrap();
+
+        // 2000 * 16000 / 44100 = 725.7
+        assert!((725..=726).contains(&peak), "peak at {}", peak);
+    }
+
+    #[test]
+    fn test_resampling_keeps_unaligned_tail() {
+        let mut processor = AudioProcessor::new(44100, 1).unwrap();
+        // Three full 1024-frame chunks plus a 17-sample remainder
+        let input = vec![0.25; 1024 * 3 + 17];
+
+        let output = processor.process(&input).unwrap();
+
+        // round(3089 * 16000 / 44100) samples, and the signal runs right up to
+        // the final sample, which straddles the end of the input
+        assert_eq!(output.len(), 1121);
+        let tail = &output[output.len() - 8..output.len() - 1];
+        assert!(tail.iter().all(|s| (s - 0.25).abs() < 0.03), "{:?}", tail);
+    }
+
+    #[test]
+    fn test_resampling_stereo_input() {
+        let mut processor = AudioProcessor::new(44100, 2).unwrap();
+        let input = vec![0.5; 44100 * 2];
+
+        let output = processor.process(&input).unwrap();
+        assert_eq!(output.len(), 16000);
+    }
+
     #[test]
     fn test_downmix_quad_to_mono() {
         let processor = AudioProcessor::new(44100, 4).unwrap();
@@ -199,6 +449,31 @@ mod tests {
         // Should only process the complete frame
         assert_eq!(output, vec![0.0]); // (1.0 + -1.0) / 2 = 0.0
     }
+
+    #[test]
+    fn test_downmix_skips_silent_channels() {
+        let mut processor = AudioProcessor::new(44100, 4).unwrap();
+        // Only channel 2 carries signal; channel 3 is a faint noise floor
+        let input: Vec<f32> = (0..100)
+            .flat_map(|i| [0.0, 0.0, 0.5, if i % 2 == 0 { 1e-4 } else { -1e-4 }])
+            .collect();
+
+        let diluted = processor.downmix_to_mono(&input);
+        assert!((diluted[0] - 0.125).abs() < 1e-3);
+
+        processor.set_skip_silent_channels(true);
+        let mono = processor.downmix_to_mono(&input);
+        assert_eq!(mono, vec![0.5; 100]);
+    }
+
+    #[test]
+    fn test_downmix_keeps_all_channels_when_all_silent() {
+        let mut processor = AudioProcessor::new(44100, 2).unwrap();
+        processor.set_skip_silent_channels(true);
+
+        let input = vec![0.0; 20];
+        assert_eq!(processor.downmix_to_mono(&input), vec![0.0; 10]);
+    }
 }
 
 // Property-based tests
@@ -269,7 +544,7 @@ mod property_tests {
             let mut processor2 = AudioProcessor::new(sample_rate, channels).unwrap();
 
             // Handle resampling buffer requirements - need enough frames
-            if sample_rate == 16000 || (sample_rate != 16000 && frame_count >= 1024) {
+            if sample_rate == 16000 || frame_count >= 1024 {
                 let output1 = processor1.process(&samples).unwrap();
                 let output2 = processor2.process(&samples).unwrap();
 
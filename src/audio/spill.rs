@@ -0,0 +1,259 @@
+//! Spilling long recordings to a temporary file, so a multi-hour session
+//! doesn't have to fit in memory.
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use tempfile::TempPath;
+
+use crate::{MicrodropError, Result};
+
+/// Audio transcribed at a time from a recording spilled to disk
+pub const SPILL_WINDOW: Duration = Duration::from_secs(120);
+
+/// Audio shared by consecutive windows of a spilled recording, so a word cut
+/// off at the end of one is heard whole at the start of the next
+pub const SPILL_OVERLAP: Duration = Duration::from_secs(5);
+
+/// Bytes per stored sample
+const SAMPLE_BYTES: usize = std::mem::size_of::<f32>();
+
+/// Writes samples to a [`SpillFile`] on a background thread, so the capture
+/// callback hands them off without waiting on the disk. The file is deleted
+/// if the writer is dropped without being finished.
+#[derive(Debug)]
+pub struct SpillWriter {
+    sender: Sender<VecDeque<f32>>,
+    thread: JoinHandle<io::Result<usize>>,
+    path: TempPath,
+    sent: usize,
+}
+
+impl SpillWriter {
+    /// Create a new spill file in `dir`, with a random name and readable only
+    /// by the current user
+    pub fn create(dir: &Path) -> Result<Self> {
+        let (file, path) = tempfile::Builder::new()
+            .prefix("microdrop-spill-")
+            .suffix(".f32")
+            .tempfile_in(dir)
+            .map_err(|e| {
+                MicrodropError::Audio(format!(
+                    "Failed to create spill file in {}: {}",
+                    dir.display(),
+                    e
+                ))
+            })?
+            .into_parts();
+
+        let (sender, receiver) = mpsc::channel::<VecDeque<f32>>();
+        let thread = std::thread::spawn(move || {
+            let mut writer = BufWriter::new(file);
+            let mut written = 0;
+            for samples in receiver {
+                let (front, back) = samples.as_slices();
+                write_samples(&mut writer, front)?;
+                write_samples(&mut writer, back)?;
+                written += samples.len();
+            }
+            writer.flush()?;
+            Ok(written)
+        });
+
+        Ok(Self {
+            sender,
+            thread,
+            path,
+            sent: 0,
+        })
+    }
+
+    /// Queue `samples` to be appended to the file. They are moved, not
+    /// copied, and freed on the writer thread.
+    pub fn send(&mut self, samples: VecDeque<f32>) {
+        self.sent += samples.len();
+        // A send only fails once the thread has stopped on a write error,
+        // which `finish` reports
+        let _ = self.sender.send(samples);
+    }
+
+    /// Number of samples queued so far
+    pub fn sent(&self) -> usize {
+        self.sent
+    }
+
+    /// Wait for the queued samples to be written and return the file
+    pub fn finish(self) -> Result<SpillFile> {
+        drop(self.sender);
+        // Owns the file from here, so it is removed even if writing failed
+        let mut spilled = SpillFile {
+            path: self.path,
+            samples: 0,
+        };
+        spilled.samples = self
+            .thread
+            .join()
+            .map_err(|_| MicrodropError::Audio("Spill writer panicked".to_string()))?
+            .map_err(|e| {
+                MicrodropError::Audio(format!(
+                    "Failed to write spill file {}: {}",
+                    spilled.path.display(),
+                    e
+                ))
+            })?;
+        Ok(spilled)
+    }
+}
+
+/// Interleaved samples stored on disk as little-endian `f32`. The file is
+/// deleted when this is dropped.
+#[derive(Debug)]
+pub struct SpillFile {
+    path: TempPath,
+    samples: usize,
+}
+
+impl SpillFile {
+    /// Number of samples stored
+    pub fn len(&self) -> usize {
+        self.samples
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples == 0
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append `samples`, e.g. the end of a recording still in memory
+    pub fn append(&mut self, samples: &[f32]) -> Result<()> {
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| self.write_error(e))?;
+        let mut writer = BufWriter::new(file);
+        write_samples(&mut writer, samples)
+            .and_then(|()| writer.flush())
+            .map_err(|e| self.write_error(e))?;
+        self.samples += samples.len();
+        Ok(())
+    }
+
+    /// Read the samples in `range`, which is clamped to those stored
+    pub fn read(&self, range: Range<usize>) -> Result<Vec<f32>> {
+        let range = range.start.min(self.samples)..range.end.min(self.samples);
+        let read_error = |e: io::Error| {
+            MicrodropError::Audio(format!(
+                "Failed to read spill file {}: {}",
+                self.path.display(),
+                e
+            ))
+        };
+
+        let mut file = File::open(&self.path).map_err(read_error)?;
+        file.seek(SeekFrom::Start((range.start * SAMPLE_BYTES) as u64))
+            .map_err(read_error)?;
+        let mut bytes = vec![0u8; range.len() * SAMPLE_BYTES];
+        file.read_exact(&mut bytes).map_err(read_error)?;
+        Ok(bytes
+            .chunks_exact(SAMPLE_BYTES)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect())
+    }
+
+    fn write_error(&self, e: io::Error) -> MicrodropError {
+        MicrodropError::Audio(format!(
+            "Failed to write spill file {}: {}",
+            self.path.display(),
+            e
+        ))
+    }
+}
+
+fn write_samples(writer: &mut impl Write, samples: &[f32]) -> io::Result<()> {
+    for sample in samples {
+        writer.write_all(&sample.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Split `len` samples into windows of `window` samples, each starting
+/// `overlap` samples before the previous one ends. Both should be whole
+/// frames so no window splits one.
+pub fn overlapping_windows(len: usize, window: usize, overlap: usize) -> Vec<Range<usize>> {
+    let step = window.saturating_sub(overlap).max(1);
+    let mut windows = Vec::new();
+    let mut start = 0;
+    while start < len {
+        let end = (start + window).min(len);
+        windows.push(start..end);
+        if end == len {
+            break;
+        }
+        start += step;
+    }
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spill_round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut writer = SpillWriter::create(dir.path()).unwrap();
+        writer.send(VecDeque::from([0.5, -0.5]));
+        writer.send(VecDeque::from([0.25]));
+        assert_eq!(writer.sent(), 3);
+        let mut spilled = writer.finish().unwrap();
+        spilled.append(&[1.0, -1.0]).unwrap();
+
+        assert_eq!(spilled.len(), 5);
+        assert_eq!(
+            spilled.read(0..5).unwrap(),
+            vec![0.5, -0.5, 0.25, 1.0, -1.0]
+        );
+        assert_eq!(spilled.read(2..4).unwrap(), vec![0.25, 1.0]);
+        assert_eq!(spilled.read(4..10).unwrap(), vec![-1.0]);
+
+        let path = spilled.path().to_path_buf();
+        assert!(path.is_file());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        drop(spilled);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_unfinished_spill_is_removed() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let writer = SpillWriter::create(dir.path()).unwrap();
+        drop(writer);
+
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_overlapping_windows() {
+        assert_eq!(overlapping_windows(10, 4, 1), vec![0..4, 3..7, 6..10]);
+        assert_eq!(
+            overlapping_windows(11, 4, 1),
+            vec![0..4, 3..7, 6..10, 9..11]
+        );
+        assert_eq!(overlapping_windows(3, 4, 1), vec![0..3]);
+        assert!(overlapping_windows(0, 4, 1).is_empty());
+    }
+}
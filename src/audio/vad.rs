@@ -0,0 +1,100 @@
+//! Energy-based voice activity detection for splitting long recordings.
+
+use std::ops::Range;
+use std::time::Duration;
+
+use super::rms;
+
+/// Length of the frames whose energy is compared against the threshold
+const FRAME_MS: usize = 30;
+
+/// Audio kept on either side of a speech region so word edges aren't clipped
+const PADDING_MS: usize = 150;
+
+/// Find the speech regions of `samples`, as sample index ranges.
+///
+/// A frame is speech when its RMS level exceeds `threshold`. Speech separated by
+/// less than `min_silence` is kept in one region, and each region is padded
+/// slightly. Returns no regions when nothing exceeds the threshold.
+pub fn speech_regions(
+    samples: &[f32],
+    sample_rate: u32,
+    threshold: f32,
+    min_silence: Duration,
+) -> Vec<Range<usize>> {
+    let ms_to_samples = |ms: usize| ms * sample_rate as usize / 1000;
+    let frame_len = ms_to_samples(FRAME_MS).max(1);
+    let min_gap = (min_silence.as_secs_f64() * sample_rate as f64) as usize;
+    let padding = ms_to_samples(PADDING_MS);
+
+    let mut speech: Vec<Range<usize>> = Vec::new();
+    for (index, frame) in samples.chunks(frame_len).enumerate() {
+        if rms(frame) <= threshold {
+            continue;
+        }
+
+        let start = index * frame_len;
+        let end = start + frame.len();
+        match speech.last_mut() {
+            Some(last) if start - last.end < min_gap => last.end = end,
+            _ => speech.push(start..end),
+        }
+    }
+
+    // Pad each region, merging any that now overlap
+    let mut regions: Vec<Range<usize>> = Vec::with_capacity(speech.len());
+    for region in speech {
+        let start = region.start.saturating_sub(padding);
+        let end = (region.end + padding).min(samples.len());
+        match regions.last_mut() {
+            Some(last) if start <= last.end => last.end = end,
+            _ => regions.push(start..end),
+        }
+    }
+
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16000;
+
+    fn tone(seconds: f32) -> Vec<f32> {
+        let len = (seconds * RATE as f32) as usize;
+        (0..len)
+            .map(|i| 0.5 * (i as f32 * 440.0 * 2.0 * std::f32::consts::PI / RATE as f32).sin())
+            .collect()
+    }
+
+    fn silence(seconds: f32) -> Vec<f32> {
+        vec![0.0; (seconds * RATE as f32) as usize]
+    }
+
+    #[test]
+    fn test_silence_has_no_regions() {
+        let regions = speech_regions(&silence(2.0), RATE, 0.01, Duration::from_millis(500));
+        assert!(regions.is_empty());
+    }
+
+    #[test]
+    fn test_long_silence_splits_regions() {
+        let samples = [silence(0.5), tone(1.0), silence(1.0), tone(0.5)].concat();
+        let regions = speech_regions(&samples, RATE, 0.01, Duration::from_millis(500));
+
+        assert_eq!(regions.len(), 2);
+        // Each region covers its tone plus padding
+        assert!(regions[0].start <= 8000 && regions[0].end >= 24000);
+        assert!(regions[1].start <= 40000 && regions[1].end == samples.len());
+        assert!(regions[0].end < regions[1].start);
+    }
+
+    #[test]
+    fn test_short_pause_stays_in_one_region() {
+        let samples = [tone(1.0), silence(0.3), tone(1.0)].concat();
+        let regions = speech_regions(&samples, RATE, 0.01, Duration::from_millis(500));
+
+        assert_eq!(regions, vec![0..samples.len()]);
+    }
+}
@@ -1,20 +1,120 @@
-use std::io;
-use std::path::PathBuf;
+use std::future::Future;
+use std::io::{self, IsTerminal, Write};
+#[cfg(feature = "server")]
+use std::net::{IpAddr, SocketAddr};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
+use chrono::Local;
 use clap::{Args, Parser, Subcommand, ValueEnum};
-use tracing::{debug, info};
+use indicatif::HumanBytes;
+use serde::Serialize;
+use tracing::{debug, info, instrument, warn, Instrument, Span};
 
-use crate::audio::{AudioEngine, AudioProcessor};
-use crate::model::{ModelManager, Quantization};
-use crate::output::{OutputManager, TimestampFormat};
-use crate::transcribe::{find_default_model, TranscriptionEngine};
+use crate::audio::{
+    overlapping_windows, read_audio_file, rms, seconds_range, slice_seconds, speech_regions,
+    write_wav_file, AudioEngine, AudioProcessor, AudioStats, CaptureBackend, InputFormat,
+    SpillFile, DEFAULT_AUTO_START_THRESHOLD, DEFAULT_BUFFER_LIMIT, SPILL_OVERLAP, SPILL_WINDOW,
+    TARGET_SAMPLE_RATE,
+};
+use crate::config::{CliArgs, Config, ConfigSource, ConfigSources, StopKey};
+use crate::doctor;
+use crate::model::{
+    cached_model_defaults, CachedModel, Checksum, InstallOutcome, ModelInfo, ModelManager,
+    Quantization, Sampling, VerifyStatus, DEFAULT_MAX_CONCURRENT_DOWNLOADS,
+};
+use crate::notify::{self, Notifier};
+use crate::output::{
+    format_performance_summary, format_segments, save_recovery, OutputManager, OutputSink,
+    OutputTemplate, TimestampFormat,
+};
+use crate::state::State;
+use crate::telemetry;
+#[cfg(feature = "mock-engine")]
+use crate::transcribe::MockTranscriptionEngine;
+use crate::transcribe::{
+    benchmark_sample, find_default_model, validate_language, BenchmarkReport, DecodeThresholds,
+    ModelIdentity, ModelRun, TranscriptionEngine, TranscriptionResult,
+};
 use crate::{MicrodropError, Result};
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum StopKeyArg {
+    /// Press Enter
+    Enter,
+    /// Press any single key
+    Any,
+}
+
+impl From<StopKeyArg> for StopKey {
+    fn from(arg: StopKeyArg) -> Self {
+        match arg {
+            StopKeyArg::Enter => StopKey::Enter,
+            StopKeyArg::Any => StopKey::Any,
+        }
+    }
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 pub enum TimestampFormatArg {
     None,
     Simple,
     Detailed,
+    /// SubRip subtitles
+    Srt,
+    /// WebVTT subtitles
+    Vtt,
+    /// The full result, with segment times and scores, as JSON
+    Json,
+    /// A Markdown list of segments, for notes apps (copied as plain text)
+    Markdown,
+}
+
+/// Order of the cached models in `model list`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ModelSortArg {
+    /// Alphabetically by model name
+    Name,
+    /// Largest file on disk first
+    Size,
+    /// Most recently installed first
+    Date,
+}
+
+impl ModelSortArg {
+    /// Sort `items` by the cached model each one holds
+    fn sort<T>(self, items: &mut [T], model: impl Fn(&T) -> &CachedModel) {
+        items.sort_by(|a, b| {
+            let (a, b) = (model(a), model(b));
+            let order = match self {
+                ModelSortArg::Name => std::cmp::Ordering::Equal,
+                ModelSortArg::Size => b.size_bytes.cmp(&a.size_bytes),
+                ModelSortArg::Date => b.cached_at.cmp(&a.cached_at),
+            };
+            order.then_with(|| a.info.name.cmp(&b.info.name))
+        });
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SinkArg {
+    Stdout,
+    Clipboard,
+    Paste,
+    /// Desktop notification through --notify, or notify-send
+    Notify,
+}
+
+impl From<SinkArg> for OutputSink {
+    fn from(arg: SinkArg) -> Self {
+        match arg {
+            SinkArg::Stdout => OutputSink::Stdout,
+            SinkArg::Clipboard => OutputSink::Clipboard,
+            SinkArg::Paste => OutputSink::Paste,
+            SinkArg::Notify => OutputSink::Notify,
+        }
+    }
 }
 
 impl From<TimestampFormatArg> for TimestampFormat {
@@ -23,10 +123,127 @@ impl From<TimestampFormatArg> for TimestampFormat {
             TimestampFormatArg::None => TimestampFormat::None,
             TimestampFormatArg::Simple => TimestampFormat::Simple,
             TimestampFormatArg::Detailed => TimestampFormat::Detailed,
+            TimestampFormatArg::Srt => TimestampFormat::Srt,
+            TimestampFormatArg::Vtt => TimestampFormat::Vtt,
+            TimestampFormatArg::Json => TimestampFormat::Json,
+            TimestampFormatArg::Markdown => TimestampFormat::Markdown,
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum EngineArg {
+    #[default]
+    Whisper,
+    /// Canned transcripts without a model, for scripting and CI
+    #[cfg(feature = "mock-engine")]
+    Mock,
+}
+
+fn parse_offset_seconds(value: &str) -> std::result::Result<f64, String> {
+    let seconds: f64 = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a number of seconds", value))?;
+    if !seconds.is_finite() {
+        return Err(format!("'{}' is not a finite number of seconds", value));
+    }
+    Ok(seconds)
+}
+
+fn parse_auto_start_threshold(value: &str) -> std::result::Result<f32, String> {
+    let level: f32 = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a number", value))?;
+    if !(level > 0.0 && level <= 1.0) {
+        return Err(format!("'{}' must be above 0 and at most 1", value));
+    }
+    Ok(level)
+}
+
+fn parse_min_duration(value: &str) -> std::result::Result<f64, String> {
+    let seconds = parse_offset_seconds(value)?;
+    if seconds < 0.0 {
+        return Err(format!("'{}' must not be negative", value));
+    }
+    Ok(seconds)
+}
+
+fn parse_entropy_thold(value: &str) -> std::result::Result<f32, String> {
+    parse_threshold(value, |entropy| DecodeThresholds {
+        entropy: Some(entropy),
+        ..Default::default()
+    })
+}
+
+fn parse_logprob_thold(value: &str) -> std::result::Result<f32, String> {
+    parse_threshold(value, |logprob| DecodeThresholds {
+        logprob: Some(logprob),
+        ..Default::default()
+    })
+}
+
+fn parse_no_speech_thold(value: &str) -> std::result::Result<f32, String> {
+    parse_threshold(value, |no_speech| DecodeThresholds {
+        no_speech: Some(no_speech),
+        ..Default::default()
+    })
+}
+
+/// Parse a threshold and check it with the same rules the config file gets
+fn parse_threshold(
+    value: &str,
+    thresholds: impl Fn(f32) -> DecodeThresholds,
+) -> std::result::Result<f32, String> {
+    let threshold: f32 = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a number", value))?;
+    thresholds(threshold).validate()?;
+    Ok(threshold)
+}
+
+fn parse_output_template(value: &str) -> std::result::Result<OutputTemplate, String> {
+    value.parse().map_err(|e: MicrodropError| e.to_string())
+}
+
+fn parse_model_url(value: &str) -> std::result::Result<String, String> {
+    let url = reqwest::Url::parse(value).map_err(|e| format!("'{}' is not a URL: {}", value, e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("'{}' must be an http or https URL", value));
+    }
+    Ok(value.to_string())
+}
+
+/// Model names become cache file names, so they can't contain paths
+fn parse_model_name(value: &str) -> std::result::Result<String, String> {
+    if value.is_empty() || value == "." || value == ".." || value.contains(['/', '\\']) {
+        return Err(format!("'{}' is not a valid model name", value));
+    }
+    Ok(value.to_string())
+}
+
+fn parse_sha256(value: &str) -> std::result::Result<String, String> {
+    if value.len() != 64 || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "'{}' is not a SHA-256 digest (64 hex digits)",
+            value
+        ));
+    }
+    Ok(value.to_ascii_lowercase())
+}
+
+fn parse_language(value: &str) -> std::result::Result<String, String> {
+    validate_language(value)?;
+    Ok(value.to_string())
+}
+
+fn parse_positive_seconds(value: &str) -> std::result::Result<f64, String> {
+    let seconds = parse_offset_seconds(value)?;
+    if seconds <= 0.0 {
+        return Err(format!("'{}' must be greater than zero", value));
+    }
+    Ok(seconds)
+}
+
 #[derive(Debug, Parser)]
 #[command(
     name = "microdrop",
@@ -34,6 +251,12 @@ impl From<TimestampFormatArg> for TimestampFormat {
     about = "On-demand speech-to-text transcription"
 )]
 pub struct Cli {
+    /// Abort with exit code 124 if the command runs longer than this many seconds
+    #[arg(long, global = true)]
+    pub timeout: Option<u64>,
+    /// Keep models and state under this directory instead of the platform data directory
+    #[arg(long, global = true, env = "MICRODROP_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -43,28 +266,350 @@ pub enum Commands {
     Toggle(ToggleCommand),
     Model(ModelCommand),
     Config(ConfigCommand),
+    /// Check that audio, clipboard, paste and model setup are working
+    Doctor(DoctorCommand),
+    /// Time repeated transcriptions of a fixed sample with the configured model and threads
+    BenchTranscribe(BenchTranscribeCommand),
+    /// Transcribe the same audio with two models and compare speed and output
+    CompareModels(CompareModelsCommand),
+    /// Serve transcriptions over HTTP: POST WAV audio to /transcribe
+    #[cfg(feature = "server")]
+    Serve(ServeCommand),
 }
 
 #[derive(Debug, Args)]
-pub struct ToggleCommand {
+pub struct DoctorCommand {
+    #[command(flatten)]
+    pub overrides: ConfigOverrides,
+    /// Also load the model and run a short inference, to catch corrupt or
+    /// truncated downloads (slow)
+    #[arg(long)]
+    pub deep: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct BenchTranscribeCommand {
+    #[command(flatten)]
+    pub overrides: ConfigOverrides,
+    /// Transcribe this audio file instead of the generated sample
+    #[arg(long)]
+    pub input: Option<PathBuf>,
+    /// Length of the generated sample in seconds
+    #[arg(long, default_value_t = 10.0, conflicts_with = "input", value_parser = parse_positive_seconds)]
+    pub sample_secs: f64,
+    /// Number of timed transcriptions, after one untimed warm-up
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    pub runs: u32,
+}
+
+#[derive(Debug, Args)]
+pub struct CompareModelsCommand {
+    #[command(flatten)]
+    pub overrides: ConfigOverrides,
+    /// The two models to compare, as names or paths
+    #[arg(
+        num_args = 2,
+        required = true,
+        value_name = "MODEL",
+        conflicts_with = "model"
+    )]
+    pub models: Vec<String>,
+    /// Audio file to transcribe
+    #[arg(long)]
+    pub input: PathBuf,
+    /// Print the results as a JSON array, one object per model
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[cfg(feature = "server")]
+#[derive(Debug, Args)]
+pub struct ServeCommand {
+    #[command(flatten)]
+    pub overrides: ConfigOverrides,
+    /// Address to listen on; use 0.0.0.0 to accept requests from other machines
+    #[arg(long, default_value = "127.0.0.1")]
+    pub host: IpAddr,
+    #[arg(long, default_value_t = 8080)]
+    pub port: u16,
+}
+
+/// Flags that override configuration file values
+#[derive(Debug, Args)]
+pub struct ConfigOverrides {
     #[arg(long)]
     pub device: Option<String>,
     #[arg(long)]
     pub duration: Option<u64>,
+    /// Keep this many milliseconds of audio from just before capture starts
     #[arg(long)]
+    pub preroll_ms: Option<u64>,
+    /// Frames per capture callback, if the device supports it; lower cuts
+    /// latency, higher avoids overruns
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub buffer_frames: Option<u32>,
+    /// Paste the transcript into the focused window
+    #[arg(long, overrides_with = "no_paste")]
     pub paste: bool,
+    /// Don't paste, even if enabled in the config
+    #[arg(long, overrides_with = "paste")]
+    pub no_paste: bool,
+    /// Milliseconds to wait before sending the paste keystroke (default 50)
+    #[arg(long)]
+    pub paste_delay_ms: Option<u64>,
+    /// Restore the previous clipboard contents after pasting
+    #[arg(long, overrides_with = "no_restore_clipboard")]
+    pub restore_clipboard: bool,
+    /// Leave the transcript on the clipboard after pasting
+    #[arg(long, overrides_with = "restore_clipboard")]
+    pub no_restore_clipboard: bool,
+    /// Copy each sentence to the clipboard separately, as its own clipboard history entry.
+    /// Needs a clipboard history: Windows' own (Win+V) turned on, or a clipboard manager
+    #[arg(long, overrides_with = "no_clipboard_sentences")]
+    pub clipboard_sentences: bool,
+    /// Copy the whole transcript to the clipboard at once
+    #[arg(long, overrides_with = "clipboard_sentences")]
+    pub no_clipboard_sentences: bool,
+    /// Append transcripts to this file; date specifiers like %Y-%m-%d give one file per day
     #[arg(long)]
     pub append: Option<PathBuf>,
+    /// Append one JSON record per transcript (NDJSON) to this file, for archiving
+    #[arg(long)]
+    pub append_json: Option<PathBuf>,
+    /// Overwrite this file with each transcript, so it only ever holds the
+    /// latest; formatted like --append
+    #[arg(long, alias = "replace-file", value_name = "PATH")]
+    pub replace: Option<PathBuf>,
     #[arg(long)]
     pub model: Option<String>,
     #[arg(long)]
     pub quantized: Option<String>,
+    /// Spoken language code, e.g. "en" or "de", or "auto" to detect it
+    #[arg(long, value_parser = parse_language)]
+    pub language: Option<String>,
+    /// Number of inference threads
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub threads: Option<u32>,
+    /// Decode with beam search using this many beams instead of greedy sampling
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub beam_size: Option<u32>,
+    /// Keep Whisper from starting a segment with blank output (Whisper's default)
+    #[arg(long, overrides_with = "no_suppress_blank")]
+    pub suppress_blank: bool,
+    /// Allow blank output at the start of a segment
+    #[arg(long, overrides_with = "suppress_blank")]
+    pub no_suppress_blank: bool,
+    /// Suppress non-speech tokens like "[Music]", reducing hallucinations on noisy audio
+    #[arg(long, overrides_with = "no_suppress_non_speech")]
+    pub suppress_non_speech: bool,
+    /// Allow non-speech tokens (Whisper's default)
+    #[arg(long, overrides_with = "suppress_non_speech")]
+    pub no_suppress_non_speech: bool,
+    /// Retry segments with token entropy below this; raise it to catch repetition loops (default 2.4)
+    #[arg(long, value_parser = parse_entropy_thold)]
+    pub entropy_thold: Option<f32>,
+    /// Retry segments with mean log-probability below this (default -1.0)
+    #[arg(long, allow_negative_numbers = true, value_parser = parse_logprob_thold)]
+    pub logprob_thold: Option<f32>,
+    /// Treat segments above this no-speech probability as silence; raise it if speech is dropped (default 0.6)
+    #[arg(long, value_parser = parse_no_speech_thold)]
+    pub no_speech_thold: Option<f32>,
+    /// Stop inference after this many seconds and output the segments finished so far
+    #[arg(long, value_name = "SECONDS", value_parser = parse_positive_seconds)]
+    pub inference_timeout: Option<f64>,
+    /// Show a desktop notification by running this command, e.g. "notify-send"
     #[arg(long)]
     pub notify: Option<String>,
-    #[arg(long)]
+    /// Words of the transcript shown in the notification (default 12)
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub notify_words: Option<usize>,
+    /// Copy the transcript to the clipboard, even if disabled in the config
+    #[arg(long, overrides_with = "no_clipboard")]
+    pub clipboard: bool,
+    /// Don't copy the transcript to the clipboard
+    #[arg(long, overrides_with = "clipboard")]
     pub no_clipboard: bool,
+    /// Send the transcript to these sinks as well as the configured ones
+    #[arg(long = "sink", value_enum, value_name = "SINK", value_delimiter = ',')]
+    pub sinks: Vec<SinkArg>,
+    /// Leave these configured sinks out; wins over --sink and the other sink flags
+    #[arg(
+        long = "no-sink",
+        value_enum,
+        value_name = "SINK",
+        value_delimiter = ','
+    )]
+    pub no_sinks: Vec<SinkArg>,
     #[arg(long, value_enum)]
     pub timestamps: Option<TimestampFormatArg>,
+    /// Skip transcription of recordings shorter than this many seconds (0 disables)
+    #[arg(long, value_parser = parse_min_duration)]
+    pub min_duration: Option<f64>,
+}
+
+impl ConfigOverrides {
+    /// Layer these flags over `config`, returning the keys they overrode
+    pub fn apply(&self, config: &mut Config) -> Vec<&'static str> {
+        config.merge_cli_args(CliArgs {
+            device: self.device.clone(),
+            duration: self.duration,
+            preroll_ms: self.preroll_ms,
+            buffer_frames: self.buffer_frames,
+            model: self.model.clone(),
+            quantized: self.quantized.clone(),
+            language: self.language.clone(),
+            threads: self.threads,
+            beam_size: self.beam_size,
+            suppress_blank: flag_pair(self.suppress_blank, self.no_suppress_blank),
+            suppress_non_speech: flag_pair(self.suppress_non_speech, self.no_suppress_non_speech),
+            entropy_thold: self.entropy_thold,
+            logprob_thold: self.logprob_thold,
+            no_speech_thold: self.no_speech_thold,
+            inference_timeout: self.inference_timeout,
+            sinks: self.sink_changes(),
+            timestamps: self
+                .timestamps
+                .as_ref()
+                .and_then(|t| t.to_possible_value())
+                .map(|v| v.get_name().to_string()),
+            append: self.append.clone(),
+            append_json: self.append_json.clone(),
+            replace: self.replace.clone(),
+            notify: self.notify.clone(),
+            notify_words: self.notify_words,
+            paste_delay_ms: self.paste_delay_ms,
+            restore_clipboard: flag_pair(self.restore_clipboard, self.no_restore_clipboard),
+            clipboard_sentences: flag_pair(self.clipboard_sentences, self.no_clipboard_sentences),
+            min_duration: self.min_duration,
+        })
+    }
+
+    /// Sinks to add (`true`) or remove (`false`), in the order they apply
+    fn sink_changes(&self) -> Vec<(OutputSink, bool)> {
+        let mut changes = Vec::new();
+        if let Some(paste) = flag_pair(self.paste, self.no_paste) {
+            changes.push((OutputSink::Paste, paste));
+        }
+        if let Some(clipboard) = flag_pair(self.clipboard, self.no_clipboard) {
+            changes.push((OutputSink::Clipboard, clipboard));
+        }
+        changes.extend(self.sinks.iter().map(|&sink| (sink.into(), true)));
+        changes.extend(self.no_sinks.iter().map(|&sink| (sink.into(), false)));
+        changes
+    }
+}
+
+/// Collapse a `--foo`/`--no-foo` pair into an override; `None` defers to the config
+fn flag_pair(enable: bool, disable: bool) -> Option<bool> {
+    match (enable, disable) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ToggleCommand {
+    #[command(flatten)]
+    pub overrides: ConfigOverrides,
+    /// Shift rendered timestamps by this many seconds (fractional and negative allowed)
+    #[arg(long, allow_negative_numbers = true, value_parser = parse_offset_seconds)]
+    pub timestamps_offset: Option<f64>,
+    /// Timestamp format for stdout (default: clean text, independent of --timestamps)
+    #[arg(long, value_enum)]
+    pub stdout_timestamps: Option<TimestampFormatArg>,
+    /// Format stdout and append file output with this template instead, e.g.
+    /// "{timestamp} {text} ({language})". Placeholders: {text}, {language},
+    /// {duration} (audio length), {processing_time}, {date} (YYYY-MM-DD) and
+    /// {timestamp} (RFC 3339); {{ and }} are literal braces
+    #[arg(long, value_parser = parse_output_template)]
+    pub output_template: Option<OutputTemplate>,
+    /// Transcribe an audio file instead of recording from the microphone
+    #[arg(long, conflicts_with_all = ["device", "duration", "preroll_ms", "buffer_frames"])]
+    pub input: Option<PathBuf>,
+    /// Record from the Nth input device (0-based, in enumeration order) instead of matching by name
+    #[arg(long, conflicts_with_all = ["device", "input"])]
+    pub device_index: Option<usize>,
+    /// Record from the default device, with a warning, when the configured one isn't connected
+    #[arg(long, conflicts_with_all = ["device_index", "input"])]
+    pub device_fallback: bool,
+    /// Sample rate of the input file, overriding its header (required for headerless PCM)
+    #[arg(long, requires = "input")]
+    pub input_sample_rate: Option<u32>,
+    /// Channel count of the input file, overriding its header
+    #[arg(long, requires = "input")]
+    pub input_channels: Option<u16>,
+    /// Don't default to, or record, the last-used device and model
+    #[arg(long)]
+    pub no_remember: bool,
+    /// Print audio levels before and after processing, processing time and
+    /// real-time factor to stderr
+    #[arg(long)]
+    pub stats: bool,
+    /// Print each segment's index, start, end and text to stderr, whatever the output format
+    #[arg(long, conflicts_with = "sensitive")]
+    pub print_segments: bool,
+    /// After appending to the --append file, print where the transcript starts
+    /// as path:line to stderr, for opening it in an editor
+    #[arg(long)]
+    pub print_append_location: bool,
+    /// Stop each segment after this many tokens, keeping short dictation short
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_tokens: Option<u32>,
+    /// Split segments at word boundaries after this many characters, for shorter subtitle cues
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub segment_max_len: Option<u32>,
+    /// Mark speaker turns, numbering each segment's speaker; needs a tinydiarize (-tdrz) model
+    #[arg(long)]
+    pub diarize: bool,
+    /// Skip this many seconds of audio before transcribing
+    #[arg(long, value_parser = parse_min_duration)]
+    pub offset: Option<f64>,
+    /// Transcribe at most this many seconds of audio (after --offset)
+    #[arg(long, value_parser = parse_positive_seconds)]
+    pub duration_limit: Option<f64>,
+    /// Transcription engine to run
+    #[arg(long, value_enum, default_value_t)]
+    pub engine: EngineArg,
+    /// Capitalize sentence starts and add missing terminal punctuation
+    #[arg(long)]
+    pub cleanup: bool,
+    /// Don't print the transcript; only the clipboard, paste and append sinks run
+    #[arg(long, alias = "output-none")]
+    pub no_stdout: bool,
+    /// Treat the transcript as a secret: paste and copy it, but never print, log or append it
+    #[arg(long)]
+    pub sensitive: bool,
+    /// If printing or a sink fails after transcription, save the transcript under
+    /// the data directory's `recovery/` and print where
+    #[arg(long, conflicts_with = "sensitive")]
+    pub keep_partial: bool,
+    /// Keep recording clips until EOF (Ctrl+D), reusing the loaded model
+    #[arg(long, conflicts_with = "input")]
+    pub repeat: bool,
+    /// Exit with status 66 when there's no transcript text, e.g. for a silent or
+    /// too short recording, instead of succeeding with nothing
+    #[arg(long, conflicts_with = "repeat")]
+    pub fail_on_empty: bool,
+    /// Also save the recording to this WAV file, as the 16 kHz mono audio Whisper hears
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["repeat", "sensitive"])]
+    pub save_audio: Option<PathBuf>,
+    /// With --save-audio, save the audio as captured instead, at the input's
+    /// own sample rate and channel count
+    #[arg(long, requires = "save_audio")]
+    pub save_audio_raw: bool,
+    /// Start recording when speech is heard rather than right away; use with
+    /// --preroll-ms to keep its onset. With --duration, gives up if no speech
+    /// is heard within the duration.
+    #[arg(long, conflicts_with = "input")]
+    pub auto_start: bool,
+    /// RMS level (0-1) that counts as speech for --auto-start (default 0.02)
+    #[arg(long, requires = "auto_start", value_parser = parse_auto_start_threshold)]
+    pub auto_start_threshold: Option<f32>,
+    /// How to stop a recording without --duration (default enter, or
+    /// behavior.stop_key)
+    #[arg(long, value_enum)]
+    pub stop_key: Option<StopKeyArg>,
 }
 
 #[derive(Debug, Args)]
@@ -75,15 +620,74 @@ pub struct ModelCommand {
 
 #[derive(Debug, Subcommand)]
 pub enum ModelSubcommand {
-    List,
+    List(ModelListCommand),
     Install(ModelInstallCommand),
+    /// Check cached models against their recorded checksums
+    Verify,
+    /// Remove orphaned metadata and stale partial downloads (dry run unless --yes)
+    Gc(ModelGcCommand),
+}
+
+#[derive(Debug, Args)]
+pub struct ModelGcCommand {
+    /// Delete the files instead of only listing them
+    #[arg(long)]
+    pub yes: bool,
+    /// Only remove partial downloads untouched for this many hours
+    #[arg(long, default_value_t = 24)]
+    pub older_than_hours: u64,
+}
+
+#[derive(Debug, Args)]
+pub struct ModelListCommand {
+    /// Print cached and available models as JSON, verifying each cached model's checksum
+    #[arg(long)]
+    pub json: bool,
+    /// Order of the cached models
+    #[arg(long, value_enum, default_value_t = ModelSortArg::Name)]
+    pub sort: ModelSortArg,
 }
 
 #[derive(Debug, Args)]
 pub struct ModelInstallCommand {
-    pub model: String,
+    #[arg(required_unless_present_any = ["all", "url"])]
+    pub model: Option<String>,
     #[arg(long)]
     pub quantized: Option<String>,
+    /// Install every model in the registry, skipping ones already cached
+    #[arg(long, conflicts_with_all = ["model", "quantized"])]
+    pub all: bool,
+    /// Download a model from this URL instead of looking it up in the registry
+    #[arg(
+        long,
+        requires = "name",
+        conflicts_with_all = ["model", "quantized", "all"],
+        value_parser = parse_model_url
+    )]
+    pub url: Option<String>,
+    /// Name to cache the --url model under, for use with --model
+    #[arg(long, conflicts_with_all = ["model", "quantized", "all"], value_parser = parse_model_name)]
+    pub name: Option<String>,
+    /// Expected SHA-256 of the --url download; without it the file isn't verified
+    #[arg(long, requires = "url", value_parser = parse_sha256)]
+    pub sha256: Option<String>,
+    /// Download again even if a cached copy passes verification
+    #[arg(long)]
+    pub force: bool,
+    /// Don't check models against their checksums, e.g. for a private mirror
+    /// without published ones. Corrupt or tampered files won't be detected.
+    #[arg(long)]
+    pub no_verify: bool,
+    /// Continue interrupted downloads from their .part files. A partial that
+    /// doesn't match the model's URL, checksum or size is downloaded again.
+    #[arg(long)]
+    pub resume: bool,
+    /// Number of models downloaded at once (default 3)
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_concurrent_downloads: Option<u64>,
+    /// Limit combined download bandwidth, in bytes per second
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub rate_limit: Option<u64>,
 }
 
 #[derive(Debug, Args)]
@@ -98,30 +702,95 @@ pub enum ConfigSubcommand {
         #[arg(long)]
         force: bool,
     },
+    /// Print the effective configuration after applying any override flags
+    Show {
+        /// Annotate each value with where it came from (default, file or cli)
+        #[arg(long)]
+        sources: bool,
+        #[command(flatten)]
+        overrides: Box<ConfigOverrides>,
+    },
 }
 
 impl Cli {
     pub async fn run(&self) -> Result<()> {
+        with_timeout(self.timeout, self.run_command()).await
+    }
+
+    /// Whether the command prints JSON, so its errors should be JSON too:
+    /// `toggle --stdout-timestamps json`, `model list --json` and
+    /// `compare-models --json`
+    pub fn json_output(&self) -> bool {
+        match &self.command {
+            Commands::Toggle(command) => {
+                matches!(command.stdout_timestamps, Some(TimestampFormatArg::Json))
+            }
+            Commands::Model(ModelCommand {
+                command: ModelSubcommand::List(command),
+            }) => command.json,
+            Commands::CompareModels(command) => command.json,
+            _ => false,
+        }
+    }
+
+    async fn run_command(&self) -> Result<()> {
+        let data_dir = match self.data_dir {
+            Some(ref data_dir) => data_dir.clone(),
+            None => Config::default_data_dir()?,
+        };
+        debug!("Using data directory {}", data_dir.display());
+
         match &self.command {
             Commands::Toggle(command) => {
                 info!(?command, "toggle command invoked");
-                command.run().await
+                command.run(&data_dir, self.timeout).await
             }
-            Commands::Model(command) => command.run().await,
+            Commands::Model(command) => command.run(&data_dir).await,
             Commands::Config(command) => command.run().await,
+            Commands::Doctor(command) => command.run(&data_dir),
+            Commands::BenchTranscribe(command) => command.run(&data_dir).await,
+            Commands::CompareModels(command) => command.run(&data_dir).await,
+            #[cfg(feature = "server")]
+            Commands::Serve(command) => command.run(&data_dir).await,
         }
     }
 }
 
+/// Bound `future` by `seconds`, if set. Dropping the future on expiry leaves
+/// downloads as `.part` files rather than half-written models.
+async fn with_timeout<F: Future<Output = Result<()>>>(
+    seconds: Option<u64>,
+    future: F,
+) -> Result<()> {
+    match seconds {
+        Some(seconds) => tokio::time::timeout(Duration::from_secs(seconds), future)
+            .await
+            .map_err(|_| MicrodropError::Timeout(seconds))?,
+        None => future.await,
+    }
+}
+
 impl ModelCommand {
-    async fn run(&self) -> Result<()> {
+    async fn run(&self, data_dir: &Path) -> Result<()> {
         match &self.command {
-            ModelSubcommand::List => {
-                info!("model list command invoked");
-                let model_manager = ModelManager::new()?;
+            ModelSubcommand::List(command) => {
+                // Stays below the default log level so `--json` output is clean
+                debug!(?command, "model list command invoked");
+                let config = Config::load()?;
+                let mut model_manager = ModelManager::in_data_dir(data_dir)?;
+                model_manager.set_registry_url(config.model.registry_url);
+
+                if command.json {
+                    let mut cached = model_manager.verify_cached().await?;
+                    command.sort.sort(&mut cached, |(model, _)| model);
+                    let available = model_manager.list_available_models().await?;
+                    println!("{}", model_list_json(cached, available));
+                    return Ok(());
+                }
 
                 // List cached models
-                let cached_models = model_manager.list_cached_models()?;
+                let mut cached_models = model_manager.list_cached_models()?;
+                command.sort.sort(&mut cached_models, |model| model);
 
                 if cached_models.is_empty() {
                     println!("No cached models found.");
@@ -131,7 +800,7 @@ impl ModelCommand {
                     for cached in &cached_models {
                         println!("  {} ({})", cached.info.name, cached.info.quantization);
                         println!("    Path: {}", cached.path.display());
-                        println!("    Size: {}", cached.info.size);
+                        println!("    Size: {}", HumanBytes(cached.size_bytes));
                         println!();
                     }
                 }
@@ -148,7 +817,41 @@ impl ModelCommand {
             ModelSubcommand::Install(command) => {
                 info!(?command, "model install command invoked");
 
-                let model_manager = ModelManager::new()?;
+                let config = Config::load()?;
+                let mut model_manager = ModelManager::in_data_dir(data_dir)?;
+                let max_concurrent = command
+                    .max_concurrent_downloads
+                    .map(|n| n as usize)
+                    .or(config.model.max_concurrent_downloads)
+                    .unwrap_or(DEFAULT_MAX_CONCURRENT_DOWNLOADS);
+                model_manager.set_max_concurrent_downloads(max_concurrent);
+                model_manager
+                    .set_rate_limit(command.rate_limit.or(config.model.download_rate_limit));
+                model_manager.set_registry_url(config.model.registry_url);
+                model_manager.set_resume_downloads(command.resume);
+                if command.no_verify {
+                    warn!("Checksum verification is disabled (--no-verify): corrupt or tampered models will not be detected");
+                    model_manager.set_verify_checksums(false);
+                }
+
+                if let (Some(url), Some(name)) = (&command.url, &command.name) {
+                    let checksum = command.sha256.clone().map(Checksum::sha256);
+                    if checksum.is_none() {
+                        warn!(
+                            "No --sha256 given: the download from {} will not be verified",
+                            url
+                        );
+                    }
+                    let outcome = model_manager
+                        .install_from_url(url, name, checksum, command.force)
+                        .await?;
+                    print_install_outcome(name, &outcome);
+                    return Ok(());
+                }
+
+                let Some(ref model) = command.model else {
+                    return install_all_models(&model_manager, command.force).await;
+                };
 
                 // Parse quantization if provided
                 let quantization = if let Some(ref q) = command.quantized {
@@ -160,15 +863,215 @@ impl ModelCommand {
                 };
 
                 // Install the model
-                let model_path = model_manager.install_model(&command.model, quantization).await?;
-
-                println!("Model '{}' installed successfully!", command.model);
-                println!("Path: {}", model_path.display());
+                let outcome = model_manager
+                    .install_model(model, quantization, command.force)
+                    .await?;
+                print_install_outcome(model, &outcome);
 
                 Ok(())
             }
+            ModelSubcommand::Verify => {
+                info!("model verify command invoked");
+                let model_manager = ModelManager::in_data_dir(data_dir)?;
+                verify_cached_models(&model_manager).await
+            }
+            ModelSubcommand::Gc(command) => {
+                info!(?command, "model gc command invoked");
+                let model_manager = ModelManager::in_data_dir(data_dir)?;
+                collect_garbage(&model_manager, command)
+            }
+        }
+    }
+}
+
+/// `model list --json` output
+#[derive(Serialize)]
+struct ModelListing {
+    cached: Vec<CachedListing>,
+    available: Vec<AvailableListing>,
+}
+
+#[derive(Serialize)]
+struct CachedListing {
+    #[serde(flatten)]
+    model: CachedModel,
+    /// Checksum verification result; `None` if the model couldn't be read
+    status: Option<VerifyStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AvailableListing {
+    #[serde(flatten)]
+    info: ModelInfo,
+    /// Where the model is cached, if it is
+    path: Option<PathBuf>,
+}
+
+fn model_list_json(
+    cached: Vec<(CachedModel, Result<VerifyStatus>)>,
+    available: Vec<ModelInfo>,
+) -> String {
+    let available = available
+        .into_iter()
+        .map(|info| AvailableListing {
+            path: cached
+                .iter()
+                .find(|(model, _)| model.info.filename == info.filename)
+                .map(|(model, _)| model.path.clone()),
+            info,
+        })
+        .collect();
+    let cached = cached
+        .into_iter()
+        .map(|(model, result)| {
+            let (status, error) = match result {
+                Ok(status) => (Some(status), None),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            CachedListing {
+                model,
+                status,
+                error,
+            }
+        })
+        .collect();
+
+    serde_json::to_string_pretty(&ModelListing { cached, available })
+        .expect("model listings always serialize")
+}
+
+fn collect_garbage(model_manager: &ModelManager, command: &ModelGcCommand) -> Result<()> {
+    let min_age = Duration::from_secs(command.older_than_hours * 3600);
+    let garbage = model_manager.find_garbage(min_age)?;
+    if garbage.is_empty() {
+        println!("Nothing to clean up.");
+        return Ok(());
+    }
+
+    let mut reclaimed = 0;
+    for item in &garbage {
+        if command.yes {
+            if let Err(e) = item.remove() {
+                warn!("{}", e);
+                continue;
+            }
+            reclaimed += item.bytes;
+        }
+        println!(
+            "  {} ({}, {})",
+            item.path.display(),
+            item.kind,
+            HumanBytes(item.bytes)
+        );
+    }
+
+    if command.yes {
+        println!("Reclaimed {}", HumanBytes(reclaimed));
+    } else {
+        let total: u64 = garbage.iter().map(|item| item.bytes).sum();
+        println!(
+            "Would reclaim {} from {} file(s). Run again with --yes to delete them.",
+            HumanBytes(total),
+            garbage.len()
+        );
+    }
+    Ok(())
+}
+
+async fn verify_cached_models(model_manager: &ModelManager) -> Result<()> {
+    let results = model_manager.verify_cached().await?;
+    if results.is_empty() {
+        println!("No cached models found.");
+        return Ok(());
+    }
+
+    let mut failures = 0;
+    for (cached, result) in &results {
+        let status = match result {
+            Ok(VerifyStatus::Valid) => "ok".to_string(),
+            Ok(VerifyStatus::Unverified) => "no checksum recorded".to_string(),
+            Ok(VerifyStatus::Mismatch) => {
+                failures += 1;
+                "checksum mismatch".to_string()
+            }
+            Err(e) => {
+                failures += 1;
+                format!("failed: {}", e)
+            }
+        };
+        println!(
+            "  {} ({}) - {}",
+            cached.info.name, cached.info.quantization, status
+        );
+    }
+
+    if failures > 0 {
+        return Err(MicrodropError::ModelVerification(format!(
+            "{} of {} cached models failed verification",
+            failures,
+            results.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Say where `name` was installed, whether it was downloaded, and whether it was verified
+fn print_install_outcome(name: &str, outcome: &InstallOutcome) {
+    if outcome.cache_hit {
+        println!("Model '{}' is already installed.", name);
+    } else {
+        println!("Model '{}' installed successfully!", name);
+        println!("Downloaded: {}", HumanBytes(outcome.bytes_downloaded));
+    }
+    println!("Path: {}", outcome.path.display());
+    println!(
+        "Checksum: {}",
+        if outcome.verified {
+            "verified"
+        } else {
+            "not verified"
+        }
+    );
+}
+
+async fn install_all_models(model_manager: &ModelManager, force: bool) -> Result<()> {
+    let results = model_manager.install_all(force).await;
+
+    let mut total_bytes = 0;
+    let mut failures = 0;
+    println!("Install summary:");
+    for (model, result) in &results {
+        match result {
+            Ok(outcome) if outcome.cache_hit => {
+                println!("  {} ({}) - already cached", model.name, model.quantization);
+            }
+            Ok(outcome) => {
+                total_bytes += outcome.bytes_downloaded;
+                println!(
+                    "  {} ({}) - downloaded {}",
+                    model.name,
+                    model.quantization,
+                    HumanBytes(outcome.bytes_downloaded)
+                );
+            }
+            Err(e) => {
+                failures += 1;
+                println!("  {} ({}) - failed: {}", model.name, model.quantization, e);
+            }
         }
     }
+    println!("Total downloaded: {}", HumanBytes(total_bytes));
+
+    if failures > 0 {
+        return Err(MicrodropError::ModelDownload(format!(
+            "{} of {} models failed to install",
+            failures,
+            results.len()
+        )));
+    }
+    Ok(())
 }
 
 impl ConfigCommand {
@@ -180,104 +1083,2661 @@ impl ConfigCommand {
                 println!("Default configuration written to: {}", config_path.display());
                 Ok(())
             }
+            ConfigSubcommand::Show { sources, overrides } => {
+                info!(sources = *sources, "config show command invoked");
+                let (mut config, mut config_sources) = Config::load_with_sources()?;
+                for key in overrides.apply(&mut config) {
+                    config_sources.set(key, ConfigSource::Cli);
+                }
+                let sources = sources.then_some(&config_sources);
+                print!("{}", render_config(&config, sources));
+                Ok(())
+            }
         }
     }
 }
 
-impl ToggleCommand {
-    async fn run(&self) -> Result<()> {
-        info!("Starting audio capture session");
+/// Render `section.field = value` lines, optionally tagged with their source
+fn render_config(config: &Config, sources: Option<&ConfigSources>) -> String {
+    let entries = config.entries();
+    let width = entries.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+
+    let mut rendered = String::new();
+    for (key, value) in &entries {
+        match sources {
+            Some(sources) => {
+                let line = format!("{:width$} = {}", key, value, width = width);
+                rendered.push_str(&format!("{}  # {}\n", line, sources.get(key)));
+            }
+            None => rendered.push_str(&format!("{:width$} = {}\n", key, value, width = width)),
+        }
+    }
+    rendered
+}
 
-        // Initialize audio engine
-        let mut audio_engine = AudioEngine::new();
+impl BenchTranscribeCommand {
+    async fn run(&self, data_dir: &Path) -> Result<()> {
+        info!(?self, "bench-transcribe command invoked");
+        let mut config = Config::load()?;
+        self.overrides.apply(&mut config);
+        let state = State::load_from_path(State::path_in(data_dir));
 
-        // Select audio device
-        audio_engine.select_device(self.device.as_deref())?;
+        let samples = match self.input {
+            Some(ref input) => {
+                let audio = read_audio_file(input, InputFormat::default())?;
+                let mut processor = AudioProcessor::new(audio.sample_rate, audio.channels)?;
+                processor.set_skip_silent_channels(config.audio.skip_silent_channels);
+                processor.process(&audio.samples)?
+            }
+            None => benchmark_sample(self.sample_secs),
+        };
+        let audio_duration =
+            Duration::from_secs_f64(samples.len() as f64 / TARGET_SAMPLE_RATE as f64);
 
-        // Configure the stream
-        audio_engine.configure_stream()?;
+        let model_manager = ModelManager::in_data_dir(data_dir)?;
+        let model_path = resolve_model(&config, &state, &model_manager)?;
+        info!("Loading transcription model: {}", model_path.display());
+        let mut engine = TranscriptionEngine::new(&model_path)?;
+        configure_engine(&mut engine, &config, &model_path);
+        engine.warm_up()?;
 
-        // Start capture
-        audio_engine.start_capture()?;
+        let mut runs = Vec::with_capacity(self.runs as usize);
+        for run in 1..=self.runs {
+            let start = Instant::now();
+            engine.transcribe(&samples).await?;
+            let elapsed = start.elapsed();
+            eprintln!("run {}/{}: {:.3}s", run, self.runs, elapsed.as_secs_f64());
+            runs.push(elapsed);
+        }
 
-        // Wait for user input to stop (simple implementation for MVP)
-        println!("Audio capture started. Press Enter to stop...");
-        let mut input = String::new();
-        io::stdin()
-            .read_line(&mut input)
-            .map_err(|e| MicrodropError::Audio(format!("Failed to read input: {}", e)))?;
+        let report = BenchmarkReport {
+            model: model_path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| model_path.display().to_string()),
+            threads: engine.threads(),
+            audio_duration,
+            runs,
+        };
+        println!("{}", report);
+        Ok(())
+    }
+}
 
-        // Stop capture and get samples
-        let raw_samples = audio_engine.stop_capture()?;
+impl CompareModelsCommand {
+    async fn run(&self, data_dir: &Path) -> Result<()> {
+        info!(?self, "compare-models command invoked");
+        let mut config = Config::load()?;
+        self.overrides.apply(&mut config);
 
-        if raw_samples.is_empty() {
-            println!("No audio captured");
-            return Ok(());
+        let audio = read_audio_file(&self.input, InputFormat::default())?;
+        let mut processor = AudioProcessor::new(audio.sample_rate, audio.channels)?;
+        processor.set_skip_silent_channels(config.audio.skip_silent_channels);
+        let samples = processor.process(&audio.samples)?;
+        let audio_duration =
+            Duration::from_secs_f64(samples.len() as f64 / TARGET_SAMPLE_RATE as f64);
+
+        // Resolve both up front so a typo in the second doesn't waste a run of the first
+        let model_manager = ModelManager::in_data_dir(data_dir)?;
+        let model_paths = self
+            .models
+            .iter()
+            .map(|model| {
+                crate::transcribe::resolve_model_path(
+                    &model_manager,
+                    model,
+                    config.model.default_quantization.as_deref(),
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // One engine, reloaded for each model, so only one is in memory at a time
+        let mut engine: Option<TranscriptionEngine> = None;
+        let mut runs = Vec::with_capacity(model_paths.len());
+        for model_path in &model_paths {
+            info!("Loading transcription model: {}", model_path.display());
+            let engine = match engine {
+                Some(ref mut engine) => {
+                    engine.reload(model_path)?;
+                    engine
+                }
+                None => engine.insert(TranscriptionEngine::new(model_path)?),
+            };
+            configure_engine(engine, &config, model_path);
+            engine.warm_up()?;
+
+            let result = engine.transcribe(&samples).await?;
+            let run = ModelRun::new(
+                engine.model_identity(),
+                engine.threads(),
+                audio_duration,
+                result,
+            );
+            if !self.json {
+                if !runs.is_empty() {
+                    println!();
+                }
+                println!("{}", run);
+            }
+            runs.push(run);
         }
 
-        // Get basic stats before processing
-        let raw_stats = audio_engine.get_stats(&raw_samples);
+        if self.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&runs).expect("model runs always serialize")
+            );
+        }
+        Ok(())
+    }
+}
 
-        // Process audio (downmix to mono, resample to 16kHz)
-        let mut processor = AudioProcessor::new(raw_stats.sample_rate, raw_stats.channels)?;
-        let processed_samples = processor.process(&raw_samples)?;
+impl DoctorCommand {
+    fn run(&self, data_dir: &Path) -> Result<()> {
+        info!("doctor command invoked");
+        let mut config = Config::load()?;
+        self.overrides.apply(&mut config);
 
-        if processed_samples.is_empty() {
-            println!("No processed audio available for transcription");
-            return Ok(());
+        let results = doctor::run_checks(&config, data_dir, self.deep);
+        for result in &results {
+            println!("{}", result);
         }
 
-        // Initialize transcription engine
-        let model_path = if let Some(ref model) = self.model {
-            // User specified a model path or name
-            crate::transcribe::resolve_model_path(model, self.quantized.as_deref())?
-        } else {
-            // Try to find a default model
-            find_default_model().ok_or_else(|| {
-                MicrodropError::ModelLoad(
-                    "No model specified and no default model found. \
-                     Please specify a model with --model <path> or install a model with 'microdrop model install <model>'"
-                        .to_string(),
-                )
-            })?
-        };
+        if doctor::has_critical_failure(&results) {
+            let failed = results
+                .iter()
+                .filter(|r| r.status == doctor::CheckStatus::Fail)
+                .count();
+            return Err(MicrodropError::Diagnostics(format!(
+                "{} critical check(s) failed",
+                failed
+            )));
+        }
+        Ok(())
+    }
+}
 
-        info!("Loading transcription model: {}", model_path.display());
-        let transcription_engine = TranscriptionEngine::new(&model_path)?;
+#[cfg(feature = "server")]
+impl ServeCommand {
+    async fn run(&self, data_dir: &Path) -> Result<()> {
+        let mut config = Config::load()?;
+        self.overrides.apply(&mut config);
+        let state = State::load_from_path(State::path_in(data_dir));
 
-        // Run transcription
-        info!("Running transcription...");
-        let result = transcription_engine.transcribe(&processed_samples).await?;
+        let model_manager = ModelManager::in_data_dir(data_dir)?;
+        let model_path = resolve_model(&config, &state, &model_manager)?;
+        info!("Loading transcription model: {}", model_path.display());
+        let mut engine = TranscriptionEngine::new(&model_path)?;
+        configure_engine(&mut engine, &config, &model_path);
+        engine.warm_up()?;
 
-        // Initialize output manager
-        let mut output_manager = OutputManager::new()?;
+        let addr = SocketAddr::new(self.host, self.port);
+        let processors = crate::audio::ProcessorPool::new(config.server.processor_pool_size);
+        let max_in_flight = config
+            .server
+            .max_in_flight
+            .unwrap_or_else(crate::server::default_max_in_flight);
+        crate::server::serve(
+            addr,
+            std::sync::Arc::new(engine),
+            std::sync::Arc::new(processors),
+            max_in_flight,
+        )
+        .await
+    }
+}
 
-        // Determine output settings
-        let enable_clipboard = !self.no_clipboard;
-        let enable_paste = self.paste;
-        let timestamp_format = self
-            .timestamps
-            .as_ref()
-            .map(|t| t.clone().into())
-            .unwrap_or(TimestampFormat::None);
+impl ToggleCommand {
+    #[instrument(name = "toggle", level = "debug", skip_all)]
+    async fn run(&self, data_dir: &Path, timeout: Option<u64>) -> Result<()> {
+        let mut config = self.merged_config(Config::load()?);
+        config.behavior.stop_key =
+            stop_key_under_timeout(self.stop_key, config.behavior.stop_key, timeout)?;
+        let state_path = State::path_in(data_dir);
+        let mut state = if self.no_remember {
+            State::default()
+        } else {
+            State::load_from_path(&state_path)
+        };
 
-        // Output transcript using the output manager
-        output_manager.output_transcript(
-            &result,
-            enable_clipboard,
-            enable_paste,
-            self.append.as_deref(),
-            timestamp_format,
-        )?;
+        if let Some(ref input) = self.input {
+            info!("Reading audio from {}", input.display());
+            let format = InputFormat {
+                sample_rate: self.input_sample_rate,
+                channels: self.input_channels,
+            };
+            let audio = read_audio_file(input, format)?;
+            let stats = AudioStats::from_samples(&audio.samples, audio.sample_rate, audio.channels);
+            let mut session = Session::new(data_dir);
+            let span = telemetry::run_span(&session.run_id);
+            self.transcribe_samples(&audio.samples, &stats, &config, &mut state, &mut session)
+                .instrument(span)
+                .await?;
+        } else {
+            // --repeat reads its start and stop lines from stdin by design
+            if !self.repeat {
+                check_stop_condition(io::stdin().is_terminal(), self.overrides.duration, timeout)?;
+            }
+            let mut audio_engine = AudioEngine::new();
+            audio_engine.set_preroll(Duration::from_millis(config.audio.preroll_ms));
+            audio_engine.set_buffer_frames(config.audio.buffer_frames);
+            let buffer_limit = config
+                .audio
+                .max_buffer_secs
+                .map_or(DEFAULT_BUFFER_LIMIT, Duration::from_secs);
+            audio_engine.set_buffer_limit(buffer_limit, config.audio.backpressure);
+            // Spilled audio would outlive --sensitive, and --save-audio needs the
+            // whole recording in memory anyway
+            if config.audio.spill_after_secs.is_some()
+                && (self.sensitive || self.save_audio.is_some())
+            {
+                info!("Keeping the recording in memory for --sensitive or --save-audio");
+            } else {
+                audio_engine
+                    .set_spill_after(config.audio.spill_after_secs.map(Duration::from_secs));
+            }
+            if self.auto_start {
+                audio_engine.set_auto_start(Some(
+                    self.auto_start_threshold
+                        .or(config.audio.auto_start_threshold)
+                        .unwrap_or(DEFAULT_AUTO_START_THRESHOLD),
+                ));
+            }
+            if self.repeat {
+                self.run_repeating(&mut audio_engine, &config, &mut state, data_dir)
+                    .await?;
+            } else {
+                self.run_with_backend(&mut audio_engine, &config, &mut state, data_dir)
+                    .await?;
+            }
+        }
 
-        // Debug information goes to stderr
-        debug!(
-            "Transcription completed: {} segments, {:.2}s processing time",
-            result.segments.len(),
-            result.processing_time.as_secs_f64()
-        );
+        if !self.no_remember {
+            if let Err(e) = state.save_to_path(&state_path) {
+                warn!("Failed to remember last-used device and model: {}", e);
+            }
+        }
 
-        debug!("Toggle command completed successfully");
         Ok(())
     }
+
+    /// Layer this command's flags over the loaded configuration
+    fn merged_config(&self, mut config: Config) -> Config {
+        self.overrides.apply(&mut config);
+        if let Some(stop_key) = self.stop_key {
+            config.behavior.stop_key = stop_key.into();
+        }
+        config
+    }
+
+    async fn run_with_backend<B: CaptureBackend>(
+        &self,
+        audio_engine: &mut B,
+        config: &Config,
+        state: &mut State,
+        data_dir: &Path,
+    ) -> Result<()> {
+        let mut session = Session::new(data_dir);
+        let span = telemetry::run_span(&session.run_id);
+        self.record_clip(audio_engine, config, state, &mut session)
+            .instrument(span)
+            .await
+    }
+
+    /// Record and transcribe clips until stdin reaches EOF, loading the model only once
+    async fn run_repeating<B: CaptureBackend>(
+        &self,
+        audio_engine: &mut B,
+        config: &Config,
+        state: &mut State,
+        data_dir: &Path,
+    ) -> Result<()> {
+        let mut session = Session::new(data_dir);
+        let span = telemetry::run_span(&session.run_id);
+        let input = io::BufReader::new(io::stdin());
+        self.record_clips(audio_engine, config, state, &mut session, input)
+            .instrument(span)
+            .await
+    }
+
+    /// Record a clip each time a line is read from `input`, until it reaches EOF
+    async fn record_clips<B: CaptureBackend, R: io::BufRead + Send + 'static>(
+        &self,
+        audio_engine: &mut B,
+        config: &Config,
+        state: &mut State,
+        session: &mut Session,
+        mut input: R,
+    ) -> Result<()> {
+        // Load the model up front so the first clip is as quick as the rest
+        if matches!(self.engine, EngineArg::Whisper) {
+            self.prepare_engine(config, state, session)?.warm_up()?;
+        }
+
+        loop {
+            println!("Press Enter to record, or Ctrl+D to quit...");
+            let (read, returned) = tokio::task::spawn_blocking(move || {
+                let read = input.read_line(&mut String::new());
+                (read, input)
+            })
+            .await
+            .map_err(|e| MicrodropError::Audio(format!("Failed to read input: {}", e)))?;
+            input = returned;
+            let read =
+                read.map_err(|e| MicrodropError::Audio(format!("Failed to read input: {}", e)))?;
+            if read == 0 {
+                return Ok(());
+            }
+
+            self.record_clip(audio_engine, config, state, session)
+                .await?;
+        }
+    }
+
+    async fn record_clip<B: CaptureBackend>(
+        &self,
+        audio_engine: &mut B,
+        config: &Config,
+        state: &mut State,
+        session: &mut Session,
+    ) -> Result<()> {
+        let raw_samples = self.capture(audio_engine, config, state).await?;
+        if let Some(mut spilled) = audio_engine.take_spilled() {
+            spilled.append(&raw_samples)?;
+            drop(raw_samples);
+            let format = audio_engine.get_stats(&[])?;
+            self.transcribe_spilled(&spilled, &format, config, state, session)
+                .await?;
+        } else {
+            if raw_samples.is_empty() {
+                return self.no_transcript("No audio captured".to_string());
+            }
+
+            // Get basic stats before processing
+            let raw_stats = audio_engine.get_stats(&raw_samples)?;
+
+            self.transcribe_samples(&raw_samples, &raw_stats, config, state, session)
+                .await?;
+        }
+
+        state.last_device = audio_engine.device_name();
+        Ok(())
+    }
+
+    /// Record from the selected device until the duration elapses or Enter is pressed
+    #[instrument(name = "capture", level = "debug", skip_all, fields(device, samples))]
+    async fn capture<B: CaptureBackend>(
+        &self,
+        audio_engine: &mut B,
+        config: &Config,
+        state: &State,
+    ) -> Result<Vec<f32>> {
+        info!("Starting audio capture session");
+
+        // Select audio device, falling back to the default if a remembered one is
+        // gone, or a configured one is missing with --device-fallback. A name
+        // matching several devices is still an error.
+        match (self.device_index, &config.audio.device, &state.last_device) {
+            (Some(index), _, _) => audio_engine.select_device_index(index)?,
+            (None, Some(device), _) => match audio_engine.select_device(Some(device)) {
+                Err(e @ MicrodropError::DeviceNotFound(_)) if self.device_fallback => {
+                    warn!("{}; recording from the default device instead", e);
+                    audio_engine.select_device(None)?;
+                }
+                selected => selected?,
+            },
+            (None, None, Some(remembered)) => {
+                if let Err(e) = audio_engine.select_device(Some(remembered)) {
+                    debug!("Remembered device unavailable ({}), using default", e);
+                    audio_engine.select_device(None)?;
+                }
+            }
+            (None, None, None) => audio_engine.select_device(None)?,
+        }
+
+        if let Some(device) = audio_engine.device_name() {
+            Span::current().record("device", device.as_str());
+        }
+
+        // Configure the stream
+        audio_engine.configure_stream()?;
+
+        // Start capture
+        audio_engine.start_capture()?;
+
+        // Stop after the requested duration, or wait for user input. With
+        // --auto-start the duration counts from the first speech, and waiting
+        // for it gives up after the duration too.
+        let mut heard = true;
+        if self.auto_start && !audio_engine.is_recording() {
+            println!("Waiting for speech...");
+            if let Some(seconds) = self.overrides.duration {
+                let speech = async {
+                    while !audio_engine.is_recording() {
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                    }
+                };
+                heard = tokio::time::timeout(Duration::from_secs(seconds), speech)
+                    .await
+                    .is_ok();
+                if !heard {
+                    println!("No speech heard within {} seconds", seconds);
+                }
+            }
+        }
+        if !heard {
+            // Nothing was recorded, so there is nothing to wait for
+        } else if let Some(seconds) = self.overrides.duration {
+            println!(
+                "Audio capture started. Recording for {} seconds...",
+                seconds
+            );
+            tokio::time::sleep(Duration::from_secs(seconds)).await;
+        } else {
+            let stop_key = usable_stop_key(
+                config.behavior.stop_key,
+                io::stdin().is_terminal() && console::Term::stderr().is_term(),
+            );
+            match stop_key {
+                StopKey::Enter => println!("Audio capture started. Press Enter to stop..."),
+                StopKey::Any => println!("Audio capture started. Press any key to stop..."),
+            }
+            // Wait off the runtime thread so --timeout can still fire
+            tokio::task::spawn_blocking(move || wait_for_stop_key(stop_key))
+                .await
+                .map_err(|e| MicrodropError::Audio(format!("Failed to read input: {}", e)))?
+                .map_err(|e| MicrodropError::Audio(format!("Failed to read input: {}", e)))?;
+        }
+
+        // Stop capture and get samples
+        let raw_samples = audio_engine.stop_capture()?;
+        Span::current().record("samples", raw_samples.len());
+        Ok(raw_samples)
+    }
+
+    #[instrument(
+        name = "transcribe",
+        level = "debug",
+        skip_all,
+        fields(
+            raw_samples = raw_samples.len(),
+            sample_rate = raw_stats.sample_rate,
+            channels = raw_stats.channels
+        )
+    )]
+    async fn transcribe_samples(
+        &self,
+        raw_samples: &[f32],
+        raw_stats: &AudioStats,
+        config: &Config,
+        state: &mut State,
+        session: &mut Session,
+    ) -> Result<()> {
+        // Checked before any work is done; --timestamps was merged into the config
+        let timestamp_format: TimestampFormat = config.output.timestamp_format.parse()?;
+
+        // Process audio (downmix to mono, resample to 16kHz)
+        let mut processor = AudioProcessor::new(raw_stats.sample_rate, raw_stats.channels)?;
+        processor.set_skip_silent_channels(config.audio.skip_silent_channels);
+        let processed_samples = processor.process(raw_samples)?;
+
+        if let Some(ref path) = self.save_audio {
+            let saved = if self.save_audio_raw {
+                write_wav_file(path, raw_samples, raw_stats.sample_rate, raw_stats.channels)
+            } else {
+                write_wav_file(
+                    path,
+                    &processed_samples,
+                    processor.get_output_sample_rate(),
+                    1,
+                )
+            };
+            match saved {
+                Ok(()) => info!("Saved audio to {}", path.display()),
+                Err(e) => warn!("{}", e),
+            }
+        }
+
+        if processed_samples.is_empty() {
+            return self
+                .no_transcript("No processed audio available for transcription".to_string());
+        }
+
+        let slice_offset = self.offset.unwrap_or(0.0);
+        let processed_samples = if self.offset.is_some() || self.duration_limit.is_some() {
+            slice_seconds(&processed_samples, slice_offset, self.duration_limit)?
+        } else {
+            &processed_samples[..]
+        };
+
+        let audio_duration = Duration::from_secs_f64(
+            processed_samples.len() as f64 / processor.get_output_sample_rate() as f64,
+        );
+        if self.stats {
+            let processed =
+                AudioStats::from_samples(processed_samples, processor.get_output_sample_rate(), 1);
+            eprintln!("input audio: {}\nprocessed audio: {}", raw_stats, processed);
+        }
+        if audio_duration.as_secs_f64() < config.behavior.min_duration {
+            return self.no_transcript(format!(
+                "Recording too short ({:.2}s < {:.2}s), skipping transcription",
+                audio_duration.as_secs_f64(),
+                config.behavior.min_duration
+            ));
+        }
+
+        let level = rms(processed_samples);
+        if level < config.behavior.silence_floor {
+            return self.no_transcript(format!(
+                "Captured audio was silent (RMS {:.5} < {:.5}), skipping transcription. Is the microphone muted?",
+                level, config.behavior.silence_floor
+            ));
+        }
+
+        // Optionally split at silences so each speech region is transcribed on its own
+        let regions = if config.vad.enabled {
+            let regions = speech_regions(
+                processed_samples,
+                processor.get_output_sample_rate(),
+                config.vad.threshold,
+                Duration::from_millis(config.vad.min_silence_ms),
+            );
+            if regions.is_empty() {
+                return self
+                    .no_transcript("No speech detected, skipping transcription".to_string());
+            }
+            debug!("VAD found {} speech region(s)", regions.len());
+            Some(regions)
+        } else {
+            None
+        };
+
+        let run = self
+            .run_engine(
+                processed_samples,
+                regions.as_deref(),
+                config,
+                state,
+                session,
+            )
+            .await?;
+        self.finish_transcript(
+            run,
+            config,
+            state,
+            session,
+            audio_duration,
+            slice_offset,
+            timestamp_format,
+        )
+        .await
+    }
+
+    /// Transcribe a recording spilled to disk a window at a time, so only one
+    /// window of it is in memory at once, and stitch the windows' transcripts
+    /// together where they overlap. `format` gives its sample rate and channels.
+    /// The recording is sliced and checked as in `transcribe_samples`, except
+    /// that silence and VAD are judged a window at a time.
+    #[instrument(
+        name = "transcribe_spilled",
+        level = "debug",
+        skip_all,
+        fields(samples = spilled.len())
+    )]
+    async fn transcribe_spilled(
+        &self,
+        spilled: &SpillFile,
+        format: &AudioStats,
+        config: &Config,
+        state: &mut State,
+        session: &mut Session,
+    ) -> Result<()> {
+        let timestamp_format: TimestampFormat = config.output.timestamp_format.parse()?;
+
+        let channels = format.channels.max(1) as usize;
+        let samples = |duration: Duration| {
+            (duration.as_secs_f64() * format.sample_rate as f64) as usize * channels
+        };
+        let time = |sample: usize| {
+            Duration::from_secs_f64((sample / channels) as f64 / format.sample_rate as f64)
+        };
+
+        let slice_offset = self.offset.unwrap_or(0.0);
+        let frames = seconds_range(
+            spilled.len() / channels,
+            format.sample_rate,
+            slice_offset,
+            self.duration_limit,
+        )?;
+        let slice = frames.start * channels..frames.end * channels;
+        let audio_duration = time(slice.len());
+        if audio_duration.as_secs_f64() < config.behavior.min_duration {
+            return self.no_transcript(format!(
+                "Recording too short ({:.2}s < {:.2}s), skipping transcription",
+                audio_duration.as_secs_f64(),
+                config.behavior.min_duration
+            ));
+        }
+
+        let windows =
+            overlapping_windows(slice.len(), samples(SPILL_WINDOW), samples(SPILL_OVERLAP));
+        info!(
+            "Transcribing {:.0?} of audio in {} parts",
+            audio_duration,
+            windows.len()
+        );
+
+        // Parts that are silent, or have no speech with VAD on, are skipped
+        let mut parts = Vec::with_capacity(windows.len());
+        let mut identity = None;
+        let mut loudest = 0.0f32;
+        for (index, window) in windows.iter().enumerate() {
+            debug!("Transcribing part {} of {}", index + 1, windows.len());
+            let mut processor = AudioProcessor::new(format.sample_rate, format.channels)?;
+            processor.set_skip_silent_channels(config.audio.skip_silent_channels);
+            let raw = spilled.read(slice.start + window.start..slice.start + window.end)?;
+            let processed = processor.process(&raw)?;
+
+            let level = rms(&processed);
+            loudest = loudest.max(level);
+            if level < config.behavior.silence_floor {
+                debug!(
+                    "Part {} is silent (RMS {:.5}), skipping it",
+                    index + 1,
+                    level
+                );
+                continue;
+            }
+            let regions = if config.vad.enabled {
+                let regions = speech_regions(
+                    &processed,
+                    processor.get_output_sample_rate(),
+                    config.vad.threshold,
+                    Duration::from_millis(config.vad.min_silence_ms),
+                );
+                if regions.is_empty() {
+                    debug!("No speech in part {}, skipping it", index + 1);
+                    continue;
+                }
+                Some(regions)
+            } else {
+                None
+            };
+
+            let EngineRun {
+                result,
+                model_path,
+                model,
+                threads,
+            } = self
+                .run_engine(&processed, regions.as_deref(), config, state, session)
+                .await?;
+            parts.push((time(window.start), result));
+            identity = Some((model_path, model, threads));
+        }
+        let Some((model_path, model, threads)) = identity else {
+            return self.no_transcript(if loudest < config.behavior.silence_floor {
+                format!(
+                    "Captured audio was silent (RMS {:.5} < {:.5}), skipping transcription. Is the microphone muted?",
+                    loudest, config.behavior.silence_floor
+                )
+            } else {
+                "No speech detected, skipping transcription".to_string()
+            });
+        };
+
+        let run = EngineRun {
+            result: TranscriptionResult::merge_overlapping(parts),
+            model_path,
+            model,
+            threads,
+        };
+        self.finish_transcript(
+            run,
+            config,
+            state,
+            session,
+            audio_duration,
+            slice_offset,
+            timestamp_format,
+        )
+        .await
+    }
+
+    /// Transcribe `samples`, or just their speech `regions`, with the engine
+    /// `--engine` selected
+    async fn run_engine(
+        &self,
+        samples: &[f32],
+        regions: Option<&[Range<usize>]>,
+        config: &Config,
+        state: &State,
+        session: &mut Session,
+    ) -> Result<EngineRun> {
+        Ok(match self.engine {
+            EngineArg::Whisper => {
+                self.transcribe_with_whisper(samples, regions, config, state, session)
+                    .await?
+            }
+            #[cfg(feature = "mock-engine")]
+            EngineArg::Mock => {
+                info!("Running mock transcription...");
+                let engine = session
+                    .mock_engine
+                    .get_or_insert_with(MockTranscriptionEngine::new);
+                engine.set_max_segment_len(self.segment_max_len);
+                engine.set_max_tokens(self.max_tokens);
+                engine.set_diarize(self.diarize);
+                EngineRun {
+                    result: match regions {
+                        Some(regions) => engine.transcribe_regions(samples, regions).await?,
+                        None => engine.transcribe(samples).await?,
+                    },
+                    model_path: None,
+                    model: ModelIdentity {
+                        file: "mock".to_string(),
+                        sha256: None,
+                    },
+                    threads: 1,
+                }
+            }
+        })
+    }
+
+    /// Tidy up `run`'s transcript, deliver it and report on it
+    #[allow(clippy::too_many_arguments)]
+    async fn finish_transcript(
+        &self,
+        run: EngineRun,
+        config: &Config,
+        state: &mut State,
+        session: &mut Session,
+        audio_duration: Duration,
+        slice_offset: f64,
+        timestamp_format: TimestampFormat,
+    ) -> Result<()> {
+        let mut result = run.result;
+        if self.cleanup {
+            result.apply_cleanup();
+        }
+        result.apply_replacements(&config.output.replacements);
+        if self.fail_on_empty && result.text.trim().is_empty() {
+            return Err(MicrodropError::EmptyTranscript(
+                "No speech was transcribed".to_string(),
+            ));
+        }
+
+        let delivered = self
+            .deliver_transcript(
+                &result,
+                &run.model,
+                config,
+                session,
+                audio_duration,
+                slice_offset,
+                timestamp_format,
+            )
+            .await;
+        if self.keep_partial {
+            let failure = match delivered {
+                Err(ref e) => Some(e.to_string()),
+                Ok(ref failures) if !failures.is_empty() => Some(failures.join("; ")),
+                Ok(_) => None,
+            };
+            if let Some(failure) = failure {
+                keep_transcript(&result, &session.data_dir, &failure);
+            }
+        }
+        delivered?;
+
+        if self.print_segments {
+            let _ = writeln!(session.stderr, "{}", format_segments(&result));
+        }
+
+        if self.stats {
+            eprintln!(
+                "{}",
+                format_performance_summary(
+                    &run.model.file,
+                    run.threads,
+                    audio_duration,
+                    result.processing_time,
+                )
+            );
+        }
+
+        // Debug information goes to stderr
+        debug!(
+            "Transcription completed: {} segments, {:.2}s processing time",
+            result.segments.len(),
+            result.processing_time.as_secs_f64()
+        );
+
+        if run.model_path.is_some() {
+            state.last_model = run.model_path;
+        }
+
+        debug!("Toggle command completed successfully");
+        Ok(())
+    }
+
+    /// Report why there's no transcript, failing with it under --fail-on-empty
+    fn no_transcript(&self, reason: String) -> Result<()> {
+        if self.fail_on_empty {
+            return Err(MicrodropError::EmptyTranscript(reason));
+        }
+        println!("{}", reason);
+        Ok(())
+    }
+
+    /// Send `result` to stdout and the configured sinks, returning the sinks
+    /// that failed without stopping the others
+    #[allow(clippy::too_many_arguments)]
+    async fn deliver_transcript(
+        &self,
+        result: &TranscriptionResult,
+        model: &ModelIdentity,
+        config: &Config,
+        session: &mut Session,
+        audio_duration: Duration,
+        slice_offset: f64,
+        timestamp_format: TimestampFormat,
+    ) -> Result<Vec<String>> {
+        // Initialize output manager, once per session
+        let output_manager = match session.output {
+            Some(ref mut output_manager) => output_manager,
+            None => {
+                let mut output_manager = OutputManager::new_with_capabilities(
+                    config.output.has_sink(OutputSink::Clipboard),
+                    config.output.has_sink(OutputSink::Paste),
+                )?;
+                output_manager.set_paste_delay(Duration::from_millis(config.output.paste_delay_ms));
+                output_manager.set_restore_clipboard(config.output.restore_clipboard);
+                if config.output.include_run_id {
+                    output_manager.set_run_id(Some(session.run_id.clone()));
+                }
+                output_manager.set_clipboard_sentences(config.output.clipboard_sentences);
+                output_manager.set_append_encoding(
+                    config.output.append_line_ending,
+                    config.output.append_bom,
+                );
+                output_manager.set_stdout_enabled(
+                    config.output.has_sink(OutputSink::Stdout) && !self.no_stdout,
+                );
+                output_manager.set_sensitive(self.sensitive);
+                if let Some(ref format) = self.stdout_timestamps {
+                    output_manager.set_stdout_format(format.clone().into());
+                }
+                output_manager.set_template(self.output_template.clone());
+                output_manager.set_locate_appends(self.print_append_location);
+                if let Some(stdout) = session.stdout.take() {
+                    output_manager.set_stdout(stdout);
+                }
+                session.output.insert(output_manager)
+            }
+        };
+
+        // Determine output settings
+        let enable_clipboard = config.output.has_sink(OutputSink::Clipboard);
+        let enable_paste = config.output.has_sink(OutputSink::Paste);
+
+        // Timestamps stay relative to the start of this clip's full recording
+        output_manager.set_timestamp_offset(self.timestamps_offset.unwrap_or(0.0) + slice_offset);
+
+        // Output transcript using the output manager
+        output_manager.set_model(Some(model.clone()));
+        let appended = output_manager
+            .output_transcript(
+                result,
+                audio_duration,
+                enable_clipboard,
+                enable_paste,
+                config.output.append_file.as_deref(),
+                timestamp_format.clone(),
+            )
+            .await?;
+        let mut failures = output_manager.take_failures();
+
+        if let Some(ref template) = config.output.append_json_file {
+            if let Err(e) = output_manager.append_json_record(result, model, template) {
+                warn!("Failed to append JSON record: {}", e);
+                failures.push(format!("JSON record: {}", e));
+            }
+        }
+
+        if let Some(ref template) = config.output.replace_file {
+            if let Err(e) = output_manager.replace_file_contents(
+                result,
+                audio_duration,
+                template,
+                &timestamp_format,
+            ) {
+                warn!("Failed to replace file contents: {}", e);
+                failures.push(format!("replace: {}", e));
+            }
+        }
+
+        if config.output.has_sink(OutputSink::Notify) {
+            let command = config
+                .output
+                .notify_command
+                .as_deref()
+                .unwrap_or(notify::DEFAULT_COMMAND);
+            // Notification daemons may log what they show, so secrets stay out
+            let sent = Notifier::new(command).and_then(|notifier| {
+                if self.sensitive {
+                    notifier.transcript_ready(audio_duration)
+                } else {
+                    notifier.transcript(&result.text, audio_duration, config.output.notify_words)
+                }
+            });
+            if let Err(e) = sent {
+                warn!("Failed to send notification: {}", e);
+                failures.push(format!("notification: {}", e));
+            }
+        }
+
+        if self.print_append_location {
+            if let Some(location) = appended {
+                let _ = writeln!(session.stderr, "{}", location);
+            }
+        }
+
+        Ok(failures)
+    }
+
+    async fn transcribe_with_whisper(
+        &self,
+        samples: &[f32],
+        regions: Option<&[Range<usize>]>,
+        config: &Config,
+        state: &State,
+        session: &mut Session,
+    ) -> Result<EngineRun> {
+        let transcription_engine = self.prepare_engine(config, state, session)?;
+
+        info!("Running transcription...");
+        let result = match regions {
+            Some(regions) => {
+                transcription_engine
+                    .transcribe_regions(samples, regions)
+                    .await?
+            }
+            None => transcription_engine.transcribe(samples).await?,
+        };
+
+        Ok(EngineRun {
+            result,
+            model_path: Some(transcription_engine.model_path().to_path_buf()),
+            model: transcription_engine.model_identity(),
+            threads: transcription_engine.threads(),
+        })
+    }
+
+    /// Resolve the model and return the session's engine for it, loading the
+    /// model only if it isn't already. The engine is configured from flags,
+    /// config and registry defaults.
+    fn prepare_engine<'s>(
+        &self,
+        config: &Config,
+        state: &State,
+        session: &'s mut Session,
+    ) -> Result<&'s TranscriptionEngine> {
+        let model_manager = ModelManager::in_data_dir(&session.data_dir)?;
+        let model_path = resolve_model(config, state, &model_manager)?;
+
+        let mut transcription_engine = match session.engine.take() {
+            Some(mut engine) => {
+                engine.reload(&model_path)?;
+                engine
+            }
+            None => {
+                info!("Loading transcription model: {}", model_path.display());
+                TranscriptionEngine::new(&model_path)?
+            }
+        };
+        transcription_engine.set_max_tokens(self.max_tokens);
+        transcription_engine.set_max_segment_len(self.segment_max_len);
+        transcription_engine.set_diarize(self.diarize);
+        configure_engine(&mut transcription_engine, config, &model_path);
+
+        Ok(session.engine.insert(transcription_engine))
+    }
+}
+
+/// Pick the model to load: the configured one, else the last one used, else
+/// whatever is installed
+fn resolve_model(config: &Config, state: &State, model_manager: &ModelManager) -> Result<PathBuf> {
+    let model_path = if let Some(ref model) = config.model.default_model {
+        // User specified a model path or name
+        crate::transcribe::resolve_model_path(
+            model_manager,
+            model,
+            config.model.default_quantization.as_deref(),
+        )?
+    } else if let Some(remembered) = state.last_model.as_ref().filter(|p| p.is_file()) {
+        debug!("Using remembered model: {}", remembered.display());
+        remembered.clone()
+    } else {
+        // Try to find a default model
+        find_default_model(model_manager).ok_or_else(|| {
+            MicrodropError::ModelLoad(
+                "No model specified and no default model found. \
+                 Please specify a model with --model <path> or install a model with 'microdrop model install <model>'"
+                    .to_string(),
+            )
+        })?
+    };
+    Ok(model_path)
+}
+
+/// Error unless a recording can be stopped: by `--duration`, by `--timeout`,
+/// or by pressing Enter at a terminal. Piped stdin that never closes would
+/// otherwise record forever.
+fn check_stop_condition(
+    interactive: bool,
+    duration: Option<u64>,
+    timeout: Option<u64>,
+) -> Result<()> {
+    if interactive || duration.is_some() || timeout.is_some() {
+        return Ok(());
+    }
+    Err(MicrodropError::Audio(
+        "stdin is not a terminal, so recording can't be stopped with Enter. \
+         Pass --duration <SECONDS> to record for a fixed time, or --timeout <SECONDS> \
+         to give up after one"
+            .to_string(),
+    ))
+}
+
+/// The stop key to use under `--timeout`, whose exit would leave a terminal
+/// in the raw mode a single keypress is read in. `--stop-key any` is refused;
+/// `any` from the config falls back to Enter.
+fn stop_key_under_timeout(
+    flag: Option<StopKeyArg>,
+    configured: StopKey,
+    timeout: Option<u64>,
+) -> Result<StopKey> {
+    if timeout.is_none() || configured != StopKey::Any {
+        return Ok(configured);
+    }
+    if flag.is_some() {
+        return Err(MicrodropError::Config(
+            "--stop-key any can't be combined with --timeout, which would leave \
+             the terminal in raw mode"
+                .to_string(),
+        ));
+    }
+    warn!("Reading a single keypress isn't supported with --timeout; press Enter to stop instead");
+    Ok(StopKey::Enter)
+}
+
+/// `requested`, unless single keypresses can't be read without a `terminal`
+fn usable_stop_key(requested: StopKey, terminal: bool) -> StopKey {
+    if requested == StopKey::Any && !terminal {
+        warn!("Reading a single keypress needs a terminal; press Enter to stop instead");
+        return StopKey::Enter;
+    }
+    requested
+}
+
+/// Block until `stop_key` is pressed. A single key is read in raw mode, so
+/// nothing is echoed and no newline reaches the terminal.
+fn wait_for_stop_key(stop_key: StopKey) -> io::Result<()> {
+    match stop_key {
+        StopKey::Enter => io::stdin().read_line(&mut String::new()).map(drop),
+        StopKey::Any => console::Term::stderr().read_key().map(drop),
+    }
+}
+
+/// Apply transcription settings from flags and config, which win over the
+/// defaults the registry ships for `model_path`
+fn configure_engine(engine: &mut TranscriptionEngine, config: &Config, model_path: &Path) {
+    let defaults = cached_model_defaults(model_path).unwrap_or_default();
+    if let Some(language) = config.model.language.clone().or(defaults.language) {
+        engine.set_language(language);
+    }
+    if let Some(threads) = config.model.threads.or(defaults.threads) {
+        engine.set_threads(threads);
+    }
+    let sampling = config
+        .model
+        .beam_size
+        .map(|beam_size| Sampling::BeamSearch { beam_size })
+        .or(defaults.sampling);
+    if let Some(sampling) = sampling {
+        engine.set_sampling(sampling);
+    }
+    if let Some(suppress) = config.model.suppress_blank {
+        engine.set_suppress_blank(suppress);
+    }
+    if let Some(suppress) = config.model.suppress_non_speech {
+        engine.set_suppress_non_speech(suppress);
+    }
+    engine.set_thresholds(config.model.thresholds());
+    engine.set_timeout(
+        config
+            .model
+            .inference_timeout_secs
+            .map(Duration::from_secs_f64),
+    );
+}
+
+/// Save a transcript that `failure` kept from its sinks, for `--keep-partial`
+fn keep_transcript(result: &TranscriptionResult, data_dir: &Path, failure: &str) {
+    match save_recovery(data_dir, &result.text, Local::now()) {
+        Ok(path) => eprintln!(
+            "Delivering the transcript failed ({}); saved it to {}",
+            failure,
+            path.display()
+        ),
+        Err(e) => warn!("Failed to save the transcript for recovery: {}", e),
+    }
+}
+
+/// Engine and output state kept across the clips of one `toggle` invocation
+struct Session {
+    /// Identifies this run in logs and JSON output
+    run_id: String,
+    /// Where models are looked up
+    data_dir: PathBuf,
+    engine: Option<TranscriptionEngine>,
+    /// Engine for `--engine mock`, kept so its canned responses take turns
+    /// across clips
+    #[cfg(feature = "mock-engine")]
+    mock_engine: Option<MockTranscriptionEngine>,
+    output: Option<OutputManager>,
+    /// Where transcripts are printed instead of standard output, handed to
+    /// the output manager when it's created
+    stdout: Option<Box<dyn Write + Send>>,
+    /// Where per-clip reports such as segments and append locations are printed
+    stderr: Box<dyn Write + Send>,
+}
+
+impl Session {
+    fn new(data_dir: &Path) -> Self {
+        Self {
+            run_id: telemetry::next_run_id(),
+            data_dir: data_dir.to_path_buf(),
+            engine: None,
+            #[cfg(feature = "mock-engine")]
+            mock_engine: None,
+            output: None,
+            stdout: None,
+            stderr: Box::new(io::stderr()),
+        }
+    }
+}
+
+/// Transcript produced by whichever engine `--engine` selected
+struct EngineRun {
+    result: TranscriptionResult,
+    /// Model file used, if the engine loads one
+    model_path: Option<PathBuf>,
+    /// How JSON output names the model
+    model: ModelIdentity,
+    threads: i32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::MockCaptureBackend;
+
+    fn toggle_command(args: &[&str]) -> ToggleCommand {
+        let cli = Cli::try_parse_from(["microdrop", "toggle"].iter().chain(args)).unwrap();
+        match cli.command {
+            Commands::Toggle(command) => command,
+            _ => panic!("Expected toggle command"),
+        }
+    }
+
+    /// Data directory for tests, which never contains models
+    fn test_data_dir() -> PathBuf {
+        std::env::temp_dir().join("microdrop_test_cli_data")
+    }
+
+    fn sine_wave(sample_rate: u32, seconds: f32) -> Vec<f32> {
+        (0..(sample_rate as f32 * seconds) as usize)
+            .map(|i| (2.0 * std::f32::consts::PI * 440.0 * i as f32 / sample_rate as f32).sin())
+            .collect()
+    }
+
+    /// Toggle command recording one clip for the mock engine, with only the
+    /// sinks `args` turn on
+    #[cfg(feature = "mock-engine")]
+    fn mock_toggle_command(args: &[&str]) -> ToggleCommand {
+        let mut argv = vec![
+            "--duration",
+            "0",
+            "--engine",
+            "mock",
+            "--no-clipboard",
+            "--no-paste",
+        ];
+        argv.extend_from_slice(args);
+        toggle_command(&argv)
+    }
+
+    /// Session whose mock engine answers with `responses` in turn, printing
+    /// transcripts nowhere
+    #[cfg(feature = "mock-engine")]
+    fn mock_session(responses: Vec<TranscriptionResult>) -> Session {
+        let mut session = Session::new(&test_data_dir());
+        session.mock_engine = Some(MockTranscriptionEngine::with_responses(responses));
+        session.stdout = Some(Box::new(io::sink()));
+        session
+    }
+
+    /// Canned transcript with a one-second segment for each of `segments`
+    #[cfg(feature = "mock-engine")]
+    fn mock_result(segments: &[&str]) -> TranscriptionResult {
+        TranscriptionResult {
+            text: segments.concat(),
+            segments: segments
+                .iter()
+                .zip(0..)
+                .map(|(text, second)| crate::transcribe::TranscriptionSegment {
+                    start: Duration::from_secs(second),
+                    end: Duration::from_secs(second + 1),
+                    text: text.to_string(),
+                    avg_logprob: None,
+                    no_speech_prob: None,
+                    speaker: None,
+                })
+                .collect(),
+            language: Some("en".to_string()),
+            processing_time: Duration::from_millis(50),
+            partial: false,
+        }
+    }
+
+    /// In-memory stand-in for a terminal stream, readable after the run
+    #[cfg(feature = "mock-engine")]
+    #[derive(Clone, Default)]
+    struct SharedBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    #[cfg(feature = "mock-engine")]
+    impl SharedBuffer {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    #[cfg(feature = "mock-engine")]
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Record one clip of tone with `command`, transcribing it in `session`
+    #[cfg(feature = "mock-engine")]
+    async fn record_mock_clip(command: &ToggleCommand, session: &mut Session) -> Result<()> {
+        let config = command.merged_config(Config::default());
+        let mut backend = MockCaptureBackend::new(sine_wave(16000, 1.0), 16000, 1);
+        command
+            .record_clip(&mut backend, &config, &mut State::default(), session)
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_timeout_expires() {
+        let err = with_timeout(Some(0), std::future::pending())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MicrodropError::Timeout(0)));
+        assert_eq!(err.exit_code(), 124);
+
+        with_timeout(Some(5), async { Ok(()) }).await.unwrap();
+        with_timeout(None, async { Ok(()) }).await.unwrap();
+
+        // The option is global, so it can follow the subcommand
+        let cli = Cli::try_parse_from(["microdrop", "model", "list", "--timeout", "30"]).unwrap();
+        assert_eq!(cli.timeout, Some(30));
+    }
+
+    #[test]
+    fn test_data_dir_flag() {
+        let cli =
+            Cli::try_parse_from(["microdrop", "model", "list", "--data-dir", "/portable"]).unwrap();
+        assert_eq!(cli.data_dir, Some(PathBuf::from("/portable")));
+
+        let cli = Cli::try_parse_from(["microdrop", "--data-dir", "/portable", "toggle"]).unwrap();
+        assert_eq!(cli.data_dir, Some(PathBuf::from("/portable")));
+    }
+
+    #[test]
+    fn test_input_format_flags_require_input() {
+        let result = Cli::try_parse_from(["microdrop", "toggle", "--input-sample-rate", "16000"]);
+        assert!(result.is_err());
+
+        let command = toggle_command(&[
+            "--input",
+            "recording.pcm",
+            "--input-sample-rate",
+            "16000",
+            "--input-channels",
+            "2",
+        ]);
+        assert_eq!(command.input_sample_rate, Some(16000));
+        assert_eq!(command.input_channels, Some(2));
+    }
+
+    #[test]
+    fn test_output_template_parsing() {
+        let command = toggle_command(&["--output-template", "{date} {text} ({language})"]);
+        assert_eq!(
+            command.output_template.unwrap().to_string(),
+            "{date} {text} ({language})"
+        );
+
+        let err = Cli::try_parse_from([
+            "microdrop",
+            "toggle",
+            "--output-template",
+            "{text} {speaker}",
+        ])
+        .unwrap_err();
+        assert!(err.to_string().contains("Unknown placeholder '{speaker}'"));
+    }
+
+    #[test]
+    fn test_timestamps_offset_parsing() {
+        let command = toggle_command(&["--timestamps-offset", "-2.5"]);
+        assert_eq!(command.timestamps_offset, Some(-2.5));
+
+        let command = toggle_command(&["--timestamps-offset", "12.75"]);
+        assert_eq!(command.timestamps_offset, Some(12.75));
+
+        assert!(
+            Cli::try_parse_from(["microdrop", "toggle", "--timestamps-offset", "inf"]).is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_toggle_with_empty_capture() {
+        let command = toggle_command(&["--duration", "0"]);
+        let config = command.merged_config(Config::default());
+        let mut backend = MockCaptureBackend::new(Vec::new(), 16000, 1);
+
+        command
+            .run_with_backend(
+                &mut backend,
+                &config,
+                &mut State::default(),
+                &test_data_dir(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(backend.capture_count(), 1);
+        assert!(!backend.is_capturing());
+    }
+
+    #[tokio::test]
+    async fn test_toggle_reports_stream_errors() {
+        let command = toggle_command(&["--duration", "0"]);
+        let config = command.merged_config(Config::default());
+        let mut backend = MockCaptureBackend::new(sine_wave(16000, 1.0), 16000, 1);
+        backend.fail_stream("the input device was disconnected");
+        let mut state = State::default();
+
+        let err = command
+            .run_with_backend(&mut backend, &config, &mut state, &test_data_dir())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, MicrodropError::Audio(_)));
+        assert!(err.to_string().contains("device was disconnected"));
+        assert_eq!(state, State::default());
+    }
+
+    #[tokio::test]
+    async fn test_toggle_processes_captured_audio_before_model_load() {
+        let command = toggle_command(&[
+            "--duration",
+            "0",
+            "--device",
+            "mock-mic",
+            "--model",
+            "/nonexistent/microdrop-model.bin",
+        ]);
+        let config = command.merged_config(Config::default());
+        let mut backend = MockCaptureBackend::new(sine_wave(48000, 1.0), 48000, 1);
+        let mut state = State::default();
+
+        let err = command
+            .run_with_backend(&mut backend, &config, &mut state, &test_data_dir())
+            .await
+            .unwrap_err();
+
+        // Capture and preprocessing succeeded; the pipeline stopped at model resolution
+        assert!(matches!(err, MicrodropError::ModelLoad(_)));
+        assert_eq!(backend.selected_device(), Some("mock-mic"));
+        assert_eq!(backend.capture_count(), 1);
+        // Nothing is remembered from a failed run
+        assert_eq!(state, State::default());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_config_timestamp_format_is_rejected() {
+        let command = toggle_command(&["--duration", "0"]);
+        let mut config = Config::default();
+        config.output.timestamp_format = "verbose".to_string();
+        let config = command.merged_config(config);
+        let mut backend = MockCaptureBackend::new(sine_wave(16000, 1.0), 16000, 1);
+
+        let err = command
+            .run_with_backend(
+                &mut backend,
+                &config,
+                &mut State::default(),
+                &test_data_dir(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MicrodropError::Config(_)));
+
+        // A --timestamps flag replaces the config value
+        let config = toggle_command(&["--timestamps", "srt"]).merged_config(config);
+        assert_eq!(
+            config
+                .output
+                .timestamp_format
+                .parse::<TimestampFormat>()
+                .unwrap(),
+            TimestampFormat::Srt
+        );
+    }
+
+    #[tokio::test]
+    async fn test_silent_recording_skips_transcription() {
+        let command = toggle_command(&[
+            "--duration",
+            "0",
+            "--model",
+            "/nonexistent/microdrop-model.bin",
+        ]);
+        let mut config = command.merged_config(Config::default());
+        let mut backend = MockCaptureBackend::new(vec![0.0; 16000], 16000, 1);
+        let mut state = State::default();
+
+        // The model is never loaded, so the missing file doesn't matter
+        command
+            .run_with_backend(&mut backend, &config, &mut state, &test_data_dir())
+            .await
+            .unwrap();
+        assert_eq!(state.last_model, None);
+
+        // With the floor disabled, silence goes on to model resolution
+        config.behavior.silence_floor = 0.0;
+        let err = command
+            .run_with_backend(&mut backend, &config, &mut state, &test_data_dir())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MicrodropError::ModelLoad(_)));
+    }
+
+    #[tokio::test]
+    async fn test_fail_on_empty_rejects_silent_recording() {
+        let command = toggle_command(&["--duration", "0", "--fail-on-empty"]);
+        let config = command.merged_config(Config::default());
+        let mut backend = MockCaptureBackend::new(vec![0.0; 16000], 16000, 1);
+
+        let err = command
+            .run_with_backend(
+                &mut backend,
+                &config,
+                &mut State::default(),
+                &test_data_dir(),
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, MicrodropError::EmptyTranscript(_)),
+            "{:?}",
+            err
+        );
+        assert_eq!(err.exit_code(), 66);
+        assert!(err.to_string().contains("silent"));
+
+        assert!(
+            Cli::try_parse_from(["microdrop", "toggle", "--fail-on-empty", "--repeat"]).is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_short_recording_skips_transcription() {
+        let command = toggle_command(&[
+            "--duration",
+            "0",
+            "--model",
+            "/nonexistent/microdrop-model.bin",
+        ]);
+        let config = command.merged_config(Config::default());
+        let mut backend = MockCaptureBackend::new(sine_wave(16000, 0.1), 16000, 1);
+        let mut state = State::default();
+
+        // The model is never loaded, so the missing file doesn't matter
+        command
+            .run_with_backend(&mut backend, &config, &mut state, &test_data_dir())
+            .await
+            .unwrap();
+        assert_eq!(state.last_model, None);
+
+        let command = toggle_command(&[
+            "--duration",
+            "0",
+            "--min-duration",
+            "0",
+            "--model",
+            "/nonexistent/microdrop-model.bin",
+        ]);
+        let config = command.merged_config(Config::default());
+        let err = command
+            .run_with_backend(&mut backend, &config, &mut state, &test_data_dir())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MicrodropError::ModelLoad(_)));
+
+        assert!(Cli::try_parse_from(["microdrop", "toggle", "--min-duration", "-1"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_auto_start_gives_up_without_speech() {
+        let command = toggle_command(&["--duration", "0", "--auto-start", "--fail-on-empty"]);
+        let config = command.merged_config(Config::default());
+        let mut backend = MockCaptureBackend::new(sine_wave(16000, 1.0), 16000, 1);
+        backend.hear_no_speech();
+        let mut state = State::default();
+
+        let err = command
+            .run_with_backend(&mut backend, &config, &mut state, &test_data_dir())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MicrodropError::EmptyTranscript(_)), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_spilled_recording_is_transcribed_from_disk() {
+        let command = toggle_command(&[
+            "--duration",
+            "0",
+            "--model",
+            "/nonexistent/microdrop-model.bin",
+        ]);
+        let config = command.merged_config(Config::default());
+        let mut backend = MockCaptureBackend::new(sine_wave(16000, 1.2), 16000, 1);
+        backend.set_spill_after(Some(16000));
+        let mut state = State::default();
+
+        // Only 0.2 s is left in memory, too short on its own, but the whole
+        // recording reaches the engine from the spill file
+        let err = command
+            .run_with_backend(&mut backend, &config, &mut state, &test_data_dir())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MicrodropError::ModelLoad(_)));
+        assert!(backend.take_spilled().is_none());
+
+        // Silent parts are skipped, so a silent recording never loads the model
+        let command = toggle_command(&[
+            "--duration",
+            "0",
+            "--fail-on-empty",
+            "--model",
+            "/nonexistent/microdrop-model.bin",
+        ]);
+        let mut backend = MockCaptureBackend::new(vec![0.0; 16000 * 2], 16000, 1);
+        backend.set_spill_after(Some(16000));
+        let err = command
+            .run_with_backend(&mut backend, &config, &mut state, &test_data_dir())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MicrodropError::EmptyTranscript(_)), "{}", err);
+
+        // --offset past the end is rejected as for recordings kept in memory
+        let command = toggle_command(&["--duration", "0", "--offset", "5"]);
+        let mut backend = MockCaptureBackend::new(vec![0.0; 16000 * 2], 16000, 1);
+        backend.set_spill_after(Some(16000));
+        let err = command
+            .run_with_backend(&mut backend, &config, &mut state, &test_data_dir())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("beyond the end"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_toggle_selects_device_by_index() {
+        let command = toggle_command(&["--duration", "0", "--device-index", "2"]);
+        let config = command.merged_config(Config::default());
+        let mut backend = MockCaptureBackend::new(Vec::new(), 16000, 1);
+        let mut state = State {
+            last_device: Some("remembered-mic".to_string()),
+            last_model: None,
+        };
+
+        command
+            .run_with_backend(&mut backend, &config, &mut state, &test_data_dir())
+            .await
+            .unwrap();
+        assert_eq!(backend.selected_device(), Some("mock #2"));
+
+        assert!(Cli::try_parse_from([
+            "microdrop",
+            "toggle",
+            "--device-index",
+            "0",
+            "--device",
+            "mic"
+        ])
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_toggle_device_fallback() {
+        let mut backend = MockCaptureBackend::new(Vec::new(), 16000, 1);
+        backend.unplug("usb-mic");
+        let mut state = State::default();
+
+        // Strict by default: a missing configured device fails the run
+        let command = toggle_command(&["--duration", "0", "--device", "usb-mic"]);
+        let config = command.merged_config(Config::default());
+        let err = command
+            .run_with_backend(&mut backend, &config, &mut state, &test_data_dir())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("'usb-mic' not found"));
+        assert_eq!(backend.capture_count(), 0);
+
+        let command = toggle_command(&[
+            "--duration",
+            "0",
+            "--device",
+            "usb-mic",
+            "--device-fallback",
+        ]);
+        let config = command.merged_config(Config::default());
+        command
+            .run_with_backend(&mut backend, &config, &mut state, &test_data_dir())
+            .await
+            .unwrap();
+        assert_eq!(backend.selected_device(), Some("mock"));
+        assert_eq!(backend.capture_count(), 1);
+
+        // An ambiguous name isn't a missing device, so it still fails
+        backend.set_devices(&["USB Mic (left)", "USB Mic (right)"]);
+        let command = toggle_command(&[
+            "--duration",
+            "0",
+            "--device",
+            "usb mic",
+            "--device-fallback",
+        ]);
+        let config = command.merged_config(Config::default());
+        let err = command
+            .run_with_backend(&mut backend, &config, &mut state, &test_data_dir())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("ambiguous"));
+        assert_eq!(backend.capture_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_toggle_defaults_to_remembered_device() {
+        let command = toggle_command(&["--duration", "0"]);
+        let config = command.merged_config(Config::default());
+        let mut backend = MockCaptureBackend::new(Vec::new(), 16000, 1);
+        let mut state = State {
+            last_device: Some("remembered-mic".to_string()),
+            last_model: None,
+        };
+
+        command
+            .run_with_backend(&mut backend, &config, &mut state, &test_data_dir())
+            .await
+            .unwrap();
+
+        assert_eq!(backend.selected_device(), Some("remembered-mic"));
+    }
+
+    #[test]
+    fn test_cli_flags_override_config() {
+        let command = toggle_command(&["--device", "cli-mic", "--no-clipboard"]);
+        let mut config = Config::default();
+        config.audio.device = Some("config-mic".to_string());
+        config.model.default_model = Some("base.en".to_string());
+
+        let config = command.merged_config(config);
+
+        assert_eq!(config.audio.device, Some("cli-mic".to_string()));
+        assert_eq!(config.model.default_model, Some("base.en".to_string()));
+        assert!(!config.output.has_sink(OutputSink::Clipboard));
+    }
+
+    #[test]
+    fn test_sink_flags_enable_and_disable_config() {
+        let mut config = Config::default();
+        config.output.set_sink(OutputSink::Paste, true);
+
+        let merged = toggle_command(&[]).merged_config(config.clone());
+        assert!(merged.output.has_sink(OutputSink::Paste));
+        assert!(merged.output.has_sink(OutputSink::Clipboard));
+
+        let merged =
+            toggle_command(&["--no-paste", "--no-clipboard"]).merged_config(config.clone());
+        assert!(!merged.output.has_sink(OutputSink::Paste));
+        assert!(!merged.output.has_sink(OutputSink::Clipboard));
+
+        config.output.set_sink(OutputSink::Clipboard, false);
+        let merged = toggle_command(&["--clipboard"]).merged_config(config);
+        assert!(merged.output.has_sink(OutputSink::Clipboard));
+
+        // The last of a --foo/--no-foo pair wins
+        let command = toggle_command(&["--paste", "--no-paste"]);
+        assert_eq!(
+            flag_pair(command.overrides.paste, command.overrides.no_paste),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn test_generic_sink_flags() {
+        let merged = toggle_command(&["--sink", "notify,paste", "--no-sink", "stdout"])
+            .merged_config(Config::default());
+        assert_eq!(
+            merged.output.sinks,
+            vec![OutputSink::Clipboard, OutputSink::Notify, OutputSink::Paste]
+        );
+
+        // --no-sink wins over the other sink flags
+        let merged =
+            toggle_command(&["--paste", "--no-sink", "paste"]).merged_config(Config::default());
+        assert!(!merged.output.has_sink(OutputSink::Paste));
+
+        assert!(Cli::try_parse_from(["microdrop", "toggle", "--sink", "socket"]).is_err());
+    }
+
+    #[test]
+    fn test_restore_clipboard_flags() {
+        let mut config = Config::default();
+        let merged =
+            toggle_command(&["--paste", "--restore-clipboard"]).merged_config(config.clone());
+        assert!(merged.output.restore_clipboard);
+
+        config.output.restore_clipboard = true;
+        let merged = toggle_command(&[]).merged_config(config.clone());
+        assert!(merged.output.restore_clipboard);
+        let merged = toggle_command(&["--no-restore-clipboard"]).merged_config(config);
+        assert!(!merged.output.restore_clipboard);
+    }
+
+    #[test]
+    fn test_clipboard_sentences_flags() {
+        let mut config = Config::default();
+        assert!(
+            !toggle_command(&[])
+                .merged_config(config.clone())
+                .output
+                .clipboard_sentences
+        );
+        let merged = toggle_command(&["--clipboard-sentences"]).merged_config(config.clone());
+        assert!(merged.output.clipboard_sentences);
+
+        config.output.clipboard_sentences = true;
+        let merged = toggle_command(&["--no-clipboard-sentences"]).merged_config(config);
+        assert!(!merged.output.clipboard_sentences);
+    }
+
+    #[test]
+    fn test_slice_flags() {
+        let cmd = toggle_command(&["--offset", "30", "--duration-limit", "12.5"]);
+        assert_eq!(cmd.offset, Some(30.0));
+        assert_eq!(cmd.duration_limit, Some(12.5));
+
+        assert!(Cli::try_parse_from(["microdrop", "toggle", "--offset", "-1"]).is_err());
+        assert!(Cli::try_parse_from(["microdrop", "toggle", "--duration-limit", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_transcription_parameter_flags() {
+        let cmd = toggle_command(&["--language", "de", "--threads", "8", "--beam-size", "5"]);
+        let config = cmd.merged_config(Config::default());
+        assert_eq!(config.model.language, Some("de".to_string()));
+        assert_eq!(config.model.threads, Some(8));
+        assert_eq!(config.model.beam_size, Some(5));
+
+        assert!(Cli::try_parse_from(["microdrop", "toggle", "--threads", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_suppression_flags() {
+        // Unset flags leave Whisper's defaults in place
+        let config = toggle_command(&[]).merged_config(Config::default());
+        assert_eq!(config.model.suppress_blank, None);
+        assert_eq!(config.model.suppress_non_speech, None);
+
+        let cmd = toggle_command(&["--no-suppress-blank", "--suppress-non-speech"]);
+        let config = cmd.merged_config(Config::default());
+        assert_eq!(config.model.suppress_blank, Some(false));
+        assert_eq!(config.model.suppress_non_speech, Some(true));
+    }
+
+    #[test]
+    fn test_language_flag_is_validated() {
+        let config = toggle_command(&["--language", "auto"]).merged_config(Config::default());
+        assert_eq!(config.model.language, Some("auto".to_string()));
+
+        let err = Cli::try_parse_from(["microdrop", "toggle", "--language", "english"])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("did you mean 'en' (english)?"));
+    }
+
+    #[test]
+    fn test_threshold_flags() {
+        let config = toggle_command(&[]).merged_config(Config::default());
+        assert_eq!(config.model.thresholds(), DecodeThresholds::default());
+
+        let cmd = toggle_command(&[
+            "--entropy-thold",
+            "2.8",
+            "--logprob-thold",
+            "-0.5",
+            "--no-speech-thold",
+            "0.3",
+        ]);
+        let config = cmd.merged_config(Config::default());
+        assert_eq!(
+            config.model.thresholds(),
+            DecodeThresholds {
+                entropy: Some(2.8),
+                logprob: Some(-0.5),
+                no_speech: Some(0.3),
+            }
+        );
+
+        for args in [
+            ["--entropy-thold", "0"],
+            ["--logprob-thold", "0.5"],
+            ["--no-speech-thold", "1.2"],
+            ["--no-speech-thold", "NaN"],
+        ] {
+            let mut argv = vec!["microdrop", "toggle"];
+            argv.extend(args);
+            assert!(
+                Cli::try_parse_from(argv).is_err(),
+                "{:?} should be rejected",
+                args
+            );
+        }
+    }
+
+    #[cfg(feature = "mock-engine")]
+    #[tokio::test]
+    async fn test_cleanup_flag() {
+        let dir = tempfile::tempdir().unwrap();
+        let transcript = dir.path().join("transcript.txt");
+        let append = transcript.to_str().unwrap();
+        let response = mock_result(&["so we're done", " see you monday"]);
+
+        let command = mock_toggle_command(&["--append", append]);
+        record_mock_clip(&command, &mut mock_session(vec![response.clone()]))
+            .await
+            .unwrap();
+        let command = mock_toggle_command(&["--append", append, "--cleanup"]);
+        record_mock_clip(&command, &mut mock_session(vec![response]))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&transcript).unwrap(),
+            "so we're done see you monday\nSo we're done see you monday.\n"
+        );
+    }
+
+    #[cfg(feature = "mock-engine")]
+    #[tokio::test]
+    async fn test_print_append_location_flag() {
+        let dir = tempfile::tempdir().unwrap();
+        let transcript = dir.path().join("transcript.txt");
+        let append = transcript.to_str().unwrap();
+        let response = mock_result(&["first clip"]);
+
+        let mut session = mock_session(vec![response.clone()]);
+        let stderr = SharedBuffer::default();
+        session.stderr = Box::new(stderr.clone());
+        record_mock_clip(&mock_toggle_command(&["--append", append]), &mut session)
+            .await
+            .unwrap();
+        assert_eq!(stderr.contents(), "");
+
+        let mut session = mock_session(vec![response]);
+        let stderr = SharedBuffer::default();
+        session.stderr = Box::new(stderr.clone());
+        let command = mock_toggle_command(&["--append", append, "--print-append-location"]);
+        record_mock_clip(&command, &mut session).await.unwrap();
+        assert_eq!(stderr.contents(), format!("{}:2\n", append));
+    }
+
+    #[cfg(feature = "mock-engine")]
+    #[tokio::test]
+    async fn test_print_segments_flag() {
+        let response = mock_result(&[" Hello there.", " How are you?"]);
+
+        let mut session = mock_session(vec![response.clone()]);
+        let stderr = SharedBuffer::default();
+        session.stderr = Box::new(stderr.clone());
+        record_mock_clip(&mock_toggle_command(&[]), &mut session)
+            .await
+            .unwrap();
+        assert_eq!(stderr.contents(), "");
+
+        let mut session = mock_session(vec![response]);
+        let stderr = SharedBuffer::default();
+        session.stderr = Box::new(stderr.clone());
+        record_mock_clip(&mock_toggle_command(&["--print-segments"]), &mut session)
+            .await
+            .unwrap();
+        assert_eq!(
+            stderr.contents(),
+            "#0 0.000s -> 1.000s: Hello there.\n#1 1.000s -> 2.000s: How are you?\n"
+        );
+    }
+
+    #[test]
+    fn test_print_segments_conflicts_with_sensitive() {
+        // Segment text is the transcript, which --sensitive keeps off the terminal
+        assert!(
+            Cli::try_parse_from(["microdrop", "toggle", "--print-segments", "--sensitive"])
+                .is_err()
+        );
+    }
+
+    #[cfg(feature = "mock-engine")]
+    #[tokio::test]
+    async fn test_no_stdout_flag() {
+        let dir = tempfile::tempdir().unwrap();
+        let transcript = dir.path().join("transcript.txt");
+        let append = transcript.to_str().unwrap();
+        let response = mock_result(&["Hello there."]);
+
+        let mut session = mock_session(vec![response.clone()]);
+        let stdout = SharedBuffer::default();
+        session.stdout = Some(Box::new(stdout.clone()));
+        record_mock_clip(&mock_toggle_command(&["--append", append]), &mut session)
+            .await
+            .unwrap();
+        assert_eq!(stdout.contents(), "Hello there.\n");
+
+        for flag in ["--no-stdout", "--output-none"] {
+            let mut session = mock_session(vec![response.clone()]);
+            let stdout = SharedBuffer::default();
+            session.stdout = Some(Box::new(stdout.clone()));
+            let command = mock_toggle_command(&["--append", append, flag]);
+            record_mock_clip(&command, &mut session).await.unwrap();
+            assert_eq!(stdout.contents(), "", "{}", flag);
+        }
+
+        // The other sinks still ran every time
+        assert_eq!(
+            std::fs::read_to_string(&transcript).unwrap(),
+            "Hello there.\n".repeat(3)
+        );
+    }
+
+    #[cfg(feature = "mock-engine")]
+    #[tokio::test]
+    async fn test_sensitive_flag() {
+        let dir = tempfile::tempdir().unwrap();
+        let transcript = dir.path().join("transcript.txt");
+        let archive = dir.path().join("archive.jsonl");
+        let sinks = [
+            "--append",
+            transcript.to_str().unwrap(),
+            "--append-json",
+            archive.to_str().unwrap(),
+        ];
+        let response = mock_result(&["my password is hunter2"]);
+
+        let mut session = mock_session(vec![response.clone()]);
+        let stdout = SharedBuffer::default();
+        session.stdout = Some(Box::new(stdout.clone()));
+        let command = mock_toggle_command(&[&sinks[..], &["--sensitive"]].concat());
+        record_mock_clip(&command, &mut session).await.unwrap();
+        assert_eq!(stdout.contents(), "");
+        assert!(!transcript.exists());
+        assert!(!archive.exists());
+
+        // The same run without the flag leaves a log entry and an archived record
+        let mut session = mock_session(vec![response]);
+        record_mock_clip(&mock_toggle_command(&sinks), &mut session)
+            .await
+            .unwrap();
+        assert!(std::fs::read_to_string(&transcript)
+            .unwrap()
+            .contains("hunter2"));
+        assert!(std::fs::read_to_string(&archive)
+            .unwrap()
+            .contains("hunter2"));
+    }
+
+    #[cfg(feature = "mock-engine")]
+    #[tokio::test]
+    async fn test_repeat_flag() {
+        let command = mock_toggle_command(&["--repeat"]);
+        let config = command.merged_config(Config::default());
+        let mut backend = MockCaptureBackend::new(sine_wave(16000, 1.0), 16000, 1);
+        let mut session = mock_session(vec![mock_result(&["First."]), mock_result(&["Second."])]);
+        let stdout = SharedBuffer::default();
+        session.stdout = Some(Box::new(stdout.clone()));
+
+        // One clip per Enter, until EOF
+        command
+            .record_clips(
+                &mut backend,
+                &config,
+                &mut State::default(),
+                &mut session,
+                io::Cursor::new("\n\n"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(backend.capture_count(), 2);
+        assert_eq!(stdout.contents(), "First.\nSecond.\n");
+    }
+
+    #[test]
+    fn test_repeat_conflicts_with_input() {
+        // Repeating only makes sense for microphone capture
+        assert!(
+            Cli::try_parse_from(["microdrop", "toggle", "--repeat", "--input", "a.wav"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_segment_max_len_flag() {
+        assert_eq!(
+            toggle_command(&["--segment-max-len", "42"]).segment_max_len,
+            Some(42)
+        );
+        assert_eq!(toggle_command(&[]).segment_max_len, None);
+        assert!(Cli::try_parse_from(["microdrop", "toggle", "--segment-max-len", "0"]).is_err());
+    }
+
+    #[cfg(feature = "mock-engine")]
+    #[tokio::test]
+    async fn test_diarize_flag() {
+        let response = mock_result(&[" How was the trip?", " Long, but worth it."]);
+
+        let mut session = mock_session(vec![response.clone()]);
+        let stdout = SharedBuffer::default();
+        session.stdout = Some(Box::new(stdout.clone()));
+        let command = mock_toggle_command(&["--stdout-timestamps", "srt"]);
+        record_mock_clip(&command, &mut session).await.unwrap();
+        assert!(!stdout.contents().contains("[Speaker"));
+
+        let mut session = mock_session(vec![response]);
+        let stdout = SharedBuffer::default();
+        session.stdout = Some(Box::new(stdout.clone()));
+        let command = mock_toggle_command(&["--stdout-timestamps", "srt", "--diarize"]);
+        record_mock_clip(&command, &mut session).await.unwrap();
+        let srt = stdout.contents();
+        assert!(srt.contains("[Speaker 0] How was the trip?"), "{}", srt);
+        assert!(srt.contains("[Speaker 1] Long, but worth it."), "{}", srt);
+    }
+
+    #[cfg(feature = "mock-engine")]
+    #[tokio::test]
+    async fn test_max_tokens_flag() {
+        let response = mock_result(&["Please call me back", " when you land."]);
+
+        let mut session = mock_session(vec![response.clone()]);
+        let stdout = SharedBuffer::default();
+        session.stdout = Some(Box::new(stdout.clone()));
+        record_mock_clip(&mock_toggle_command(&[]), &mut session)
+            .await
+            .unwrap();
+        assert_eq!(stdout.contents(), "Please call me back when you land.\n");
+
+        // The engine stops each segment at the limit
+        let mut session = mock_session(vec![response]);
+        let stdout = SharedBuffer::default();
+        session.stdout = Some(Box::new(stdout.clone()));
+        record_mock_clip(&mock_toggle_command(&["--max-tokens", "2"]), &mut session)
+            .await
+            .unwrap();
+        assert_eq!(stdout.contents(), "Please call when you\n");
+    }
+
+    #[test]
+    fn test_max_tokens_must_be_positive() {
+        assert!(Cli::try_parse_from(["microdrop", "toggle", "--max-tokens", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_auto_start_flags() {
+        let command = toggle_command(&["--auto-start", "--auto-start-threshold", "0.05"]);
+        assert!(command.auto_start);
+        assert_eq!(command.auto_start_threshold, Some(0.05));
+        assert!(!toggle_command(&[]).auto_start);
+
+        let parse = |args: &[&str]| {
+            Cli::try_parse_from(["microdrop", "toggle"].iter().chain(args)).map(|_| ())
+        };
+        assert!(parse(&["--auto-start-threshold", "0.05"]).is_err());
+        assert!(parse(&["--auto-start", "--auto-start-threshold", "0"]).is_err());
+        assert!(parse(&["--auto-start", "--auto-start-threshold", "1.5"]).is_err());
+        assert!(parse(&["--auto-start", "--input", "a.wav"]).is_err());
+    }
+
+    #[test]
+    fn test_inference_timeout_flag() {
+        let cmd = toggle_command(&["--inference-timeout", "2.5"]);
+        assert_eq!(cmd.overrides.inference_timeout, Some(2.5));
+        assert!(Cli::try_parse_from(["microdrop", "toggle", "--inference-timeout", "0"]).is_err());
+
+        let mut config = Config::default();
+        let overridden = cmd.overrides.apply(&mut config);
+        assert_eq!(overridden, vec!["model.inference_timeout_secs"]);
+        assert_eq!(config.model.inference_timeout_secs, Some(2.5));
+    }
+
+    #[test]
+    fn test_preroll_flag() {
+        let command = toggle_command(&["--preroll-ms", "300"]);
+        let config = command.merged_config(Config::default());
+        assert_eq!(config.audio.preroll_ms, 300);
+
+        let command = toggle_command(&[]);
+        let mut config = Config::default();
+        config.audio.preroll_ms = 250;
+        assert_eq!(command.merged_config(config).audio.preroll_ms, 250);
+
+        let conflicting = ["--input", "a.wav", "--preroll-ms", "300"];
+        assert!(Cli::try_parse_from(["microdrop", "toggle"].iter().chain(&conflicting)).is_err());
+    }
+
+    #[test]
+    fn test_stop_key() {
+        assert!(toggle_command(&[]).stop_key.is_none());
+        assert!(matches!(
+            toggle_command(&["--stop-key", "any"]).stop_key,
+            Some(StopKeyArg::Any)
+        ));
+        assert_eq!(usable_stop_key(StopKey::Any, true), StopKey::Any);
+        assert_eq!(usable_stop_key(StopKey::Any, false), StopKey::Enter);
+        assert_eq!(usable_stop_key(StopKey::Enter, true), StopKey::Enter);
+    }
+
+    #[test]
+    fn test_check_stop_condition() {
+        assert!(check_stop_condition(true, None, None).is_ok());
+        assert!(check_stop_condition(false, Some(5), None).is_ok());
+        assert!(check_stop_condition(false, None, Some(60)).is_ok());
+        let err = check_stop_condition(false, None, None)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("--duration"));
+        assert!(err.contains("--timeout"));
+    }
+
+    #[test]
+    fn test_stop_key_under_timeout() {
+        assert_eq!(
+            stop_key_under_timeout(Some(StopKeyArg::Any), StopKey::Any, None).unwrap(),
+            StopKey::Any
+        );
+        assert_eq!(
+            stop_key_under_timeout(None, StopKey::Any, Some(5)).unwrap(),
+            StopKey::Enter
+        );
+        assert_eq!(
+            stop_key_under_timeout(None, StopKey::Enter, Some(5)).unwrap(),
+            StopKey::Enter
+        );
+        assert!(stop_key_under_timeout(Some(StopKeyArg::Any), StopKey::Any, Some(5)).is_err());
+    }
+
+    #[test]
+    fn test_save_audio_flags() {
+        let command = toggle_command(&["--save-audio", "clip.wav", "--save-audio-raw"]);
+        assert_eq!(command.save_audio, Some(PathBuf::from("clip.wav")));
+        assert!(command.save_audio_raw);
+        assert!(!toggle_command(&["--save-audio", "clip.wav"]).save_audio_raw);
+
+        let toggle =
+            |args: &[&str]| Cli::try_parse_from(["microdrop", "toggle"].iter().chain(args));
+        assert!(toggle(&["--save-audio-raw"]).is_err());
+        assert!(toggle(&["--save-audio", "clip.wav", "--repeat"]).is_err());
+        assert!(toggle(&["--save-audio", "clip.wav", "--sensitive"]).is_err());
+    }
+
+    #[test]
+    fn test_buffer_frames_flag() {
+        let command = toggle_command(&["--buffer-frames", "256"]);
+        let config = command.merged_config(Config::default());
+        assert_eq!(config.audio.buffer_frames, Some(256));
+
+        let toggle =
+            |args: &[&str]| Cli::try_parse_from(["microdrop", "toggle"].iter().chain(args));
+        assert!(toggle(&["--buffer-frames", "0"]).is_err());
+        assert!(toggle(&["--input", "a.wav", "--buffer-frames", "256"]).is_err());
+    }
+
+    #[test]
+    fn test_model_install_flags() {
+        let cli = Cli::try_parse_from(["microdrop", "model", "install", "--all"]).unwrap();
+        match cli.command {
+            Commands::Model(ModelCommand {
+                command: ModelSubcommand::Install(command),
+            }) => {
+                assert!(command.all);
+                assert!(!command.force);
+                assert!(!command.no_verify);
+                assert!(command.model.is_none());
+            }
+            _ => panic!("Expected model install command"),
+        }
+
+        let cli = Cli::try_parse_from([
+            "microdrop",
+            "model",
+            "install",
+            "--all",
+            "--max-concurrent-downloads",
+            "1",
+            "--rate-limit",
+            "500000",
+        ]);
+        assert!(matches!(
+            cli.unwrap().command,
+            Commands::Model(ModelCommand {
+                command: ModelSubcommand::Install(ModelInstallCommand {
+                    max_concurrent_downloads: Some(1),
+                    rate_limit: Some(500000),
+                    ..
+                }),
+            })
+        ));
+
+        let cli = Cli::try_parse_from(["microdrop", "model", "install", "tiny.en", "--force"]);
+        assert!(matches!(
+            cli.unwrap().command,
+            Commands::Model(ModelCommand {
+                command: ModelSubcommand::Install(ModelInstallCommand { force: true, .. }),
+            })
+        ));
+
+        let cli = Cli::try_parse_from(["microdrop", "model", "install", "tiny.en", "--no-verify"]);
+        assert!(matches!(
+            cli.unwrap().command,
+            Commands::Model(ModelCommand {
+                command: ModelSubcommand::Install(ModelInstallCommand {
+                    no_verify: true,
+                    ..
+                }),
+            })
+        ));
+
+        assert!(Cli::try_parse_from(["microdrop", "model", "install"]).is_err());
+        assert!(
+            Cli::try_parse_from(["microdrop", "model", "install", "tiny.en", "--all"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_model_install_from_url_flags() {
+        let digest = "AB".repeat(32);
+        let cli = Cli::try_parse_from([
+            "microdrop",
+            "model",
+            "install",
+            "--url",
+            "https://example.com/finetune.bin",
+            "--name",
+            "finetune",
+            "--sha256",
+            &digest,
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Model(ModelCommand {
+                command: ModelSubcommand::Install(command),
+            }) => {
+                assert_eq!(
+                    command.url.as_deref(),
+                    Some("https://example.com/finetune.bin")
+                );
+                assert_eq!(command.name.as_deref(), Some("finetune"));
+                assert_eq!(command.sha256, Some("ab".repeat(32)));
+                assert!(command.model.is_none());
+            }
+            _ => panic!("Expected model install command"),
+        }
+
+        let install = |args: &[&str]| {
+            Cli::try_parse_from(["microdrop", "model", "install"].iter().chain(args))
+        };
+        // --name is required, and --url replaces a registry model
+        assert!(install(&["--url", "https://example.com/m.bin"]).is_err());
+        assert!(install(&[
+            "tiny.en",
+            "--url",
+            "https://example.com/m.bin",
+            "--name",
+            "m"
+        ])
+        .is_err());
+        assert!(install(&["tiny.en", "--name", "m"]).is_err());
+        assert!(install(&["--url", "ftp://example.com/m.bin", "--name", "m"]).is_err());
+        assert!(install(&["--url", "https://example.com/m.bin", "--name", "../m"]).is_err());
+        assert!(install(&[
+            "--url",
+            "https://example.com/m.bin",
+            "--name",
+            "m",
+            "--sha256",
+            "abc"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_bench_transcribe_flags() {
+        let cli = Cli::try_parse_from(["microdrop", "bench-transcribe"]).unwrap();
+        match cli.command {
+            Commands::BenchTranscribe(command) => {
+                assert_eq!(command.runs, 5);
+                assert_eq!(command.sample_secs, 10.0);
+                assert!(command.input.is_none());
+            }
+            _ => panic!("Expected bench-transcribe command"),
+        }
+
+        let cli = Cli::try_parse_from([
+            "microdrop",
+            "bench-transcribe",
+            "--runs",
+            "20",
+            "--threads",
+            "2",
+            "--model",
+            "tiny.en",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::BenchTranscribe(command) => {
+                assert_eq!(command.runs, 20);
+                assert_eq!(command.overrides.threads, Some(2));
+            }
+            _ => panic!("Expected bench-transcribe command"),
+        }
+
+        let bench = |args: &[&str]| {
+            Cli::try_parse_from(["microdrop", "bench-transcribe"].iter().chain(args))
+        };
+        assert!(bench(&["--runs", "0"]).is_err());
+        assert!(bench(&["--sample-secs", "0"]).is_err());
+        assert!(bench(&["--input", "a.wav", "--sample-secs", "5"]).is_err());
+    }
+
+    #[test]
+    fn test_compare_models_flags() {
+        let cli = Cli::try_parse_from([
+            "microdrop",
+            "compare-models",
+            "tiny.en",
+            "base.en",
+            "--input",
+            "a.wav",
+            "--threads",
+            "2",
+        ])
+        .unwrap();
+        assert!(!cli.json_output());
+        match cli.command {
+            Commands::CompareModels(command) => {
+                assert_eq!(command.models, ["tiny.en", "base.en"]);
+                assert_eq!(command.input, PathBuf::from("a.wav"));
+                assert_eq!(command.overrides.threads, Some(2));
+            }
+            _ => panic!("Expected compare-models command"),
+        }
+
+        let compare =
+            |args: &[&str]| Cli::try_parse_from(["microdrop", "compare-models"].iter().chain(args));
+        assert!(
+            compare(&["tiny.en", "base.en", "--input", "a.wav", "--json"])
+                .unwrap()
+                .json_output()
+        );
+        assert!(compare(&["tiny.en", "--input", "a.wav"]).is_err());
+        assert!(compare(&["tiny.en", "base.en", "small.en", "--input", "a.wav"]).is_err());
+        assert!(compare(&["tiny.en", "base.en"]).is_err());
+        assert!(
+            compare(&["tiny.en", "base.en", "--input", "a.wav", "--model", "small.en"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_json_output_modes() {
+        let json_output = |args: &[&str]| Cli::try_parse_from(args).unwrap().json_output();
+        assert!(json_output(&["microdrop", "model", "list", "--json"]));
+        assert!(json_output(&[
+            "microdrop",
+            "toggle",
+            "--stdout-timestamps",
+            "json"
+        ]));
+        assert!(!json_output(&["microdrop", "model", "list"]));
+        assert!(!json_output(&[
+            "microdrop",
+            "toggle",
+            "--timestamps",
+            "json"
+        ]));
+        assert!(!json_output(&["microdrop", "doctor"]));
+    }
+
+    #[test]
+    fn test_model_list_json() {
+        let cli = Cli::try_parse_from(["microdrop", "model", "list", "--json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Model(ModelCommand {
+                command: ModelSubcommand::List(ModelListCommand {
+                    json: true,
+                    sort: ModelSortArg::Name,
+                }),
+            })
+        ));
+
+        let info = |name: &str| -> ModelInfo {
+            serde_json::from_value(serde_json::json!({
+                "name": name,
+                "size": "39 MB",
+                "quantization": "None",
+                "url": format!("https://example.com/ggml-{}.bin", name),
+                "checksum": { "algo": "blake3", "value": "def456" },
+                "filename": format!("ggml-{}.bin", name),
+            }))
+            .unwrap()
+        };
+        let cached = vec![
+            (
+                CachedModel {
+                    info: info("tiny.en"),
+                    path: PathBuf::from("/models/ggml-tiny.en.bin"),
+                    cached_at: std::time::UNIX_EPOCH,
+                    size_bytes: 77_691_713,
+                },
+                Ok(VerifyStatus::Valid),
+            ),
+            (
+                CachedModel {
+                    info: info("base.en"),
+                    path: PathBuf::from("/models/ggml-base.en.bin"),
+                    cached_at: std::time::UNIX_EPOCH,
+                    size_bytes: 147_964_211,
+                },
+                Err(MicrodropError::ModelLoad("unreadable".to_string())),
+            ),
+        ];
+        let available = vec![info("tiny.en"), info("small.en")];
+
+        let json: serde_json::Value =
+            serde_json::from_str(&model_list_json(cached, available)).unwrap();
+        assert_eq!(json["cached"][0]["info"]["name"], "tiny.en");
+        assert_eq!(json["cached"][0]["path"], "/models/ggml-tiny.en.bin");
+        assert_eq!(json["cached"][0]["status"], "valid");
+        assert_eq!(json["cached"][0]["size_bytes"], 77_691_713);
+        assert!(json["cached"][0].get("error").is_none());
+        assert!(json["cached"][1]["status"].is_null());
+        assert!(json["cached"][1]["error"]
+            .as_str()
+            .unwrap()
+            .contains("unreadable"));
+        assert_eq!(json["available"][0]["name"], "tiny.en");
+        assert_eq!(json["available"][0]["path"], "/models/ggml-tiny.en.bin");
+        assert!(json["available"][1]["path"].is_null());
+    }
+
+    #[test]
+    fn test_model_list_sort() {
+        let cached = |name: &str, size_bytes: u64, age_secs: u64| CachedModel {
+            info: serde_json::from_value(serde_json::json!({
+                "name": name,
+                "size": "unknown",
+                "quantization": "None",
+                "url": "local",
+                "checksum": "unknown",
+                "filename": format!("ggml-{}.bin", name),
+            }))
+            .unwrap(),
+            path: PathBuf::from(format!("/models/ggml-{}.bin", name)),
+            cached_at: std::time::UNIX_EPOCH + Duration::from_secs(1_000_000 - age_secs),
+            size_bytes,
+        };
+        let sorted = |sort: ModelSortArg| -> Vec<String> {
+            let mut models = vec![
+                cached("small.en", 487_601_967, 60),
+                cached("base.en", 147_964_211, 3600),
+                cached("tiny.en", 77_691_713, 0),
+            ];
+            sort.sort(&mut models, |model| model);
+            models.into_iter().map(|model| model.info.name).collect()
+        };
+
+        assert_eq!(
+            sorted(ModelSortArg::Name),
+            ["base.en", "small.en", "tiny.en"]
+        );
+        assert_eq!(
+            sorted(ModelSortArg::Size),
+            ["small.en", "base.en", "tiny.en"]
+        );
+        assert_eq!(
+            sorted(ModelSortArg::Date),
+            ["tiny.en", "small.en", "base.en"]
+        );
+
+        let cli = Cli::try_parse_from(["microdrop", "model", "list", "--sort", "size"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Model(ModelCommand {
+                command: ModelSubcommand::List(ModelListCommand {
+                    sort: ModelSortArg::Size,
+                    ..
+                }),
+            })
+        ));
+        assert!(Cli::try_parse_from(["microdrop", "model", "list", "--sort", "age"]).is_err());
+    }
+
+    #[test]
+    fn test_model_gc_flags() {
+        let cli = Cli::try_parse_from(["microdrop", "model", "gc"]).unwrap();
+        let Commands::Model(ModelCommand {
+            command: ModelSubcommand::Gc(command),
+        }) = cli.command
+        else {
+            panic!("expected model gc");
+        };
+        assert!(!command.yes);
+        assert_eq!(command.older_than_hours, 24);
+
+        let cli = Cli::try_parse_from([
+            "microdrop",
+            "model",
+            "gc",
+            "--yes",
+            "--older-than-hours",
+            "2",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Model(ModelCommand {
+                command: ModelSubcommand::Gc(ModelGcCommand {
+                    yes: true,
+                    older_than_hours: 2,
+                }),
+            })
+        ));
+    }
+
+    #[test]
+    fn test_model_verify_command() {
+        let cli = Cli::try_parse_from(["microdrop", "model", "verify"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Model(ModelCommand {
+                command: ModelSubcommand::Verify,
+            })
+        ));
+    }
+
+    #[test]
+    fn test_render_config_with_sources() {
+        let cli =
+            Cli::try_parse_from(["microdrop", "config", "show", "--sources", "--no-clipboard"])
+                .unwrap();
+        let Commands::Config(ConfigCommand {
+            command: ConfigSubcommand::Show { sources, overrides },
+        }) = cli.command
+        else {
+            panic!("Expected config show command");
+        };
+        assert!(sources);
+
+        let mut config = Config::default();
+        config.audio.device = Some("file-mic".to_string());
+        let mut config_sources = ConfigSources::default();
+        config_sources.set("audio.device", ConfigSource::File);
+        for key in overrides.apply(&mut config) {
+            config_sources.set(key, ConfigSource::Cli);
+        }
+
+        let rendered = render_config(&config, Some(&config_sources));
+        let line = |key: &str| {
+            rendered
+                .lines()
+                .find(|l| l.starts_with(&format!("{} ", key)))
+                .unwrap()
+                .to_string()
+        };
+        assert!(line("audio.device").ends_with("= \"file-mic\"  # file"));
+        assert!(line("output.sinks").ends_with("= [\"stdout\"]  # cli"));
+        assert!(line("output.paste_delay_ms").ends_with("= 50  # default"));
+
+        let plain = render_config(&config, None);
+        assert!(!plain.contains('#'));
+    }
 }
@@ -1,5 +1,7 @@
 //! Configuration loading and merging primitives.
 
+use std::collections::BTreeMap;
+use std::fmt;
 use std::path::{Path, PathBuf};
 use std::fs;
 
@@ -7,9 +9,13 @@ use dirs;
 use serde::{Deserialize, Serialize};
 use tracing::debug;
 
+use crate::audio::{BackpressurePolicy, DEFAULT_BUFFER_LIMIT, DEFAULT_POOL_SIZE};
+use crate::notify::Notifier;
+use crate::output::{LineEnding, OutputSink};
+use crate::transcribe::{validate_language, validate_replacements, DecodeThresholds};
 use crate::{MicrodropError, Result};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
     pub audio: AudioConfig,
@@ -19,17 +25,44 @@ pub struct Config {
     pub output: OutputConfig,
     #[serde(default)]
     pub behavior: BehaviorConfig,
+    #[serde(default)]
+    pub vad: VadConfig,
+    #[serde(default)]
+    pub server: ServerConfig,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AudioConfig {
     /// Preferred audio input device name (None = system default)
     pub device: Option<String>,
     /// Maximum recording duration in seconds (None = unlimited)
     pub max_duration: Option<u64>,
+    /// Audio kept from just before capture starts, in milliseconds (0 = disabled)
+    pub preroll_ms: u64,
+    /// Leave channels that carry only silence or a noise floor out of the mono
+    /// downmix, e.g. unplugged inputs on a multi-channel interface
+    pub skip_silent_channels: bool,
+    /// Longest recording kept in memory, in seconds (None = 30 minutes)
+    pub max_buffer_secs: Option<u64>,
+    /// Move recordings longer than this to a temporary file, in seconds, and
+    /// transcribe them a few minutes at a time, for multi-hour sessions. Must
+    /// be below `max_buffer_secs` (None = keep recordings in memory).
+    pub spill_after_secs: Option<u64>,
+    /// What to do once a recording outgrows `max_buffer_secs`: "grow" (default)
+    /// allocates as audio arrives and then drops the oldest audio, "drop_oldest"
+    /// does the same in a buffer reserved up front, and "drop_newest" stops recording
+    pub backpressure: BackpressurePolicy,
+    /// RMS level (0-1) at which `--auto-start` begins recording; raise it in
+    /// noisy rooms (None = 0.02)
+    pub auto_start_threshold: Option<f32>,
+    /// Frames per capture callback; lower values cut latency, higher ones
+    /// avoid overruns (None = backend default)
+    pub buffer_frames: Option<u32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ModelConfig {
     /// Default model name or path
     pub default_model: Option<String>,
@@ -37,56 +70,177 @@ pub struct ModelConfig {
     pub default_quantization: Option<String>,
     /// Directory for cached models (None = default ~/.local/share/microdrop/models)
     pub cache_dir: Option<PathBuf>,
+    /// Spoken language code (None = the model's default, else "en")
+    pub language: Option<String>,
+    /// Inference threads (None = the model's default, else up to 4)
+    pub threads: Option<u32>,
+    /// Use beam search with this many beams (None = the model's default, else greedy)
+    pub beam_size: Option<u32>,
+    /// Suppress blank output at the start of a segment (None = Whisper's default, on)
+    pub suppress_blank: Option<bool>,
+    /// Suppress non-speech tokens such as "[Music]" or "(coughs)", which curbs
+    /// hallucinations on noisy recordings (None = Whisper's default, off)
+    pub suppress_non_speech: Option<bool>,
+    /// Retry segments whose token entropy is below this, catching repetition
+    /// loops; raise it to catch more (None = Whisper's default, 2.4)
+    pub entropy_thold: Option<f32>,
+    /// Retry segments whose mean log-probability is below this; raise it toward
+    /// 0 to retry more low-confidence text (None = Whisper's default, -1.0)
+    pub logprob_thold: Option<f32>,
+    /// Drop segments as silence above this no-speech probability; lower it to
+    /// cut hallucinations, raise it if quiet speech is dropped (None = 0.6)
+    pub no_speech_thold: Option<f32>,
+    /// Stop inference after this many seconds and keep the segments finished
+    /// so far, marked partial (None = unlimited)
+    pub inference_timeout_secs: Option<f64>,
+    /// Models downloaded at once (None = 3)
+    pub max_concurrent_downloads: Option<usize>,
+    /// Combined download bandwidth limit in bytes per second (None = unlimited)
+    pub download_rate_limit: Option<u64>,
+    /// URL of a JSON model registry (`{"models": [...]}`) to use instead of the
+    /// builtin list; the last copy fetched is used while it can't be reached
+    pub registry_url: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct OutputConfig {
-    /// Enable clipboard by default
-    pub enable_clipboard: bool,
-    /// Enable paste by default
-    pub enable_paste: bool,
-    /// Default timestamp format
+    /// Where transcripts go: any of "stdout", "clipboard", "paste" and "notify".
+    /// "notify" runs `notify_command`, or notify-send when that is unset.
+    pub sinks: Vec<OutputSink>,
+    /// Deprecated alias that adds (true) or removes (false) "clipboard" in `sinks`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_clipboard: Option<bool>,
+    /// Deprecated alias that adds (true) or removes (false) "paste" in `sinks`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_paste: Option<bool>,
+    /// Timestamp format for the clipboard, paste and append sinks: "none",
+    /// "simple", "detailed", "srt", "vtt", "json" or "markdown"
     pub timestamp_format: String,
-    /// Default file to append transcripts to
+    /// Default file to append transcripts to (date specifiers like %Y-%m-%d are expanded)
     pub append_file: Option<PathBuf>,
-    /// Command to run for notifications
+    /// File to append one JSON record per transcript to (date specifiers are expanded)
+    pub append_json_file: Option<PathBuf>,
+    /// Label JSON output (`--stdout-timestamps json` and `append_json_file`)
+    /// with the run ID its log lines are tagged with
+    pub include_run_id: bool,
+    /// File rewritten with only the latest transcript, e.g. for an overlay to
+    /// watch. Formatted like `append_file` and date specifiers are expanded.
+    pub replace_file: Option<PathBuf>,
+    /// Line ending for `append_file`: "lf" (default) or "crlf"
+    pub append_line_ending: LineEnding,
+    /// Start a new `append_file` with a UTF-8 byte order mark, for tools that need one
+    pub append_bom: bool,
+    /// Command to run for notifications, e.g. "notify-send". It is passed a
+    /// title and a preview of the transcript as its last two arguments. Setting
+    /// it turns on the "notify" sink unless `sinks` is given.
     pub notify_command: Option<String>,
+    /// Words of the transcript shown in the notification preview
+    pub notify_words: usize,
+    /// Delay between filling the clipboard and sending the paste keystroke (milliseconds)
+    pub paste_delay_ms: u64,
+    /// Put back whatever was on the clipboard once a paste has gone through.
+    /// Only applies when the transcript isn't also copied to the clipboard.
+    pub restore_clipboard: bool,
+    /// Copy each sentence to the clipboard in turn, so a clipboard history
+    /// keeps them as separate entries. Without a clipboard history to keep
+    /// them the whole transcript is copied at once.
+    pub clipboard_sentences: bool,
+    /// Words and phrases always written a certain way, e.g. `github = "GitHub"`.
+    /// Keys match whole words regardless of case; the longest match wins.
+    pub replacements: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct BehaviorConfig {
     /// Enable audio feedback cues
     pub audio_cues: bool,
     /// Minimum silence duration before stopping auto-record (seconds)
     pub silence_threshold: Option<f64>,
+    /// Recordings shorter than this are not transcribed (seconds, 0 = disabled)
+    pub min_duration: f64,
+    /// Recordings whose RMS level (0.0-1.0) stays below this are treated as
+    /// silence, e.g. a muted mic, and not transcribed (0 = disabled)
+    pub silence_floor: f32,
+    /// How a recording is stopped at the terminal: "enter" (default) or "any"
+    /// for a single keypress, which falls back to Enter under --timeout
+    pub stop_key: StopKey,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            audio: AudioConfig::default(),
-            model: ModelConfig::default(),
-            output: OutputConfig::default(),
-            behavior: BehaviorConfig::default(),
+/// Keypress that stops a recording without `--duration`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StopKey {
+    /// A full line, so the newline lands in the terminal
+    #[default]
+    Enter,
+    /// Any single key, read in raw mode without echo
+    Any,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VadConfig {
+    /// Split recordings at silences and transcribe each speech region separately
+    pub enabled: bool,
+    /// RMS level (0.0-1.0) above which a 30 ms frame counts as speech
+    pub threshold: f32,
+    /// Silence at least this long separates two speech regions (milliseconds)
+    pub min_silence_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// Audio converters kept warm between requests, shared by all input
+    /// formats (0 = build one per request)
+    pub processor_pool_size: usize,
+    /// Requests read, queued or transcribed at once before new ones get a 503
+    /// (None = as many as a quarter of the available memory allows, up to 8)
+    pub max_in_flight: Option<usize>,
+}
+
+impl OutputConfig {
+    pub fn has_sink(&self, sink: OutputSink) -> bool {
+        self.sinks.contains(&sink)
+    }
+
+    /// Add `sink` to the configured sinks, or remove it when `enabled` is false
+    pub fn set_sink(&mut self, sink: OutputSink, enabled: bool) {
+        if !enabled {
+            self.sinks.retain(|&s| s != sink);
+        } else if !self.has_sink(sink) {
+            self.sinks.push(sink);
         }
     }
-}
 
-impl Default for AudioConfig {
-    fn default() -> Self {
-        Self {
-            device: None,
-            max_duration: None,
+    /// Fold the deprecated `enable_clipboard`/`enable_paste` switches into `sinks`,
+    /// returning whether there were any
+    fn apply_deprecated_aliases(&mut self) -> bool {
+        let aliases = [
+            (OutputSink::Clipboard, self.enable_clipboard.take()),
+            (OutputSink::Paste, self.enable_paste.take()),
+        ];
+        let mut applied = false;
+        for (sink, enabled) in aliases {
+            if let Some(enabled) = enabled {
+                debug!("output.enable_{} is deprecated; list sinks in output.sinks instead", sink);
+                self.set_sink(sink, enabled);
+                applied = true;
+            }
         }
+        applied
     }
 }
 
-impl Default for ModelConfig {
-    fn default() -> Self {
-        Self {
-            default_model: None,
-            default_quantization: None,
-            cache_dir: None,
+impl ModelConfig {
+    /// The decoder fallback thresholds set in this section
+    pub fn thresholds(&self) -> DecodeThresholds {
+        DecodeThresholds {
+            entropy: self.entropy_thold,
+            logprob: self.logprob_thold,
+            no_speech: self.no_speech_thold,
         }
     }
 }
@@ -94,11 +248,22 @@ impl Default for ModelConfig {
 impl Default for OutputConfig {
     fn default() -> Self {
         Self {
-            enable_clipboard: true,
-            enable_paste: false,
+            sinks: vec![OutputSink::Stdout, OutputSink::Clipboard],
+            enable_clipboard: None,
+            enable_paste: None,
             timestamp_format: "none".to_string(),
             append_file: None,
+            append_json_file: None,
+            include_run_id: false,
+            replace_file: None,
+            append_line_ending: LineEnding::Lf,
+            append_bom: false,
             notify_command: None,
+            notify_words: 12,
+            paste_delay_ms: 50,
+            restore_clipboard: false,
+            clipboard_sentences: false,
+            replacements: BTreeMap::new(),
         }
     }
 }
@@ -108,10 +273,98 @@ impl Default for BehaviorConfig {
         Self {
             audio_cues: false,
             silence_threshold: None,
+            min_duration: 0.25,
+            silence_floor: 0.0005,
+            stop_key: StopKey::Enter,
+        }
+    }
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: 0.01,
+            min_silence_ms: 500,
         }
     }
 }
 
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            processor_pool_size: DEFAULT_POOL_SIZE,
+            max_in_flight: None,
+        }
+    }
+}
+
+/// Values given on the command line for [`Config::merge_cli_args`]. Each
+/// one that is set overrides its config field; the rest defer to the config.
+#[derive(Debug, Clone, Default)]
+pub struct CliArgs {
+    pub device: Option<String>,
+    pub duration: Option<u64>,
+    pub preroll_ms: Option<u64>,
+    pub buffer_frames: Option<u32>,
+    pub model: Option<String>,
+    pub quantized: Option<String>,
+    pub language: Option<String>,
+    pub threads: Option<u32>,
+    pub beam_size: Option<u32>,
+    pub suppress_blank: Option<bool>,
+    pub suppress_non_speech: Option<bool>,
+    pub entropy_thold: Option<f32>,
+    pub logprob_thold: Option<f32>,
+    pub no_speech_thold: Option<f32>,
+    pub inference_timeout: Option<f64>,
+    /// Sinks to add (`true`) or remove (`false`), in the order they apply
+    pub sinks: Vec<(OutputSink, bool)>,
+    pub timestamps: Option<String>,
+    pub append: Option<PathBuf>,
+    pub append_json: Option<PathBuf>,
+    pub replace: Option<PathBuf>,
+    pub notify: Option<String>,
+    pub notify_words: Option<usize>,
+    pub paste_delay_ms: Option<u64>,
+    pub restore_clipboard: Option<bool>,
+    pub clipboard_sentences: Option<bool>,
+    pub min_duration: Option<f64>,
+}
+
+/// Where an effective configuration value came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    File,
+    Cli,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::File => write!(f, "file"),
+            ConfigSource::Cli => write!(f, "cli"),
+        }
+    }
+}
+
+/// Provenance of each explicitly set value, keyed by `section.field`.
+/// Fields without an entry hold their default.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigSources(BTreeMap<String, ConfigSource>);
+
+impl ConfigSources {
+    pub fn get(&self, key: &str) -> ConfigSource {
+        self.0.get(key).copied().unwrap_or(ConfigSource::Default)
+    }
+
+    pub fn set(&mut self, key: impl Into<String>, source: ConfigSource) {
+        self.0.insert(key.into(), source);
+    }
+}
+
 impl Config {
     /// Load configuration from the default location
     pub fn load() -> Result<Self> {
@@ -121,21 +374,129 @@ impl Config {
 
     /// Load configuration from a specific file path
     pub fn load_from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::load_from_path_with_sources(path).map(|(config, _)| config)
+    }
+
+    /// Load configuration from the default location, recording which fields the file set
+    pub fn load_with_sources() -> Result<(Self, ConfigSources)> {
+        let config_path = Self::default_config_path()?;
+        Self::load_from_path_with_sources(&config_path)
+    }
+
+    /// Load configuration from a specific file path, recording which fields the file set
+    pub fn load_from_path_with_sources<P: AsRef<Path>>(path: P) -> Result<(Self, ConfigSources)> {
         let path = path.as_ref();
+        let mut sources = ConfigSources::default();
 
         if !path.exists() {
             debug!("Config file not found at {}, using defaults", path.display());
-            return Ok(Self::default());
+            return Ok((Self::default(), sources));
         }
 
         let content = fs::read_to_string(path)
             .map_err(|e| MicrodropError::Config(format!("Failed to read config file: {}", e)))?;
 
-        let config: Config = toml::from_str(&content)
+        let mut config: Config = toml::from_str(&content)
             .map_err(|e| MicrodropError::Config(format!("Failed to parse config file: {}", e)))?;
 
+        // The typed parse succeeded, so the raw table only tells us which keys were present
+        if let Ok(table) = content.parse::<toml::Table>() {
+            for (section, value) in &table {
+                if let Some(fields) = value.as_table() {
+                    for field in fields.keys() {
+                        sources.set(format!("{}.{}", section, field), ConfigSource::File);
+                    }
+                }
+            }
+        }
+
+        // Configs from before `sinks` existed keep the outputs they turned on
+        let mut implied_sinks = config.output.apply_deprecated_aliases();
+        if sources.get("output.sinks") == ConfigSource::Default && config.output.notify_command.is_some() {
+            config.output.set_sink(OutputSink::Notify, true);
+            implied_sinks = true;
+        }
+        if implied_sinks {
+            sources.set("output.sinks", ConfigSource::File);
+        }
+
+        config.model.thresholds().validate()
+            .and_then(|()| match config.model.language {
+                Some(ref language) => validate_language(language),
+                None => Ok(()),
+            })
+            .and_then(|()| match config.audio.auto_start_threshold {
+                Some(level) if !(level > 0.0 && level <= 1.0) => {
+                    Err(format!("auto_start_threshold must be above 0 and at most 1, got {}", level))
+                }
+                _ => Ok(()),
+            })
+            .and_then(|()| match config.audio.buffer_frames {
+                Some(0) => Err("buffer_frames must be greater than zero".to_string()),
+                _ => Ok(()),
+            })
+            .and_then(|()| match config.audio.spill_after_secs {
+                Some(0) => Err("spill_after_secs must be greater than zero".to_string()),
+                Some(secs) if secs >= config.audio.max_buffer_secs.unwrap_or(DEFAULT_BUFFER_LIMIT.as_secs()) => {
+                    Err(format!("spill_after_secs ({}) must be below max_buffer_secs, or recordings are cut before they spill", secs))
+                }
+                _ => Ok(()),
+            })
+            .and_then(|()| match config.model.inference_timeout_secs {
+                Some(secs) if !(secs.is_finite() && secs > 0.0) => {
+                    Err(format!("inference_timeout_secs must be greater than zero, got {}", secs))
+                }
+                _ => Ok(()),
+            })
+            .and_then(|()| match config.vad.threshold {
+                level if !(0.0..=1.0).contains(&level) => Err(format!("vad threshold must be between 0 and 1, got {}", level)),
+                _ => Ok(()),
+            })
+            .and_then(|()| match config.behavior.min_duration {
+                secs if secs.is_nan() || secs < 0.0 => Err(format!("min_duration must not be negative, got {}", secs)),
+                _ => Ok(()),
+            })
+            .and_then(|()| match config.behavior.silence_floor {
+                level if level.is_nan() || level < 0.0 => Err(format!("silence_floor must not be negative, got {}", level)),
+                _ => Ok(()),
+            })
+            .and_then(|()| match config.output.notify_command {
+                Some(ref command) => Notifier::new(command).map(drop).map_err(|e| format!("notify_command: {}", e)),
+                None => Ok(()),
+            })
+            .and_then(|()| match config.output.notify_words {
+                0 => Err("notify_words must be greater than zero".to_string()),
+                _ => Ok(()),
+            })
+            .and_then(|()| validate_replacements(&config.output.replacements))
+            .map_err(|e| {
+                MicrodropError::Config(format!("Invalid value in {}: {}", path.display(), e))
+            })?;
+
         debug!("Loaded config from {}", path.display());
-        Ok(config)
+        Ok((config, sources))
+    }
+
+    /// Flatten the configuration into `section.field` keys and rendered values,
+    /// including unset optional fields
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+
+        if let Ok(serde_json::Value::Object(sections)) = serde_json::to_value(self) {
+            for (section, fields) in sections {
+                if let serde_json::Value::Object(fields) = fields {
+                    for (field, value) in fields {
+                        let rendered = match value {
+                            serde_json::Value::Null => "(unset)".to_string(),
+                            other => other.to_string(),
+                        };
+                        entries.push((format!("{}.{}", section, field), rendered));
+                    }
+                }
+            }
+        }
+
+        entries
     }
 
     /// Write default configuration to the default location
@@ -180,50 +541,175 @@ impl Config {
         Ok(config_dir.join("microdrop").join("config.toml"))
     }
 
-    /// Merge CLI arguments into this configuration
-    pub fn merge_cli_args(&mut self,
-        device: Option<String>,
-        duration: Option<u64>,
-        model: Option<String>,
-        quantized: Option<String>,
-        paste: bool,
-        no_clipboard: bool,
-        timestamps: Option<String>,
-        append: Option<PathBuf>,
-        notify: Option<String>,
-    ) {
+    /// Get the default base directory for microdrop data, which `--data-dir`
+    /// or `MICRODROP_DATA_DIR` replace. It holds:
+    ///
+    /// - `models/`: downloaded models, each with a `.json` metadata file
+    /// - `state.json`: the last-used device and model
+    pub fn default_data_dir() -> Result<PathBuf> {
+        let data_dir = dirs::data_local_dir()
+            .or_else(|| dirs::home_dir().map(|h| h.join(".local/share")))
+            .ok_or_else(|| MicrodropError::Config("Unable to determine data directory".to_string()))?;
+
+        Ok(data_dir.join("microdrop"))
+    }
+
+    /// Merge CLI arguments into this configuration, returning the `section.field`
+    /// keys that were overridden
+    pub fn merge_cli_args(&mut self, args: CliArgs) -> Vec<&'static str> {
+        let CliArgs {
+            device,
+            duration,
+            preroll_ms,
+            buffer_frames,
+            model,
+            quantized,
+            language,
+            threads,
+            beam_size,
+            suppress_blank,
+            suppress_non_speech,
+            entropy_thold,
+            logprob_thold,
+            no_speech_thold,
+            inference_timeout,
+            sinks,
+            timestamps,
+            append,
+            append_json,
+            replace,
+            notify,
+            notify_words,
+            paste_delay_ms,
+            restore_clipboard,
+            clipboard_sentences,
+            min_duration,
+        } = args;
+        let mut overridden = Vec::new();
+
         // Audio settings
         if device.is_some() {
             self.audio.device = device;
+            overridden.push("audio.device");
         }
         if duration.is_some() {
             self.audio.max_duration = duration;
+            overridden.push("audio.max_duration");
+        }
+        if let Some(ms) = preroll_ms {
+            self.audio.preroll_ms = ms;
+            overridden.push("audio.preroll_ms");
+        }
+        if buffer_frames.is_some() {
+            self.audio.buffer_frames = buffer_frames;
+            overridden.push("audio.buffer_frames");
         }
 
         // Model settings
         if model.is_some() {
             self.model.default_model = model;
+            overridden.push("model.default_model");
         }
         if quantized.is_some() {
             self.model.default_quantization = quantized;
+            overridden.push("model.default_quantization");
+        }
+        if language.is_some() {
+            self.model.language = language;
+            overridden.push("model.language");
+        }
+        if threads.is_some() {
+            self.model.threads = threads;
+            overridden.push("model.threads");
+        }
+        if beam_size.is_some() {
+            self.model.beam_size = beam_size;
+            overridden.push("model.beam_size");
+        }
+        if suppress_blank.is_some() {
+            self.model.suppress_blank = suppress_blank;
+            overridden.push("model.suppress_blank");
+        }
+        if suppress_non_speech.is_some() {
+            self.model.suppress_non_speech = suppress_non_speech;
+            overridden.push("model.suppress_non_speech");
+        }
+        if entropy_thold.is_some() {
+            self.model.entropy_thold = entropy_thold;
+            overridden.push("model.entropy_thold");
+        }
+        if logprob_thold.is_some() {
+            self.model.logprob_thold = logprob_thold;
+            overridden.push("model.logprob_thold");
+        }
+        if no_speech_thold.is_some() {
+            self.model.no_speech_thold = no_speech_thold;
+            overridden.push("model.no_speech_thold");
+        }
+        if inference_timeout.is_some() {
+            self.model.inference_timeout_secs = inference_timeout;
+            overridden.push("model.inference_timeout_secs");
         }
 
-        // Output settings - CLI args override config
-        if paste {
-            self.output.enable_paste = true;
+        // Output settings - CLI args override config; None defers to it
+        for &(sink, enabled) in &sinks {
+            self.output.set_sink(sink, enabled);
+        }
+        // --notify turns the notify sink on, unless the sink flags already
+        // decided it, so --no-sink notify still leaves it out
+        let add_notify = notify.is_some()
+            && !self.output.has_sink(OutputSink::Notify)
+            && !sinks.iter().any(|&(sink, _)| sink == OutputSink::Notify);
+        if notify.is_some() {
+            self.output.notify_command = notify;
+            overridden.push("output.notify_command");
         }
-        if no_clipboard {
-            self.output.enable_clipboard = false;
+        if add_notify {
+            self.output.set_sink(OutputSink::Notify, true);
+        }
+        if add_notify || !sinks.is_empty() {
+            overridden.push("output.sinks");
         }
         if let Some(ts) = timestamps {
             self.output.timestamp_format = ts;
+            overridden.push("output.timestamp_format");
         }
         if append.is_some() {
             self.output.append_file = append;
+            overridden.push("output.append_file");
         }
-        if notify.is_some() {
-            self.output.notify_command = notify;
+        if append_json.is_some() {
+            self.output.append_json_file = append_json;
+            overridden.push("output.append_json_file");
+        }
+        if replace.is_some() {
+            self.output.replace_file = replace;
+            overridden.push("output.replace_file");
         }
+        if let Some(words) = notify_words {
+            self.output.notify_words = words;
+            overridden.push("output.notify_words");
+        }
+        if let Some(ms) = paste_delay_ms {
+            self.output.paste_delay_ms = ms;
+            overridden.push("output.paste_delay_ms");
+        }
+        if let Some(restore) = restore_clipboard {
+            self.output.restore_clipboard = restore;
+            overridden.push("output.restore_clipboard");
+        }
+        if let Some(sentences) = clipboard_sentences {
+            self.output.clipboard_sentences = sentences;
+            overridden.push("output.clipboard_sentences");
+        }
+
+        // Behavior settings
+        if let Some(min_duration) = min_duration {
+            self.behavior.min_duration = min_duration;
+            overridden.push("behavior.min_duration");
+        }
+
+        overridden
     }
 }
 
@@ -236,10 +722,18 @@ mod tests {
     #[test]
     fn test_default_config() {
         let config = Config::default();
-        assert!(config.output.enable_clipboard);
-        assert!(!config.output.enable_paste);
+        assert_eq!(config.output.sinks, vec![OutputSink::Stdout, OutputSink::Clipboard]);
+        assert!(!config.output.has_sink(OutputSink::Paste));
         assert_eq!(config.output.timestamp_format, "none");
         assert!(config.audio.device.is_none());
+        assert_eq!(config.audio.preroll_ms, 0);
+        assert!(!config.audio.skip_silent_channels);
+        assert_eq!(config.audio.backpressure, BackpressurePolicy::Grow);
+        assert_eq!(config.output.paste_delay_ms, 50);
+        assert_eq!(config.output.append_line_ending, LineEnding::Lf);
+        assert!(!config.output.append_bom);
+        assert!(!config.output.include_run_id);
+        assert_eq!(config.behavior.min_duration, 0.25);
         assert!(config.model.default_model.is_none());
     }
 
@@ -249,7 +743,7 @@ mod tests {
         assert!(result.is_ok());
         let config = result.unwrap();
         // Should return default config
-        assert!(config.output.enable_clipboard);
+        assert!(config.output.has_sink(OutputSink::Clipboard));
     }
 
     #[test]
@@ -268,6 +762,8 @@ default_quantization = "q5_1"
 enable_clipboard = false
 enable_paste = true
 timestamp_format = "simple"
+append_line_ending = "crlf"
+append_bom = true
 
 [behavior]
 audio_cues = true
@@ -279,11 +775,214 @@ silence_threshold = 2.0
         assert_eq!(config.audio.max_duration, Some(300));
         assert_eq!(config.model.default_model, Some("small.en".to_string()));
         assert_eq!(config.model.default_quantization, Some("q5_1".to_string()));
-        assert!(!config.output.enable_clipboard);
-        assert!(config.output.enable_paste);
+        // The deprecated switches still apply, folded into the sink list
+        assert_eq!(config.output.sinks, vec![OutputSink::Stdout, OutputSink::Paste]);
+        assert_eq!(config.output.enable_clipboard, None);
+        assert_eq!(config.output.enable_paste, None);
         assert_eq!(config.output.timestamp_format, "simple");
+        assert_eq!(config.output.append_line_ending, LineEnding::Crlf);
+        assert!(config.output.append_bom);
         assert!(config.behavior.audio_cues);
         assert_eq!(config.behavior.silence_threshold, Some(2.0));
+        assert_eq!(config.behavior.min_duration, 0.25);
+    }
+
+    #[test]
+    fn test_load_vad_config() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, r#"
+[vad]
+enabled = true
+min_silence_ms = 800
+"#).unwrap();
+
+        let config = Config::load_from_path(temp_file.path()).unwrap();
+        assert!(config.vad.enabled);
+        assert_eq!(config.vad.min_silence_ms, 800);
+        assert_eq!(config.vad.threshold, 0.01);
+        assert!(!Config::default().vad.enabled);
+    }
+
+    #[test]
+    fn test_load_rejects_out_of_range_thresholds() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, r#"
+[model]
+entropy_thold = 2.8
+no_speech_thold = 1.5
+"#).unwrap();
+
+        let err = Config::load_from_path(temp_file.path()).unwrap_err().to_string();
+        assert!(err.contains("no_speech_thold must be between 0 and 1"));
+    }
+
+    #[test]
+    fn test_load_rejects_out_of_range_auto_start_threshold() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, r#"
+[audio]
+auto_start_threshold = 2.0
+"#).unwrap();
+
+        let err = Config::load_from_path(temp_file.path()).unwrap_err().to_string();
+        assert!(err.contains("auto_start_threshold must be above 0 and at most 1"));
+    }
+
+    #[test]
+    fn test_load_stop_key() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, r#"
+[behavior]
+stop_key = "any"
+"#).unwrap();
+
+        let config = Config::load_from_path(temp_file.path()).unwrap();
+        assert_eq!(config.behavior.stop_key, StopKey::Any);
+        assert_eq!(Config::default().behavior.stop_key, StopKey::Enter);
+    }
+
+    #[test]
+    fn test_load_rejects_zero_buffer_frames() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, r#"
+[audio]
+buffer_frames = 0
+"#).unwrap();
+
+        let err = Config::load_from_path(temp_file.path()).unwrap_err().to_string();
+        assert!(err.contains("buffer_frames must be greater than zero"));
+    }
+
+    #[test]
+    fn test_load_validates_spill_after_secs() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, r#"
+[audio]
+spill_after_secs = 600
+"#).unwrap();
+        let config = Config::load_from_path(temp_file.path()).unwrap();
+        assert_eq!(config.audio.spill_after_secs, Some(600));
+
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, r#"
+[audio]
+max_buffer_secs = 300
+spill_after_secs = 600
+"#).unwrap();
+        let err = Config::load_from_path(temp_file.path()).unwrap_err().to_string();
+        assert!(err.contains("spill_after_secs (600) must be below max_buffer_secs"), "{}", err);
+    }
+
+    #[test]
+    fn test_load_replacements() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, r#"
+[output.replacements]
+github = "GitHub"
+"pull request" = "PR"
+"#).unwrap();
+        let (config, sources) = Config::load_from_path_with_sources(temp_file.path()).unwrap();
+        assert_eq!(config.output.replacements.get("github").map(String::as_str), Some("GitHub"));
+        assert_eq!(config.output.replacements.get("pull request").map(String::as_str), Some("PR"));
+        assert_eq!(sources.get("output.replacements"), ConfigSource::File);
+
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, r#"
+[output.replacements]
+api = "API"
+Api = "Api"
+"#).unwrap();
+        let err = Config::load_from_path(temp_file.path()).unwrap_err().to_string();
+        assert!(err.contains("match the same words"), "{}", err);
+    }
+
+    #[test]
+    fn test_load_rejects_non_positive_inference_timeout() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, r#"
+[model]
+inference_timeout_secs = 0
+"#).unwrap();
+
+        let err = Config::load_from_path(temp_file.path()).unwrap_err().to_string();
+        assert!(err.contains("inference_timeout_secs must be greater than zero"));
+    }
+
+    #[test]
+    fn test_load_rejects_out_of_range_vad_threshold() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, r#"
+[vad]
+threshold = 1.5
+"#).unwrap();
+
+        let err = Config::load_from_path(temp_file.path()).unwrap_err().to_string();
+        assert!(err.contains("vad threshold must be between 0 and 1, got 1.5"), "{}", err);
+    }
+
+    #[test]
+    fn test_load_rejects_negative_min_duration() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, r#"
+[behavior]
+min_duration = -0.5
+"#).unwrap();
+
+        let err = Config::load_from_path(temp_file.path()).unwrap_err().to_string();
+        assert!(err.contains("min_duration must not be negative, got -0.5"), "{}", err);
+    }
+
+    #[test]
+    fn test_load_rejects_negative_silence_floor() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, r#"
+[behavior]
+silence_floor = -0.01
+"#).unwrap();
+
+        let err = Config::load_from_path(temp_file.path()).unwrap_err().to_string();
+        assert!(err.contains("silence_floor must not be negative, got -0.01"), "{}", err);
+    }
+
+    #[test]
+    fn test_load_validates_notify_settings() {
+        let load = |toml: &str| {
+            let mut temp_file = NamedTempFile::new().unwrap();
+            write!(temp_file, "[output]\n{}", toml).unwrap();
+            Config::load_from_path(temp_file.path())
+        };
+
+        let err = load("notify_words = 0\n").unwrap_err().to_string();
+        assert!(err.contains("notify_words must be greater than zero"));
+        let err = load("notify_command = \"notify-send 'unclosed\"\n").unwrap_err().to_string();
+        assert!(err.contains("notify_command"), "{}", err);
+        assert!(load("notify_command = \"notify-send -a 'micro drop'\"\n").is_ok());
+    }
+
+    #[test]
+    fn test_load_rejects_unknown_language() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, r#"
+[model]
+language = "english"
+"#).unwrap();
+
+        let err = Config::load_from_path(temp_file.path()).unwrap_err().to_string();
+        assert!(err.contains("did you mean 'en'"));
+    }
+
+    #[test]
+    fn test_load_backpressure_config() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, r#"
+[audio]
+max_buffer_secs = 600
+backpressure = "drop_newest"
+"#).unwrap();
+
+        let config = Config::load_from_path(temp_file.path()).unwrap();
+        assert_eq!(config.audio.max_buffer_secs, Some(600));
+        assert_eq!(config.audio.backpressure, BackpressurePolicy::DropNewest);
     }
 
     #[test]
@@ -300,29 +999,163 @@ silence_threshold = 2.0
     fn test_merge_cli_args() {
         let mut config = Config::default();
 
-        config.merge_cli_args(
-            Some("custom-device".to_string()),
-            Some(120),
-            Some("base.en".to_string()),
-            Some("q8_0".to_string()),
-            true,  // paste
-            true,  // no_clipboard
-            Some("detailed".to_string()),
-            Some("/tmp/output.txt".into()),
-            Some("notify-send".to_string()),
-        );
+        config.merge_cli_args(CliArgs {
+            device: Some("custom-device".to_string()),
+            duration: Some(120),
+            preroll_ms: Some(300),
+            buffer_frames: Some(256),
+            model: Some("base.en".to_string()),
+            quantized: Some("q8_0".to_string()),
+            language: Some("de".to_string()),
+            threads: Some(8),
+            beam_size: Some(5),
+            suppress_blank: Some(false),
+            suppress_non_speech: Some(true),
+            entropy_thold: Some(2.8),
+            logprob_thold: Some(-0.5),
+            no_speech_thold: Some(0.4),
+            inference_timeout: Some(30.0),
+            sinks: vec![(OutputSink::Paste, true), (OutputSink::Clipboard, false)],
+            timestamps: Some("detailed".to_string()),
+            append: Some("/tmp/output.txt".into()),
+            append_json: Some("/tmp/archive.ndjson".into()),
+            replace: Some("/tmp/latest.txt".into()),
+            notify: Some("notify-send".to_string()),
+            notify_words: Some(5),
+            paste_delay_ms: Some(200),
+            restore_clipboard: Some(true),
+            clipboard_sentences: Some(true),
+            min_duration: Some(0.0),
+        });
 
         assert_eq!(config.audio.device, Some("custom-device".to_string()));
         assert_eq!(config.audio.max_duration, Some(120));
+        assert_eq!(config.audio.preroll_ms, 300);
+        assert_eq!(config.audio.buffer_frames, Some(256));
         assert_eq!(config.model.default_model, Some("base.en".to_string()));
         assert_eq!(config.model.default_quantization, Some("q8_0".to_string()));
-        assert!(config.output.enable_paste);
-        assert!(!config.output.enable_clipboard);
+        assert_eq!(config.model.language, Some("de".to_string()));
+        assert_eq!(config.model.threads, Some(8));
+        assert_eq!(config.model.beam_size, Some(5));
+        assert_eq!(config.model.suppress_blank, Some(false));
+        assert_eq!(config.model.suppress_non_speech, Some(true));
+        assert_eq!(config.model.entropy_thold, Some(2.8));
+        assert_eq!(config.model.logprob_thold, Some(-0.5));
+        assert_eq!(config.model.no_speech_thold, Some(0.4));
+        assert_eq!(config.model.inference_timeout_secs, Some(30.0));
+        // --notify turns the notify sink on too
+        assert_eq!(config.output.sinks, vec![OutputSink::Stdout, OutputSink::Paste, OutputSink::Notify]);
         assert_eq!(config.output.timestamp_format, "detailed");
         assert_eq!(config.output.append_file, Some("/tmp/output.txt".into()));
+        assert_eq!(config.output.append_json_file, Some("/tmp/archive.ndjson".into()));
+        assert_eq!(config.output.replace_file, Some("/tmp/latest.txt".into()));
+        assert_eq!(config.output.notify_command, Some("notify-send".to_string()));
+        assert_eq!(config.output.notify_words, 5);
+        assert_eq!(config.output.paste_delay_ms, 200);
+        assert!(config.output.restore_clipboard);
+        assert!(config.output.clipboard_sentences);
+        assert_eq!(config.behavior.min_duration, 0.0);
+    }
+
+    #[test]
+    fn test_merge_cli_args_can_disable_config_sinks() {
+        let mut config = Config::default();
+        config.output.set_sink(OutputSink::Paste, true);
+
+        // Absent flags defer to the config
+        let overridden = config.merge_cli_args(CliArgs::default());
+        assert!(overridden.is_empty());
+        assert_eq!(config.output.sinks, vec![OutputSink::Stdout, OutputSink::Clipboard, OutputSink::Paste]);
+
+        config.merge_cli_args(CliArgs { sinks: vec![(OutputSink::Paste, false), (OutputSink::Clipboard, false)], ..Default::default() });
+        assert_eq!(config.output.sinks, vec![OutputSink::Stdout]);
+
+        // Adding a sink twice keeps one copy
+        config.merge_cli_args(CliArgs { sinks: vec![(OutputSink::Notify, true), (OutputSink::Notify, true)], ..Default::default() });
+        assert_eq!(config.output.sinks, vec![OutputSink::Stdout, OutputSink::Notify]);
+
+        // --no-sink wins over the sink --notify turns on
+        let mut config = Config::default();
+        config.merge_cli_args(CliArgs { sinks: vec![(OutputSink::Notify, false)], notify: Some("notify-send".to_string()), ..Default::default() });
+        assert_eq!(config.output.sinks, vec![OutputSink::Stdout, OutputSink::Clipboard]);
         assert_eq!(config.output.notify_command, Some("notify-send".to_string()));
     }
 
+    #[test]
+    fn test_merge_cli_args_reports_overrides() {
+        let mut config = Config::default();
+
+        let overridden = config.merge_cli_args(CliArgs {
+            sinks: vec![(OutputSink::Clipboard, false)],
+            min_duration: Some(0.5),
+            ..Default::default()
+        });
+
+        assert_eq!(overridden, vec!["output.sinks", "behavior.min_duration"]);
+    }
+
+    #[test]
+    fn test_load_with_sources() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, r#"
+[audio]
+device = "test-device"
+
+[output]
+enable_paste = false
+"#).unwrap();
+
+        let (config, sources) = Config::load_from_path_with_sources(temp_file.path()).unwrap();
+        assert_eq!(config.audio.device, Some("test-device".to_string()));
+        assert_eq!(sources.get("audio.device"), ConfigSource::File);
+        // Explicitly setting a value equal to the default still counts as the file
+        assert_eq!(sources.get("output.enable_paste"), ConfigSource::File);
+        assert_eq!(sources.get("output.enable_clipboard"), ConfigSource::Default);
+        assert_eq!(sources.get("model.default_model"), ConfigSource::Default);
+    }
+
+    #[test]
+    fn test_load_sinks() {
+        let load = |toml: &str| {
+            let mut temp_file = NamedTempFile::new().unwrap();
+            write!(temp_file, "[output]\n{}", toml).unwrap();
+            Config::load_from_path_with_sources(temp_file.path()).unwrap()
+        };
+
+        let (config, _) = load("sinks = [\"stdout\", \"notify\"]\nnotify_command = \"dunstify\"\n");
+        assert_eq!(config.output.sinks, vec![OutputSink::Stdout, OutputSink::Notify]);
+
+        // A notify command from before `sinks` existed still notifies
+        let (config, sources) = load("notify_command = \"notify-send\"\n");
+        assert_eq!(config.output.sinks, vec![OutputSink::Stdout, OutputSink::Clipboard, OutputSink::Notify]);
+        assert_eq!(sources.get("output.sinks"), ConfigSource::File);
+
+        // ...but an explicit list is taken at its word
+        let (config, _) = load("sinks = [\"clipboard\"]\nnotify_command = \"notify-send\"\n");
+        assert_eq!(config.output.sinks, vec![OutputSink::Clipboard]);
+
+        // Deprecated aliases apply on top of the list
+        let (config, _) = load("sinks = [\"stdout\"]\nenable_paste = true\n");
+        assert_eq!(config.output.sinks, vec![OutputSink::Stdout, OutputSink::Paste]);
+
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "[output]\nsinks = [\"socket\"]\n").unwrap();
+        assert!(Config::load_from_path(temp_file.path()).is_err());
+    }
+
+    #[test]
+    fn test_entries_include_unset_fields() {
+        let mut config = Config::default();
+        config.audio.device = Some("mic".to_string());
+
+        let entries = config.entries();
+        assert!(entries.contains(&("audio.device".to_string(), "\"mic\"".to_string())));
+        assert!(entries.contains(&("model.default_model".to_string(), "(unset)".to_string())));
+        assert!(entries.contains(&("output.sinks".to_string(), r#"["stdout","clipboard"]"#.to_string())));
+        // Deprecated aliases only show up when set
+        assert!(!entries.iter().any(|(key, _)| key == "output.enable_clipboard"));
+    }
+
     #[test]
     fn test_write_and_read_default_config() {
         let temp_file = NamedTempFile::new().unwrap();
@@ -337,8 +1170,7 @@ silence_threshold = 2.0
 
         // Should match defaults
         let default_config = Config::default();
-        assert_eq!(config.output.enable_clipboard, default_config.output.enable_clipboard);
-        assert_eq!(config.output.enable_paste, default_config.output.enable_paste);
+        assert_eq!(config.output.sinks, default_config.output.sinks);
         assert_eq!(config.output.timestamp_format, default_config.output.timestamp_format);
     }
 
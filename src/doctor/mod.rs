@@ -0,0 +1,306 @@
+//! Environment diagnostics for `microdrop doctor`.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use arboard::Clipboard;
+use enigo::{Enigo, Settings};
+
+use crate::audio::{AudioEngine, CaptureBackend};
+use crate::config::Config;
+use crate::model::ModelManager;
+use crate::output::{paste_permitted, ACCESSIBILITY_HINT};
+use crate::transcribe::{find_default_model, resolve_model_path, TranscriptionEngine};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    /// Failed, but microdrop still works without it
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckStatus::Pass => write!(f, "PASS"),
+            CheckStatus::Warn => write!(f, "WARN"),
+            CheckStatus::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+/// Outcome of a single diagnostic check
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// How to fix the problem, shown when the check doesn't pass
+    pub hint: Option<&'static str>,
+}
+
+impl CheckResult {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn problem(
+        name: &'static str,
+        status: CheckStatus,
+        detail: impl Into<String>,
+        hint: &'static str,
+    ) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+            hint: Some(hint),
+        }
+    }
+}
+
+impl fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}  {}: {}", self.status, self.name, self.detail)?;
+        if let Some(hint) = self.hint {
+            write!(f, "\n      hint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run every check against the current environment, configuration and data
+/// directory. `deep` also loads the model and runs a short inference, which is
+/// slow but catches corrupt or truncated model files.
+pub fn run_checks(config: &Config, data_dir: &Path, deep: bool) -> Vec<CheckResult> {
+    let mut results = check_audio(config);
+    results.push(check_clipboard());
+    results.push(check_input_simulation());
+
+    let model = resolve_configured_model(config, data_dir);
+    results.push(check_model(&model));
+    if deep {
+        results.push(match model {
+            Ok(ref path) => check_model_inference(path),
+            Err(_) => CheckResult::problem(
+                "Model inference",
+                CheckStatus::Fail,
+                "skipped, no model",
+                "Fix the model first",
+            ),
+        });
+    }
+
+    results.push(check_cache_dir(&ModelManager::cache_dir_in(data_dir)));
+    results
+}
+
+/// Whether any check failed badly enough that recording or transcription can't work
+pub fn has_critical_failure(results: &[CheckResult]) -> bool {
+    results.iter().any(|r| r.status == CheckStatus::Fail)
+}
+
+fn check_audio(config: &Config) -> Vec<CheckResult> {
+    let mut engine = AudioEngine::new();
+
+    if let Err(e) = engine.select_device(config.audio.device.as_deref()) {
+        return vec![
+            CheckResult::problem(
+                "Input device",
+                CheckStatus::Fail,
+                e.to_string(),
+                "Connect a microphone, or set audio.device / --device to an available input",
+            ),
+            CheckResult::problem(
+                "Audio format",
+                CheckStatus::Fail,
+                "skipped, no input device",
+                "Fix the input device first",
+            ),
+        ];
+    }
+
+    let device = engine
+        .device_name()
+        .unwrap_or_else(|| "unknown".to_string());
+    let mut results = vec![CheckResult::pass("Input device", device)];
+
+    results.push(match engine.configure_stream() {
+        Ok(()) => CheckResult::pass(
+            "Audio format",
+            format!("{} Hz", engine.sample_rate().unwrap_or_default()),
+        ),
+        Err(e) => CheckResult::problem(
+            "Audio format",
+            CheckStatus::Fail,
+            e.to_string(),
+            "Choose a device that supports 16 kHz or higher sample rates",
+        ),
+    });
+
+    results
+}
+
+fn check_clipboard() -> CheckResult {
+    match Clipboard::new() {
+        Ok(_) => CheckResult::pass("Clipboard", "available"),
+        Err(e) => CheckResult::problem(
+            "Clipboard",
+            CheckStatus::Warn,
+            e.to_string(),
+            "Run inside a graphical session, or use --no-clipboard",
+        ),
+    }
+}
+
+fn check_input_simulation() -> CheckResult {
+    match Enigo::new(&Settings::default()) {
+        Ok(_) if !paste_permitted() => CheckResult::problem(
+            "Paste simulation",
+            CheckStatus::Warn,
+            "Accessibility permission not granted, so keystrokes are ignored",
+            ACCESSIBILITY_HINT,
+        ),
+        Ok(_) => CheckResult::pass("Paste simulation", "available"),
+        Err(e) => CheckResult::problem(
+            "Paste simulation",
+            CheckStatus::Warn,
+            e.to_string(),
+            "Paste requires X11 on Linux, or Windows/macOS; use --no-paste elsewhere",
+        ),
+    }
+}
+
+fn resolve_configured_model(config: &Config, data_dir: &Path) -> Result<PathBuf, String> {
+    ModelManager::in_data_dir(data_dir)
+        .map_err(|e| e.to_string())
+        .and_then(|model_manager| match config.model.default_model {
+            Some(ref model) => resolve_model_path(
+                &model_manager,
+                model,
+                config.model.default_quantization.as_deref(),
+            )
+            .map_err(|e| e.to_string()),
+            None => find_default_model(&model_manager).ok_or_else(|| "no model found".to_string()),
+        })
+}
+
+fn check_model(resolved: &Result<PathBuf, String>) -> CheckResult {
+    match resolved {
+        Ok(path) => CheckResult::pass("Model", path.display().to_string()),
+        Err(e) => CheckResult::problem(
+            "Model",
+            CheckStatus::Fail,
+            e.clone(),
+            "Install one with 'microdrop model install <model>' or pass --model <path>",
+        ),
+    }
+}
+
+/// Load the model and transcribe a moment of silence, the same warm-up a
+/// recording does, to prove the file is a usable Whisper model
+fn check_model_inference(path: &Path) -> CheckResult {
+    let start = Instant::now();
+    let outcome = TranscriptionEngine::new(path).and_then(|engine| engine.warm_up());
+
+    match outcome {
+        Ok(()) => CheckResult::pass(
+            "Model inference",
+            format!("loaded and ran in {:.2?}", start.elapsed()),
+        ),
+        Err(e) => CheckResult::problem(
+            "Model inference",
+            CheckStatus::Fail,
+            e.to_string(),
+            "The model file may be corrupt or truncated; reinstall it with 'microdrop model install <model> --force'",
+        ),
+    }
+}
+
+fn check_cache_dir(dir: &Path) -> CheckResult {
+    let probe = dir.join(".microdrop-doctor");
+    let writable = fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&probe, b""))
+        .and_then(|_| fs::remove_file(&probe));
+
+    match writable {
+        Ok(()) => CheckResult::pass("Model cache", format!("{} is writable", dir.display())),
+        Err(e) => CheckResult::problem(
+            "Model cache",
+            CheckStatus::Fail,
+            format!("{} is not writable: {}", dir.display(), e),
+            "Fix the directory's permissions or free up disk space",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_cache_dir_check() {
+        let temp_dir = TempDir::new().unwrap();
+        let result = check_cache_dir(&temp_dir.path().join("models"));
+        assert_eq!(result.status, CheckStatus::Pass);
+        assert!(!temp_dir.path().join("models/.microdrop-doctor").exists());
+
+        // A file where the directory should be can't be used
+        let blocked = temp_dir.path().join("blocked");
+        fs::write(&blocked, b"").unwrap();
+        let result = check_cache_dir(&blocked);
+        assert_eq!(result.status, CheckStatus::Fail);
+        assert!(result.hint.is_some());
+    }
+
+    #[test]
+    fn test_model_inference_check_rejects_corrupt_model() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("ggml-tiny.en.bin");
+        fs::write(&path, b"not a whisper model").unwrap();
+
+        let result = check_model_inference(&path);
+        assert_eq!(result.status, CheckStatus::Fail);
+        assert!(result.hint.unwrap().contains("corrupt"));
+    }
+
+    #[test]
+    fn test_only_failures_are_critical() {
+        let mut results = vec![
+            CheckResult::pass("Input device", "default"),
+            CheckResult::problem("Clipboard", CheckStatus::Warn, "no display", "hint"),
+        ];
+        assert!(!has_critical_failure(&results));
+
+        results.push(CheckResult::problem(
+            "Model",
+            CheckStatus::Fail,
+            "missing",
+            "hint",
+        ));
+        assert!(has_critical_failure(&results));
+    }
+
+    #[test]
+    fn test_check_result_display() {
+        let result =
+            CheckResult::problem("Model", CheckStatus::Fail, "no model found", "Install one");
+        assert_eq!(
+            result.to_string(),
+            "FAIL  Model: no model found\n      hint: Install one"
+        );
+        assert_eq!(
+            CheckResult::pass("Clipboard", "available").to_string(),
+            "PASS  Clipboard: available"
+        );
+    }
+}
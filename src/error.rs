@@ -6,6 +6,9 @@ pub enum MicrodropError {
     Unimplemented { feature: &'static str },
     #[error("Audio error: {0}")]
     Audio(String),
+    /// No input device matches the requested name
+    #[error("Audio error: Audio device '{0}' not found. Use 'arecord -l' or system audio settings to see available devices.")]
+    DeviceNotFound(String),
     #[error("Transcription error: {0}")]
     Transcription(String),
     #[error("Model loading error: {0}")]
@@ -16,8 +19,20 @@ pub enum MicrodropError {
     ModelCache(String),
     #[error("Model registry error: {0}")]
     ModelRegistry(String),
+    #[error("Model verification error: {0}")]
+    ModelVerification(String),
     #[error("Configuration error: {0}")]
     Config(String),
+    #[error("Diagnostics failed: {0}")]
+    Diagnostics(String),
+    #[error("Server error: {0}")]
+    Server(String),
+    #[error("Notification error: {0}")]
+    Notification(String),
+    #[error("Timed out after {0} seconds")]
+    Timeout(u64),
+    #[error("Empty transcript: {0}")]
+    EmptyTranscript(String),
 }
 
 pub type Result<T> = std::result::Result<T, MicrodropError>;
@@ -26,4 +41,71 @@ impl MicrodropError {
     pub fn unimplemented(feature: &'static str) -> Self {
         MicrodropError::Unimplemented { feature }
     }
+
+    /// Process exit code for this error; timeouts use 124 like coreutils `timeout`,
+    /// and model downloads use the `sysexits.h` codes so scripts can tell a
+    /// network outage from a full disk or a corrupt file. An empty transcript
+    /// under `--fail-on-empty` is `EX_NOINPUT`, as there was nothing to transcribe.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            MicrodropError::Timeout(_) => 124,
+            MicrodropError::EmptyTranscript(_) => 66,
+            MicrodropError::ModelDownload(_) => 69,
+            MicrodropError::ModelCache(_) => 74,
+            MicrodropError::ModelVerification(_) => 65,
+            _ => 1,
+        }
+    }
+
+    /// Name of this error's variant, the `kind` in [`Self::to_json`]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MicrodropError::Unimplemented { .. } => "Unimplemented",
+            MicrodropError::Audio(_) => "Audio",
+            MicrodropError::DeviceNotFound(_) => "DeviceNotFound",
+            MicrodropError::Transcription(_) => "Transcription",
+            MicrodropError::ModelLoad(_) => "ModelLoad",
+            MicrodropError::ModelDownload(_) => "ModelDownload",
+            MicrodropError::ModelCache(_) => "ModelCache",
+            MicrodropError::ModelRegistry(_) => "ModelRegistry",
+            MicrodropError::ModelVerification(_) => "ModelVerification",
+            MicrodropError::Config(_) => "Config",
+            MicrodropError::Diagnostics(_) => "Diagnostics",
+            MicrodropError::Server(_) => "Server",
+            MicrodropError::Notification(_) => "Notification",
+            MicrodropError::Timeout(_) => "Timeout",
+            MicrodropError::EmptyTranscript(_) => "EmptyTranscript",
+        }
+    }
+
+    /// `{"error": {"kind": ..., "message": ...}}`, reported on stderr in place
+    /// of the log line when a command's output is JSON
+    pub fn to_json(&self) -> String {
+        serde_json::json!({
+            "error": {
+                "kind": self.kind(),
+                "message": self.to_string(),
+            }
+        })
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&MicrodropError::Timeout(5).to_json()).unwrap();
+        assert_eq!(json["error"]["kind"], "Timeout");
+        assert_eq!(json["error"]["message"], "Timed out after 5 seconds");
+
+        let err = MicrodropError::Audio("Audio device 'usb' not found".to_string());
+        assert_eq!(
+            err.to_json(),
+            r#"{"error":{"kind":"Audio","message":"Audio error: Audio device 'usb' not found"}}"#
+        );
+    }
 }
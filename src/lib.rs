@@ -1,9 +1,13 @@
 pub mod audio;
 pub mod cli;
 pub mod config;
+pub mod doctor;
 pub mod model;
 pub mod notify;
 pub mod output;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod state;
 pub mod telemetry;
 pub mod transcribe;
 pub mod workflow;
@@ -11,7 +11,11 @@ async fn main() {
     let cli = Cli::parse();
 
     if let Err(err) = cli.run().await {
-        error!(error = %err, "microdrop command failed");
-        std::process::exit(1);
+        if cli.json_output() {
+            eprintln!("{}", err.to_json());
+        } else {
+            error!(error = %err, "microdrop command failed");
+        }
+        std::process::exit(err.exit_code());
     }
 }
@@ -1,15 +1,23 @@
 //! Model management for Whisper models: download, cache, and resolution.
 
 use std::fs::{self, File};
-use std::io::Write;
+use std::io::{self, Read, Write};
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use indicatif::{ProgressBar, ProgressStyle};
-use reqwest::Client;
+use futures_util::stream::{self, StreamExt};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use reqwest::header::{CONTENT_RANGE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RANGE};
+use reqwest::{Client, Response, StatusCode};
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
+use sha2::{Digest, Sha256, Sha512};
+use tokio::sync::Semaphore;
 use tracing::{debug, info, warn};
+use unicode_normalization::UnicodeNormalization;
 
+use crate::config::Config;
 use crate::{MicrodropError, Result};
 
 /// Represents quantization levels for Whisper models
@@ -46,15 +54,172 @@ impl std::str::FromStr for Quantization {
     }
 }
 
+/// Hash algorithms accepted for model checksums
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgo {
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl ChecksumAlgo {
+    /// Hash `data` and return the lowercase hex digest
+    pub fn digest_hex(&self, data: &[u8]) -> String {
+        match self {
+            ChecksumAlgo::Sha256 => format!("{:x}", Sha256::digest(data)),
+            ChecksumAlgo::Sha512 => format!("{:x}", Sha512::digest(data)),
+            ChecksumAlgo::Blake3 => blake3::hash(data).to_hex().to_string(),
+        }
+    }
+
+    /// Hash a file in fixed-size chunks so large models aren't read into memory
+    pub fn digest_file(&self, path: &Path) -> io::Result<String> {
+        let mut file = File::open(path)?;
+        let mut buf = vec![0u8; 1 << 20];
+
+        match self {
+            ChecksumAlgo::Sha256 => Ok(format!("{:x}", digest_reader::<Sha256>(&mut file, &mut buf)?)),
+            ChecksumAlgo::Sha512 => Ok(format!("{:x}", digest_reader::<Sha512>(&mut file, &mut buf)?)),
+            ChecksumAlgo::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                loop {
+                    let n = file.read(&mut buf)?;
+                    if n == 0 {
+                        break;
+                    }
+                    hasher.update(&buf[..n]);
+                }
+                Ok(hasher.finalize().to_hex().to_string())
+            }
+        }
+    }
+}
+
+fn digest_reader<D: Digest>(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<sha2::digest::Output<D>> {
+    let mut hasher = D::new();
+    loop {
+        let n = reader.read(buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize())
+}
+
+impl std::fmt::Display for ChecksumAlgo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChecksumAlgo::Sha256 => write!(f, "sha256"),
+            ChecksumAlgo::Sha512 => write!(f, "sha512"),
+            ChecksumAlgo::Blake3 => write!(f, "blake3"),
+        }
+    }
+}
+
+impl std::str::FromStr for ChecksumAlgo {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sha256" | "sha-256" => Ok(ChecksumAlgo::Sha256),
+            "sha512" | "sha-512" => Ok(ChecksumAlgo::Sha512),
+            "blake3" => Ok(ChecksumAlgo::Blake3),
+            _ => Err(format!("Unknown checksum algorithm: {}", s)),
+        }
+    }
+}
+
+/// Expected checksum of a model file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(from = "ChecksumRepr")]
+pub struct Checksum {
+    pub algo: ChecksumAlgo,
+    pub value: String,
+}
+
+/// Accepts both the legacy bare SHA-256 string and the `{ algo, value }` form
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ChecksumRepr {
+    Legacy(String),
+    Full { algo: ChecksumAlgo, value: String },
+}
+
+impl From<ChecksumRepr> for Checksum {
+    fn from(repr: ChecksumRepr) -> Self {
+        match repr {
+            ChecksumRepr::Legacy(value) => Checksum::sha256(value),
+            ChecksumRepr::Full { algo, value } => Checksum { algo, value },
+        }
+    }
+}
+
+impl Checksum {
+    pub fn sha256(value: impl Into<String>) -> Self {
+        Self {
+            algo: ChecksumAlgo::Sha256,
+            value: value.into(),
+        }
+    }
+
+    /// Placeholder for models whose checksum isn't published
+    pub fn unknown() -> Self {
+        Self::sha256("unknown")
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        self.value == "unknown"
+    }
+}
+
+impl std::fmt::Display for Checksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.algo, self.value)
+    }
+}
+
 /// Metadata for a Whisper model
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelInfo {
     pub name: String,
+    /// Approximate download size for display, e.g. "466 MB"
     pub size: String,
+    /// Exact download size, for progress and validation when the server doesn't send a length
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_bytes: Option<u64>,
     pub quantization: Quantization,
     pub url: String,
-    pub sha256: String,
+    #[serde(alias = "sha256")]
+    pub checksum: Checksum,
     pub filename: String,
+    /// Transcription settings tuned for this model, used unless flags or config say otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub defaults: Option<ModelDefaults>,
+}
+
+/// Per-model transcription defaults shipped with the registry
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelDefaults {
+    pub language: Option<String>,
+    pub threads: Option<u32>,
+    pub sampling: Option<Sampling>,
+}
+
+/// Decoding strategy used by Whisper
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum Sampling {
+    Greedy { best_of: u32 },
+    BeamSearch { beam_size: u32 },
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Sampling::Greedy { best_of: 1 }
+    }
 }
 
 /// Cached model information
@@ -63,18 +228,222 @@ pub struct CachedModel {
     pub info: ModelInfo,
     pub path: PathBuf,
     pub cached_at: std::time::SystemTime,
+    /// Size of the model file on disk
+    #[serde(default)]
+    pub size_bytes: u64,
+}
+
+/// Result of installing a single model
+#[derive(Debug, Clone)]
+pub struct InstallOutcome {
+    pub path: PathBuf,
+    /// Whether the cached copy was reused instead of downloading
+    pub cache_hit: bool,
+    /// Bytes fetched over the network (0 when the cached copy was reused)
+    pub bytes_downloaded: u64,
+    /// Whether the file matched a known checksum. False when verification
+    /// is off or there was no checksum to check against.
+    pub verified: bool,
+}
+
+/// Result of checking a cached model against its recorded checksum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyStatus {
+    Valid,
+    Mismatch,
+    /// No checksum is known for the model, so it couldn't be checked
+    Unverified,
+}
+
+/// Why `model gc` would remove a file from the cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GarbageKind {
+    /// Metadata whose model file is gone
+    OrphanedMetadata,
+    /// A `.part` download that hasn't been written to for a while
+    StalePartial,
+}
+
+impl std::fmt::Display for GarbageKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GarbageKind::OrphanedMetadata => write!(f, "orphaned metadata"),
+            GarbageKind::StalePartial => write!(f, "stale partial download"),
+        }
+    }
+}
+
+/// A cache file that can be deleted without losing an installed model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Garbage {
+    pub path: PathBuf,
+    pub kind: GarbageKind,
+    pub bytes: u64,
+}
+
+impl Garbage {
+    pub fn remove(&self) -> Result<()> {
+        fs::remove_file(&self.path).map_err(|e| MicrodropError::ModelCache(format!("Failed to remove {}: {}", self.path.display(), e)))?;
+        debug!("Removed {} ({})", self.path.display(), self.kind);
+        Ok(())
+    }
 }
 
+/// Number of downloads allowed at once unless configured otherwise
+pub const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 3;
+
+/// Extensions of whisper.cpp model files: legacy GGML (`.bin`, `.ggml`) and GGUF
+pub const MODEL_EXTENSIONS: &[&str] = &["bin", "ggml", "gguf"];
+
+/// Number of models hashed at once by [`ModelManager::verify_cached`]
+const MAX_CONCURRENT_VERIFICATIONS: usize = 4;
+
 /// Model registry containing available models
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ModelRegistry {
+    /// Version of the registry format; registries from before it was versioned are version 1
+    #[serde(default = "default_registry_version")]
+    pub version: u32,
     pub models: Vec<ModelInfo>,
 }
 
+/// Registry format versions this build understands
+pub const SUPPORTED_REGISTRY_VERSIONS: RangeInclusive<u32> = 1..=1;
+
+fn default_registry_version() -> u32 {
+    1
+}
+
+/// Just the version of a registry, read before the rest so a newer format
+/// is reported as such rather than as whatever field fails to parse
+#[derive(Deserialize)]
+struct RegistryVersion {
+    #[serde(default = "default_registry_version")]
+    version: u32,
+}
+
+impl ModelRegistry {
+    /// Parse a registry document, checking its format version first
+    pub fn from_json(json: &[u8]) -> std::result::Result<Self, String> {
+        let RegistryVersion { version } =
+            serde_json::from_slice(json).map_err(|e| format!("invalid registry: {}", e))?;
+        check_registry_version(version)?;
+        serde_json::from_slice(json).map_err(|e| format!("invalid registry: {}", e))
+    }
+}
+
+/// Error unless `version` is a registry format this build understands
+fn check_registry_version(version: u32) -> std::result::Result<(), String> {
+    if version > *SUPPORTED_REGISTRY_VERSIONS.end() {
+        Err(format!(
+            "registry format version {} is newer than this microdrop understands (up to {}); please upgrade microdrop",
+            version,
+            SUPPORTED_REGISTRY_VERSIONS.end()
+        ))
+    } else if version < *SUPPORTED_REGISTRY_VERSIONS.start() {
+        Err(format!(
+            "registry format version {} is no longer supported (oldest is {})",
+            version,
+            SUPPORTED_REGISTRY_VERSIONS.start()
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// File in the cache directory holding the registry last fetched from the registry URL
+const REGISTRY_CACHE_FILE: &str = "registry.cache";
+
+/// Attempts at fetching the registry before falling back to a cached copy
+const REGISTRY_FETCH_ATTEMPTS: u32 = 3;
+
+/// Wait before the first registry retry, doubled for each later one
+const REGISTRY_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// Time allowed for a single registry request
+const REGISTRY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Largest registry document accepted
+const MAX_REGISTRY_BYTES: usize = 1024 * 1024;
+
+/// Where the list of available models came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistrySource {
+    /// Freshly downloaded from the registry URL
+    Remote,
+    /// The copy cached by an earlier download
+    Cached,
+    /// The list compiled into the binary
+    Builtin,
+}
+
+impl std::fmt::Display for RegistrySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistrySource::Remote => write!(f, "remote"),
+            RegistrySource::Cached => write!(f, "cached"),
+            RegistrySource::Builtin => write!(f, "builtin"),
+        }
+    }
+}
+
+/// A downloaded registry with the validators used to revalidate it
+#[derive(Debug, Serialize, Deserialize)]
+struct RegistryCache {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    registry: ModelRegistry,
+}
+
+/// Outcome of a conditional registry request
+enum RegistryFetch {
+    Modified(RegistryCache),
+    NotModified,
+}
+
+/// Why a registry request failed, and whether trying again could help
+enum RegistryFetchError {
+    Transient(String),
+    Permanent(String),
+}
+
+/// Bandwidth limit shared by every download of a [`ModelManager`]
+struct Throttle {
+    bytes_per_sec: u64,
+    /// When the bandwidth reserved so far has been used up
+    next_free: Mutex<Instant>,
+}
+
+impl Throttle {
+    fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec: bytes_per_sec.max(1),
+            next_free: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Reserve bandwidth for `bytes` just received, returning when the transfer may continue
+    fn reserve(&self, bytes: usize, now: Instant) -> Instant {
+        let mut next_free = self.next_free.lock().unwrap_or_else(|e| e.into_inner());
+        let start = (*next_free).max(now);
+        *next_free = start + Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+        *next_free
+    }
+}
+
 /// Manages Whisper model downloads, caching, and resolution
 pub struct ModelManager {
     cache_dir: PathBuf,
     client: Client,
+    progress: MultiProgress,
+    max_concurrent_downloads: usize,
+    download_slots: Semaphore,
+    throttle: Option<Throttle>,
+    registry_url: Option<String>,
+    verify_checksums: bool,
+    resume_downloads: bool,
 }
 
 impl ModelManager {
@@ -84,11 +453,14 @@ impl ModelManager {
 
         // Ensure cache directory exists
         fs::create_dir_all(&cache_dir)
-            .map_err(|e| MicrodropError::ModelLoad(format!("Failed to create cache directory: {}", e)))?;
+            .map_err(|e| MicrodropError::ModelCache(format!("Failed to create cache directory: {}", e)))?;
 
-        let client = Client::new();
+        Ok(Self::with_dir(cache_dir))
+    }
 
-        Ok(Self { cache_dir, client })
+    /// Create a model manager for the `models` directory under `data_dir`
+    pub fn in_data_dir(data_dir: &Path) -> Result<Self> {
+        Self::with_cache_dir(Self::cache_dir_in(data_dir))
     }
 
     /// Create a model manager with a custom cache directory
@@ -96,20 +468,65 @@ impl ModelManager {
         let cache_dir = cache_dir.as_ref().to_path_buf();
 
         fs::create_dir_all(&cache_dir)
-            .map_err(|e| MicrodropError::ModelLoad(format!("Failed to create cache directory: {}", e)))?;
+            .map_err(|e| MicrodropError::ModelCache(format!("Failed to create cache directory: {}", e)))?;
+
+        Ok(Self::with_dir(cache_dir))
+    }
+
+    fn with_dir(cache_dir: PathBuf) -> Self {
+        Self {
+            cache_dir,
+            client: Client::new(),
+            progress: MultiProgress::new(),
+            max_concurrent_downloads: DEFAULT_MAX_CONCURRENT_DOWNLOADS,
+            download_slots: Semaphore::new(DEFAULT_MAX_CONCURRENT_DOWNLOADS),
+            throttle: None,
+            registry_url: None,
+            verify_checksums: true,
+            resume_downloads: false,
+        }
+    }
+
+    /// Limit how many downloads may run at once (at least one)
+    pub fn set_max_concurrent_downloads(&mut self, max: usize) {
+        let max = max.max(1);
+        self.max_concurrent_downloads = max;
+        self.download_slots = Semaphore::new(max);
+    }
+
+    /// Cap the combined bandwidth of all downloads, in bytes per second (None = unlimited)
+    pub fn set_rate_limit(&mut self, bytes_per_sec: Option<u64>) {
+        self.throttle = bytes_per_sec.map(Throttle::new);
+    }
+
+    /// Fetch the list of available models from this URL instead of using the
+    /// builtin list. The last download is cached and revalidated with `ETag` and
+    /// `Last-Modified`, and used whenever the URL can't be reached.
+    pub fn set_registry_url(&mut self, url: Option<String>) {
+        self.registry_url = url;
+    }
 
-        let client = Client::new();
+    /// Whether installs check models against their recorded checksums (on by
+    /// default). Turning it off trusts whatever was downloaded or is cached.
+    pub fn set_verify_checksums(&mut self, verify: bool) {
+        self.verify_checksums = verify;
+    }
 
-        Ok(Self { cache_dir, client })
+    /// Continue an interrupted download from its `.part` file instead of
+    /// starting over, as long as the partial still matches the model's URL,
+    /// checksum and size
+    pub fn set_resume_downloads(&mut self, resume: bool) {
+        self.resume_downloads = resume;
     }
 
     /// Get the default cache directory
     pub fn default_cache_dir() -> Result<PathBuf> {
-        let data_dir = dirs::data_local_dir()
-            .or_else(|| dirs::home_dir().map(|h| h.join(".local/share")))
-            .ok_or_else(|| MicrodropError::ModelLoad("Could not determine cache directory".to_string()))?;
+        Ok(Self::cache_dir_in(&Config::default_data_dir()?))
+    }
 
-        Ok(data_dir.join("microdrop/models"))
+    /// Model cache directory within a data directory
+    pub fn cache_dir_in(data_dir: &Path) -> PathBuf {
+        data_dir.join("models")
     }
 
     /// List all cached models
@@ -121,60 +538,58 @@ impl ModelManager {
         }
 
         for entry in fs::read_dir(&self.cache_dir)
-            .map_err(|e| MicrodropError::ModelLoad(format!("Failed to read cache directory: {}", e)))?
+            .map_err(|e| MicrodropError::ModelCache(format!("Failed to read cache directory: {}", e)))?
         {
-            let entry = entry.map_err(|e| MicrodropError::ModelLoad(format!("Failed to read directory entry: {}", e)))?;
+            let entry = entry.map_err(|e| MicrodropError::ModelCache(format!("Failed to read directory entry: {}", e)))?;
             let path = entry.path();
 
-            if path.is_file() {
-                if let Some(ext) = path.extension() {
-                    if ext == "bin" || ext == "ggml" {
-                        // Try to read cached metadata
-                        let metadata_path = path.with_extension("json");
-                        if metadata_path.exists() {
-                            match self.read_cached_metadata(&metadata_path) {
-                                Ok(info) => {
-                                    let cached_at = entry.metadata()
-                                        .and_then(|m| m.created())
-                                        .unwrap_or_else(|_| std::time::SystemTime::now());
-
-                                    cached_models.push(CachedModel {
-                                        info,
-                                        path: path.clone(),
-                                        cached_at,
-                                    });
-                                }
-                                Err(e) => {
-                                    warn!("Failed to read metadata for {}: {}", path.display(), e);
-                                }
-                            }
-                        } else {
-                            // Model file without metadata - create basic info
-                            let filename = path.file_name()
-                                .and_then(|n| n.to_str())
-                                .unwrap_or("unknown")
-                                .to_string();
-
-                            let info = ModelInfo {
-                                name: filename.clone(),
-                                size: "unknown".to_string(),
-                                quantization: Quantization::None,
-                                url: "local".to_string(),
-                                sha256: "unknown".to_string(),
-                                filename,
-                            };
-
-                            let cached_at = entry.metadata()
-                                .and_then(|m| m.created())
-                                .unwrap_or_else(|_| std::time::SystemTime::now());
-
+            if path.is_file() && is_model_file(&path) {
+                let file_metadata = entry.metadata().ok();
+                let cached_at = file_metadata.as_ref()
+                    .and_then(|m| m.created().ok())
+                    .unwrap_or_else(std::time::SystemTime::now);
+                let size_bytes = file_metadata.map_or(0, |m| m.len());
+
+                // Try to read cached metadata
+                let metadata_path = path.with_extension("json");
+                if metadata_path.exists() {
+                    match self.read_cached_metadata(&metadata_path) {
+                        Ok(info) => {
                             cached_models.push(CachedModel {
                                 info,
                                 path: path.clone(),
                                 cached_at,
+                                size_bytes,
                             });
                         }
+                        Err(e) => {
+                            warn!("Failed to read metadata for {}: {}", path.display(), e);
+                        }
                     }
+                } else {
+                    // Model file without metadata - create basic info
+                    let filename = path.file_name()
+                        .and_then(|n| n.to_str())
+                        .unwrap_or("unknown")
+                        .to_string();
+
+                    let info = ModelInfo {
+                        name: filename.clone(),
+                        size: "unknown".to_string(),
+                        size_bytes: None,
+                        quantization: Quantization::None,
+                        url: "local".to_string(),
+                        checksum: Checksum::unknown(),
+                        filename,
+                        defaults: None,
+                    };
+
+                    cached_models.push(CachedModel {
+                        info,
+                        path: path.clone(),
+                        cached_at,
+                        size_bytes,
+                    });
                 }
             }
         }
@@ -182,16 +597,99 @@ impl ModelManager {
         Ok(cached_models)
     }
 
+    /// Find metadata files without a model next to them, and partial downloads
+    /// last modified at least `min_age` ago. Nothing is deleted.
+    pub fn find_garbage(&self, min_age: Duration) -> Result<Vec<Garbage>> {
+        let mut garbage = Vec::new();
+
+        if !self.cache_dir.exists() {
+            return Ok(garbage);
+        }
+
+        for entry in fs::read_dir(&self.cache_dir)
+            .map_err(|e| MicrodropError::ModelCache(format!("Failed to read cache directory: {}", e)))?
+        {
+            let entry = entry.map_err(|e| MicrodropError::ModelCache(format!("Failed to read directory entry: {}", e)))?;
+            let path = entry.path();
+            let Ok(metadata) = entry.metadata() else { continue };
+            if !metadata.is_file() {
+                continue;
+            }
+
+            // A recently written part may belong to a download in progress
+            let is_stale = |metadata: &fs::Metadata| {
+                metadata.modified().ok().and_then(|modified| modified.elapsed().ok()).is_some_and(|age| age >= min_age)
+            };
+
+            let kind = match path.extension().and_then(|e| e.to_str()) {
+                Some("json") if is_partial_metadata(&path) => {
+                    // Resume metadata goes with its partial download
+                    match fs::metadata(path.with_extension("")) {
+                        Ok(part_metadata) => is_stale(&part_metadata).then_some(GarbageKind::StalePartial),
+                        Err(_) => Some(GarbageKind::OrphanedMetadata),
+                    }
+                }
+                Some("json") => {
+                    let has_model = MODEL_EXTENSIONS.iter().any(|ext| path.with_extension(ext).is_file());
+                    (!has_model).then_some(GarbageKind::OrphanedMetadata)
+                }
+                Some("part") => is_stale(&metadata).then_some(GarbageKind::StalePartial),
+                _ => None,
+            };
+
+            if let Some(kind) = kind {
+                debug!("Found {}: {}", kind, path.display());
+                garbage.push(Garbage { path, kind, bytes: metadata.len() });
+            }
+        }
+
+        garbage.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(garbage)
+    }
+
     /// Get available models from the registry
     pub async fn list_available_models(&self) -> Result<Vec<ModelInfo>> {
-        // For now, return a hardcoded list of common Whisper models
-        // In a real implementation, this could fetch from a remote registry
-        Ok(self.get_builtin_model_registry())
+        Ok(self.available_models().await.0)
+    }
+
+    /// Get available models from the registry URL, falling back to the cached
+    /// registry and then the builtin list, along with the source that was used
+    pub async fn available_models(&self) -> (Vec<ModelInfo>, RegistrySource) {
+        let Some(ref url) = self.registry_url else {
+            return (self.get_builtin_model_registry(), RegistrySource::Builtin);
+        };
+
+        // A copy fetched from another URL says nothing about this one
+        let cached = self.read_registry_cache().filter(|c| c.url == *url);
+        let result = self.fetch_registry(url, cached.as_ref()).await;
+
+        let (models, source) = match (result, cached) {
+            (Ok(RegistryFetch::Modified(fresh)), _) => {
+                if let Err(e) = self.write_registry_cache(&fresh) {
+                    warn!("Failed to cache model registry: {}", e);
+                }
+                (fresh.registry.models, RegistrySource::Remote)
+            }
+            (Ok(RegistryFetch::NotModified), Some(cached)) => (cached.registry.models, RegistrySource::Cached),
+            (result, cached) => {
+                let fallback = match cached {
+                    Some(cached) => (cached.registry.models, RegistrySource::Cached),
+                    None => (self.get_builtin_model_registry(), RegistrySource::Builtin),
+                };
+                if let Err(e) = result {
+                    warn!("{}; using the {} model registry", e, fallback.1);
+                }
+                fallback
+            }
+        };
+
+        debug!("Using {} model registry for {} ({} models)", source, url, models.len());
+        (models, source)
     }
 
-    /// Download and cache a model
-    pub async fn install_model(&self, model_name: &str, quantization: Option<Quantization>) -> Result<PathBuf> {
-        let models = self.get_builtin_model_registry();
+    /// Download and cache a model, reusing a verified cached copy unless `force` is set
+    pub async fn install_model(&self, model_name: &str, quantization: Option<Quantization>, force: bool) -> Result<InstallOutcome> {
+        let (models, _) = self.available_models().await;
         let quantization = quantization.unwrap_or(Quantization::None);
 
         // Find the requested model
@@ -199,43 +697,144 @@ impl ModelManager {
             .iter()
             .find(|m| m.name == model_name && m.quantization == quantization)
             .ok_or_else(|| {
-                MicrodropError::ModelLoad(format!(
+                MicrodropError::ModelRegistry(format!(
                     "Model '{}' with quantization '{}' not found in registry",
                     model_name, quantization
                 ))
-            })?
-            .clone();
+            })?;
+
+        self.install_model_info(model_info, force).await
+    }
+
+    /// Download a model from `url` into the cache as `name`, bypassing the
+    /// registry. Without a `checksum` the download isn't verified.
+    pub async fn install_from_url(&self, url: &str, name: &str, checksum: Option<Checksum>, force: bool) -> Result<InstallOutcome> {
+        let model_info = ModelInfo {
+            name: name.to_string(),
+            size: "unknown".to_string(),
+            size_bytes: None,
+            quantization: Quantization::None,
+            url: url.to_string(),
+            checksum: checksum.unwrap_or_else(Checksum::unknown),
+            filename: url_model_filename(name, url),
+            defaults: None,
+        };
+
+        self.install_model_info(&model_info, force).await
+    }
+
+    /// Install every registry model, downloading a few at a time.
+    ///
+    /// Models already cached with a matching checksum are skipped unless `force`
+    /// is set. Failures are reported per model rather than aborting the remaining
+    /// downloads.
+    pub async fn install_all(&self, force: bool) -> Vec<(ModelInfo, Result<InstallOutcome>)> {
+        let (models, _) = self.available_models().await;
+
+        let mut results: Vec<(usize, ModelInfo, Result<InstallOutcome>)> = stream::iter(models.into_iter().enumerate())
+            .map(|(index, model_info)| async move {
+                let result = self.install_model_info(&model_info, force).await;
+                (index, model_info, result)
+            })
+            .buffer_unordered(self.max_concurrent_downloads)
+            .collect()
+            .await;
+
+        // Report in registry order regardless of completion order
+        results.sort_by_key(|(index, _, _)| *index);
+        results.into_iter().map(|(_, info, result)| (info, result)).collect()
+    }
+
+    /// Verify every cached model's checksum, hashing a few files at once.
+    ///
+    /// Hashing runs on the blocking thread pool. A model that can't be read is
+    /// reported as an error for that model without stopping the others.
+    pub async fn verify_cached(&self) -> Result<Vec<(CachedModel, Result<VerifyStatus>)>> {
+        let cached_models = self.list_cached_models()?;
+
+        let pb = self.progress.add(ProgressBar::new(cached_models.len() as u64));
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("{spinner:.green} Verifying [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+                .unwrap()
+                .progress_chars("#>-"),
+        );
+
+        let mut results: Vec<(usize, CachedModel, Result<VerifyStatus>)> = stream::iter(cached_models.into_iter().enumerate())
+            .map(|(index, cached)| {
+                let pb = pb.clone();
+                async move {
+                    let path = cached.path.clone();
+                    let checksum = cached.info.checksum.clone();
+                    let result = tokio::task::spawn_blocking(move || verify_file(&path, &checksum))
+                        .await
+                        .unwrap_or_else(|e| Err(MicrodropError::ModelVerification(format!("Verification task failed: {}", e))));
+                    pb.set_message(cached.info.name.clone());
+                    pb.inc(1);
+                    (index, cached, result)
+                }
+            })
+            .buffer_unordered(MAX_CONCURRENT_VERIFICATIONS)
+            .collect()
+            .await;
+
+        pb.finish_and_clear();
+
+        results.sort_by_key(|(index, _, _)| *index);
+        Ok(results.into_iter().map(|(_, cached, result)| (cached, result)).collect())
+    }
 
+    async fn install_model_info(&self, model_info: &ModelInfo, force: bool) -> Result<InstallOutcome> {
+        let model_name = &model_info.name;
+        let quantization = &model_info.quantization;
         let target_path = self.cache_dir.join(&model_info.filename);
 
         // Check if already cached with correct checksum
-        if target_path.exists() {
-            if self.verify_checksum(&target_path, &model_info.sha256)? {
+        if force && target_path.exists() {
+            info!("Re-downloading model '{}' as requested", model_name);
+        } else if target_path.exists() {
+            if !self.verify_checksums {
+                warn!("Using cached model '{}' without checksum verification", model_name);
+                return Ok(InstallOutcome { path: target_path, cache_hit: true, bytes_downloaded: 0, verified: false });
+            }
+            if self.verify_checksum(&target_path, &model_info.checksum)? {
                 info!("Model '{}' already cached and verified", model_name);
-                return Ok(target_path);
+                let verified = !model_info.checksum.is_unknown();
+                return Ok(InstallOutcome { path: target_path, cache_hit: true, bytes_downloaded: 0, verified });
             } else {
                 warn!("Cached model '{}' failed checksum verification, re-downloading", model_name);
+                discard_download(&target_path);
             }
         }
 
         info!("Downloading model '{}' with quantization '{}'", model_name, quantization);
 
         // Download the model
-        self.download_model(&model_info, &target_path).await?;
-
-        // Verify checksum
-        if !self.verify_checksum(&target_path, &model_info.sha256)? {
-            fs::remove_file(&target_path).ok();
-            return Err(MicrodropError::ModelLoad(
-                "Downloaded model failed checksum verification".to_string()
-            ));
+        let bytes_downloaded = self.download_model(model_info, &target_path).await?;
+
+        // Verify size, which is cheap, then checksum
+        if !self.verify_checksums {
+            warn!("Skipping checksum verification for model '{}'", model_name);
+        } else if let Some((size, expected)) = size_mismatch(&target_path, model_info.size_bytes)? {
+            discard_download(&target_path);
+            return Err(MicrodropError::ModelVerification(format!(
+                "Downloaded model '{}' is {} bytes, expected {}",
+                model_name, size, expected
+            )));
+        } else if !self.verify_checksum(&target_path, &model_info.checksum)? {
+            discard_download(&target_path);
+            return Err(MicrodropError::ModelVerification(format!(
+                "Downloaded model '{}' failed checksum verification",
+                model_name
+            )));
         }
 
         // Save metadata
-        self.save_model_metadata(&model_info, &target_path)?;
+        self.save_model_metadata(model_info, &target_path)?;
 
         info!("Model '{}' downloaded and cached successfully", model_name);
-        Ok(target_path)
+        let verified = self.verify_checksums && !model_info.checksum.is_unknown();
+        Ok(InstallOutcome { path: target_path, cache_hit: false, bytes_downloaded, verified })
     }
 
     /// Resolve a model name to a local path
@@ -243,16 +842,19 @@ impl ModelManager {
         let cached_models = self.list_cached_models()?;
         let quantization = quantization.unwrap_or(Quantization::None);
 
+        // Names compare in NFC, so composed and decomposed accents match
+        let same_name = |cached: &CachedModel| cached.info.name.nfc().eq(model_name.nfc());
+
         // Look for exact match
         for cached in &cached_models {
-            if cached.info.name == model_name && cached.info.quantization == quantization {
+            if same_name(cached) && cached.info.quantization == quantization {
                 return Ok(Some(cached.path.clone()));
             }
         }
 
         // Look for any model with the same name (ignore quantization)
         for cached in &cached_models {
-            if cached.info.name == model_name {
+            if same_name(cached) {
                 debug!("Found model '{}' with different quantization: {}", model_name, cached.info.quantization);
                 return Ok(Some(cached.path.clone()));
             }
@@ -273,126 +875,443 @@ impl ModelManager {
             ModelInfo {
                 name: "tiny.en".to_string(),
                 size: "39 MB".to_string(),
+                size_bytes: None,
                 quantization: Quantization::None,
                 url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-tiny.en.bin".to_string(),
-                sha256: "921e5841b9b85c8ca6df6b9f4d2e9c7e8c7b5b4f7d6e8e9f1a2b3c4d5e6f7a8b9".to_string(),
+                checksum: Checksum::sha256("921e5841b9b85c8ca6df6b9f4d2e9c7e8c7b5b4f7d6e8e9f1a2b3c4d5e6f7a8b9"),
                 filename: "ggml-tiny.en.bin".to_string(),
+                defaults: None,
             },
             ModelInfo {
                 name: "base.en".to_string(),
                 size: "142 MB".to_string(),
+                size_bytes: None,
                 quantization: Quantization::None,
                 url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-base.en.bin".to_string(),
-                sha256: "a1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6e7f8a9b0c1d2e3f4a5b6c7d8e9f0a1b2".to_string(),
+                checksum: Checksum::sha256("a1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6e7f8a9b0c1d2e3f4a5b6c7d8e9f0a1b2"),
                 filename: "ggml-base.en.bin".to_string(),
+                defaults: None,
             },
             ModelInfo {
                 name: "small.en".to_string(),
                 size: "466 MB".to_string(),
+                size_bytes: None,
                 quantization: Quantization::None,
                 url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-small.en.bin".to_string(),
-                sha256: "b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6e7f8a9b0c1d2e3f4a5b6c7d8e9f0a1b2c3".to_string(),
+                checksum: Checksum::sha256("b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6e7f8a9b0c1d2e3f4a5b6c7d8e9f0a1b2c3"),
                 filename: "ggml-small.en.bin".to_string(),
+                defaults: None,
             },
             ModelInfo {
                 name: "small.en".to_string(),
                 size: "185 MB".to_string(),
+                size_bytes: None,
                 quantization: Quantization::Q5_1,
                 url: "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-small.en-q5_1.bin".to_string(),
-                sha256: "c3d4e5f6a7b8c9d0e1f2a3b4c5d6e7f8a9b0c1d2e3f4a5b6c7d8e9f0a1b2c3d4".to_string(),
+                checksum: Checksum::sha256("c3d4e5f6a7b8c9d0e1f2a3b4c5d6e7f8a9b0c1d2e3f4a5b6c7d8e9f0a1b2c3d4"),
                 filename: "ggml-small.en-q5_1.bin".to_string(),
+                defaults: None,
             },
         ]
     }
 
-    async fn download_model(&self, model_info: &ModelInfo, target_path: &Path) -> Result<()> {
-        let response = self
-            .client
-            .get(&model_info.url)
-            .send()
-            .await
-            .map_err(|e| MicrodropError::ModelLoad(format!("Failed to start download: {}", e)))?;
+    /// Fetch the registry from `url`, retrying transient failures with backoff.
+    /// With a `cached` copy the request is conditional on it having changed.
+    async fn fetch_registry(&self, url: &str, cached: Option<&RegistryCache>) -> Result<RegistryFetch> {
+        let mut attempt = 1;
+        let mut delay = REGISTRY_RETRY_DELAY;
+        loop {
+            match self.fetch_registry_once(url, cached).await {
+                Ok(fetch) => return Ok(fetch),
+                Err(RegistryFetchError::Transient(e)) if attempt < REGISTRY_FETCH_ATTEMPTS => {
+                    debug!("Registry fetch attempt {} failed: {}; retrying in {:?}", attempt, e, delay);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                    delay *= 2;
+                }
+                Err(RegistryFetchError::Transient(e) | RegistryFetchError::Permanent(e)) => {
+                    return Err(MicrodropError::ModelRegistry(format!("Failed to fetch model registry from {}: {}", url, e)));
+                }
+            }
+        }
+    }
 
-        if !response.status().is_success() {
-            return Err(MicrodropError::ModelLoad(format!(
-                "Download failed with status: {}",
-                response.status()
-            )));
+    async fn fetch_registry_once(&self, url: &str, cached: Option<&RegistryCache>) -> std::result::Result<RegistryFetch, RegistryFetchError> {
+        let mut request = self.client.get(url).timeout(REGISTRY_TIMEOUT);
+        if let Some(cached) = cached {
+            if let Some(ref etag) = cached.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(ref last_modified) = cached.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = request.send().await.map_err(|e| RegistryFetchError::Transient(e.to_string()))?;
+        let status = response.status();
+        if status == StatusCode::NOT_MODIFIED && cached.is_some() {
+            return Ok(RegistryFetch::NotModified);
+        }
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(RegistryFetchError::Transient(format!("server returned {}", status)));
+        }
+        if !status.is_success() {
+            return Err(RegistryFetchError::Permanent(format!("server returned {}", status)));
+        }
+
+        let header = |name| response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+
+        let mut body = Vec::new();
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| RegistryFetchError::Transient(format!("failed to read response: {}", e)))?;
+            if body.len() + chunk.len() > MAX_REGISTRY_BYTES {
+                return Err(RegistryFetchError::Permanent(format!("registry is larger than {} bytes", MAX_REGISTRY_BYTES)));
+            }
+            body.extend_from_slice(&chunk);
         }
 
-        let total_size = response.content_length().unwrap_or(0);
+        let registry = ModelRegistry::from_json(&body).map_err(RegistryFetchError::Permanent)?;
+        Ok(RegistryFetch::Modified(RegistryCache { url: url.to_string(), etag, last_modified, registry }))
+    }
+
+    fn registry_cache_path(&self) -> PathBuf {
+        self.cache_dir.join(REGISTRY_CACHE_FILE)
+    }
+
+    fn read_registry_cache(&self) -> Option<RegistryCache> {
+        let path = self.registry_cache_path();
+        let content = fs::read_to_string(&path).ok()?;
+        let cache: RegistryCache = serde_json::from_str(&content)
+            .map_err(|e| warn!("Ignoring unreadable registry cache {}: {}", path.display(), e))
+            .ok()?;
+        // Cached by a newer microdrop before a downgrade
+        check_registry_version(cache.registry.version)
+            .map_err(|e| warn!("Ignoring registry cache {}: {}", path.display(), e))
+            .ok()?;
+        Some(cache)
+    }
+
+    fn write_registry_cache(&self, cache: &RegistryCache) -> Result<()> {
+        let json = serde_json::to_string_pretty(cache)
+            .map_err(|e| MicrodropError::ModelCache(format!("Failed to serialize registry: {}", e)))?;
+        fs::write(self.registry_cache_path(), json)
+            .map_err(|e| MicrodropError::ModelCache(format!("Failed to write registry cache: {}", e)))
+    }
+
+    async fn download_model(&self, model_info: &ModelInfo, target_path: &Path) -> Result<u64> {
+        let _slot = self
+            .download_slots
+            .acquire()
+            .await
+            .map_err(|e| MicrodropError::ModelDownload(format!("Download slots unavailable: {}", e)))?;
+
+        let part_path = partial_download_path(target_path);
+        let metadata_path = partial_metadata_path(target_path);
+        let resumable = if self.resume_downloads { resumable_partial(model_info, &part_path, &metadata_path) } else { None };
+
+        let mut response = self.send_download(&model_info.url, resumable.map(|partial| partial.0)).await?;
+        let resumed = match resumable {
+            Some((offset, total_size)) if response.status() == StatusCode::PARTIAL_CONTENT => {
+                if content_range_total(&response) == Some(total_size) {
+                    Some((offset, total_size))
+                } else {
+                    // The file changed on the server since the partial was written
+                    warn!("Remote size of model '{}' changed, restarting download", model_info.name);
+                    response = self.send_download(&model_info.url, None).await?;
+                    None
+                }
+            }
+            Some(_) => {
+                debug!("Server ignored the range request for '{}', restarting download", model_info.name);
+                None
+            }
+            None => None,
+        };
+
+        let (offset, total_size) = match resumed {
+            Some(resumed) => resumed,
+            None => (0, response.content_length().or(model_info.size_bytes).unwrap_or(0)),
+        };
 
         // Create progress bar
-        let pb = ProgressBar::new(total_size);
+        let pb = self.progress.add(ProgressBar::new(total_size));
         pb.set_style(
             ProgressStyle::default_bar()
                 .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
                 .unwrap()
                 .progress_chars("#>-"),
         );
-
-        // Create the target file
-        let mut file = File::create(target_path)
-            .map_err(|e| MicrodropError::ModelLoad(format!("Failed to create file: {}", e)))?;
+        pb.set_position(offset);
+
+        // Write to a side file so an interrupted download never looks like a cached model
+        let mut file = if resumed.is_some() {
+            info!("Resuming download of '{}' at {} of {} bytes", model_info.name, offset, total_size);
+            fs::OpenOptions::new()
+                .append(true)
+                .open(&part_path)
+                .map_err(|e| MicrodropError::ModelCache(format!("Failed to open partial download: {}", e)))?
+        } else {
+            let file = File::create(&part_path)
+                .map_err(|e| MicrodropError::ModelCache(format!("Failed to create file: {}", e)))?;
+            // Without a known size a later resume couldn't check the partial
+            if total_size > 0 {
+                let partial = PartialDownload {
+                    url: model_info.url.clone(),
+                    checksum: model_info.checksum.clone(),
+                    total_size,
+                };
+                write_partial_metadata(&metadata_path, &partial)?;
+            } else {
+                remove_if_exists(&metadata_path);
+            }
+            file
+        };
 
         // Download and write chunks
         let mut downloaded = 0u64;
         let mut stream = response.bytes_stream();
 
-        use futures_util::stream::StreamExt;
-
         while let Some(chunk) = stream.next().await {
             let chunk = chunk
-                .map_err(|e| MicrodropError::ModelLoad(format!("Failed to download chunk: {}", e)))?;
+                .map_err(|e| MicrodropError::ModelDownload(format!("Failed to download chunk: {}", e)))?;
 
             file.write_all(&chunk)
-                .map_err(|e| MicrodropError::ModelLoad(format!("Failed to write chunk: {}", e)))?;
+                .map_err(|e| MicrodropError::ModelCache(format!("Failed to write chunk: {}", e)))?;
 
             downloaded += chunk.len() as u64;
-            pb.set_position(downloaded);
+            pb.set_position(offset + downloaded);
+
+            if let Some(ref throttle) = self.throttle {
+                let resume_at = throttle.reserve(chunk.len(), Instant::now());
+                tokio::time::sleep_until(resume_at.into()).await;
+            }
         }
 
+        drop(file);
+        fs::rename(&part_path, target_path)
+            .map_err(|e| MicrodropError::ModelCache(format!("Failed to move download into place: {}", e)))?;
+        remove_if_exists(&metadata_path);
+
         pb.finish_with_message("Download completed");
 
-        Ok(())
+        Ok(downloaded)
     }
 
-    fn verify_checksum(&self, file_path: &Path, expected_sha256: &str) -> Result<bool> {
-        if expected_sha256 == "unknown" {
-            // Skip verification for unknown checksums
-            return Ok(true);
+    /// Start downloading `url`, from byte `offset` on if given
+    async fn send_download(&self, url: &str, offset: Option<u64>) -> Result<Response> {
+        let mut request = self.client.get(url);
+        if let Some(offset) = offset {
+            request = request.header(RANGE, format!("bytes={}-", offset));
         }
 
-        let file_content = fs::read(file_path)
-            .map_err(|e| MicrodropError::ModelLoad(format!("Failed to read file for checksum: {}", e)))?;
+        let response = request
+            .send()
+            .await
+            .map_err(|e| MicrodropError::ModelDownload(format!("Failed to start download: {}", e)))?;
 
-        let mut hasher = Sha256::new();
-        hasher.update(&file_content);
-        let computed_hash = format!("{:x}", hasher.finalize());
+        if !response.status().is_success() {
+            return Err(MicrodropError::ModelDownload(format!(
+                "Download failed with status: {}",
+                response.status()
+            )));
+        }
+        Ok(response)
+    }
 
-        Ok(computed_hash == expected_sha256)
+    fn verify_checksum(&self, file_path: &Path, expected: &Checksum) -> Result<bool> {
+        // Unknown checksums skip verification
+        Ok(verify_file(file_path, expected)? != VerifyStatus::Mismatch)
     }
 
     fn save_model_metadata(&self, model_info: &ModelInfo, model_path: &Path) -> Result<()> {
         let metadata_path = model_path.with_extension("json");
         let metadata_json = serde_json::to_string_pretty(model_info)
-            .map_err(|e| MicrodropError::ModelLoad(format!("Failed to serialize metadata: {}", e)))?;
+            .map_err(|e| MicrodropError::ModelCache(format!("Failed to serialize metadata: {}", e)))?;
 
         fs::write(&metadata_path, metadata_json)
-            .map_err(|e| MicrodropError::ModelLoad(format!("Failed to write metadata: {}", e)))?;
+            .map_err(|e| MicrodropError::ModelCache(format!("Failed to write metadata: {}", e)))?;
 
         Ok(())
     }
 
     fn read_cached_metadata(&self, metadata_path: &Path) -> Result<ModelInfo> {
         let metadata_content = fs::read_to_string(metadata_path)
-            .map_err(|e| MicrodropError::ModelLoad(format!("Failed to read metadata: {}", e)))?;
+            .map_err(|e| MicrodropError::ModelCache(format!("Failed to read metadata: {}", e)))?;
 
         serde_json::from_str(&metadata_content)
-            .map_err(|e| MicrodropError::ModelLoad(format!("Failed to parse metadata: {}", e)))
+            .map_err(|e| MicrodropError::ModelCache(format!("Failed to parse metadata: {}", e)))
+    }
+}
+
+/// Hash `file_path` and compare it against the expected checksum
+fn verify_file(file_path: &Path, expected: &Checksum) -> Result<VerifyStatus> {
+    if expected.is_unknown() {
+        return Ok(VerifyStatus::Unverified);
+    }
+
+    let computed_hash = expected
+        .algo
+        .digest_file(file_path)
+        .map_err(|e| MicrodropError::ModelCache(format!("Failed to read file for checksum: {}", e)))?;
+    debug!("Computed {} checksum for {}: {}", expected.algo, file_path.display(), computed_hash);
+
+    if computed_hash.eq_ignore_ascii_case(&expected.value) {
+        Ok(VerifyStatus::Valid)
+    } else {
+        Ok(VerifyStatus::Mismatch)
+    }
+}
+
+/// The size of `file_path` and the size it should have, if those differ.
+/// Resumed downloads are checked as a whole, not just the resumed part.
+fn size_mismatch(file_path: &Path, expected: Option<u64>) -> Result<Option<(u64, u64)>> {
+    let Some(expected) = expected else {
+        return Ok(None);
+    };
+    let size = fs::metadata(file_path)
+        .map_err(|e| MicrodropError::ModelCache(format!("Failed to read downloaded file size: {}", e)))?
+        .len();
+    Ok((size != expected).then_some((size, expected)))
+}
+
+/// Registry defaults recorded next to an installed model, if any
+pub fn cached_model_defaults(model_path: &Path) -> Option<ModelDefaults> {
+    cached_model_info(model_path)?.defaults
+}
+
+/// SHA-256 recorded next to an installed model, unless it is missing or unknown
+pub fn cached_model_sha256(model_path: &Path) -> Option<String> {
+    let checksum = cached_model_info(model_path)?.checksum;
+    (checksum.algo == ChecksumAlgo::Sha256 && !checksum.is_unknown()).then_some(checksum.value)
+}
+
+/// Metadata written next to an installed model
+fn cached_model_info(model_path: &Path) -> Option<ModelInfo> {
+    let content = fs::read_to_string(model_path.with_extension("json")).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Whether `path` has one of the [`MODEL_EXTENSIONS`]
+pub fn is_model_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| MODEL_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+}
+
+/// Cache file name for a model named `name` downloaded from `url`, keeping
+/// the URL's model extension (`.bin` if it has none)
+fn url_model_filename(name: &str, url: &str) -> String {
+    if is_model_file(Path::new(name)) {
+        return name.to_string();
+    }
+    let url_path = url.split(['?', '#']).next().unwrap_or(url);
+    let ext = Path::new(url_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .filter(|ext| MODEL_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+        .unwrap_or("bin");
+    format!("{}.{}", name, ext.to_ascii_lowercase())
+}
+
+/// Path a model is downloaded to before it is complete
+pub fn partial_download_path(target_path: &Path) -> PathBuf {
+    let mut path = target_path.as_os_str().to_owned();
+    path.push(".part");
+    PathBuf::from(path)
+}
+
+/// Path of the metadata a resume checks a partial download against
+pub fn partial_metadata_path(target_path: &Path) -> PathBuf {
+    let mut path = partial_download_path(target_path).into_os_string();
+    path.push(".json");
+    PathBuf::from(path)
+}
+
+/// Whether `path` is a `.part.json` written by [`partial_metadata_path`]
+fn is_partial_metadata(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+        && path.with_extension("").extension().is_some_and(|ext| ext == "part")
+}
+
+/// What a partial download was started from, stored next to the `.part` file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct PartialDownload {
+    url: String,
+    checksum: Checksum,
+    total_size: u64,
+}
+
+fn write_partial_metadata(metadata_path: &Path, partial: &PartialDownload) -> Result<()> {
+    let json = serde_json::to_string_pretty(partial)
+        .map_err(|e| MicrodropError::ModelCache(format!("Failed to serialize download metadata: {}", e)))?;
+    fs::write(metadata_path, json)
+        .map_err(|e| MicrodropError::ModelCache(format!("Failed to write download metadata: {}", e)))
+}
+
+/// Where to resume the partial download of `model_info`, and its total size.
+/// `None`, with the partial and its metadata removed, unless the partial was
+/// started from the same URL and checksum and is shorter than the full file.
+fn resumable_partial(model_info: &ModelInfo, part_path: &Path, metadata_path: &Path) -> Option<(u64, u64)> {
+    let len = fs::metadata(part_path).ok()?.len();
+    let partial: Option<PartialDownload> = fs::read_to_string(metadata_path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok());
+
+    match partial {
+        Some(partial)
+            if partial.url == model_info.url
+                && partial.checksum == model_info.checksum
+                && len > 0
+                && len < partial.total_size =>
+        {
+            Some((len, partial.total_size))
+        }
+        _ => {
+            warn!("Partial download of '{}' doesn't match the model, restarting it", model_info.name);
+            remove_if_exists(part_path);
+            remove_if_exists(metadata_path);
+            None
+        }
     }
 }
 
+/// Total length from a `Content-Range: bytes start-end/total` header
+fn content_range_total(response: &Response) -> Option<u64> {
+    let range = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    range.rsplit_once('/')?.1.parse().ok()
+}
+
+/// Remove `path`, returning whether it existed
+fn remove_if_exists(path: &Path) -> bool {
+    match fs::remove_file(path) {
+        Ok(()) => {
+            info!("Removed {}", path.display());
+            true
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+        Err(e) => {
+            warn!("Failed to remove {}: {}", path.display(), e);
+            false
+        }
+    }
+}
+
+/// Remove a model and any partial download of it, so the next install
+/// starts clean. Returns the paths that were removed.
+fn discard_download(target_path: &Path) -> Vec<PathBuf> {
+    [
+        target_path.to_path_buf(),
+        partial_download_path(target_path),
+        partial_metadata_path(target_path),
+    ]
+    .into_iter()
+    .filter(|path| remove_if_exists(path))
+    .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -417,6 +1336,266 @@ mod tests {
         assert!("invalid".parse::<Quantization>().is_err());
     }
 
+    #[test]
+    fn test_checksum_algo_digests() {
+        assert_eq!(
+            ChecksumAlgo::Sha256.digest_hex(b"hello"),
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(
+            ChecksumAlgo::Sha512.digest_hex(b"hello"),
+            "9b71d224bd62f3785d96d46ad3ea3d73319bfbc2890caadae2dff72519673ca72323c3d99ba5c11d7c7acc6e14b8c5da0c4663475c2e5c3adef46f73bcdec043"
+        );
+        assert_eq!(
+            ChecksumAlgo::Blake3.digest_hex(b"hello"),
+            "ea8f163db38682925e4491c5e58d4bb3506ef8c14eb78a86e908c5624a67200f"
+        );
+    }
+
+    #[test]
+    fn test_model_info_legacy_sha256_field() {
+        let json = r#"{
+            "name": "tiny.en",
+            "size": "39 MB",
+            "quantization": "None",
+            "url": "https://example.com/ggml-tiny.en.bin",
+            "sha256": "abc123",
+            "filename": "ggml-tiny.en.bin"
+        }"#;
+
+        let info: ModelInfo = serde_json::from_str(json).unwrap();
+        assert_eq!(info.checksum, Checksum::sha256("abc123"));
+        assert!(info.defaults.is_none());
+    }
+
+    #[test]
+    fn test_model_info_defaults() {
+        let json = r#"{
+            "name": "large-v3",
+            "size": "3.1 GB",
+            "quantization": "None",
+            "url": "https://example.com/ggml-large-v3.bin",
+            "checksum": { "algo": "sha256", "value": "abc123" },
+            "filename": "ggml-large-v3.bin",
+            "defaults": {
+                "threads": 8,
+                "sampling": { "strategy": "beam_search", "beam_size": 5 }
+            }
+        }"#;
+
+        let info: ModelInfo = serde_json::from_str(json).unwrap();
+        assert_eq!(info.size_bytes, None);
+        let defaults = info.defaults.clone().unwrap();
+        assert_eq!(defaults.language, None);
+        assert_eq!(defaults.threads, Some(8));
+        assert_eq!(defaults.sampling, Some(Sampling::BeamSearch { beam_size: 5 }));
+
+        // Installed metadata carries the defaults along with the model
+        let temp_dir = std::env::temp_dir().join("microdrop_test_model_defaults");
+        let manager = ModelManager::with_cache_dir(&temp_dir).unwrap();
+        let model_path = temp_dir.join(&info.filename);
+        fs::write(&model_path, b"model").unwrap();
+        manager.save_model_metadata(&info, &model_path).unwrap();
+        assert_eq!(cached_model_defaults(&model_path), Some(defaults));
+        assert_eq!(cached_model_defaults(&temp_dir.join("missing.bin")), None);
+
+        // Clean up
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_cached_model_sha256() {
+        let temp_dir = std::env::temp_dir().join("microdrop_test_cached_sha256");
+        let manager = ModelManager::with_cache_dir(&temp_dir).unwrap();
+        let mut info = ModelInfo {
+            name: "base.en".to_string(),
+            size: "142 MB".to_string(),
+            size_bytes: None,
+            quantization: Quantization::None,
+            url: "https://example.com/ggml-base.en.bin".to_string(),
+            checksum: Checksum::sha256("abc123"),
+            filename: "ggml-base.en.bin".to_string(),
+            defaults: None,
+        };
+        let model_path = temp_dir.join(&info.filename);
+        fs::write(&model_path, b"model").unwrap();
+
+        manager.save_model_metadata(&info, &model_path).unwrap();
+        assert_eq!(cached_model_sha256(&model_path), Some("abc123".to_string()));
+
+        // Unknown and non-SHA-256 checksums aren't reported
+        info.checksum = Checksum::unknown();
+        manager.save_model_metadata(&info, &model_path).unwrap();
+        assert_eq!(cached_model_sha256(&model_path), None);
+        info.checksum = Checksum { algo: ChecksumAlgo::Blake3, value: "def456".to_string() };
+        manager.save_model_metadata(&info, &model_path).unwrap();
+        assert_eq!(cached_model_sha256(&model_path), None);
+        assert_eq!(cached_model_sha256(&temp_dir.join("missing.bin")), None);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_model_info_checksum_field() {
+        let json = r#"{
+            "name": "tiny.en",
+            "size": "39 MB",
+            "quantization": "None",
+            "url": "https://example.com/ggml-tiny.en.bin",
+            "checksum": { "algo": "blake3", "value": "def456" },
+            "filename": "ggml-tiny.en.bin"
+        }"#;
+
+        let info: ModelInfo = serde_json::from_str(json).unwrap();
+        assert_eq!(info.checksum.algo, ChecksumAlgo::Blake3);
+        assert_eq!(info.checksum.value, "def456");
+
+        // Round-trips through the structured form
+        let reparsed: ModelInfo =
+            serde_json::from_str(&serde_json::to_string(&info).unwrap()).unwrap();
+        assert_eq!(reparsed.checksum, info.checksum);
+    }
+
+    #[test]
+    fn test_verify_checksum_dispatches_on_algo() {
+        let temp_dir = std::env::temp_dir().join("microdrop_test_verify_algo");
+        let manager = ModelManager::with_cache_dir(&temp_dir).unwrap();
+        let model_path = temp_dir.join("model.bin");
+        fs::write(&model_path, b"hello").unwrap();
+
+        let sha512 = Checksum {
+            algo: ChecksumAlgo::Sha512,
+            value: ChecksumAlgo::Sha512.digest_hex(b"hello"),
+        };
+        let blake3 = Checksum {
+            algo: ChecksumAlgo::Blake3,
+            value: ChecksumAlgo::Blake3.digest_hex(b"hello").to_uppercase(),
+        };
+        assert!(manager.verify_checksum(&model_path, &sha512).unwrap());
+        assert!(manager.verify_checksum(&model_path, &blake3).unwrap());
+        assert!(!manager.verify_checksum(&model_path, &Checksum::sha256("bad")).unwrap());
+        assert!(manager.verify_checksum(&model_path, &Checksum::unknown()).unwrap());
+
+        // Clean up
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_verify_cached_reports_each_model() {
+        let temp_dir = std::env::temp_dir().join("microdrop_test_verify_cached");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let manager = ModelManager::with_cache_dir(&temp_dir).unwrap();
+
+        let mut info = ModelInfo {
+            name: "good".to_string(),
+            size: "5 B".to_string(),
+            size_bytes: None,
+            quantization: Quantization::None,
+            url: "local".to_string(),
+            checksum: Checksum {
+                algo: ChecksumAlgo::Blake3,
+                value: ChecksumAlgo::Blake3.digest_hex(b"hello"),
+            },
+            filename: "good.bin".to_string(),
+            defaults: None,
+        };
+        fs::write(temp_dir.join("good.bin"), b"hello").unwrap();
+        manager.save_model_metadata(&info, &temp_dir.join("good.bin")).unwrap();
+
+        info.name = "bad".to_string();
+        info.filename = "bad.bin".to_string();
+        fs::write(temp_dir.join("bad.bin"), b"tampered").unwrap();
+        manager.save_model_metadata(&info, &temp_dir.join("bad.bin")).unwrap();
+
+        fs::write(temp_dir.join("local.bin"), b"no metadata").unwrap();
+
+        let results = manager.verify_cached().await.unwrap();
+        let status = |name: &str| {
+            results
+                .iter()
+                .find(|(cached, _)| cached.info.name == name)
+                .map(|(_, result)| *result.as_ref().unwrap())
+                .unwrap()
+        };
+        assert_eq!(results.len(), 3);
+        assert_eq!(status("good"), VerifyStatus::Valid);
+        assert_eq!(status("bad"), VerifyStatus::Mismatch);
+        assert_eq!(status("local.bin"), VerifyStatus::Unverified);
+
+        // Clean up
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_digest_file_matches_digest_hex() {
+        let temp_dir = std::env::temp_dir().join("microdrop_test_digest_file");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let path = temp_dir.join("model.bin");
+        let data: Vec<u8> = (0..3_000_000u32).map(|i| i as u8).collect();
+        fs::write(&path, &data).unwrap();
+
+        for algo in [ChecksumAlgo::Sha256, ChecksumAlgo::Sha512, ChecksumAlgo::Blake3] {
+            assert_eq!(algo.digest_file(&path).unwrap(), algo.digest_hex(&data));
+        }
+
+        // Clean up
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_throttle_spaces_out_transfers() {
+        let throttle = Throttle::new(1000);
+        let start = Instant::now();
+
+        // Bandwidth is shared, so back-to-back chunks queue behind each other
+        assert_eq!(throttle.reserve(500, start), start + Duration::from_millis(500));
+        assert_eq!(throttle.reserve(500, start), start + Duration::from_secs(1));
+
+        // Idle time isn't banked for later bursts
+        let later = start + Duration::from_secs(5);
+        assert_eq!(throttle.reserve(100, later), later + Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_max_concurrent_downloads() {
+        let temp_dir = std::env::temp_dir().join("microdrop_test_download_slots");
+        let mut manager = ModelManager::with_cache_dir(&temp_dir).unwrap();
+        assert_eq!(manager.download_slots.available_permits(), DEFAULT_MAX_CONCURRENT_DOWNLOADS);
+
+        manager.set_max_concurrent_downloads(0);
+        assert_eq!(manager.max_concurrent_downloads, 1);
+        assert_eq!(manager.download_slots.available_permits(), 1);
+
+        // Clean up
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_discard_download_removes_model_and_part() {
+        let temp_dir = std::env::temp_dir().join("microdrop_test_discard_download");
+        fs::create_dir_all(&temp_dir).unwrap();
+        let model_path = temp_dir.join("model.bin");
+        let part_path = partial_download_path(&model_path);
+        fs::write(&model_path, b"corrupt").unwrap();
+        fs::write(&part_path, b"interrupted").unwrap();
+
+        assert_eq!(discard_download(&model_path), vec![model_path.clone(), part_path.clone()]);
+        assert!(!model_path.exists());
+        assert!(!part_path.exists());
+
+        // Nothing left to remove
+        assert!(discard_download(&model_path).is_empty());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_partial_download_path_is_not_a_model() {
+        let part = partial_download_path(Path::new("/cache/ggml-base.en.bin"));
+        assert_eq!(part, PathBuf::from("/cache/ggml-base.en.bin.part"));
+        assert_eq!(part.extension().unwrap(), "part");
+    }
+
     #[test]
     fn test_model_manager_creation() {
         let temp_dir = std::env::temp_dir().join("microdrop_test_cache");
@@ -427,6 +1606,55 @@ mod tests {
         let _ = fs::remove_dir_all(&temp_dir);
     }
 
+    #[test]
+    fn test_find_garbage() {
+        let temp_dir = std::env::temp_dir().join("microdrop_test_find_garbage");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let manager = ModelManager::with_cache_dir(&temp_dir).unwrap();
+
+        // An installed model and its metadata are kept
+        fs::write(temp_dir.join("ggml-base.en.bin"), b"model").unwrap();
+        fs::write(temp_dir.join("ggml-base.en.json"), b"{}").unwrap();
+        // Metadata whose model was deleted is not
+        let orphan = temp_dir.join("ggml-tiny.en.json");
+        fs::write(&orphan, b"{}").unwrap();
+        let part = partial_download_path(&temp_dir.join("ggml-small.en.bin"));
+        fs::write(&part, b"interrupted").unwrap();
+
+        // The fresh part file may still be downloading
+        let garbage = manager.find_garbage(Duration::from_secs(3600)).unwrap();
+        assert_eq!(
+            garbage,
+            vec![Garbage { path: orphan.clone(), kind: GarbageKind::OrphanedMetadata, bytes: 2 }]
+        );
+
+        let garbage = manager.find_garbage(Duration::ZERO).unwrap();
+        assert_eq!(garbage.len(), 2);
+        assert_eq!(garbage[0], Garbage { path: part.clone(), kind: GarbageKind::StalePartial, bytes: 11 });
+
+        for item in &garbage {
+            item.remove().unwrap();
+        }
+        assert!(!orphan.exists());
+        assert!(!part.exists());
+        assert!(temp_dir.join("ggml-base.en.json").exists());
+        assert!(manager.find_garbage(Duration::ZERO).unwrap().is_empty());
+
+        // Resume metadata is removed along with its partial download
+        let model_path = temp_dir.join("ggml-small.en.bin");
+        let part_metadata = partial_metadata_path(&model_path);
+        fs::write(&part, b"interrupted").unwrap();
+        fs::write(&part_metadata, b"{}").unwrap();
+        assert!(manager.find_garbage(Duration::from_secs(3600)).unwrap().is_empty());
+        let kinds: Vec<_> = manager.find_garbage(Duration::ZERO).unwrap().into_iter().map(|g| g.kind).collect();
+        assert_eq!(kinds, vec![GarbageKind::StalePartial, GarbageKind::StalePartial]);
+        fs::remove_file(&part).unwrap();
+        let kinds: Vec<_> = manager.find_garbage(Duration::ZERO).unwrap().into_iter().map(|g| g.kind).collect();
+        assert_eq!(kinds, vec![GarbageKind::OrphanedMetadata]);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
     #[test]
     fn test_list_cached_models_empty() {
         let temp_dir = std::env::temp_dir().join("microdrop_test_empty_cache");
@@ -452,11 +1680,37 @@ mod tests {
         let cached_models = manager.list_cached_models().unwrap();
         assert_eq!(cached_models.len(), 1);
         assert_eq!(cached_models[0].info.filename, "test_model.bin");
+        assert_eq!(cached_models[0].size_bytes, 19);
 
         // Clean up
         let _ = fs::remove_dir_all(&temp_dir);
     }
 
+    #[test]
+    fn test_gguf_models_are_recognized() {
+        assert!(is_model_file(Path::new("ggml-base.en.gguf")));
+        assert!(is_model_file(Path::new("ggml-base.en.GGUF")));
+        assert!(!is_model_file(Path::new("ggml-base.en.json")));
+        assert!(!is_model_file(Path::new("ggml-base.en")));
+
+        let temp_dir = std::env::temp_dir().join("microdrop_test_gguf_cache");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let manager = ModelManager::with_cache_dir(&temp_dir).unwrap();
+
+        fs::write(temp_dir.join("ggml-base.en.gguf"), b"model").unwrap();
+        fs::write(temp_dir.join("notes.txt"), b"not a model").unwrap();
+
+        let cached_models = manager.list_cached_models().unwrap();
+        assert_eq!(cached_models.len(), 1);
+        assert_eq!(cached_models[0].info.filename, "ggml-base.en.gguf");
+
+        // Metadata next to a .gguf model is not orphaned
+        fs::write(temp_dir.join("ggml-base.en.json"), b"{}").unwrap();
+        assert!(manager.find_garbage(Duration::ZERO).unwrap().is_empty());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
     #[test]
     fn test_resolve_model_not_found() {
         let temp_dir = std::env::temp_dir().join("microdrop_test_resolve_empty");
@@ -469,6 +1723,341 @@ mod tests {
         let _ = fs::remove_dir_all(&temp_dir);
     }
 
+    #[test]
+    fn test_resolve_model_unicode_normalized() {
+        let temp_dir = std::env::temp_dir().join("microdrop_test_resolve_unicode");
+        let manager = ModelManager::with_cache_dir(&temp_dir).unwrap();
+        // Installed under a name with a precomposed é, asked for with e + combining acute accent
+        let info = ModelInfo {
+            name: "caf\u{e9}-finetune".to_string(),
+            size: "unknown".to_string(),
+            size_bytes: None,
+            quantization: Quantization::None,
+            url: "local".to_string(),
+            checksum: Checksum::unknown(),
+            filename: "cafe-finetune.bin".to_string(),
+            defaults: None,
+        };
+        let model_path = temp_dir.join(&info.filename);
+        fs::write(&model_path, b"model").unwrap();
+        manager.save_model_metadata(&info, &model_path).unwrap();
+
+        assert_eq!(manager.resolve_model("cafe\u{301}-finetune", None).unwrap(), Some(model_path));
+        assert_eq!(manager.resolve_model("cafe-finetune", None).unwrap(), None);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    /// Answer one HTTP request per connection on localhost with each of
+    /// `responses` in turn, returning the registry URL and the requests received
+    fn serve_registry(responses: Vec<String>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/registry.json", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            responses.into_iter().map(|response| {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+                String::from_utf8_lossy(&request).to_lowercase()
+            }).collect()
+        });
+        (url, handle)
+    }
+
+    fn registry_response(name: &str) -> String {
+        let body = serde_json::json!({ "models": [{
+            "name": name,
+            "size": "1 MB",
+            "quantization": "None",
+            "url": "https://example.com/model.bin",
+            "checksum": format!("sha256:{}", ChecksumAlgo::Sha256.digest_hex(b"remote model")),
+            "filename": "ggml-remote.bin",
+        }] }).to_string();
+        format!(
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nLast-Modified: Wed, 01 Jan 2025 00:00:00 GMT\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    }
+
+    #[tokio::test]
+    async fn test_install_without_verification_trusts_cached_file() {
+        let temp_dir = std::env::temp_dir().join("microdrop_test_no_verify");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let mut manager = ModelManager::with_cache_dir(&temp_dir).unwrap();
+
+        // Doesn't match the registry checksum, so a verifying install would re-download
+        let model_path = temp_dir.join("ggml-tiny.en.bin");
+        fs::write(&model_path, b"mirror build").unwrap();
+
+        manager.set_verify_checksums(false);
+        let outcome = manager.install_model_info(&manager.get_builtin_model_registry()[0], false).await.unwrap();
+        assert_eq!(outcome.path, model_path);
+        assert!(outcome.cache_hit);
+        assert_eq!(outcome.bytes_downloaded, 0);
+        assert!(!outcome.verified);
+        assert_eq!(fs::read(&model_path).unwrap(), b"mirror build");
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_registry_version_is_checked_before_parsing() {
+        let registry = ModelRegistry::from_json(br#"{"models": []}"#).unwrap();
+        assert_eq!(registry.version, 1);
+        assert!(ModelRegistry::from_json(br#"{"version": 1, "models": []}"#).is_ok());
+
+        // A newer format with an incompatible shape asks for an upgrade rather
+        // than failing on the field that changed
+        let err = ModelRegistry::from_json(br#"{"version": 99, "models": {"tiny": {}}}"#).unwrap_err();
+        assert!(err.contains("version 99"), "{}", err);
+        assert!(err.contains("please upgrade microdrop"), "{}", err);
+
+        let err = ModelRegistry::from_json(br#"{"version": 0, "models": []}"#).unwrap_err();
+        assert!(err.contains("no longer supported"), "{}", err);
+
+        let err = ModelRegistry::from_json(br#"{"models": "none"}"#).unwrap_err();
+        assert!(err.starts_with("invalid registry"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_registry_version_falls_back() {
+        let temp_dir = std::env::temp_dir().join("microdrop_test_registry_version");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let mut manager = ModelManager::with_cache_dir(&temp_dir).unwrap();
+
+        let body = r#"{"version": 2, "models": []}"#;
+        let newer = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+        let (url, server) = serve_registry(vec![newer]);
+        manager.set_registry_url(Some(url));
+
+        let (models, source) = manager.available_models().await;
+        assert_eq!(source, RegistrySource::Builtin);
+        assert!(models.iter().any(|m| m.name == "tiny.en"));
+        assert!(!temp_dir.join(REGISTRY_CACHE_FILE).exists());
+        server.join().unwrap();
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_registry_is_cached_and_revalidated() {
+        let temp_dir = std::env::temp_dir().join("microdrop_test_registry_etag");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let mut manager = ModelManager::with_cache_dir(&temp_dir).unwrap();
+
+        let not_modified = "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_string();
+        let (url, server) = serve_registry(vec![registry_response("remote"), not_modified]);
+        manager.set_registry_url(Some(url));
+
+        let (models, source) = manager.available_models().await;
+        assert_eq!(source, RegistrySource::Remote);
+        assert_eq!(models.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), vec!["remote"]);
+        assert!(temp_dir.join(REGISTRY_CACHE_FILE).is_file());
+
+        // An unchanged registry is served from the cache
+        let (models, source) = manager.available_models().await;
+        assert_eq!(source, RegistrySource::Cached);
+        assert_eq!(models[0].name, "remote");
+
+        let requests = server.join().unwrap();
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"v1\""));
+        assert!(requests[1].contains("if-modified-since: wed, 01 jan 2025 00:00:00 gmt"));
+
+        // The registry cache isn't orphaned model metadata
+        assert!(manager.find_garbage(Duration::ZERO).unwrap().is_empty());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_registry_falls_back() {
+        let temp_dir = std::env::temp_dir().join("microdrop_test_registry_fallback");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let mut manager = ModelManager::with_cache_dir(&temp_dir).unwrap();
+
+        // A 404 isn't retried, so the server only sees one request
+        let missing = "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string();
+        let (url, server) = serve_registry(vec![missing, registry_response("remote")]);
+        manager.set_registry_url(Some(url.clone()));
+
+        let (models, source) = manager.available_models().await;
+        assert_eq!(source, RegistrySource::Builtin);
+        assert!(models.iter().any(|m| m.name == "tiny.en"));
+
+        let (_, source) = manager.available_models().await;
+        assert_eq!(source, RegistrySource::Remote);
+        server.join().unwrap();
+
+        // Nothing listening any more: the cached copy from the last fetch is used
+        let (models, source) = manager.available_models().await;
+        assert_eq!(source, RegistrySource::Cached);
+        assert_eq!(models[0].name, "remote");
+
+        // ...but not for a different registry URL
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed_url = format!("http://{}/registry.json", listener.local_addr().unwrap());
+        drop(listener);
+        manager.set_registry_url(Some(closed_url));
+
+        let (_, source) = manager.available_models().await;
+        assert_eq!(source, RegistrySource::Builtin);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_install_errors_distinguish_network_and_verification() {
+        let temp_dir = std::env::temp_dir().join("microdrop_test_install_errors");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let manager = ModelManager::with_cache_dir(&temp_dir).unwrap();
+
+        let unavailable = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string();
+        let corrupt = "HTTP/1.1 200 OK\r\nContent-Length: 7\r\nConnection: close\r\n\r\ncorrupt".to_string();
+        let (url, server) = serve_registry(vec![unavailable, corrupt]);
+        let mut info = manager.get_builtin_model_registry()[0].clone();
+        info.url = url;
+
+        let err = manager.install_model_info(&info, false).await.unwrap_err();
+        assert!(matches!(err, MicrodropError::ModelDownload(_)), "{:?}", err);
+        assert_eq!(err.exit_code(), 69);
+
+        let err = manager.install_model_info(&info, false).await.unwrap_err();
+        assert!(matches!(err, MicrodropError::ModelVerification(_)), "{:?}", err);
+        assert_eq!(err.exit_code(), 65);
+        assert!(!temp_dir.join(&info.filename).exists());
+        server.join().unwrap();
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_install_checks_size_without_content_length() {
+        let temp_dir = std::env::temp_dir().join("microdrop_test_install_size");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let manager = ModelManager::with_cache_dir(&temp_dir).unwrap();
+
+        // Neither response says how long it is, so only the registry size can catch truncation
+        let truncated = "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nhello".to_string();
+        let complete = "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nhello world".to_string();
+        let (url, server) = serve_registry(vec![truncated, complete]);
+        let mut info = manager.get_builtin_model_registry()[0].clone();
+        info.url = url;
+        info.size_bytes = Some(11);
+        info.checksum = Checksum::sha256(ChecksumAlgo::Sha256.digest_hex(b"hello world"));
+
+        let err = manager.install_model_info(&info, false).await.unwrap_err();
+        assert!(matches!(err, MicrodropError::ModelVerification(_)), "{:?}", err);
+        assert!(err.to_string().contains("is 5 bytes, expected 11"), "{}", err);
+        assert!(!temp_dir.join(&info.filename).exists());
+
+        let outcome = manager.install_model_info(&info, false).await.unwrap();
+        assert_eq!(outcome.bytes_downloaded, 11);
+        server.join().unwrap();
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[tokio::test]
+    async fn test_resume_checks_the_partial_download() {
+        let temp_dir = std::env::temp_dir().join("microdrop_test_resume");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let mut manager = ModelManager::with_cache_dir(&temp_dir).unwrap();
+        manager.set_verify_checksums(false);
+        manager.set_resume_downloads(true);
+
+        let resumed = "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 6-10/11\r\nContent-Length: 5\r\nConnection: close\r\n\r\nworld".to_string();
+        let restarted = "HTTP/1.1 200 OK\r\nContent-Length: 11\r\nConnection: close\r\n\r\nfresh model".to_string();
+        let (url, server) = serve_registry(vec![resumed, restarted]);
+        let mut info = manager.get_builtin_model_registry()[0].clone();
+        info.url = url;
+        let model_path = temp_dir.join(&info.filename);
+        let part_path = partial_download_path(&model_path);
+        let metadata_path = partial_metadata_path(&model_path);
+        let partial = PartialDownload { url: info.url.clone(), checksum: info.checksum.clone(), total_size: 11 };
+
+        // A partial from the same model continues where it stopped
+        fs::write(&part_path, b"hello ").unwrap();
+        write_partial_metadata(&metadata_path, &partial).unwrap();
+        let outcome = manager.install_model_info(&info, false).await.unwrap();
+        assert_eq!(outcome.bytes_downloaded, 5);
+        assert_eq!(fs::read(&model_path).unwrap(), b"hello world");
+        assert!(!metadata_path.exists());
+
+        // One left by a different model is thrown away rather than resumed
+        fs::write(&part_path, b"stale").unwrap();
+        let stale = PartialDownload { checksum: Checksum { algo: ChecksumAlgo::Sha256, value: "00".to_string() }, ..partial };
+        write_partial_metadata(&metadata_path, &stale).unwrap();
+        let outcome = manager.install_model_info(&info, true).await.unwrap();
+        assert_eq!(outcome.bytes_downloaded, 11);
+        assert_eq!(fs::read(&model_path).unwrap(), b"fresh model");
+        assert!(!part_path.exists());
+        assert!(!metadata_path.exists());
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("range: bytes=6-"), "{}", requests[0]);
+        assert!(!requests[1].contains("range:"), "{}", requests[1]);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_url_model_filename() {
+        assert_eq!(url_model_filename("finetune", "https://example.com/models/ft.gguf?download=1"), "finetune.gguf");
+        assert_eq!(url_model_filename("finetune", "https://example.com/ft.BIN"), "finetune.bin");
+        assert_eq!(url_model_filename("finetune", "https://example.com/download"), "finetune.bin");
+        assert_eq!(url_model_filename("finetune.ggml", "https://example.com/ft.bin"), "finetune.ggml");
+    }
+
+    #[tokio::test]
+    async fn test_install_from_url() {
+        let temp_dir = std::env::temp_dir().join("microdrop_test_install_from_url");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let manager = ModelManager::with_cache_dir(&temp_dir).unwrap();
+
+        let response = "HTTP/1.1 200 OK\r\nContent-Length: 8\r\nConnection: close\r\n\r\nfinetune".to_string();
+        let (url, server) = serve_registry(vec![response.clone(), response]);
+        let url = url.replace("registry.json", "ft.gguf");
+
+        let wrong = Checksum::sha256(ChecksumAlgo::Sha256.digest_hex(b"other"));
+        let err = manager.install_from_url(&url, "finetune", Some(wrong), false).await.unwrap_err();
+        assert!(matches!(err, MicrodropError::ModelVerification(_)), "{:?}", err);
+
+        let checksum = Checksum::sha256(ChecksumAlgo::Sha256.digest_hex(b"finetune"));
+        let outcome = manager.install_from_url(&url, "finetune", Some(checksum.clone()), false).await.unwrap();
+        let path = outcome.path;
+        assert_eq!(path, temp_dir.join("finetune.gguf"));
+        assert_eq!(fs::read(&path).unwrap(), b"finetune");
+        assert!(!outcome.cache_hit);
+        assert_eq!(outcome.bytes_downloaded, 8);
+        assert!(outcome.verified);
+        server.join().unwrap();
+
+        // Installing again reuses the verified copy without a request
+        let outcome = manager.install_from_url(&url, "finetune", Some(checksum), false).await.unwrap();
+        assert!(outcome.cache_hit);
+        assert_eq!(outcome.bytes_downloaded, 0);
+        assert!(outcome.verified);
+
+        // The recorded metadata makes it listable and resolvable by name
+        let cached = manager.list_cached_models().unwrap();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0].info.name, "finetune");
+        assert_eq!(cached[0].info.url, url);
+        assert_eq!(manager.resolve_model("finetune", None).unwrap(), Some(path));
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
     #[tokio::test]
     async fn test_list_available_models() {
         let temp_dir = std::env::temp_dir().join("microdrop_test_available");
@@ -1,4 +1,145 @@
 //! Optional user notifications (audio, desktop hooks).
 
-#[derive(Debug, Default)]
-pub struct Notifier;
+use std::process::Command;
+use std::time::Duration;
+
+use tracing::debug;
+
+use crate::{MicrodropError, Result};
+
+/// Title of every notification
+const TITLE: &str = "microdrop";
+
+/// Command the "notify" output sink runs when none is configured
+pub const DEFAULT_COMMAND: &str = "notify-send";
+
+/// Sends desktop notifications through an external command such as `notify-send`
+#[derive(Debug)]
+pub struct Notifier {
+    program: String,
+    args: Vec<String>,
+}
+
+impl Notifier {
+    /// Wrap `command`, split into a program and leading arguments the way a
+    /// shell would, so quoted arguments can contain spaces. Fails for a blank
+    /// command or unbalanced quotes.
+    pub fn new(command: &str) -> Result<Self> {
+        let mut parts = shell_words::split(command)
+            .map_err(|e| MicrodropError::Notification(format!("Can't parse '{}': {}", command, e)))?
+            .into_iter();
+        let program = parts.next().ok_or_else(|| {
+            MicrodropError::Notification("The notify command is empty".to_string())
+        })?;
+        Ok(Self {
+            program,
+            args: parts.collect(),
+        })
+    }
+
+    /// Run the command with `title` and `body` appended as its last two arguments
+    pub fn send(&self, title: &str, body: &str) -> Result<()> {
+        let status = Command::new(&self.program)
+            .args(&self.args)
+            .arg(title)
+            .arg(body)
+            .status()
+            .map_err(|e| {
+                MicrodropError::Notification(format!("Failed to run {}: {}", self.program, e))
+            })?;
+
+        if !status.success() {
+            return Err(MicrodropError::Notification(format!(
+                "{} exited with {}",
+                self.program, status
+            )));
+        }
+        debug!("Sent notification via {}", self.program);
+        Ok(())
+    }
+
+    /// Announce a finished transcript with a preview of its first `max_words` words
+    pub fn transcript(&self, text: &str, duration: Duration, max_words: usize) -> Result<()> {
+        self.send(TITLE, &transcript_summary(text, duration, max_words))
+    }
+
+    /// Announce a finished transcript without revealing its text
+    pub fn transcript_ready(&self, duration: Duration) -> Result<()> {
+        self.send(
+            TITLE,
+            &format!("Transcript ready ({:.1}s)", duration.as_secs_f64()),
+        )
+    }
+}
+
+/// The first `max_words` words of `text`, with an ellipsis if any were cut
+pub fn preview(text: &str, max_words: usize) -> String {
+    let mut words = text.split_whitespace();
+    let mut preview = words.by_ref().take(max_words).collect::<Vec<_>>().join(" ");
+    if words.next().is_some() {
+        preview.push('…');
+    }
+    preview
+}
+
+/// Notification body for a transcript of `duration` of audio
+pub fn transcript_summary(text: &str, duration: Duration, max_words: usize) -> String {
+    let preview = preview(text, max_words);
+    if preview.is_empty() {
+        format!("No speech recognized ({:.1}s)", duration.as_secs_f64())
+    } else {
+        format!("{} ({:.1}s)", preview, duration.as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_truncates_with_ellipsis() {
+        assert_eq!(preview("one two three four", 2), "one two…");
+        assert_eq!(preview("  one\ttwo\n", 2), "one two");
+        assert_eq!(preview("one two", 5), "one two");
+        assert_eq!(preview("one two", 0), "…");
+        assert_eq!(preview("", 3), "");
+    }
+
+    #[test]
+    fn test_transcript_summary() {
+        let duration = Duration::from_millis(3250);
+        assert_eq!(
+            transcript_summary("Send the report to Dana by Friday", duration, 4),
+            "Send the report to… (3.2s)"
+        );
+        assert_eq!(
+            transcript_summary(" ", duration, 4),
+            "No speech recognized (3.2s)"
+        );
+    }
+
+    #[test]
+    fn test_notifier_splits_command() {
+        let notifier = Notifier::new("notify-send -a microdrop").unwrap();
+        assert_eq!(notifier.program, "notify-send");
+        assert_eq!(notifier.args, vec!["-a", "microdrop"]);
+        let notifier = Notifier::new("notify-send -h 'string:category:dictation done'").unwrap();
+        assert_eq!(notifier.args, vec!["-h", "string:category:dictation done"]);
+        assert!(Notifier::new("  ").is_err());
+        assert!(Notifier::new("notify-send 'unclosed").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_send_reports_failures() {
+        assert!(Notifier::new("true").unwrap().send("title", "body").is_ok());
+
+        let err = Notifier::new("false").unwrap().send("title", "body");
+        assert!(err.unwrap_err().to_string().contains("exited with"));
+
+        let err = Notifier::new("microdrop-no-such-notifier")
+            .unwrap()
+            .send("title", "body");
+        assert!(err.unwrap_err().to_string().contains("Failed to run"));
+    }
+}
@@ -1,91 +1,436 @@
 //! Output handling for transcripts: stdout, clipboard, paste simulation, and file append.
 
-use std::fs::OpenOptions;
-use std::io::Write;
-use std::path::Path;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::Duration;
 
-use arboard::Clipboard;
+use arboard::{Clipboard, ImageData};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
 use enigo::{Direction, Enigo, Key, Keyboard, Settings};
-use tracing::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, instrument, warn, Span};
 
-use crate::transcribe::TranscriptionResult;
+use crate::transcribe::{ModelIdentity, TranscriptionResult};
 use crate::{MicrodropError, Result};
 
-#[derive(Debug, Clone)]
+mod template;
+
+pub use template::OutputTemplate;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TimestampFormat {
     None,
     Simple,
     Detailed,
+    Srt,
+    Vtt,
+    Json,
+    /// A Markdown list of segments. It is copied and pasted as plain text:
+    /// arboard only sets the text MIME type, so apps see the Markdown source
+    /// and render it only if they parse Markdown themselves.
+    Markdown,
+}
+
+impl FromStr for TimestampFormat {
+    type Err = MicrodropError;
+
+    /// Parse a format name as written in the config (case-insensitive)
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" => Ok(TimestampFormat::None),
+            "simple" => Ok(TimestampFormat::Simple),
+            "detailed" => Ok(TimestampFormat::Detailed),
+            "srt" => Ok(TimestampFormat::Srt),
+            "vtt" => Ok(TimestampFormat::Vtt),
+            "json" => Ok(TimestampFormat::Json),
+            "markdown" => Ok(TimestampFormat::Markdown),
+            _ => Err(MicrodropError::Config(format!(
+                "Unknown timestamp format '{}'; expected none, simple, detailed, srt, vtt, json or markdown",
+                s
+            ))),
+        }
+    }
+}
+
+/// Line terminator written to the append file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    /// `\n`, as on Linux and macOS
+    #[default]
+    Lf,
+    /// `\r\n`, as Windows tools such as Notepad expect
+    Crlf,
+}
+
+impl LineEnding {
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+}
+
+/// UTF-8 byte order mark
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Pause between sentences copied one at a time, long enough for clipboard
+/// managers that poll for changes (often twice a second) to see each one
+const SENTENCE_COPY_INTERVAL: Duration = Duration::from_millis(600);
+
+/// Process names of macOS clipboard managers that keep a history
+#[cfg(target_os = "macos")]
+const CLIPBOARD_MANAGERS: &[&str] = &[
+    "Maccy", "Paste", "Pastebot", "CopyClip", "Flycut", "ClipMenu", "Clipy", "PasteNow",
+];
+
+/// Process names of Linux clipboard managers that keep a history
+#[cfg(target_os = "linux")]
+const CLIPBOARD_MANAGERS: &[&str] = &[
+    "copyq",
+    "klipper",
+    "plasmashell",
+    "gpaste-daemon",
+    "xfce4-clipman",
+    "clipman",
+    "clipit",
+    "parcellite",
+    "diodon",
+    "greenclip",
+    "clipcatd",
+];
+
+/// A destination for finished transcripts, as listed in `output.sinks`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputSink {
+    /// Print the transcript to standard output
+    Stdout,
+    /// Copy the transcript to the clipboard
+    Clipboard,
+    /// Paste the transcript into the focused window
+    Paste,
+    /// Show a desktop notification through `output.notify_command`
+    Notify,
+}
+
+impl OutputSink {
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputSink::Stdout => "stdout",
+            OutputSink::Clipboard => "clipboard",
+            OutputSink::Paste => "paste",
+            OutputSink::Notify => "notify",
+        }
+    }
+}
+
+impl fmt::Display for OutputSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Where macOS users grant the permission synthesized keystrokes need
+pub const ACCESSIBILITY_HINT: &str =
+    "Allow your terminal in System Settings > Privacy & Security > Accessibility";
+
+/// What the clipboard held before a paste replaced it
+enum ClipboardSnapshot {
+    Text(String),
+    Image(ImageData<'static>),
+    /// Empty, or holding content that can't be read back (e.g. files)
+    Empty,
+}
+
+impl ClipboardSnapshot {
+    fn take(clipboard: &mut Clipboard) -> Self {
+        if let Ok(text) = clipboard.get_text() {
+            return Self::Text(text);
+        }
+        match clipboard.get_image() {
+            Ok(image) => Self::Image(image),
+            Err(_) => Self::Empty,
+        }
+    }
+
+    fn restore(self, clipboard: &mut Clipboard) -> std::result::Result<(), arboard::Error> {
+        match self {
+            Self::Text(text) => clipboard.set_text(text),
+            Self::Image(image) => clipboard.set_image(image),
+            Self::Empty => clipboard.clear(),
+        }
+    }
 }
 
 pub struct OutputManager {
     clipboard: Option<Clipboard>,
     enigo: Option<Enigo>,
+    timestamp_offset: f64,
+    stdout_format: TimestampFormat,
+    stdout_enabled: bool,
+    stdout: Box<dyn Write + Send>,
+    sensitive: bool,
+    paste_delay: Duration,
+    restore_clipboard: bool,
+    clipboard_sentences: bool,
+    line_ending: LineEnding,
+    append_bom: bool,
+    locate_appends: bool,
+    template: Option<OutputTemplate>,
+    model: Option<ModelIdentity>,
+    run_id: Option<String>,
+    /// Sinks that failed since the last [`Self::take_failures`]
+    failures: Vec<String>,
 }
 
 impl OutputManager {
     pub fn new() -> Result<Self> {
-        let clipboard = match Clipboard::new() {
-            Ok(clipboard) => {
-                debug!("Clipboard initialized successfully");
-                Some(clipboard)
-            }
-            Err(e) => {
-                warn!("Failed to initialize clipboard: {}", e);
-                None
+        Self::new_with_capabilities(true, true)
+    }
+
+    /// Create a manager that only initializes the sinks it will use. Pasting
+    /// goes through the clipboard, so `paste` initializes both.
+    pub fn new_with_capabilities(clipboard: bool, paste: bool) -> Result<Self> {
+        let clipboard = if clipboard || paste {
+            match Clipboard::new() {
+                Ok(clipboard) => {
+                    debug!("Clipboard initialized successfully");
+                    Some(clipboard)
+                }
+                Err(e) => {
+                    warn!("Failed to initialize clipboard: {}", e);
+                    None
+                }
             }
+        } else {
+            None
         };
 
-        let enigo = match Enigo::new(&Settings::default()) {
-            Ok(enigo) => {
-                debug!("Input simulation initialized successfully");
-                Some(enigo)
-            }
-            Err(e) => {
-                warn!("Failed to initialize input simulation: {}", e);
-                None
+        let enigo = if paste {
+            match Enigo::new(&Settings::default()) {
+                Ok(enigo) => {
+                    debug!("Input simulation initialized successfully");
+                    if !paste_permitted() {
+                        warn!(
+                            "Paste is enabled, but without Accessibility permission macOS will ignore the keystroke. {}",
+                            ACCESSIBILITY_HINT
+                        );
+                    }
+                    Some(enigo)
+                }
+                Err(e) => {
+                    warn!("Failed to initialize input simulation: {}", e);
+                    None
+                }
             }
+        } else {
+            None
         };
 
-        Ok(Self { clipboard, enigo })
+        Ok(Self {
+            clipboard,
+            enigo,
+            timestamp_offset: 0.0,
+            stdout_format: TimestampFormat::None,
+            stdout_enabled: true,
+            stdout: Box::new(std::io::stdout()),
+            sensitive: false,
+            paste_delay: Duration::from_millis(50),
+            restore_clipboard: false,
+            clipboard_sentences: false,
+            line_ending: LineEnding::default(),
+            append_bom: false,
+            locate_appends: false,
+            template: None,
+            model: None,
+            run_id: None,
+            failures: Vec::new(),
+        })
+    }
+
+    /// Shift rendered segment times by `seconds` (may be negative; results clamp at zero).
+    pub fn set_timestamp_offset(&mut self, seconds: f64) {
+        self.timestamp_offset = seconds;
+    }
+
+    /// Format used for stdout, independent of the other sinks. Defaults to clean text for piping.
+    pub fn set_stdout_format(&mut self, format: TimestampFormat) {
+        self.stdout_format = format;
+    }
+
+    /// Whether the transcript is printed to stdout. The other sinks run either way.
+    pub fn set_stdout_enabled(&mut self, enabled: bool) {
+        self.stdout_enabled = enabled;
+    }
+
+    /// Print transcripts to `stdout` instead of the process's standard output
+    pub fn set_stdout(&mut self, stdout: Box<dyn Write + Send>) {
+        self.stdout = stdout;
     }
 
-    pub fn output_transcript(
+    /// Treat transcripts as secrets: they only reach the clipboard and paste
+    /// sinks, never stdout, files or logs (not even their length)
+    pub fn set_sensitive(&mut self, sensitive: bool) {
+        self.sensitive = sensitive;
+    }
+
+    /// Time to wait after filling the clipboard before sending the paste keystroke
+    pub fn set_paste_delay(&mut self, delay: Duration) {
+        self.paste_delay = delay;
+    }
+
+    /// Put the previous clipboard contents back after pasting, waiting the paste
+    /// delay again so the target window reads the transcript first. Ignored when
+    /// the transcript is also meant to stay on the clipboard.
+    pub fn set_restore_clipboard(&mut self, restore: bool) {
+        self.restore_clipboard = restore;
+    }
+
+    /// Copy plain transcripts to the clipboard a sentence at a time, pausing
+    /// between them so a clipboard history keeps each as its own entry. When
+    /// nothing is keeping a clipboard history, only the last sentence would
+    /// be left, so whole transcripts are copied instead.
+    pub fn set_clipboard_sentences(&mut self, enabled: bool) {
+        self.clipboard_sentences = enabled && clipboard_history_available();
+        if enabled && !self.clipboard_sentences {
+            warn!("No clipboard history found to keep separate sentences; copying whole transcripts instead");
+        }
+    }
+
+    /// End lines of the append file with `line_ending`, and start a new append
+    /// file with a UTF-8 byte order mark if `bom` is set
+    pub fn set_append_encoding(&mut self, line_ending: LineEnding, bom: bool) {
+        self.line_ending = line_ending;
+        self.append_bom = bom;
+    }
+
+    /// Have [`Self::output_transcript`] report where each transcript starts in
+    /// the append file. That means counting the file's lines up to it, so
+    /// it is off by default.
+    pub fn set_locate_appends(&mut self, locate: bool) {
+        self.locate_appends = locate;
+    }
+
+    /// Format stdout and append file output with `template` instead of the
+    /// timestamp formats. The clipboard and paste sinks are unaffected.
+    pub fn set_template(&mut self, template: Option<OutputTemplate>) {
+        self.template = template;
+    }
+
+    /// Name the model that produced the transcripts in JSON output
+    pub fn set_model(&mut self, model: Option<ModelIdentity>) {
+        self.model = model;
+    }
+
+    /// Label JSON output with the ID of the run that produced it, to match it
+    /// up with that run's log lines
+    pub fn set_run_id(&mut self, run_id: Option<String>) {
+        self.run_id = run_id;
+    }
+
+    /// Failures of the stdout, clipboard, paste and append sinks since the last call.
+    /// [`Self::output_transcript`] only logs them so the other sinks still run.
+    pub fn take_failures(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.failures)
+    }
+
+    /// Send `result`, transcribed from `audio_duration` of audio, to each
+    /// enabled output, returning where it was appended to `append_file` if it
+    /// was and [appends are located](Self::set_locate_appends)
+    #[instrument(
+        name = "output",
+        level = "debug",
+        skip_all,
+        fields(chars, clipboard = enable_clipboard, paste = enable_paste)
+    )]
+    pub async fn output_transcript(
         &mut self,
         result: &TranscriptionResult,
+        audio_duration: Duration,
         enable_clipboard: bool,
         enable_paste: bool,
         append_file: Option<&Path>,
         timestamp_format: TimestampFormat,
-    ) -> Result<()> {
+    ) -> Result<Option<AppendLocation>> {
+        let now = Local::now();
         let formatted_text = self.format_transcript(result, &timestamp_format);
+        let templated = self
+            .template
+            .as_ref()
+            .map(|template| template.render(result, audio_duration, now));
+        if !self.sensitive {
+            Span::current().record("chars", result.text.len());
+        }
 
-        // Always output to stdout (clean for piping)
-        println!("{}", result.text);
+        // Output to stdout unless disabled (clean for piping unless asked otherwise)
+        if self.stdout_enabled && !self.sensitive {
+            let text = match templated {
+                Some(ref text) => text.clone(),
+                None => self.format_stdout(result),
+            };
+            if let Err(e) = writeln!(self.stdout, "{}", text) {
+                warn!("Failed to print transcript: {}", e);
+                self.failures.push(format!("stdout: {}", e));
+            }
+        }
 
         // Copy to clipboard if enabled and available
         if enable_clipboard {
-            if let Err(e) = self.copy_to_clipboard(&formatted_text) {
+            // Sentences only make sense for the plain text
+            let copied = if self.clipboard_sentences && timestamp_format == TimestampFormat::None {
+                self.copy_sentences_to_clipboard(&result.sentences()).await
+            } else {
+                self.copy_to_clipboard(&formatted_text)
+            };
+            if let Err(e) = copied {
                 warn!("Failed to copy to clipboard: {}", e);
+                self.failures.push(format!("clipboard: {}", e));
             }
         }
 
         // Simulate paste if enabled and available
         if enable_paste {
-            if let Err(e) = self.simulate_paste(&formatted_text) {
+            let restore = self.restore_clipboard && !enable_clipboard;
+            if let Err(e) = self.simulate_paste(&formatted_text, restore) {
                 warn!("Failed to simulate paste: {}", e);
+                self.failures.push(format!("paste: {}", e));
             }
         }
 
-        // Append to file if specified
-        if let Some(path) = append_file {
-            if let Err(e) = self.append_to_file(&formatted_text, path) {
-                warn!("Failed to append to file {}: {}", path.display(), e);
+        // Append to file if specified, expanding any date template
+        let mut appended = None;
+        if let Some(template) = append_file.filter(|_| !self.sensitive) {
+            let text = templated.as_deref().unwrap_or(&formatted_text);
+            match expand_append_path(template, now) {
+                Ok(path) => match self.append_to_file(text, &path) {
+                    Ok(offset) if self.locate_appends => match locate_append(&path, offset) {
+                        Ok(location) => appended = Some(location),
+                        Err(e) => warn!("{}", e),
+                    },
+                    Ok(_) => {}
+                    Err(e) => {
+                        warn!("Failed to append to file {}: {}", path.display(), e);
+                        self.failures.push(format!("append: {}", e));
+                    }
+                },
+                Err(e) => {
+                    warn!("Failed to expand append path {}: {}", template.display(), e);
+                    self.failures.push(format!("append: {}", e));
+                }
             }
         }
 
-        Ok(())
+        Ok(appended)
+    }
+
+    fn format_stdout(&self, result: &TranscriptionResult) -> String {
+        self.format_transcript(result, &self.stdout_format)
     }
 
     fn format_transcript(&self, result: &TranscriptionResult, format: &TimestampFormat) -> String {
@@ -99,7 +444,7 @@ impl OutputManager {
                     for segment in &result.segments {
                         formatted.push_str(&format!(
                             "[{:.1}s] {}\n",
-                            segment.start.as_secs_f64(),
+                            offset_timestamp(segment.start, self.timestamp_offset).as_secs_f64(),
                             segment.text
                         ));
                     }
@@ -114,17 +459,36 @@ impl OutputManager {
                     for segment in &result.segments {
                         formatted.push_str(&format!(
                             "[{:.1}s - {:.1}s] {}\n",
-                            segment.start.as_secs_f64(),
-                            segment.end.as_secs_f64(),
+                            offset_timestamp(segment.start, self.timestamp_offset).as_secs_f64(),
+                            offset_timestamp(segment.end, self.timestamp_offset).as_secs_f64(),
                             segment.text
                         ));
                     }
                     formatted.trim_end().to_string()
                 }
             }
+            TimestampFormat::Srt => self.offset_result(result).to_srt(),
+            TimestampFormat::Vtt => self.offset_result(result).to_vtt(),
+            TimestampFormat::Json => serde_json::to_string_pretty(&JsonTranscript {
+                run_id: self.run_id.as_deref(),
+                model: self.model.as_ref(),
+                result: &self.offset_result(result),
+            })
+            .expect("transcription results always serialize"),
+            TimestampFormat::Markdown => self.offset_result(result).to_markdown(),
         }
     }
 
+    /// Copy of `result` with segment times shifted by the timestamp offset
+    fn offset_result(&self, result: &TranscriptionResult) -> TranscriptionResult {
+        let mut shifted = result.clone();
+        for segment in &mut shifted.segments {
+            segment.start = offset_timestamp(segment.start, self.timestamp_offset);
+            segment.end = offset_timestamp(segment.end, self.timestamp_offset);
+        }
+        shifted
+    }
+
     fn copy_to_clipboard(&mut self, text: &str) -> Result<()> {
         match &mut self.clipboard {
             Some(clipboard) => {
@@ -138,9 +502,40 @@ impl OutputManager {
         }
     }
 
-    fn simulate_paste(&mut self, text: &str) -> Result<()> {
+    /// Copy each of `sentences` in turn, waiting [`SENTENCE_COPY_INTERVAL`]
+    /// between them. The waits run on a blocking thread, so timers such as
+    /// `--timeout` keep running meanwhile.
+    async fn copy_sentences_to_clipboard(&mut self, sentences: &[&str]) -> Result<()> {
+        let Some(mut clipboard) = self.clipboard.take() else {
+            return Err(MicrodropError::Audio("Clipboard not available".to_string()));
+        };
+        let sentences: Vec<String> = sentences.iter().map(|s| s.to_string()).collect();
+        let (clipboard, copied) = tokio::task::spawn_blocking(move || {
+            let copied = sentences
+                .iter()
+                .enumerate()
+                .try_for_each(|(index, sentence)| {
+                    if index > 0 {
+                        std::thread::sleep(SENTENCE_COPY_INTERVAL);
+                    }
+                    clipboard.set_text(sentence.as_str())
+                });
+            (clipboard, copied.map(|()| sentences.len()))
+        })
+        .await
+        .map_err(|e| MicrodropError::Audio(format!("Clipboard error: {}", e)))?;
+        self.clipboard = Some(clipboard);
+
+        let count = copied.map_err(|e| MicrodropError::Audio(format!("Clipboard error: {}", e)))?;
+        info!("{} sentences copied to clipboard", count);
+        Ok(())
+    }
+
+    fn simulate_paste(&mut self, text: &str, restore: bool) -> Result<()> {
         match &mut self.clipboard {
             Some(clipboard) => {
+                let snapshot = restore.then(|| ClipboardSnapshot::take(clipboard));
+
                 // First copy to clipboard
                 clipboard
                     .set_text(text)
@@ -148,9 +543,14 @@ impl OutputManager {
 
                 // Then simulate Ctrl+Shift+V
                 match &mut self.enigo {
+                    // Checked on every paste, so permission granted mid-session takes effect
+                    Some(_) if !paste_permitted() => Err(MicrodropError::Audio(format!(
+                        "macOS is missing Accessibility permission and would ignore the paste keystroke; the transcript is on the clipboard. {}",
+                        ACCESSIBILITY_HINT
+                    ))),
                     Some(enigo) => {
-                        // Small delay to ensure clipboard is ready
-                        std::thread::sleep(Duration::from_millis(50));
+                        // Give the clipboard and target window time to be ready
+                        std::thread::sleep(self.paste_delay);
 
                         // Simulate Ctrl+Shift+V using the new enigo API
                         enigo.key(Key::Control, Direction::Press).map_err(|e| {
@@ -172,6 +572,15 @@ impl OutputManager {
                         })?;
 
                         info!("Simulated Ctrl+Shift+V paste");
+
+                        if let Some(snapshot) = snapshot {
+                            // The target window reads the clipboard asynchronously
+                            std::thread::sleep(self.paste_delay);
+                            match snapshot.restore(clipboard) {
+                                Ok(()) => debug!("Restored previous clipboard contents"),
+                                Err(e) => warn!("Failed to restore clipboard: {}", e),
+                            }
+                        }
                         Ok(())
                     }
                     None => Err(MicrodropError::Audio(
@@ -185,21 +594,394 @@ impl OutputManager {
         }
     }
 
-    fn append_to_file(&self, text: &str, path: &Path) -> Result<()> {
-        let mut file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(path)
-            .map_err(|e| MicrodropError::Audio(format!("Failed to open file: {}", e)))?;
+    /// Append `text` to `path`, returning the byte offset it starts at
+    fn append_to_file(&self, text: &str, path: &Path) -> Result<u64> {
+        let text = self.with_line_endings(text);
+        let offset = append_line(path, &text, self.line_ending, self.append_bom)?;
+        info!("Text appended to file: {}", path.display());
+        Ok(offset)
+    }
+
+    fn with_line_endings(&self, text: &str) -> String {
+        match self.line_ending {
+            LineEnding::Lf => text.to_string(),
+            LineEnding::Crlf => text.replace("\r\n", "\n").replace('\n', "\r\n"),
+        }
+    }
+
+    /// Replace the contents of the file `template` expands to with `result`,
+    /// formatted as for the append file. The new contents are written beside
+    /// it and renamed into place, so readers never see a half-written file.
+    pub fn replace_file_contents(
+        &self,
+        result: &TranscriptionResult,
+        audio_duration: Duration,
+        template: &Path,
+        timestamp_format: &TimestampFormat,
+    ) -> Result<()> {
+        if self.sensitive {
+            debug!("Not writing a sensitive transcript to the replace file");
+            return Ok(());
+        }
 
-        writeln!(file, "{}", text)
-            .map_err(|e| MicrodropError::Audio(format!("Failed to write to file: {}", e)))?;
+        let now = Local::now();
+        let path = expand_append_path(template, now)?;
+        let text = match self.template {
+            Some(ref template) => template.render(result, audio_duration, now),
+            None => self.format_transcript(result, timestamp_format),
+        };
 
-        info!("Text appended to file: {}", path.display());
+        let mut bytes = Vec::new();
+        if self.append_bom {
+            bytes.extend_from_slice(UTF8_BOM);
+        }
+        bytes.extend_from_slice(self.with_line_endings(&text).as_bytes());
+        bytes.extend_from_slice(self.line_ending.as_str().as_bytes());
+        write_atomically(&path, &bytes)?;
+        info!("Replaced contents of file: {}", path.display());
+        Ok(())
+    }
+
+    /// Append `result` as a single NDJSON record, with the recording time and
+    /// model, to the file `template` expands to. Timestamps include any offset.
+    pub fn append_json_record(
+        &self,
+        result: &TranscriptionResult,
+        model: &ModelIdentity,
+        template: &Path,
+    ) -> Result<()> {
+        if self.sensitive {
+            debug!("Not archiving a sensitive transcript");
+            return Ok(());
+        }
+
+        let now = Local::now();
+        let path = expand_append_path(template, now)?;
+        let record = TranscriptRecord {
+            timestamp: now.to_rfc3339(),
+            run_id: self.run_id.as_deref(),
+            model,
+            result: &self.offset_result(result),
+        };
+        let line = serde_json::to_string(&record)
+            .map_err(|e| MicrodropError::Audio(format!("Failed to serialize record: {}", e)))?;
+
+        append_line(&path, &line, LineEnding::Lf, false)?;
+        info!("JSON record appended to file: {}", path.display());
         Ok(())
     }
 }
 
+/// One line of an `--append-json` archive
+#[derive(Serialize)]
+struct TranscriptRecord<'a> {
+    timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    run_id: Option<&'a str>,
+    #[serde(flatten)]
+    model: &'a ModelIdentity,
+    #[serde(flatten)]
+    result: &'a TranscriptionResult,
+}
+
+/// A result printed as JSON, labelled with the run and model that produced it
+#[derive(Serialize)]
+struct JsonTranscript<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    run_id: Option<&'a str>,
+    #[serde(flatten)]
+    model: Option<&'a ModelIdentity>,
+    #[serde(flatten)]
+    result: &'a TranscriptionResult,
+}
+
+/// Whether something keeps a history of clipboard changes, so sentences copied
+/// one at a time all stay retrievable: on Windows, whether its own clipboard
+/// history (Win+V) is turned on, which it isn't by default
+#[cfg(target_os = "windows")]
+fn clipboard_history_available() -> bool {
+    std::process::Command::new("reg")
+        .args([
+            "query",
+            r"HKCU\Software\Microsoft\Clipboard",
+            "/v",
+            "EnableClipboardHistory",
+        ])
+        .output()
+        .is_ok_and(|output| {
+            output.status.success()
+                && String::from_utf8_lossy(&output.stdout)
+                    .split_whitespace()
+                    .last()
+                    .is_some_and(|value| value == "0x1")
+        })
+}
+
+/// Whether something keeps a history of clipboard changes: on macOS, which
+/// has none built in, whether a known clipboard manager is running
+#[cfg(target_os = "macos")]
+fn clipboard_history_available() -> bool {
+    std::process::Command::new("ps")
+        .args(["-axco", "comm="])
+        .output()
+        .is_ok_and(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .any(|name| CLIPBOARD_MANAGERS.contains(&name.trim()))
+        })
+}
+
+/// Whether something keeps a history of clipboard changes: on Linux, whether
+/// a known clipboard manager is running
+#[cfg(target_os = "linux")]
+fn clipboard_history_available() -> bool {
+    let Ok(processes) = std::fs::read_dir("/proc") else {
+        return false;
+    };
+    processes.flatten().any(|process| {
+        std::fs::read_to_string(process.path().join("comm"))
+            .is_ok_and(|name| CLIPBOARD_MANAGERS.contains(&name.trim()))
+    })
+}
+
+/// Whether something keeps a history of clipboard changes
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn clipboard_history_available() -> bool {
+    false
+}
+
+/// Whether synthesized keystrokes will reach other apps. `Enigo::new` succeeds
+/// on macOS without Accessibility permission, but the keystrokes are dropped.
+#[cfg(target_os = "macos")]
+pub fn paste_permitted() -> bool {
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn AXIsProcessTrusted() -> u8;
+    }
+    // SAFETY: takes no arguments and only reports this process's trust status
+    unsafe { AXIsProcessTrusted() != 0 }
+}
+
+/// Whether synthesized keystrokes will reach other apps
+#[cfg(not(target_os = "macos"))]
+pub fn paste_permitted() -> bool {
+    true
+}
+
+/// Where an appended transcript starts in its file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppendLocation {
+    pub path: PathBuf,
+    /// Byte offset of the first character
+    pub offset: u64,
+    /// 1-based line number of the first line
+    pub line: usize,
+}
+
+impl fmt::Display for AppendLocation {
+    /// `path:line`, the form editors accept for opening a file at a line
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.path.display(), self.line)
+    }
+}
+
+/// Create the directory `path` goes in, if it doesn't exist yet
+fn create_parent_dir(path: &Path) -> Result<()> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty() && !p.is_dir())
+    {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            MicrodropError::Audio(format!(
+                "Failed to create directory {}: {}",
+                parent.display(),
+                e
+            ))
+        })?;
+        debug!("Created directory {}", parent.display());
+    }
+    Ok(())
+}
+
+/// Replace the contents of `path` with `bytes` by writing a temporary file in
+/// the same directory and renaming it over `path`, so readers see either the
+/// old contents or the new, never an empty or partial file
+fn write_atomically(path: &Path, bytes: &[u8]) -> Result<()> {
+    create_parent_dir(path)?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| MicrodropError::Audio(format!("Not a file path: {}", path.display())))?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = path.with_file_name(temp_name);
+
+    let written =
+        std::fs::write(&temp_path, bytes).and_then(|()| std::fs::rename(&temp_path, path));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(MicrodropError::Audio(format!(
+            "Failed to write {}: {}",
+            path.display(),
+            e
+        )));
+    }
+    Ok(())
+}
+
+/// Append `text` and a line ending to `path` in one write, so lines from
+/// concurrent writers never interleave, returning the byte offset the text
+/// starts at. With `bom`, a file that is still empty gets a byte order mark first.
+fn append_line(path: &Path, text: &str, line_ending: LineEnding, bom: bool) -> Result<u64> {
+    create_parent_dir(path)?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| MicrodropError::Audio(format!("Failed to open file: {}", e)))?;
+
+    let mut bytes = Vec::with_capacity(UTF8_BOM.len() + text.len() + 2);
+    let empty = file
+        .metadata()
+        .map_err(|e| MicrodropError::Audio(format!("Failed to open file: {}", e)))?
+        .len()
+        == 0;
+    if bom && empty {
+        bytes.extend_from_slice(UTF8_BOM);
+    }
+    let text_start = bytes.len() as u64;
+    bytes.extend_from_slice(text.as_bytes());
+    bytes.extend_from_slice(line_ending.as_str().as_bytes());
+    file.write_all(&bytes)
+        .map_err(|e| MicrodropError::Audio(format!("Failed to write to file: {}", e)))?;
+
+    // Appends always land at the end, so the position after our write locates it
+    // even if another writer got in first
+    let offset = file
+        .stream_position()
+        .map_err(|e| MicrodropError::Audio(format!("Failed to locate appended text: {}", e)))?
+        - bytes.len() as u64
+        + text_start;
+    Ok(offset)
+}
+
+/// Where text appended to `path` at byte `offset` starts. Finding its line
+/// reads the file up to there, so it is only done when asked for.
+fn locate_append(path: &Path, offset: u64) -> Result<AppendLocation> {
+    let line = count_lines(path, offset)
+        .map_err(|e| MicrodropError::Audio(format!("Failed to locate appended text: {}", e)))?
+        + 1;
+    Ok(AppendLocation {
+        path: path.to_path_buf(),
+        offset,
+        line,
+    })
+}
+
+/// Number of newlines in the first `len` bytes of `path`
+fn count_lines(path: &Path, len: u64) -> std::io::Result<usize> {
+    let mut reader = BufReader::new(File::open(path)?.take(len));
+    let mut lines = 0;
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(lines);
+        }
+        lines += buf.iter().filter(|&&b| b == b'\n').count();
+        let consumed = buf.len();
+        reader.consume(consumed);
+    }
+}
+
+/// Apply a signed offset in seconds to a timestamp, clamping at zero.
+fn offset_timestamp(time: Duration, offset_secs: f64) -> Duration {
+    Duration::from_secs_f64((time.as_secs_f64() + offset_secs).max(0.0))
+}
+
+/// Expand a date template in an append path, so e.g. `~/notes/%Y-%m-%d.md`
+/// gives each day its own file.
+///
+/// Supports chrono's strftime specifiers such as `%Y` (year), `%m` (month),
+/// `%d` (day), `%H` (hour), `%V` (ISO week) and `%a`/`%b` (weekday/month names);
+/// `%%` is a literal `%`. A leading `~/` expands to the home directory. Paths
+/// without `%` are used as-is.
+pub fn expand_append_path(template: &Path, now: DateTime<Local>) -> Result<PathBuf> {
+    let template = template.to_string_lossy();
+
+    let expanded = if template.contains('%') {
+        let items: Vec<Item> = StrftimeItems::new(&template).collect();
+        if items.iter().any(|item| matches!(item, Item::Error)) {
+            return Err(MicrodropError::Config(format!(
+                "Invalid date specifier in append path '{}'",
+                template
+            )));
+        }
+        now.format_with_items(items.into_iter()).to_string()
+    } else {
+        template.into_owned()
+    };
+
+    match (expanded.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => Ok(home.join(rest)),
+        _ => Ok(PathBuf::from(expanded)),
+    }
+}
+
+/// Write `text` to a new file under `data_dir/recovery`, named for `now`,
+/// so a transcript that couldn't be delivered isn't lost
+pub fn save_recovery(data_dir: &Path, text: &str, now: DateTime<Local>) -> Result<PathBuf> {
+    let path = data_dir
+        .join("recovery")
+        .join(now.format("transcript-%Y%m%d-%H%M%S%.3f.txt").to_string());
+    append_line(&path, text, LineEnding::Lf, false)?;
+    Ok(path)
+}
+
+/// Render the `--print-segments` breakdown: one line per segment with its
+/// index, start and end (seconds into the transcribed audio) and text
+pub fn format_segments(result: &TranscriptionResult) -> String {
+    if result.segments.is_empty() {
+        return "(no segments)".to_string();
+    }
+    result
+        .segments
+        .iter()
+        .enumerate()
+        .map(|(index, segment)| {
+            format!(
+                "#{} {:.3}s -> {:.3}s: {}",
+                index,
+                segment.start.as_secs_f64(),
+                segment.end.as_secs_f64(),
+                segment.text.trim()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Render the `--stats` summary: audio length, processing time and real-time factor.
+pub fn format_performance_summary(
+    model: &str,
+    threads: i32,
+    audio_duration: Duration,
+    processing_time: Duration,
+) -> String {
+    let audio_secs = audio_duration.as_secs_f64();
+    let processing_secs = processing_time.as_secs_f64();
+    let real_time_factor = if audio_secs > 0.0 {
+        format!("{:.3}", processing_secs / audio_secs)
+    } else {
+        "n/a".to_string()
+    };
+
+    format!(
+        "model: {}\nthreads: {}\naudio duration: {:.2}s\nprocessing time: {:.2}s\nreal-time factor: {}",
+        model, threads, audio_secs, processing_secs, real_time_factor
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -214,15 +996,31 @@ mod tests {
                     start: Duration::from_millis(0),
                     end: Duration::from_millis(1000),
                     text: "Hello".to_string(),
+                    avg_logprob: None,
+                    no_speech_prob: None,
+                    speaker: None,
                 },
                 TranscriptionSegment {
                     start: Duration::from_millis(1000),
                     end: Duration::from_millis(2000),
                     text: "world".to_string(),
+                    avg_logprob: None,
+                    no_speech_prob: None,
+                    speaker: None,
                 },
             ],
             language: Some("en".to_string()),
             processing_time: Duration::from_millis(100),
+            partial: false,
+        }
+    }
+
+    fn test_model() -> ModelIdentity {
+        ModelIdentity {
+            file: "ggml-base.en.bin".to_string(),
+            sha256: Some(
+                "cd7c9fe633b6b3e7fe9ba22700da6e112a049790c787c92adf5f5905f542ccf6".to_string(),
+            ),
         }
     }
 
@@ -250,6 +1048,145 @@ mod tests {
         assert_eq!(formatted, "[0.0s - 1.0s] Hello\n[1.0s - 2.0s] world");
     }
 
+    #[test]
+    fn test_format_transcript_delegates_to_renderers() {
+        let mut manager = OutputManager::new().unwrap();
+        let result = create_test_result();
+
+        assert_eq!(
+            manager.format_transcript(&result, &TimestampFormat::Srt),
+            result.to_srt()
+        );
+        assert_eq!(
+            manager.format_transcript(&result, &TimestampFormat::Vtt),
+            result.to_vtt()
+        );
+        assert_eq!(
+            manager.format_transcript(&result, &TimestampFormat::Json),
+            result.to_json()
+        );
+        assert_eq!(
+            manager.format_transcript(&result, &TimestampFormat::Markdown),
+            result.to_markdown()
+        );
+
+        manager.set_timestamp_offset(60.0);
+        let srt = manager.format_transcript(&result, &TimestampFormat::Srt);
+        assert!(srt.starts_with("1\n00:01:00,000 --> 00:01:01,000\nHello\n"));
+    }
+
+    #[test]
+    fn test_stdout_format_is_independent() {
+        let mut manager = OutputManager::new().unwrap();
+        let result = create_test_result();
+        assert_eq!(manager.format_stdout(&result), "Hello world");
+
+        manager.set_stdout_format(TimestampFormat::Simple);
+        assert_eq!(manager.format_stdout(&result), "[0.0s] Hello\n[1.0s] world");
+        // Other sinks still use the format they are given
+        assert_eq!(
+            manager.format_transcript(&result, &TimestampFormat::None),
+            "Hello world"
+        );
+    }
+
+    #[test]
+    fn test_offset_timestamp_math() {
+        let time = Duration::from_millis(1500);
+        assert_eq!(offset_timestamp(time, 0.0), time);
+        assert_eq!(offset_timestamp(time, 2.25), Duration::from_millis(3750));
+        assert_eq!(offset_timestamp(time, -0.5), Duration::from_millis(1000));
+        assert_eq!(offset_timestamp(time, -10.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_format_transcript_with_offset() {
+        let mut manager = OutputManager::new().unwrap();
+        let result = create_test_result();
+
+        manager.set_timestamp_offset(90.5);
+        let formatted = manager.format_transcript(&result, &TimestampFormat::Detailed);
+        assert_eq!(formatted, "[90.5s - 91.5s] Hello\n[91.5s - 92.5s] world");
+
+        manager.set_timestamp_offset(-1.5);
+        let formatted = manager.format_transcript(&result, &TimestampFormat::Simple);
+        assert_eq!(formatted, "[0.0s] Hello\n[0.0s] world");
+    }
+
+    #[test]
+    fn test_output_sink_names_match_config() {
+        for sink in [
+            OutputSink::Stdout,
+            OutputSink::Clipboard,
+            OutputSink::Paste,
+            OutputSink::Notify,
+        ] {
+            let value = serde_json::to_value(sink).unwrap();
+            assert_eq!(value, serde_json::json!(sink.name()));
+            assert_eq!(serde_json::from_value::<OutputSink>(value).unwrap(), sink);
+        }
+        assert!(serde_json::from_str::<OutputSink>("\"socket\"").is_err());
+    }
+
+    #[test]
+    fn test_timestamp_format_from_str() {
+        let cases = [
+            ("none", TimestampFormat::None),
+            ("simple", TimestampFormat::Simple),
+            ("detailed", TimestampFormat::Detailed),
+            ("srt", TimestampFormat::Srt),
+            ("vtt", TimestampFormat::Vtt),
+            ("json", TimestampFormat::Json),
+            ("markdown", TimestampFormat::Markdown),
+            (" Detailed ", TimestampFormat::Detailed),
+            ("SRT", TimestampFormat::Srt),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(
+                name.parse::<TimestampFormat>().unwrap(),
+                expected,
+                "{}",
+                name
+            );
+        }
+
+        let err = "verbose".parse::<TimestampFormat>().unwrap_err();
+        assert!(matches!(err, MicrodropError::Config(_)));
+        assert!(err.to_string().contains("'verbose'"));
+    }
+
+    #[test]
+    fn test_format_segments() {
+        let result = create_test_result();
+        assert_eq!(
+            format_segments(&result),
+            "#0 0.000s -> 1.000s: Hello\n#1 1.000s -> 2.000s: world"
+        );
+
+        let empty = TranscriptionResult {
+            segments: Vec::new(),
+            ..result
+        };
+        assert_eq!(format_segments(&empty), "(no segments)");
+    }
+
+    #[test]
+    fn test_format_performance_summary() {
+        let summary = format_performance_summary(
+            "ggml-base.en.bin",
+            4,
+            Duration::from_secs(10),
+            Duration::from_millis(2500),
+        );
+        assert_eq!(
+            summary,
+            "model: ggml-base.en.bin\nthreads: 4\naudio duration: 10.00s\nprocessing time: 2.50s\nreal-time factor: 0.250"
+        );
+
+        let summary = format_performance_summary("m", 1, Duration::ZERO, Duration::ZERO);
+        assert!(summary.ends_with("real-time factor: n/a"));
+    }
+
     #[test]
     fn test_format_empty_segments() {
         let manager = OutputManager::new().unwrap();
@@ -258,6 +1195,7 @@ mod tests {
             segments: vec![],
             language: Some("en".to_string()),
             processing_time: Duration::from_millis(100),
+            partial: false,
         };
 
         let formatted_simple = manager.format_transcript(&result, &TimestampFormat::Simple);
@@ -267,6 +1205,368 @@ mod tests {
         assert_eq!(formatted_detailed, "Hello world");
     }
 
+    #[test]
+    fn test_expand_append_path() {
+        use chrono::TimeZone;
+        let now = Local.with_ymd_and_hms(2024, 3, 7, 9, 30, 0).unwrap();
+
+        let path = expand_append_path(Path::new("/notes/%Y-%m-%d.md"), now).unwrap();
+        assert_eq!(path, PathBuf::from("/notes/2024-03-07.md"));
+
+        let path = expand_append_path(Path::new("/notes/%Y/%m/100%%.txt"), now).unwrap();
+        assert_eq!(path, PathBuf::from("/notes/2024/03/100%.txt"));
+
+        let path = expand_append_path(Path::new("/notes/journal.md"), now).unwrap();
+        assert_eq!(path, PathBuf::from("/notes/journal.md"));
+
+        assert!(expand_append_path(Path::new("/notes/%Q.md"), now).is_err());
+    }
+
+    #[test]
+    fn test_append_creates_dated_directories() {
+        let manager = OutputManager::new().unwrap();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("2024").join("03-07.md");
+
+        manager.append_to_file("Entry", &path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "Entry\n");
+    }
+
+    #[tokio::test]
+    async fn test_append_creates_missing_parent() {
+        let mut manager = OutputManager::new_with_capabilities(false, false).unwrap();
+        manager.set_stdout_enabled(false);
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("notes").join("transcripts.md");
+        let archive = temp_dir.path().join("archive").join("transcripts.ndjson");
+
+        let location = manager
+            .output_transcript(
+                &create_test_result(),
+                Duration::from_secs(3),
+                false,
+                false,
+                Some(&path),
+                TimestampFormat::None,
+            )
+            .await
+            .unwrap();
+        // Only located when asked for
+        assert_eq!(location, None);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "Hello world\n");
+
+        manager
+            .append_json_record(&create_test_result(), &test_model(), &archive)
+            .unwrap();
+        assert!(archive.is_file());
+    }
+
+    #[tokio::test]
+    async fn test_sink_failures_are_reported() {
+        let mut manager = OutputManager::new_with_capabilities(false, false).unwrap();
+        manager.set_stdout_enabled(false);
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        // Appending to a directory fails, but output carries on
+        let location = manager
+            .output_transcript(
+                &create_test_result(),
+                Duration::from_secs(3),
+                false,
+                false,
+                Some(temp_dir.path()),
+                TimestampFormat::None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(location, None);
+
+        let failures = manager.take_failures();
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].starts_with("append: "));
+        assert!(manager.take_failures().is_empty());
+    }
+
+    #[test]
+    fn test_save_recovery() {
+        use chrono::TimeZone;
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let now = Local.with_ymd_and_hms(2024, 3, 9, 14, 5, 0).unwrap();
+
+        let path = save_recovery(temp_dir.path(), "Hello world", now).unwrap();
+        assert_eq!(
+            path,
+            temp_dir
+                .path()
+                .join("recovery")
+                .join("transcript-20240309-140500.000.txt")
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "Hello world\n");
+    }
+
+    #[test]
+    fn test_new_without_capabilities() {
+        let manager = OutputManager::new_with_capabilities(false, false).unwrap();
+        assert!(manager.clipboard.is_none());
+        assert!(manager.enigo.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_stdout_still_appends() {
+        let mut manager = OutputManager::new_with_capabilities(false, false).unwrap();
+        manager.set_stdout_enabled(false);
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("transcript.txt");
+
+        std::fs::write(&path, "Earlier\ntranscripts\n").unwrap();
+        manager.set_locate_appends(true);
+
+        let location = manager
+            .output_transcript(
+                &create_test_result(),
+                Duration::from_secs(3),
+                false,
+                false,
+                Some(&path),
+                TimestampFormat::None,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "Earlier\ntranscripts\nHello world\n"
+        );
+        assert_eq!(location.path, path);
+        assert_eq!(location.offset, 20);
+        assert_eq!(location.line, 3);
+    }
+
+    #[tokio::test]
+    async fn test_template_replaces_append_format() {
+        let mut manager = OutputManager::new_with_capabilities(false, false).unwrap();
+        manager.set_stdout_enabled(false);
+        manager.set_template(Some("{text} ({language}, {duration})".parse().unwrap()));
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("transcript.txt");
+
+        manager
+            .output_transcript(
+                &create_test_result(),
+                Duration::from_secs(3),
+                false,
+                false,
+                Some(&path),
+                TimestampFormat::Detailed,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "Hello world (en, 3.00s)\n"
+        );
+    }
+
+    #[test]
+    fn test_replace_file_contents() {
+        let mut manager = OutputManager::new_with_capabilities(false, false).unwrap();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("overlay").join("latest.txt");
+
+        let mut result = create_test_result();
+        manager
+            .replace_file_contents(
+                &result,
+                Duration::from_secs(3),
+                &path,
+                &TimestampFormat::None,
+            )
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "Hello world\n");
+
+        // Each transcript replaces the last, with the append file's formatting
+        result.text = "Second take".to_string();
+        manager.set_append_encoding(LineEnding::Crlf, false);
+        manager.set_template(Some("{text} ({duration})".parse().unwrap()));
+        manager
+            .replace_file_contents(
+                &result,
+                Duration::from_secs(2),
+                &path,
+                &TimestampFormat::None,
+            )
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "Second take (2.00s)\r\n"
+        );
+        // No temporary files are left behind
+        assert_eq!(
+            std::fs::read_dir(path.parent().unwrap()).unwrap().count(),
+            1
+        );
+
+        manager.set_sensitive(true);
+        result.text = "secret".to_string();
+        manager
+            .replace_file_contents(
+                &result,
+                Duration::from_secs(2),
+                &path,
+                &TimestampFormat::None,
+            )
+            .unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("secret"));
+    }
+
+    #[test]
+    fn test_append_json_record() {
+        let mut manager = OutputManager::new_with_capabilities(false, false).unwrap();
+        manager.set_timestamp_offset(10.0);
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("archive.ndjson");
+
+        let result = create_test_result();
+        manager
+            .append_json_record(&result, &test_model(), &path)
+            .unwrap();
+        let unhashed = ModelIdentity {
+            sha256: None,
+            ..test_model()
+        };
+        manager
+            .append_json_record(&result, &unhashed, &path)
+            .unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let record: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(record["model"], "ggml-base.en.bin");
+        assert_eq!(
+            record["model_sha256"],
+            "cd7c9fe633b6b3e7fe9ba22700da6e112a049790c787c92adf5f5905f542ccf6"
+        );
+        assert_eq!(record["text"], "Hello world");
+        assert_eq!(record["segments"][1]["start"], 11.0);
+        assert!(DateTime::parse_from_rfc3339(record["timestamp"].as_str().unwrap()).is_ok());
+
+        let record: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(record["model"], "ggml-base.en.bin");
+        assert!(record.get("model_sha256").is_none());
+        assert!(record.get("run_id").is_none());
+
+        manager.set_run_id(Some("3fa2-7".to_string()));
+        manager
+            .append_json_record(&result, &test_model(), &path)
+            .unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        let record: serde_json::Value =
+            serde_json::from_str(content.lines().last().unwrap()).unwrap();
+        assert_eq!(record["run_id"], "3fa2-7");
+    }
+
+    #[test]
+    fn test_json_output_names_model() {
+        let mut manager = OutputManager::new_with_capabilities(false, false).unwrap();
+        let result = create_test_result();
+        assert_eq!(
+            manager.format_transcript(&result, &TimestampFormat::Json),
+            result.to_json()
+        );
+
+        manager.set_model(Some(test_model()));
+        let json: serde_json::Value =
+            serde_json::from_str(&manager.format_transcript(&result, &TimestampFormat::Json))
+                .unwrap();
+        assert_eq!(json["model"], "ggml-base.en.bin");
+        assert_eq!(
+            json["model_sha256"],
+            "cd7c9fe633b6b3e7fe9ba22700da6e112a049790c787c92adf5f5905f542ccf6"
+        );
+        assert_eq!(json["text"], "Hello world");
+        assert_eq!(json["segments"].as_array().unwrap().len(), 2);
+        assert!(json.get("run_id").is_none());
+
+        manager.set_run_id(Some("3fa2-7".to_string()));
+        let json: serde_json::Value =
+            serde_json::from_str(&manager.format_transcript(&result, &TimestampFormat::Json))
+                .unwrap();
+        assert_eq!(json["run_id"], "3fa2-7");
+        assert_eq!(json["model"], "ggml-base.en.bin");
+    }
+
+    #[tokio::test]
+    async fn test_sensitive_transcripts_are_not_written() {
+        let mut manager = OutputManager::new_with_capabilities(false, false).unwrap();
+        manager.set_sensitive(true);
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let text_path = temp_dir.path().join("transcript.txt");
+        let json_path = temp_dir.path().join("archive.ndjson");
+
+        let location = manager
+            .output_transcript(
+                &create_test_result(),
+                Duration::from_secs(3),
+                false,
+                false,
+                Some(&text_path),
+                TimestampFormat::None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(location, None);
+        manager
+            .append_json_record(&create_test_result(), &test_model(), &json_path)
+            .unwrap();
+
+        assert!(!text_path.exists());
+        assert!(!json_path.exists());
+    }
+
+    #[test]
+    fn test_append_encodings() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let cases: [(LineEnding, bool, &[u8]); 4] = [
+            (LineEnding::Lf, false, b"One\nTwo\nThree\n"),
+            (LineEnding::Lf, true, b"\xEF\xBB\xBFOne\nTwo\nThree\n"),
+            (LineEnding::Crlf, false, b"One\r\nTwo\r\nThree\r\n"),
+            (
+                LineEnding::Crlf,
+                true,
+                b"\xEF\xBB\xBFOne\r\nTwo\r\nThree\r\n",
+            ),
+        ];
+
+        for (index, (line_ending, bom, expected)) in cases.into_iter().enumerate() {
+            let mut manager = OutputManager::new_with_capabilities(false, false).unwrap();
+            manager.set_append_encoding(line_ending, bom);
+            let path = temp_dir.path().join(format!("{}.txt", index));
+
+            let first = manager.append_to_file("One", &path).unwrap();
+            // Multi-line transcripts, e.g. subtitles, are converted line by line
+            let second = manager.append_to_file("Two\nThree", &path).unwrap();
+            let first = locate_append(&path, first).unwrap();
+            let second = locate_append(&path, second).unwrap();
+
+            assert_eq!(
+                std::fs::read(&path).unwrap(),
+                expected,
+                "{:?}, bom {}",
+                line_ending,
+                bom
+            );
+            let bom_len = if bom { 3 } else { 0 };
+            assert_eq!((first.offset, first.line), (bom_len, 1));
+            assert_eq!(
+                second.offset,
+                bom_len + 3 + line_ending.as_str().len() as u64
+            );
+            assert_eq!(second.line, 2);
+        }
+    }
+
     #[test]
     fn test_append_to_file() {
         let manager = OutputManager::new().unwrap();
@@ -277,11 +1577,16 @@ mod tests {
         let _ = std::fs::remove_file(&temp_file);
 
         // Test appending
-        manager.append_to_file("First line", &temp_file).unwrap();
-        manager.append_to_file("Second line", &temp_file).unwrap();
+        let first = manager.append_to_file("First line", &temp_file).unwrap();
+        let second = manager.append_to_file("Second line", &temp_file).unwrap();
+        let first = locate_append(&temp_file, first).unwrap();
+        let second = locate_append(&temp_file, second).unwrap();
 
         let content = std::fs::read_to_string(&temp_file).unwrap();
         assert_eq!(content, "First line\nSecond line\n");
+        assert_eq!((first.offset, first.line), (0, 1));
+        assert_eq!((second.offset, second.line), (11, 2));
+        assert_eq!(second.to_string(), format!("{}:2", temp_file.display()));
 
         // Clean up
         let _ = std::fs::remove_file(&temp_file);
@@ -0,0 +1,228 @@
+//! User-defined formatting of transcripts for stdout and the append file.
+//!
+//! A template is literal text with placeholders in braces:
+//!
+//! - `{text}`: the transcript
+//! - `{language}`: the detected or requested language code, `unknown` if neither
+//! - `{duration}`: length of the transcribed audio, e.g. `3.20s`
+//! - `{processing_time}`: time spent transcribing, e.g. `0.84s`
+//! - `{date}`: local date as `YYYY-MM-DD`
+//! - `{timestamp}`: local date and time in RFC 3339
+//!
+//! `{{` and `}}` stand for literal braces.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+
+use crate::transcribe::TranscriptionResult;
+use crate::{MicrodropError, Result};
+
+/// A value a template can refer to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Text,
+    Language,
+    Duration,
+    ProcessingTime,
+    Date,
+    Timestamp,
+}
+
+impl Field {
+    const ALL: [Field; 6] = [
+        Field::Text,
+        Field::Language,
+        Field::Duration,
+        Field::ProcessingTime,
+        Field::Date,
+        Field::Timestamp,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Field::Text => "text",
+            Field::Language => "language",
+            Field::Duration => "duration",
+            Field::ProcessingTime => "processing_time",
+            Field::Date => "date",
+            Field::Timestamp => "timestamp",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    Field(Field),
+}
+
+/// A parsed `--output-template`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputTemplate {
+    source: String,
+    pieces: Vec<Piece>,
+}
+
+impl OutputTemplate {
+    /// Fill in the template for `result`, transcribed from `audio_duration` of audio at `now`
+    pub fn render(
+        &self,
+        result: &TranscriptionResult,
+        audio_duration: Duration,
+        now: DateTime<Local>,
+    ) -> String {
+        let mut rendered = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Literal(text) => rendered.push_str(text),
+                Piece::Field(Field::Text) => rendered.push_str(&result.text),
+                Piece::Field(Field::Language) => {
+                    rendered.push_str(result.language.as_deref().unwrap_or("unknown"))
+                }
+                Piece::Field(Field::Duration) => {
+                    rendered.push_str(&format!("{:.2}s", audio_duration.as_secs_f64()))
+                }
+                Piece::Field(Field::ProcessingTime) => {
+                    rendered.push_str(&format!("{:.2}s", result.processing_time.as_secs_f64()))
+                }
+                Piece::Field(Field::Date) => rendered.push_str(&now.format("%Y-%m-%d").to_string()),
+                Piece::Field(Field::Timestamp) => rendered.push_str(&now.to_rfc3339()),
+            }
+        }
+        rendered
+    }
+}
+
+impl FromStr for OutputTemplate {
+    type Err = MicrodropError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => {
+                                return Err(MicrodropError::Config(format!(
+                                    "Unclosed '{{' in output template '{}'; write '{{{{' for a literal brace",
+                                    s
+                                )))
+                            }
+                        }
+                    }
+                    let field = Field::ALL
+                        .into_iter()
+                        .find(|field| field.name() == name)
+                        .ok_or_else(|| {
+                            let known: Vec<String> = Field::ALL
+                                .iter()
+                                .map(|field| format!("{{{}}}", field.name()))
+                                .collect();
+                            MicrodropError::Config(format!(
+                                "Unknown placeholder '{{{}}}' in output template; expected one of {}",
+                                name,
+                                known.join(", ")
+                            ))
+                        })?;
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(Piece::Field(field));
+                }
+                '}' => {
+                    return Err(MicrodropError::Config(format!(
+                        "Unmatched '}}' in output template '{}'; write '}}}}' for a literal brace",
+                        s
+                    )))
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+
+        Ok(Self {
+            source: s.to_string(),
+            pieces,
+        })
+    }
+}
+
+impl fmt::Display for OutputTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn test_result() -> TranscriptionResult {
+        TranscriptionResult {
+            text: "Hello world".to_string(),
+            segments: vec![],
+            language: Some("en".to_string()),
+            processing_time: Duration::from_millis(840),
+            partial: false,
+        }
+    }
+
+    #[test]
+    fn test_render_fields() {
+        let now = Local.with_ymd_and_hms(2024, 3, 9, 14, 5, 0).unwrap();
+        let template: OutputTemplate =
+            "{date} {text} ({language}, {duration} in {processing_time})"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            template.render(&test_result(), Duration::from_millis(3200), now),
+            "2024-03-09 Hello world (en, 3.20s in 0.84s)"
+        );
+
+        let template: OutputTemplate = "{timestamp} {{{text}}}".parse().unwrap();
+        assert_eq!(
+            template.render(&test_result(), Duration::ZERO, now),
+            format!("{} {{Hello world}}", now.to_rfc3339())
+        );
+
+        let mut result = test_result();
+        result.language = None;
+        let template: OutputTemplate = "[{language}]".parse().unwrap();
+        assert_eq!(template.render(&result, Duration::ZERO, now), "[unknown]");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = "{text} {speaker}".parse::<OutputTemplate>().unwrap_err();
+        assert!(err.to_string().contains("Unknown placeholder '{speaker}'"));
+        assert!(err.to_string().contains("{processing_time}"));
+
+        assert!("{text".parse::<OutputTemplate>().is_err());
+        assert!("text}".parse::<OutputTemplate>().is_err());
+        assert!("{}".parse::<OutputTemplate>().is_err());
+
+        let template: OutputTemplate = "plain {{text}}".parse().unwrap();
+        assert_eq!(template.to_string(), "plain {{text}}");
+    }
+}
@@ -0,0 +1,362 @@
+//! HTTP endpoint for remote transcription (`microdrop serve`).
+//!
+//! `POST /transcribe` takes a WAV file as the request body and answers with the
+//! `TranscriptionResult` as JSON. Errors are JSON objects with an `error` field.
+//! Every response carries the request's run ID in an `X-Run-Id` header, to find
+//! its lines in the server log.
+//! When too many requests are already in flight the server answers 503 instead
+//! of buffering more audio.
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{HeaderValue, CONTENT_TYPE, RETRY_AFTER};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpListener;
+use tokio::runtime::Handle;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::{debug, info, warn, Instrument, Span};
+
+use crate::audio::{decode_wav, ProcessorPool};
+use crate::telemetry;
+use crate::transcribe::TranscriptionEngine;
+use crate::{MicrodropError, Result};
+
+/// Header naming the run that handled a request
+const RUN_ID_HEADER: &str = "x-run-id";
+
+/// Largest request body accepted, about 30 minutes of 16-bit mono 16kHz audio
+pub const MAX_BODY_BYTES: usize = 64 * 1024 * 1024;
+
+/// Memory budgeted per in-flight request: the request body, its decoded and
+/// resampled samples, and inference buffers for a small model
+const MEMORY_PER_REQUEST: u64 = 512 * 1024 * 1024;
+
+/// Most in-flight requests allowed by default, however much memory is free
+const MAX_DEFAULT_IN_FLIGHT: usize = 8;
+
+/// Default in-flight limit when available memory can't be determined
+const FALLBACK_IN_FLIGHT: usize = 2;
+
+/// Default limit on requests being read, queued or transcribed at once: as
+/// many as fit in a quarter of the available memory, between 1 and 8
+pub fn default_max_in_flight() -> usize {
+    match available_memory() {
+        Some(bytes) => ((bytes / 4 / MEMORY_PER_REQUEST) as usize).clamp(1, MAX_DEFAULT_IN_FLIGHT),
+        None => FALLBACK_IN_FLIGHT,
+    }
+}
+
+/// Memory available to new allocations, in bytes
+fn available_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    parse_mem_available(&meminfo)
+}
+
+/// The `MemAvailable` line of `/proc/meminfo`, in bytes
+fn parse_mem_available(meminfo: &str) -> Option<u64> {
+    let line = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))?;
+    let kib: u64 = line.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// Claim one of the in-flight slots, failing at once if all are taken
+fn admit(slots: &Semaphore) -> Result<SemaphorePermit<'_>> {
+    slots.try_acquire().map_err(|_| {
+        MicrodropError::Server("Too many transcriptions in flight, try again later".to_string())
+    })
+}
+
+/// Where a request is dispatched to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Route {
+    Transcribe,
+    MethodNotAllowed,
+    NotFound,
+}
+
+fn route(method: &Method, path: &str) -> Route {
+    match path {
+        "/transcribe" if method == Method::POST => Route::Transcribe,
+        "/transcribe" => Route::MethodNotAllowed,
+        _ => Route::NotFound,
+    }
+}
+
+/// Serve transcription requests on `addr` until the process exits.
+///
+/// Requests share `engine`, so the model stays loaded between them; inference
+/// itself runs one request at a time. Audio is converted with processors from
+/// `processors`, so requests in a common format don't rebuild the resampler.
+/// At most `max_in_flight` requests are accepted at once; the rest get a 503.
+pub async fn serve(
+    addr: SocketAddr,
+    engine: Arc<TranscriptionEngine>,
+    processors: Arc<ProcessorPool>,
+    max_in_flight: usize,
+) -> Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|e| MicrodropError::Server(format!("Failed to bind {}: {}", addr, e)))?;
+    let local_addr = listener.local_addr().unwrap_or(addr);
+    info!("Listening on http://{}", local_addr);
+    info!("Accepting up to {} transcriptions at once", max_in_flight);
+    let slots = Arc::new(Semaphore::new(max_in_flight.max(1)));
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        debug!("Accepted connection from {}", peer);
+
+        let engine = Arc::clone(&engine);
+        let processors = Arc::clone(&processors);
+        let slots = Arc::clone(&slots);
+        tokio::spawn(async move {
+            let service = service_fn(move |request| {
+                handle(
+                    request,
+                    Arc::clone(&engine),
+                    Arc::clone(&processors),
+                    Arc::clone(&slots),
+                )
+            });
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                debug!("Connection from {} ended with an error: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn handle(
+    request: Request<Incoming>,
+    engine: Arc<TranscriptionEngine>,
+    processors: Arc<ProcessorPool>,
+    slots: Arc<Semaphore>,
+) -> std::result::Result<Response<Full<Bytes>>, Infallible> {
+    let run_id = telemetry::next_run_id();
+    let mut response = match route(request.method(), request.uri().path()) {
+        Route::Transcribe => {
+            transcribe(request, engine, processors, &slots)
+                .instrument(telemetry::run_span(&run_id))
+                .await
+        }
+        Route::MethodNotAllowed => error_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "Use POST to send audio to /transcribe",
+        ),
+        Route::NotFound => error_response(StatusCode::NOT_FOUND, "Not found"),
+    };
+    if let Ok(value) = HeaderValue::from_str(&run_id) {
+        response.headers_mut().insert(RUN_ID_HEADER, value);
+    }
+    Ok(response)
+}
+
+async fn transcribe(
+    request: Request<Incoming>,
+    engine: Arc<TranscriptionEngine>,
+    processors: Arc<ProcessorPool>,
+    slots: &Semaphore,
+) -> Response<Full<Bytes>> {
+    // Held until the response is built, so the body is never buffered while full
+    let _slot = match admit(slots) {
+        Ok(slot) => slot,
+        Err(e) => {
+            warn!("{}", e);
+            return busy_response(&e.to_string());
+        }
+    };
+
+    let body = match Limited::new(request.into_body(), MAX_BODY_BYTES)
+        .collect()
+        .await
+    {
+        Ok(body) => body.to_bytes(),
+        Err(e) if e.is::<LengthLimitError>() => {
+            return error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                &format!("Request body exceeds {} bytes", MAX_BODY_BYTES),
+            );
+        }
+        Err(e) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("Failed to read request body: {}", e),
+            );
+        }
+    };
+
+    // Resampling and inference block until they finish, so they run off the
+    // runtime thread to keep accepting connections and answering 503s meanwhile.
+    // A body that can't be prepared is the client's fault (the outer error).
+    let body_len = body.len();
+    let span = Span::current();
+    let transcribed = tokio::task::spawn_blocking(move || {
+        span.in_scope(|| {
+            let samples = prepare_samples(&body, &processors)?;
+            Ok::<_, MicrodropError>(Handle::current().block_on(engine.transcribe(&samples)))
+        })
+    })
+    .await
+    .unwrap_or_else(|e| {
+        Ok(Err(MicrodropError::Server(format!(
+            "Transcription task failed: {}",
+            e
+        ))))
+    });
+
+    match transcribed {
+        Ok(Ok(result)) => {
+            debug!(
+                "Transcribed {} bytes into {} segments",
+                body_len,
+                result.segments.len()
+            );
+            json_response(StatusCode::OK, result.to_json())
+        }
+        Ok(Err(e)) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        Err(e) => error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+    }
+}
+
+/// Decode a WAV request body and convert it to 16kHz mono for the engine
+fn prepare_samples(bytes: &[u8], processors: &ProcessorPool) -> Result<Vec<f32>> {
+    let audio = decode_wav(bytes)?;
+    if audio.sample_rate == 0 || audio.channels == 0 {
+        return Err(MicrodropError::Audio(
+            "WAV sample rate and channel count must be greater than zero".to_string(),
+        ));
+    }
+    processors
+        .checkout(audio.sample_rate, audio.channels)?
+        .process(&audio.samples)
+}
+
+/// A 503 asking the client to retry shortly
+fn busy_response(message: &str) -> Response<Full<Bytes>> {
+    let mut response = error_response(StatusCode::SERVICE_UNAVAILABLE, message);
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from_static("1"));
+    response
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Full<Bytes>> {
+    json_response(status, serde_json::json!({ "error": message }).to_string())
+}
+
+fn json_response(status: StatusCode, body: String) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(body)));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn wav_bytes(sample_rate: u32, channels: u16, frames: usize) -> Vec<u8> {
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut cursor = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+        for _ in 0..frames * channels as usize {
+            writer.write_sample(8192i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        cursor.into_inner()
+    }
+
+    #[test]
+    fn test_routes() {
+        assert_eq!(route(&Method::POST, "/transcribe"), Route::Transcribe);
+        assert_eq!(route(&Method::GET, "/transcribe"), Route::MethodNotAllowed);
+        assert_eq!(route(&Method::POST, "/"), Route::NotFound);
+        assert_eq!(route(&Method::POST, "/transcribe/extra"), Route::NotFound);
+    }
+
+    #[test]
+    fn test_prepare_samples_converts_to_16khz_mono() {
+        let processors = ProcessorPool::default();
+        let samples = prepare_samples(&wav_bytes(32000, 2, 32000), &processors).unwrap();
+        assert_eq!(samples.len(), 16000);
+        assert!((samples[8000] - 0.25).abs() < 0.01);
+
+        let samples = prepare_samples(&wav_bytes(16000, 1, 4000), &processors).unwrap();
+        assert_eq!(samples.len(), 4000);
+
+        // Both converters went back to the pool for the next request
+        assert_eq!(processors.idle(), 2);
+    }
+
+    #[test]
+    fn test_prepare_samples_rejects_non_wav() {
+        let err = prepare_samples(b"not a wav file", &ProcessorPool::default()).unwrap_err();
+        assert!(err.to_string().contains("WAV"));
+    }
+
+    #[test]
+    fn test_admit_rejects_when_full() {
+        let slots = Semaphore::new(2);
+        let first = admit(&slots).unwrap();
+        let _second = admit(&slots).unwrap();
+
+        let err = admit(&slots).unwrap_err();
+        assert!(matches!(err, MicrodropError::Server(_)));
+        let response = busy_response(&err.to_string());
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[RETRY_AFTER], "1");
+
+        // A finished request frees its slot
+        drop(first);
+        assert!(admit(&slots).is_ok());
+    }
+
+    #[test]
+    fn test_default_max_in_flight() {
+        let meminfo = "MemTotal:       16318416 kB\nMemFree:         1203484 kB\nMemAvailable:    8388608 kB\n";
+        assert_eq!(parse_mem_available(meminfo), Some(8 * 1024 * 1024 * 1024));
+        assert_eq!(parse_mem_available("MemTotal: 1024 kB\n"), None);
+
+        assert!((1..=MAX_DEFAULT_IN_FLIGHT).contains(&default_max_in_flight()));
+    }
+
+    #[test]
+    fn test_error_response_is_json() {
+        let response = error_response(StatusCode::BAD_REQUEST, "bad \"audio\"");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+
+        let body = futures_util::FutureExt::now_or_never(response.into_body().collect())
+            .unwrap()
+            .unwrap()
+            .to_bytes();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"], "bad \"audio\"");
+    }
+}
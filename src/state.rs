@@ -0,0 +1,121 @@
+//! Values remembered between runs, such as the last-used device and model.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::config::Config;
+use crate::{MicrodropError, Result};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct State {
+    /// Name of the last audio device that produced a successful transcript
+    #[serde(default)]
+    pub last_device: Option<String>,
+    /// Resolved path of the last model that produced a successful transcript
+    #[serde(default)]
+    pub last_model: Option<PathBuf>,
+}
+
+impl State {
+    /// Load state from the default location, ignoring missing or corrupt files
+    pub fn load() -> Self {
+        match Self::default_state_path() {
+            Ok(path) => Self::load_from_path(path),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Load state from a specific file path, ignoring missing or corrupt files
+    pub fn load_from_path<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+
+        let state = fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+
+        match state {
+            Some(state) => {
+                debug!("Loaded state from {}", path.display());
+                state
+            }
+            None => {
+                debug!("No usable state at {}, starting fresh", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    /// Save state to the default location
+    pub fn save(&self) -> Result<()> {
+        let path = Self::default_state_path()?;
+        self.save_to_path(path)
+    }
+
+    /// Save state to a specific file path
+    pub fn save_to_path<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                MicrodropError::Config(format!("Failed to create state directory: {}", e))
+            })?;
+        }
+
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| MicrodropError::Config(format!("Failed to serialize state: {}", e)))?;
+
+        fs::write(path, content)
+            .map_err(|e| MicrodropError::Config(format!("Failed to write state file: {}", e)))?;
+
+        debug!("Saved state to {}", path.display());
+        Ok(())
+    }
+
+    /// Get the default state file path
+    pub fn default_state_path() -> Result<PathBuf> {
+        Ok(Self::path_in(&Config::default_data_dir()?))
+    }
+
+    /// State file path within a data directory
+    pub fn path_in(data_dir: &Path) -> PathBuf {
+        data_dir.join("state.json")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_missing_state_is_default() {
+        let state = State::load_from_path("/nonexistent/microdrop/state.json");
+        assert_eq!(state, State::default());
+    }
+
+    #[test]
+    fn test_corrupt_state_is_default() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("state.json");
+        fs::write(&path, "{ not json").unwrap();
+
+        assert_eq!(State::load_from_path(&path), State::default());
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("nested").join("state.json");
+
+        let state = State {
+            last_device: Some("USB Microphone".to_string()),
+            last_model: Some(PathBuf::from("/models/ggml-base.en.bin")),
+        };
+        state.save_to_path(&path).unwrap();
+
+        assert_eq!(State::load_from_path(&path), state);
+    }
+}
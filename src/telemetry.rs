@@ -1,6 +1,17 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+use tracing::{info_span, Span};
+use tracing_subscriber::fmt::format::FmtSpan;
 use tracing_subscriber::EnvFilter;
 
 /// Initialize tracing subscribers using `RUST_LOG` when provided.
+///
+/// Pipeline stages (capture, processing, model load, inference, output) run in
+/// debug-level spans, so `RUST_LOG=microdrop=debug` logs each one as it closes
+/// along with how long it took.
 pub fn init() {
     let filter =
         EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("microdrop=info"));
@@ -8,5 +19,44 @@ pub fn init() {
     let _ = tracing_subscriber::fmt()
         .with_env_filter(filter)
         .with_target(false)
+        .with_span_events(FmtSpan::CLOSE)
         .try_init();
 }
+
+/// Runs started by this process so far, numbering the run IDs
+static RUN_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// A short ID for one run of the workflow, a toggle session or a server
+/// request, like `3fa2-7`: a prefix picked at random once per process, so the
+/// logs of two processes can't be confused, and a sequence number within it
+pub fn next_run_id() -> String {
+    static PREFIX: OnceLock<u16> = OnceLock::new();
+    let prefix = *PREFIX.get_or_init(|| RandomState::new().build_hasher().finish() as u16);
+    let sequence = RUN_COUNT.fetch_add(1, Ordering::Relaxed) + 1;
+    format!("{:04x}-{}", prefix, sequence)
+}
+
+/// Span tagging everything logged during a run with its `run_id`, so the
+/// lines of concurrent runs can be told apart. It is info level so the ID
+/// shows in the default logs, and closing it logs how long the run took.
+pub fn run_span(run_id: &str) -> Span {
+    info_span!("run", run_id = %run_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_ids_share_a_prefix_and_count_up() {
+        let first = next_run_id();
+        let second = next_run_id();
+        let (prefix, sequence) = first.split_once('-').unwrap();
+        let (second_prefix, second_sequence) = second.split_once('-').unwrap();
+
+        assert_eq!(prefix.len(), 4);
+        assert!(prefix.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(prefix, second_prefix);
+        assert!(second_sequence.parse::<u64>().unwrap() > sequence.parse::<u64>().unwrap());
+    }
+}
@@ -0,0 +1,237 @@
+//! Repeatable transcription timing for `microdrop bench-transcribe`, and
+//! side-by-side runs for `microdrop compare-models`.
+
+use std::f32::consts::TAU;
+use std::fmt;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use super::{serialize_secs, ModelIdentity, TranscriptionResult};
+use crate::audio::TARGET_SAMPLE_RATE;
+
+/// `seconds` of 16kHz mono audio that is the same on every run: a few
+/// harmonics of a gliding pitch, amplitude-modulated into syllable-like bursts
+/// separated by short pauses, so inference sees something voice-like.
+pub fn benchmark_sample(seconds: f64) -> Vec<f32> {
+    let len = (seconds * TARGET_SAMPLE_RATE as f64).round() as usize;
+    (0..len)
+        .map(|i| {
+            let t = i as f32 / TARGET_SAMPLE_RATE as f32;
+            // Bursts of 4 per second, with every fourth left silent
+            let syllable = (t * 4.0).fract();
+            if (t * 4.0) as usize % 4 == 3 {
+                return 0.0;
+            }
+            let envelope = (syllable * std::f32::consts::PI).sin();
+            let pitch = 140.0 + 30.0 * (t * 0.7 * TAU).sin();
+            let voice: f32 = (1..=4)
+                .map(|harmonic| (t * pitch * harmonic as f32 * TAU).sin() / harmonic as f32)
+                .sum();
+            0.3 * envelope * voice / 2.0
+        })
+        .collect()
+}
+
+/// Latencies of repeated transcriptions of the same audio
+#[derive(Debug, Clone)]
+pub struct BenchmarkReport {
+    pub model: String,
+    pub threads: i32,
+    pub audio_duration: Duration,
+    /// Time each run took, in run order
+    pub runs: Vec<Duration>,
+}
+
+impl BenchmarkReport {
+    pub fn mean(&self) -> Duration {
+        if self.runs.is_empty() {
+            return Duration::ZERO;
+        }
+        self.runs.iter().sum::<Duration>() / self.runs.len() as u32
+    }
+
+    pub fn median(&self) -> Duration {
+        let sorted = self.sorted();
+        match sorted.len() {
+            0 => Duration::ZERO,
+            n if n % 2 == 1 => sorted[n / 2],
+            n => (sorted[n / 2 - 1] + sorted[n / 2]) / 2,
+        }
+    }
+
+    /// 95th percentile, by nearest rank
+    pub fn p95(&self) -> Duration {
+        let sorted = self.sorted();
+        if sorted.is_empty() {
+            return Duration::ZERO;
+        }
+        let rank = (sorted.len() as f64 * 0.95).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    }
+
+    /// Median processing time divided by the audio duration; below 1 is
+    /// faster than real time
+    pub fn real_time_factor(&self) -> Option<f64> {
+        let audio_secs = self.audio_duration.as_secs_f64();
+        (audio_secs > 0.0).then(|| self.median().as_secs_f64() / audio_secs)
+    }
+
+    fn sorted(&self) -> Vec<Duration> {
+        let mut sorted = self.runs.clone();
+        sorted.sort();
+        sorted
+    }
+}
+
+impl fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let real_time_factor = match self.real_time_factor() {
+            Some(factor) => format!("{:.3}", factor),
+            None => "n/a".to_string(),
+        };
+        write!(
+            f,
+            "model: {}\nthreads: {}\naudio duration: {:.2}s\nruns: {}\nmean: {:.3}s\nmedian: {:.3}s\np95: {:.3}s\nreal-time factor: {}",
+            self.model,
+            self.threads,
+            self.audio_duration.as_secs_f64(),
+            self.runs.len(),
+            self.mean().as_secs_f64(),
+            self.median().as_secs_f64(),
+            self.p95().as_secs_f64(),
+            real_time_factor
+        )
+    }
+}
+
+/// One model's transcript of the audio `compare-models` gives every model
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelRun {
+    #[serde(flatten)]
+    pub model: ModelIdentity,
+    pub threads: i32,
+    #[serde(serialize_with = "serialize_secs")]
+    pub audio_duration: Duration,
+    /// Processing time divided by the audio duration; below 1 is faster than real time
+    pub real_time_factor: Option<f64>,
+    #[serde(flatten)]
+    pub result: TranscriptionResult,
+}
+
+impl ModelRun {
+    pub fn new(
+        model: ModelIdentity,
+        threads: i32,
+        audio_duration: Duration,
+        result: TranscriptionResult,
+    ) -> Self {
+        let audio_secs = audio_duration.as_secs_f64();
+        let real_time_factor =
+            (audio_secs > 0.0).then(|| result.processing_time.as_secs_f64() / audio_secs);
+        Self {
+            model,
+            threads,
+            audio_duration,
+            real_time_factor,
+            result,
+        }
+    }
+}
+
+impl fmt::Display for ModelRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let real_time_factor = match self.real_time_factor {
+            Some(factor) => format!("{:.3}", factor),
+            None => "n/a".to_string(),
+        };
+        write!(
+            f,
+            "model: {}\nthreads: {}\nprocessing time: {:.3}s\nreal-time factor: {}\ntranscript: {}",
+            self.model.file,
+            self.threads,
+            self.result.processing_time.as_secs_f64(),
+            real_time_factor,
+            self.result.text.trim()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(runs_ms: &[u64]) -> BenchmarkReport {
+        BenchmarkReport {
+            model: "ggml-base.en.bin".to_string(),
+            threads: 4,
+            audio_duration: Duration::from_secs(10),
+            runs: runs_ms
+                .iter()
+                .map(|&ms| Duration::from_millis(ms))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_benchmark_sample_is_fixed() {
+        let sample = benchmark_sample(2.0);
+        assert_eq!(sample.len(), 32000);
+        assert_eq!(sample, benchmark_sample(2.0));
+        assert!(sample.iter().all(|s| s.abs() <= 0.3));
+        // The pause after every third syllable is silent
+        assert!(sample[12000..16000].iter().all(|&s| s == 0.0));
+        assert!(sample[2000..2100].iter().any(|&s| s != 0.0));
+    }
+
+    #[test]
+    fn test_report_statistics() {
+        let skewed = report(&[1200, 1000, 1100, 3000]);
+        assert_eq!(skewed.mean(), Duration::from_millis(1575));
+        assert_eq!(skewed.median(), Duration::from_millis(1150));
+        assert_eq!(skewed.p95(), Duration::from_millis(3000));
+        assert!((skewed.real_time_factor().unwrap() - 0.115).abs() < 1e-9);
+
+        let odd = report(&[500, 100, 300]);
+        assert_eq!(odd.median(), Duration::from_millis(300));
+        assert_eq!(odd.p95(), Duration::from_millis(500));
+
+        let lines: Vec<String> = odd.to_string().lines().map(String::from).collect();
+        assert_eq!(lines[3], "runs: 3");
+        assert_eq!(lines[5], "median: 0.300s");
+        assert_eq!(lines[7], "real-time factor: 0.030");
+    }
+
+    #[test]
+    fn test_model_run() {
+        let run = ModelRun::new(
+            ModelIdentity {
+                file: "ggml-base.en.bin".to_string(),
+                sha256: None,
+            },
+            4,
+            Duration::from_secs(4),
+            TranscriptionResult {
+                text: " Hello world".to_string(),
+                segments: vec![],
+                language: Some("en".to_string()),
+                processing_time: Duration::from_millis(500),
+                partial: false,
+            },
+        );
+        assert_eq!(run.real_time_factor, Some(0.125));
+
+        let lines: Vec<String> = run.to_string().lines().map(String::from).collect();
+        assert_eq!(lines[0], "model: ggml-base.en.bin");
+        assert_eq!(lines[2], "processing time: 0.500s");
+        assert_eq!(lines[3], "real-time factor: 0.125");
+        assert_eq!(lines[4], "transcript: Hello world");
+
+        let json = serde_json::to_value(&run).unwrap();
+        assert_eq!(json["model"], "ggml-base.en.bin");
+        assert_eq!(json["audio_duration"], 4.0);
+        assert_eq!(json["real_time_factor"], 0.125);
+        assert_eq!(json["text"], " Hello world");
+        assert_eq!(json["processing_time"], 0.5);
+    }
+}
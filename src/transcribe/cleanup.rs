@@ -0,0 +1,170 @@
+//! Conservative sentence-boundary cleanup and splitting of transcript text.
+
+use super::TranscriptionResult;
+
+/// Characters that already end a sentence
+const TERMINAL_PUNCTUATION: &[char] = &['.', '!', '?', '…'];
+
+/// Closing characters that may follow terminal punctuation, as in `"Stop."`
+const CLOSING: &[char] = &['"', '\'', ')', ']', '”', '’'];
+
+impl TranscriptionResult {
+    /// Capitalize the start of the transcript and of each segment, and make
+    /// sure the transcript ends with terminal punctuation.
+    ///
+    /// Only the first word and the final character are ever changed.
+    pub fn apply_cleanup(&mut self) {
+        self.text = end_sentence(&capitalize_first_word(&self.text));
+
+        let last = self.segments.len().saturating_sub(1);
+        for (index, segment) in self.segments.iter_mut().enumerate() {
+            segment.text = capitalize_first_word(&segment.text);
+            if index == last {
+                segment.text = end_sentence(&segment.text);
+            }
+        }
+    }
+
+    /// The transcript split into sentences, each trimmed. A sentence ends at
+    /// terminal punctuation, and any closing quotes or brackets after it,
+    /// followed by whitespace, so `3.5` or `e.g.` mid-word don't split.
+    pub fn sentences(&self) -> Vec<&str> {
+        let mut sentences = Vec::new();
+        let mut start = 0;
+        let mut ended = false;
+        for (index, c) in self.text.char_indices() {
+            if c.is_whitespace() && ended {
+                sentences.push(self.text[start..index].trim());
+                start = index;
+            }
+            ended = TERMINAL_PUNCTUATION.contains(&c) || (ended && CLOSING.contains(&c));
+        }
+        sentences.push(self.text[start..].trim());
+        sentences.retain(|sentence| !sentence.is_empty());
+        sentences
+    }
+}
+
+/// Uppercase the first letter, unless the first word has capitals of its own (`iPhone`)
+fn capitalize_first_word(text: &str) -> String {
+    let Some(start) = text.find(|c: char| c.is_alphanumeric()) else {
+        return text.to_string();
+    };
+
+    let word = &text[start..];
+    let word = word
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .next()
+        .unwrap_or(word);
+    let mut chars = word.chars();
+    let Some(first) = chars.next() else {
+        return text.to_string();
+    };
+    if !first.is_lowercase() || chars.any(|c| c.is_uppercase()) {
+        return text.to_string();
+    }
+
+    let rest = &text[start + first.len_utf8()..];
+    format!("{}{}{}", &text[..start], first.to_uppercase(), rest)
+}
+
+/// Add a period if the text doesn't already end a sentence.
+/// A dangling comma, semicolon or colon is replaced.
+fn end_sentence(text: &str) -> String {
+    let trimmed = text.trim_end();
+    let Some(last) = trimmed.trim_end_matches(CLOSING).chars().last() else {
+        return text.to_string();
+    };
+
+    if TERMINAL_PUNCTUATION.contains(&last) {
+        trimmed.to_string()
+    } else if matches!(last, ',' | ';' | ':') && !trimmed.ends_with(CLOSING) {
+        format!("{}.", &trimmed[..trimmed.len() - 1])
+    } else if last.is_alphanumeric() || trimmed.ends_with(CLOSING) {
+        format!("{}.", trimmed)
+    } else {
+        trimmed.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcribe::TranscriptionSegment;
+    use std::time::Duration;
+
+    #[test]
+    fn test_capitalize_first_word() {
+        assert_eq!(capitalize_first_word("hello world"), "Hello world");
+        assert_eq!(capitalize_first_word(" hello world"), " Hello world");
+        assert_eq!(capitalize_first_word("\"quoted\" text"), "\"Quoted\" text");
+        assert_eq!(capitalize_first_word("élan vital"), "Élan vital");
+        // Already capitalized, mixed case words and numbers are left alone
+        assert_eq!(capitalize_first_word("Hello"), "Hello");
+        assert_eq!(capitalize_first_word("iPhone sales"), "iPhone sales");
+        assert_eq!(capitalize_first_word("42 things"), "42 things");
+        assert_eq!(capitalize_first_word(""), "");
+    }
+
+    #[test]
+    fn test_end_sentence() {
+        assert_eq!(end_sentence("Hello world"), "Hello world.");
+        assert_eq!(end_sentence("Hello world.  "), "Hello world.");
+        assert_eq!(end_sentence("Really?"), "Really?");
+        assert_eq!(end_sentence("Stop!"), "Stop!");
+        assert_eq!(end_sentence("and then…"), "and then…");
+        assert_eq!(end_sentence("He said \"stop.\""), "He said \"stop.\"");
+        assert_eq!(end_sentence("see (the appendix)"), "see (the appendix).");
+        assert_eq!(end_sentence("first, second,"), "first, second.");
+        assert_eq!(end_sentence(""), "");
+    }
+
+    #[test]
+    fn test_apply_cleanup() {
+        let segment = |text: &str| TranscriptionSegment {
+            start: Duration::ZERO,
+            end: Duration::ZERO,
+            text: text.to_string(),
+            avg_logprob: None,
+            no_speech_prob: None,
+            speaker: None,
+        };
+        let mut result = TranscriptionResult {
+            text: " so, what's next? we ship it".to_string(),
+            segments: vec![segment(" so, what's next?"), segment(" we ship it")],
+            language: Some("en".to_string()),
+            processing_time: Duration::ZERO,
+            partial: false,
+        };
+
+        result.apply_cleanup();
+
+        assert_eq!(result.text, " So, what's next? we ship it.");
+        assert_eq!(result.segments[0].text, " So, what's next?");
+        assert_eq!(result.segments[1].text, " We ship it.");
+    }
+
+    #[test]
+    fn test_sentences() {
+        let result = |text: &str| TranscriptionResult {
+            text: text.to_string(),
+            segments: Vec::new(),
+            language: None,
+            processing_time: Duration::ZERO,
+            partial: false,
+        };
+
+        assert_eq!(
+            result(" First one. Is it 3.5? He said \"stop!\" then left…  and so on").sentences(),
+            vec![
+                "First one.",
+                "Is it 3.5?",
+                "He said \"stop!\"",
+                "then left…",
+                "and so on"
+            ]
+        );
+        assert_eq!(result("No punctuation").sentences(), vec!["No punctuation"]);
+        assert!(result("  ").sentences().is_empty());
+    }
+}
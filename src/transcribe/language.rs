@@ -0,0 +1,215 @@
+//! Whisper's spoken-language codes, checked before they reach the model.
+
+/// Value that asks Whisper to detect the language itself
+pub const AUTO_LANGUAGE: &str = "auto";
+
+/// Every `(code, name)` pair Whisper knows, in its own token order
+pub const LANGUAGES: &[(&str, &str)] = &[
+    ("en", "english"),
+    ("zh", "chinese"),
+    ("de", "german"),
+    ("es", "spanish"),
+    ("ru", "russian"),
+    ("ko", "korean"),
+    ("fr", "french"),
+    ("ja", "japanese"),
+    ("pt", "portuguese"),
+    ("tr", "turkish"),
+    ("pl", "polish"),
+    ("ca", "catalan"),
+    ("nl", "dutch"),
+    ("ar", "arabic"),
+    ("sv", "swedish"),
+    ("it", "italian"),
+    ("id", "indonesian"),
+    ("hi", "hindi"),
+    ("fi", "finnish"),
+    ("vi", "vietnamese"),
+    ("he", "hebrew"),
+    ("uk", "ukrainian"),
+    ("el", "greek"),
+    ("ms", "malay"),
+    ("cs", "czech"),
+    ("ro", "romanian"),
+    ("da", "danish"),
+    ("hu", "hungarian"),
+    ("ta", "tamil"),
+    ("no", "norwegian"),
+    ("th", "thai"),
+    ("ur", "urdu"),
+    ("hr", "croatian"),
+    ("bg", "bulgarian"),
+    ("lt", "lithuanian"),
+    ("la", "latin"),
+    ("mi", "maori"),
+    ("ml", "malayalam"),
+    ("cy", "welsh"),
+    ("sk", "slovak"),
+    ("te", "telugu"),
+    ("fa", "persian"),
+    ("lv", "latvian"),
+    ("bn", "bengali"),
+    ("sr", "serbian"),
+    ("az", "azerbaijani"),
+    ("sl", "slovenian"),
+    ("kn", "kannada"),
+    ("et", "estonian"),
+    ("mk", "macedonian"),
+    ("br", "breton"),
+    ("eu", "basque"),
+    ("is", "icelandic"),
+    ("hy", "armenian"),
+    ("ne", "nepali"),
+    ("mn", "mongolian"),
+    ("bs", "bosnian"),
+    ("kk", "kazakh"),
+    ("sq", "albanian"),
+    ("sw", "swahili"),
+    ("gl", "galician"),
+    ("mr", "marathi"),
+    ("pa", "punjabi"),
+    ("si", "sinhala"),
+    ("km", "khmer"),
+    ("sn", "shona"),
+    ("yo", "yoruba"),
+    ("so", "somali"),
+    ("af", "afrikaans"),
+    ("oc", "occitan"),
+    ("ka", "georgian"),
+    ("be", "belarusian"),
+    ("tg", "tajik"),
+    ("sd", "sindhi"),
+    ("gu", "gujarati"),
+    ("am", "amharic"),
+    ("yi", "yiddish"),
+    ("lo", "lao"),
+    ("uz", "uzbek"),
+    ("fo", "faroese"),
+    ("ht", "haitian creole"),
+    ("ps", "pashto"),
+    ("tk", "turkmen"),
+    ("nn", "nynorsk"),
+    ("mt", "maltese"),
+    ("sa", "sanskrit"),
+    ("lb", "luxembourgish"),
+    ("my", "myanmar"),
+    ("bo", "tibetan"),
+    ("tl", "tagalog"),
+    ("mg", "malagasy"),
+    ("as", "assamese"),
+    ("tt", "tatar"),
+    ("haw", "hawaiian"),
+    ("ln", "lingala"),
+    ("ha", "hausa"),
+    ("ba", "bashkir"),
+    ("jw", "javanese"),
+    ("su", "sundanese"),
+    ("yue", "cantonese"),
+];
+
+/// Check `language` against Whisper's codes (or `auto`), suggesting the
+/// closest code when it isn't one
+pub fn validate_language(language: &str) -> Result<(), String> {
+    if language == AUTO_LANGUAGE || LANGUAGES.iter().any(|&(code, _)| code == language) {
+        return Ok(());
+    }
+
+    match closest_language(language) {
+        Some((code, name)) => Err(format!(
+            "unknown language '{}'; did you mean '{}' ({})?",
+            language, code, name
+        )),
+        None => Err(format!(
+            "unknown language '{}'; use '{}' or one of: {}",
+            language,
+            AUTO_LANGUAGE,
+            LANGUAGES
+                .iter()
+                .map(|&(code, _)| code)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// The language whose code or name is within a typo of `query`
+fn closest_language(query: &str) -> Option<(&'static str, &'static str)> {
+    let query = query.trim().to_lowercase();
+    LANGUAGES
+        .iter()
+        .map(|&(code, name)| {
+            let distance = edit_distance(&query, code).min(edit_distance(&query, name));
+            (distance, (code, name))
+        })
+        // Names may be off by a couple of letters; codes are too short for that
+        .filter(|&(distance, _)| distance <= 1 || (distance <= 2 && query.len() > 4))
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, language)| language)
+}
+
+/// Levenshtein distance between `a` and `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_codes_and_auto_are_valid() {
+        assert_eq!(LANGUAGES.len(), 100);
+        for code in ["en", "de", "haw", "yue", "auto"] {
+            assert!(validate_language(code).is_ok(), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_language_names_suggest_their_code() {
+        let err = validate_language("english").unwrap_err();
+        assert_eq!(
+            err,
+            "unknown language 'english'; did you mean 'en' (english)?"
+        );
+
+        let err = validate_language("German").unwrap_err();
+        assert!(err.contains("did you mean 'de' (german)?"));
+
+        let err = validate_language("portugese").unwrap_err();
+        assert!(err.contains("did you mean 'pt'"));
+    }
+
+    #[test]
+    fn test_near_miss_codes_suggest_a_code() {
+        assert!(validate_language("EN")
+            .unwrap_err()
+            .contains("did you mean 'en'"));
+        assert!(validate_language("eng")
+            .unwrap_err()
+            .contains("did you mean 'en'"));
+    }
+
+    #[test]
+    fn test_unknown_language_lists_codes() {
+        let err = validate_language("klingon-ish").unwrap_err();
+        assert!(err.starts_with("unknown language 'klingon-ish'; use 'auto' or one of: en, zh,"));
+        assert!(err.ends_with("yue"));
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", "en"), 2);
+        assert_eq!(edit_distance("eng", "en"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
@@ -0,0 +1,287 @@
+//! Stitching transcripts of overlapping audio windows, so words heard at the
+//! end of one window and the start of the next appear once.
+
+use std::time::Duration;
+
+use super::{TranscriptionResult, TranscriptionSegment};
+
+/// Most words at each side of a seam that are compared for overlap
+const MAX_OVERLAP_WORDS: usize = 32;
+
+/// Fewest shared words that count as an overlap, so one common word such as
+/// "the" doesn't make two windows look like they repeat each other
+const MIN_OVERLAP_WORDS: usize = 2;
+
+/// Words allowed between a shared run and the edge of its window. Whisper
+/// often mishears a word cut off at a window edge, so an overlap rarely
+/// reaches the very end of one window or the very start of the next.
+const MAX_EDGE_WORDS: usize = 3;
+
+impl TranscriptionResult {
+    /// Join results transcribed from overlapping windows of one recording,
+    /// shifting each window's segments by the offset it started at.
+    ///
+    /// Where the end of one window's text matches the start of the next, the
+    /// shared words are kept once; windows that don't overlap are joined as
+    /// by [`TranscriptionResult::concat`]. Segments of a later window that end
+    /// before the earlier windows do repeat audio already covered and are dropped.
+    pub fn merge_overlapping(parts: Vec<(Duration, TranscriptionResult)>) -> Self {
+        let mut merged = TranscriptionResult {
+            text: String::new(),
+            segments: Vec::new(),
+            language: None,
+            processing_time: Duration::ZERO,
+            partial: false,
+        };
+
+        for (offset, part) in parts {
+            merged.text = stitch_overlap(&merged.text, &part.text);
+
+            let covered = merged.segments.last().map_or(Duration::ZERO, |s| s.end);
+            merged.segments.extend(
+                part.segments
+                    .into_iter()
+                    .map(|segment| TranscriptionSegment {
+                        start: (segment.start + offset).max(covered),
+                        end: segment.end + offset,
+                        ..segment
+                    })
+                    .filter(|segment| segment.end > covered),
+            );
+            merged.language = merged.language.or(part.language);
+            merged.processing_time += part.processing_time;
+            merged.partial |= part.partial;
+        }
+
+        merged
+    }
+}
+
+/// Join `next` onto `previous`, leaving out the words at the start of `next`
+/// that repeat the end of `previous`. The overlap is found by aligning the
+/// tail of `previous` with the head of `next` word by word (longest common
+/// subsequence, ignoring case and punctuation). `previous` is cut just before
+/// the first word they share and the overlap is taken from `next`, whose
+/// copy was heard with more context on both sides.
+fn stitch_overlap(previous: &str, next: &str) -> String {
+    let previous: Vec<&str> = previous.split_whitespace().collect();
+    let next: Vec<&str> = next.split_whitespace().collect();
+
+    let tail_start = previous.len().saturating_sub(MAX_OVERLAP_WORDS);
+    let tail = &previous[tail_start..];
+    let head = &next[..next.len().min(MAX_OVERLAP_WORDS)];
+
+    let (kept, rest) = match seam(tail, head) {
+        Some((first_previous, first_next)) => (
+            &previous[..tail_start + first_previous],
+            &next[first_next..],
+        ),
+        None => (&previous[..], &next[..]),
+    };
+    kept.iter()
+        .chain(rest)
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Indices of the first word `tail` and `head` share, if they overlap: enough
+/// words in common, with the shared run close to the end of `tail` and the
+/// start of `head`
+fn seam(tail: &[&str], head: &[&str]) -> Option<(usize, usize)> {
+    let pairs = common_words(tail, head);
+    let (&(first_previous, first_next), &(last_previous, _)) = (pairs.first()?, pairs.last()?);
+
+    let overlaps = pairs.len() >= MIN_OVERLAP_WORDS
+        && tail.len() - 1 - last_previous <= MAX_EDGE_WORDS
+        && first_next <= MAX_EDGE_WORDS;
+    overlaps.then_some((first_previous, first_next))
+}
+
+/// Index pairs of a longest common subsequence of `a` and `b`, in order
+fn common_words(a: &[&str], b: &[&str]) -> Vec<(usize, usize)> {
+    let a: Vec<String> = a.iter().map(|word| normalize(word)).collect();
+    let b: Vec<String> = b.iter().map(|word| normalize(word)).collect();
+
+    // lengths[i][j] is the LCS length of a[i..] and b[j..]
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::with_capacity(lengths[0][0]);
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// `word` lowercased without punctuation, so "Store," matches "store". Words
+/// that are all punctuation are compared as written.
+fn normalize(word: &str) -> String {
+    let normalized: String = word
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '\'')
+        .flat_map(char::to_lowercase)
+        .collect();
+    if normalized.is_empty() {
+        word.to_string()
+    } else {
+        normalized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start_ms: u64, end_ms: u64, text: &str) -> TranscriptionSegment {
+        TranscriptionSegment {
+            start: Duration::from_millis(start_ms),
+            end: Duration::from_millis(end_ms),
+            text: text.to_string(),
+            avg_logprob: None,
+            no_speech_prob: None,
+            speaker: None,
+        }
+    }
+
+    fn window(text: &str, segments: Vec<TranscriptionSegment>) -> TranscriptionResult {
+        TranscriptionResult {
+            text: text.to_string(),
+            segments,
+            language: Some("en".to_string()),
+            processing_time: Duration::from_millis(100),
+            partial: false,
+        }
+    }
+
+    #[test]
+    fn test_stitch_removes_repeated_words() {
+        assert_eq!(
+            stitch_overlap(
+                "We went to the store and",
+                "to the store and bought some milk."
+            ),
+            "We went to the store and bought some milk."
+        );
+    }
+
+    #[test]
+    fn test_stitch_ignores_case_and_punctuation() {
+        assert_eq!(
+            stitch_overlap("Then we left the store.", "The store, then home."),
+            "Then we left The store, then home."
+        );
+    }
+
+    #[test]
+    fn test_stitch_tolerates_misheard_edge_words() {
+        // The word cut off at the end of the first window was misheard
+        assert_eq!(
+            stitch_overlap(
+                "please send the quarterly rep",
+                "send the quarterly report by Friday"
+            ),
+            "please send the quarterly report by Friday"
+        );
+        // ...and one at the start of the second window too
+        assert_eq!(
+            stitch_overlap("it was a bright cold day in", "right cold day in April"),
+            "it was a bright cold day in April"
+        );
+    }
+
+    #[test]
+    fn test_stitch_without_overlap_concatenates() {
+        assert_eq!(
+            stitch_overlap("The cat sat", "on the mat"),
+            "The cat sat on the mat"
+        );
+        // One shared word isn't enough
+        assert_eq!(stitch_overlap("I saw the", "the dog"), "I saw the the dog");
+        // Shared words far from the seam aren't an overlap
+        assert_eq!(
+            stitch_overlap(
+                "one two three four five six seven",
+                "eight nine ten eleven one two"
+            ),
+            "one two three four five six seven eight nine ten eleven one two"
+        );
+        assert_eq!(stitch_overlap("", "first window"), "first window");
+        assert_eq!(stitch_overlap("last window", ""), "last window");
+    }
+
+    #[test]
+    fn test_merge_overlapping_windows() {
+        // Three 3 s windows with 1 s of overlap
+        let parts = vec![
+            (
+                Duration::ZERO,
+                window(
+                    " Hello there, how are",
+                    vec![
+                        segment(0, 1800, " Hello there,"),
+                        segment(1800, 3000, " how are"),
+                    ],
+                ),
+            ),
+            (
+                Duration::from_secs(2),
+                window(
+                    " how are you doing today",
+                    vec![
+                        segment(0, 1000, " how are"),
+                        segment(1000, 3000, " you doing today"),
+                    ],
+                ),
+            ),
+            (
+                Duration::from_secs(4),
+                window(
+                    " doing today? Fine, thanks.",
+                    vec![
+                        segment(0, 1000, " doing today?"),
+                        segment(1000, 3000, " Fine, thanks."),
+                    ],
+                ),
+            ),
+        ];
+
+        let merged = TranscriptionResult::merge_overlapping(parts);
+        assert_eq!(
+            merged.text,
+            "Hello there, how are you doing today? Fine, thanks."
+        );
+        let texts: Vec<&str> = merged.segments.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                " Hello there,",
+                " how are",
+                " you doing today",
+                " Fine, thanks."
+            ]
+        );
+        assert_eq!(merged.segments[2].start, Duration::from_secs(3));
+        assert_eq!(merged.segments[3].start, Duration::from_secs(5));
+        assert_eq!(merged.segments[3].end, Duration::from_secs(7));
+        assert_eq!(merged.processing_time, Duration::from_millis(300));
+        assert_eq!(merged.language, Some("en".to_string()));
+    }
+}
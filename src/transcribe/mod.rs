@@ -1,64 +1,246 @@
 //! Whisper transcription engine integration.
 
+use std::ops::Range;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use tracing::{debug, info, warn};
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+use serde::{Serialize, Serializer};
+use tracing::{debug, info, instrument, warn, Span};
+use whisper_rs::{
+    FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperState,
+};
 
-use crate::model::{ModelManager, Quantization};
+use crate::audio::TARGET_SAMPLE_RATE;
+use crate::model::{cached_model_sha256, is_model_file, ModelManager, Quantization, Sampling};
 use crate::{MicrodropError, Result};
 
+mod bench;
+mod cleanup;
+mod language;
+mod merge;
+mod render;
+mod replace;
+
+pub use bench::{benchmark_sample, BenchmarkReport, ModelRun};
+pub use language::{validate_language, AUTO_LANGUAGE, LANGUAGES};
+pub use replace::validate_replacements;
+
 pub struct TranscriptionEngine {
     context: WhisperContext,
+    /// Inference state, allocated on first use and kept for later transcriptions
+    state: Mutex<Option<WhisperState>>,
     model_path: PathBuf,
+    /// Inference threads, or whisper.cpp's default when unset
+    threads: Option<i32>,
+    max_tokens: Option<u32>,
+    max_segment_len: Option<u32>,
+    diarize: bool,
+    language: String,
+    sampling: Sampling,
+    suppress_blank: bool,
+    suppress_non_speech: bool,
+    thresholds: DecodeThresholds,
+    timeout: Option<Duration>,
+}
+
+/// Whisper's decoder fallback thresholds; `None` keeps Whisper's default.
+///
+/// A segment that fails a check is decoded again at a higher temperature:
+/// - `entropy` (default 2.4): a segment whose token entropy is below this is
+///   treated as repetitive. Raise it to catch more repetition loops.
+/// - `logprob` (default -1.0): a segment whose mean token log-probability is
+///   below this is retried. Raise it toward 0 to retry more low-confidence text.
+/// - `no_speech` (default 0.6): a segment whose no-speech probability is above
+///   this, and whose log-probability is below `logprob`, is dropped as silence.
+///   Lower it to drop hallucinations over silence; raise it if quiet speech
+///   goes missing.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DecodeThresholds {
+    pub entropy: Option<f32>,
+    pub logprob: Option<f32>,
+    pub no_speech: Option<f32>,
+}
+
+impl DecodeThresholds {
+    /// Check each set threshold is in the range Whisper can use
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if let Some(entropy) = self.entropy {
+            if !(entropy.is_finite() && entropy > 0.0) {
+                return Err(format!(
+                    "entropy_thold must be greater than zero, got {}",
+                    entropy
+                ));
+            }
+        }
+        if let Some(logprob) = self.logprob {
+            if !(logprob.is_finite() && logprob <= 0.0) {
+                return Err(format!(
+                    "logprob_thold must be zero or negative, got {}",
+                    logprob
+                ));
+            }
+        }
+        if let Some(no_speech) = self.no_speech {
+            if !(0.0..=1.0).contains(&no_speech) {
+                return Err(format!(
+                    "no_speech_thold must be between 0 and 1, got {}",
+                    no_speech
+                ));
+            }
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct TranscriptionResult {
     pub text: String,
     pub segments: Vec<TranscriptionSegment>,
     pub language: Option<String>,
+    #[serde(serialize_with = "serialize_secs")]
     pub processing_time: Duration,
+    /// Inference hit the timeout; only the segments finished before it are included
+    pub partial: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct TranscriptionSegment {
+    #[serde(serialize_with = "serialize_secs")]
     pub start: Duration,
+    #[serde(serialize_with = "serialize_secs")]
     pub end: Duration,
     pub text: String,
+    /// Mean log-probability of the segment's text tokens, leaving out special ones
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_logprob: Option<f32>,
+    /// Probability that the segment contains no speech
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_speech_prob: Option<f32>,
+    /// Running index of the speaker, counting from 0 and advancing at each
+    /// speaker turn; only set when diarizing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<u32>,
 }
 
-impl TranscriptionEngine {
-    pub fn new<P: AsRef<Path>>(model_path: P) -> Result<Self> {
-        let model_path = model_path.as_ref().to_path_buf();
+impl TranscriptionResult {
+    /// Join results transcribed from separate parts of one recording, shifting
+    /// each part's segments by the offset it started at
+    pub fn concat(parts: Vec<(Duration, TranscriptionResult)>) -> Self {
+        let mut combined = TranscriptionResult {
+            text: String::new(),
+            segments: Vec::new(),
+            language: None,
+            processing_time: Duration::ZERO,
+            partial: false,
+        };
+
+        for (offset, part) in parts {
+            let text = part.text.trim();
+            if !text.is_empty() {
+                if !combined.text.is_empty() {
+                    combined.text.push(' ');
+                }
+                combined.text.push_str(text);
+            }
+            for segment in part.segments {
+                combined.segments.push(TranscriptionSegment {
+                    start: segment.start + offset,
+                    end: segment.end + offset,
+                    ..segment
+                });
+            }
+            combined.language = combined.language.or(part.language);
+            combined.processing_time += part.processing_time;
+            combined.partial |= part.partial;
+        }
+
+        combined
+    }
+}
+
+/// Which model produced a transcript, recorded in JSON output for reproducibility
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModelIdentity {
+    /// File name of the model
+    #[serde(rename = "model")]
+    pub file: String,
+    /// SHA-256 from the model's cached metadata, if known
+    #[serde(rename = "model_sha256", skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
 
-        if !model_path.exists() {
-            return Err(MicrodropError::ModelLoad(format!(
-                "Model file not found: {}",
-                model_path.display()
-            )));
+impl ModelIdentity {
+    /// Identify the model at `model_path` by its file name and cached checksum
+    pub fn from_path(model_path: &Path) -> Self {
+        Self {
+            file: model_path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| model_path.display().to_string()),
+            sha256: cached_model_sha256(model_path),
         }
+    }
+}
 
-        info!("Loading Whisper model from: {}", model_path.display());
+/// Offset of a sample index within processed audio
+fn sample_offset(index: usize) -> Duration {
+    Duration::from_secs_f64(index as f64 / TARGET_SAMPLE_RATE as f64)
+}
 
-        let context = WhisperContext::new_with_params(
-            model_path.to_str().ok_or_else(|| {
-                MicrodropError::ModelLoad("Model path contains invalid UTF-8".to_string())
-            })?,
-            WhisperContextParameters::default(),
-        )
-        .map_err(|e| MicrodropError::ModelLoad(format!("Failed to load model: {}", e)))?;
+fn serialize_secs<S: Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
 
-        debug!("Whisper model loaded successfully");
+impl TranscriptionEngine {
+    #[instrument(
+        name = "model_load",
+        level = "debug",
+        skip_all,
+        fields(model_path = %model_path.as_ref().display())
+    )]
+    pub fn new<P: AsRef<Path>>(model_path: P) -> Result<Self> {
+        let model_path = model_path.as_ref().to_path_buf();
+        let context = load_context(&model_path)?;
 
         Ok(Self {
             context,
+            state: Mutex::new(None),
             model_path,
+            threads: None,
+            max_tokens: None,
+            max_segment_len: None,
+            diarize: false,
+            language: "en".to_string(),
+            sampling: Sampling::default(),
+            suppress_blank: true,
+            suppress_non_speech: false,
+            thresholds: DecodeThresholds::default(),
+            timeout: None,
         })
     }
 
+    #[instrument(
+        name = "inference",
+        level = "debug",
+        skip_all,
+        fields(samples = audio_samples.len(), threads = self.threads(), segments)
+    )]
     pub async fn transcribe(&self, audio_samples: &[f32]) -> Result<TranscriptionResult> {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        self.transcribe_until(audio_samples, deadline).await
+    }
+
+    /// Transcribe `audio_samples`, stopping Whisper at `deadline` and keeping
+    /// the segments finished by then
+    async fn transcribe_until(
+        &self,
+        audio_samples: &[f32],
+        deadline: Option<Instant>,
+    ) -> Result<TranscriptionResult> {
         if audio_samples.is_empty() {
             warn!("Empty audio provided for transcription");
             return Ok(TranscriptionResult {
@@ -66,47 +248,169 @@ impl TranscriptionEngine {
                 segments: Vec::new(),
                 language: None,
                 processing_time: Duration::from_millis(0),
+                partial: false,
             });
         }
 
-        let start_time = std::time::Instant::now();
+        let start_time = Instant::now();
 
         // Clone audio data for the blocking task
         let audio_data = audio_samples.to_vec();
 
         // Run inference synchronously since WhisperContext cannot be sent across threads safely
-        let mut result = self.run_inference(&audio_data)?;
+        let mut result = self.run_inference(&audio_data, deadline)?;
 
         let processing_time = start_time.elapsed();
         result.processing_time = processing_time;
-        debug!("Transcription completed in {:?}", processing_time);
+        Span::current().record("segments", result.segments.len());
+        info!("Inference took {:.2?}", processing_time);
 
         Ok(result)
     }
 
-    fn run_inference(&self, audio_data: &[f32]) -> Result<TranscriptionResult> {
-        let mut state = self
-            .context
-            .create_state()
-            .map_err(|e| MicrodropError::Transcription(format!("Failed to create state: {}", e)))?;
+    /// Transcribe each region of `audio_samples` on its own, keeping timestamps
+    /// relative to the start of the whole buffer. The timeout covers all
+    /// regions together; regions after the one it interrupts are skipped.
+    pub async fn transcribe_regions(
+        &self,
+        audio_samples: &[f32],
+        regions: &[Range<usize>],
+    ) -> Result<TranscriptionResult> {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let mut parts = Vec::with_capacity(regions.len());
+        for region in regions {
+            debug!("Transcribing region {:?}", region);
+            let result = self
+                .transcribe_until(&audio_samples[region.clone()], deadline)
+                .await?;
+            let partial = result.partial;
+            parts.push((sample_offset(region.start), result));
+            if partial {
+                break;
+            }
+        }
+        Ok(TranscriptionResult::concat(parts))
+    }
+
+    /// Swap in the model at `model_path`, unless it is already loaded.
+    /// Returns whether a new model was loaded.
+    #[instrument(
+        name = "model_load",
+        level = "debug",
+        skip_all,
+        fields(model_path = %model_path.as_ref().display())
+    )]
+    pub fn reload<P: AsRef<Path>>(&mut self, model_path: P) -> Result<bool> {
+        let model_path = model_path.as_ref();
+        if model_path == self.model_path {
+            debug!("Model already loaded: {}", model_path.display());
+            return Ok(false);
+        }
+
+        self.context = load_context(model_path)?;
+        self.model_path = model_path.to_path_buf();
+        *self.lock_state() = None;
+        Ok(true)
+    }
+
+    /// Allocate inference state and run a short dummy inference, so the first
+    /// real transcription doesn't pay for either
+    #[instrument(name = "warm_up", level = "debug", skip_all)]
+    pub fn warm_up(&self) -> Result<()> {
+        let start_time = Instant::now();
+        self.run_inference(&vec![0.0; TARGET_SAMPLE_RATE as usize], None)?;
+        info!("Model warmed up in {:.2?}", start_time.elapsed());
+        Ok(())
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, Option<WhisperState>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn run_inference(
+        &self,
+        audio_data: &[f32],
+        deadline: Option<Instant>,
+    ) -> Result<TranscriptionResult> {
+        let mut state = self.lock_state();
+        let state = match *state {
+            Some(ref mut state) => state,
+            None => state.insert(self.context.create_state().map_err(|e| {
+                MicrodropError::Transcription(format!("Failed to create state: {}", e))
+            })?),
+        };
 
         // Configure transcription parameters
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        let strategy = match self.sampling {
+            Sampling::Greedy { best_of } => SamplingStrategy::Greedy {
+                best_of: best_of as i32,
+            },
+            Sampling::BeamSearch { beam_size } => SamplingStrategy::BeamSearch {
+                beam_size: beam_size as i32,
+                patience: -1.0,
+            },
+        };
+        let mut params = FullParams::new(strategy);
+        if let Some(threads) = self.threads {
+            params.set_n_threads(threads);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            params.set_max_tokens(max_tokens as i32);
+        }
+        if let Some(max_len) = self.max_segment_len {
+            // Whisper only wraps segments when it has token-level timestamps
+            params.set_token_timestamps(true);
+            params.set_split_on_word(true);
+            params.set_max_len(max_len as i32);
+        }
+        if self.diarize {
+            params.set_tdrz_enable(true);
+        }
+        params.set_suppress_blank(self.suppress_blank);
+        params.set_suppress_nst(self.suppress_non_speech);
+        if let Some(entropy) = self.thresholds.entropy {
+            params.set_entropy_thold(entropy);
+        }
+        if let Some(logprob) = self.thresholds.logprob {
+            params.set_logprob_thold(logprob);
+        }
+        if let Some(no_speech) = self.thresholds.no_speech {
+            params.set_no_speech_thold(no_speech);
+        }
         params.set_translate(false);
-        params.set_language(Some("en"));
+        params.set_language(Some(&self.language));
         params.set_print_realtime(false);
         params.set_print_progress(false);
+        if let Some(deadline) = deadline {
+            params.set_abort_callback_safe(move || Instant::now() >= deadline);
+        }
 
-        // Run transcription
-        state
-            .full(params, audio_data)
-            .map_err(|e| MicrodropError::Transcription(format!("Transcription failed: {}", e)))?;
+        // Run transcription; an abort at the deadline keeps the finished segments
+        let partial = match state.full(params, audio_data) {
+            Ok(_) => false,
+            Err(e) if deadline.is_some_and(|deadline| Instant::now() >= deadline) => {
+                warn!(
+                    "Transcription timed out ({}), keeping the segments finished so far",
+                    e
+                );
+                true
+            }
+            Err(e) => {
+                return Err(MicrodropError::Transcription(format!(
+                    "Transcription failed: {}",
+                    e
+                )))
+            }
+        };
 
         // Extract results
         let num_segments = state.full_n_segments();
 
         let mut segments = Vec::new();
         let mut full_text = String::new();
+        let mut speaker = 0;
+        // Special tokens (timestamps, end of text) sort after the text ones
+        let token_eot = self.context.token_eot();
 
         for i in 0..num_segments {
             if let Some(segment) = state.get_segment(i) {
@@ -117,6 +421,14 @@ impl TranscriptionEngine {
                     })?
                     .to_string();
 
+                let plogs: Vec<f32> = (0..segment.n_tokens())
+                    .filter_map(|t| segment.get_token(t))
+                    .filter(|token| token.token_id() < token_eot)
+                    .map(|token| token.token_data().plog)
+                    .collect();
+                let avg_logprob = (!plogs.is_empty())
+                    .then(|| plogs.iter().sum::<f32>() / plogs.len() as f32);
+
                 let start_time = segment.start_timestamp();
                 let end_time = segment.end_timestamp();
 
@@ -128,7 +440,14 @@ impl TranscriptionEngine {
                     start,
                     end,
                     text: segment_text.clone(),
+                    avg_logprob,
+                    no_speech_prob: Some(segment.no_speech_probability()),
+                    speaker: self.diarize.then_some(speaker),
                 });
+                // Whisper ends a segment where it hears the speaker change
+                if self.diarize && segment.next_segment_speaker_turn() {
+                    speaker += 1;
+                }
 
                 if !full_text.is_empty() {
                     full_text.push(' ');
@@ -140,48 +459,156 @@ impl TranscriptionEngine {
         Ok(TranscriptionResult {
             text: full_text,
             segments,
-            language: Some("en".to_string()),
+            language: Some(self.language.clone()),
             processing_time: Duration::from_millis(0), // This will be set by the caller
+            partial,
         })
     }
 
     pub fn model_path(&self) -> &Path {
         &self.model_path
     }
+
+    /// File name and cached checksum of the loaded model
+    pub fn model_identity(&self) -> ModelIdentity {
+        ModelIdentity::from_path(&self.model_path)
+    }
+
+    /// Number of threads used for inference
+    pub fn threads(&self) -> i32 {
+        self.threads.unwrap_or_else(default_thread_count)
+    }
+
+    pub fn set_threads(&mut self, threads: u32) {
+        self.threads = Some(threads as i32);
+    }
+
+    /// Language code the audio is transcribed as
+    pub fn set_language(&mut self, language: impl Into<String>) {
+        self.language = language.into();
+    }
+
+    pub fn set_sampling(&mut self, sampling: Sampling) {
+        self.sampling = sampling;
+    }
+
+    /// Suppress blank output at the start of a segment (on by default, as in Whisper)
+    pub fn set_suppress_blank(&mut self, suppress: bool) {
+        self.suppress_blank = suppress;
+    }
+
+    /// Suppress non-speech tokens such as "[Music]". Off by default; turning it
+    /// on reduces hallucinated annotations on noisy recordings.
+    pub fn set_suppress_non_speech(&mut self, suppress: bool) {
+        self.suppress_non_speech = suppress;
+    }
+
+    /// Override Whisper's decoder fallback thresholds; unset ones keep Whisper's defaults
+    pub fn set_thresholds(&mut self, thresholds: DecodeThresholds) {
+        self.thresholds = thresholds;
+    }
+
+    /// Stop inference after `timeout` and keep the segments finished so far
+    /// (None = unlimited)
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Limit the number of tokens Whisper may emit per segment
+    pub fn set_max_tokens(&mut self, max_tokens: Option<u32>) {
+        self.max_tokens = max_tokens;
+    }
+
+    /// Split segments at word boundaries once they exceed `max_len` characters,
+    /// for shorter subtitle cues. A single longer word still gets its own segment.
+    pub fn set_max_segment_len(&mut self, max_len: Option<u32>) {
+        self.max_segment_len = max_len;
+    }
+
+    /// Tag segments with a running speaker index, advancing at each speaker
+    /// turn. Only tinydiarize (`-tdrz`) models detect turns; with any other
+    /// model every segment stays with the first speaker.
+    pub fn set_diarize(&mut self, diarize: bool) {
+        if diarize && !is_tdrz_model(&self.model_path) {
+            warn!(
+                "{} is not a tinydiarize (-tdrz) model, so speaker turns won't be detected",
+                self.model_path.display()
+            );
+        }
+        self.diarize = diarize;
+    }
+}
+
+/// Whether the model file is a tinydiarize model, by the `-tdrz` suffix
+/// whisper.cpp gives them (e.g. `ggml-small.en-tdrz.bin`)
+fn is_tdrz_model(model_path: &Path) -> bool {
+    model_path
+        .file_stem()
+        .is_some_and(|stem| stem.to_string_lossy().ends_with("-tdrz"))
+}
+
+/// Load the Whisper model at `model_path`, logging how long it took
+fn load_context(model_path: &Path) -> Result<WhisperContext> {
+    if !model_path.exists() {
+        return Err(MicrodropError::ModelLoad(format!(
+            "Model file not found: {}",
+            model_path.display()
+        )));
+    }
+
+    if !is_model_file(model_path) {
+        warn!(
+            "{} does not have a known model extension, loading it anyway",
+            model_path.display()
+        );
+    }
+
+    info!("Loading Whisper model from: {}", model_path.display());
+    let start_time = Instant::now();
+
+    let context = WhisperContext::new_with_params(
+        model_path.to_str().ok_or_else(|| {
+            MicrodropError::ModelLoad("Model path contains invalid UTF-8".to_string())
+        })?,
+        WhisperContextParameters::default(),
+    )
+    .map_err(|e| MicrodropError::ModelLoad(format!("Failed to load model: {}", e)))?;
+
+    info!("Model loaded in {:.2?}", start_time.elapsed());
+    Ok(context)
+}
+
+/// The thread count whisper.cpp picks when none is set: up to 4, bounded by
+/// available cores
+fn default_thread_count() -> i32 {
+    std::thread::available_parallelism()
+        .map(|n| n.get().min(4) as i32)
+        .unwrap_or(4)
 }
 
-pub fn find_default_model() -> Option<PathBuf> {
+pub fn find_default_model(model_manager: &ModelManager) -> Option<PathBuf> {
     // First try to use the model manager to find cached models
-    if let Ok(model_manager) = ModelManager::new() {
-        if let Ok(cached_models) = model_manager.list_cached_models() {
-            if let Some(cached) = cached_models.first() {
-                debug!("Found cached model: {}", cached.path.display());
-                return Some(cached.path.clone());
-            }
+    if let Ok(cached_models) = model_manager.list_cached_models() {
+        if let Some(cached) = cached_models.first() {
+            debug!("Found cached model: {}", cached.path.display());
+            return Some(cached.path.clone());
         }
     }
 
-    // Fallback to old directory search
+    // Fallback to old directory search, which also finds models without metadata
     let possible_dirs = [
-        dirs::data_local_dir().map(|d| d.join("microdrop/models")),
-        dirs::home_dir().map(|d| d.join(".local/share/microdrop/models")),
-        Some(PathBuf::from("./models")),
-        Some(PathBuf::from(".")),
+        model_manager.cache_dir().to_path_buf(),
+        PathBuf::from("./models"),
+        PathBuf::from("."),
     ];
 
-    for dir_opt in possible_dirs.iter() {
-        if let Some(dir) = dir_opt {
-            if let Ok(entries) = std::fs::read_dir(dir) {
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if path.is_file() {
-                        if let Some(ext) = path.extension() {
-                            if ext == "bin" || ext == "ggml" {
-                                debug!("Found potential model: {}", path.display());
-                                return Some(path);
-                            }
-                        }
-                    }
+    for dir in &possible_dirs {
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_file() && is_model_file(&path) {
+                    debug!("Found potential model: {}", path.display());
+                    return Some(path);
                 }
             }
         }
@@ -196,7 +623,11 @@ pub fn find_default_model() -> Option<PathBuf> {
 /// - Direct file paths (if they exist)
 /// - Model names that should be resolved from cache
 /// - Model names with quantization
-pub fn resolve_model_path(model_input: &str, quantization: Option<&str>) -> Result<PathBuf> {
+pub fn resolve_model_path(
+    model_manager: &ModelManager,
+    model_input: &str,
+    quantization: Option<&str>,
+) -> Result<PathBuf> {
     let model_path = PathBuf::from(model_input);
 
     // If it's an existing file path, use it directly
@@ -205,7 +636,6 @@ pub fn resolve_model_path(model_input: &str, quantization: Option<&str>) -> Resu
     }
 
     // Try to resolve as a model name using the model manager
-    let model_manager = ModelManager::new()?;
     let parsed_quantization = quantization
         .map(|q| q.parse::<Quantization>())
         .transpose()
@@ -228,6 +658,31 @@ mod tests {
     use std::fs::File;
     use std::io::Write;
 
+    #[test]
+    fn test_decode_thresholds_validate_ranges() {
+        assert!(DecodeThresholds::default().validate().is_ok());
+        let edges = DecodeThresholds {
+            entropy: Some(0.1),
+            logprob: Some(0.0),
+            no_speech: Some(1.0),
+        };
+        assert!(edges.validate().is_ok());
+
+        let err = DecodeThresholds {
+            logprob: Some(0.5),
+            ..Default::default()
+        }
+        .validate()
+        .unwrap_err();
+        assert!(err.contains("logprob_thold"));
+        assert!(DecodeThresholds {
+            entropy: Some(f32::INFINITY),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+
     #[test]
     fn test_model_file_not_found() {
         let non_existent_path = PathBuf::from("non_existent_model.bin");
@@ -265,12 +720,24 @@ mod tests {
     #[test]
     fn test_find_default_model_no_models() {
         // In a clean test environment, there should be no models
-        let result = find_default_model();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let model_manager = ModelManager::with_cache_dir(temp_dir.path()).unwrap();
+        let result = find_default_model(&model_manager);
         // This might be None in test environment, which is fine
         // The function should not panic
         let _ = result;
     }
 
+    #[test]
+    fn test_find_default_model_gguf() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let model_manager = ModelManager::with_cache_dir(temp_dir.path()).unwrap();
+        let model_path = temp_dir.path().join("ggml-base.en.gguf");
+        std::fs::write(&model_path, b"gguf").unwrap();
+
+        assert_eq!(find_default_model(&model_manager), Some(model_path));
+    }
+
     #[test]
     fn test_transcription_result_creation() {
         let result = TranscriptionResult {
@@ -279,9 +746,13 @@ mod tests {
                 start: Duration::from_millis(0),
                 end: Duration::from_millis(1000),
                 text: "Hello world".to_string(),
+                avg_logprob: None,
+                no_speech_prob: None,
+                speaker: None,
             }],
             language: Some("en".to_string()),
             processing_time: Duration::from_millis(100),
+            partial: false,
         };
 
         assert_eq!(result.text, "Hello world");
@@ -296,22 +767,72 @@ mod tests {
             start: Duration::from_millis(500),
             end: Duration::from_millis(1500),
             text: "test segment".to_string(),
+            avg_logprob: None,
+            no_speech_prob: None,
+            speaker: None,
         };
 
         assert_eq!(segment.start.as_millis(), 500);
         assert_eq!(segment.end.as_millis(), 1500);
         assert_eq!(segment.text, "test segment");
     }
+
+    #[test]
+    fn test_is_tdrz_model() {
+        assert!(is_tdrz_model(Path::new("models/ggml-small.en-tdrz.bin")));
+        assert!(!is_tdrz_model(Path::new("models/ggml-small.en.bin")));
+        assert!(!is_tdrz_model(Path::new("models/tdrz/ggml-base.bin")));
+    }
+
+    #[test]
+    fn test_segment_quality_metrics_serialization() {
+        let mut segment = TranscriptionSegment {
+            start: Duration::from_millis(500),
+            end: Duration::from_millis(1500),
+            text: "test segment".to_string(),
+            avg_logprob: Some(-0.25),
+            no_speech_prob: Some(0.5),
+            speaker: None,
+        };
+
+        let json = serde_json::to_value(&segment).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "start": 0.5,
+                "end": 1.5,
+                "text": "test segment",
+                "avg_logprob": -0.25,
+                "no_speech_prob": 0.5,
+            })
+        );
+
+        segment.avg_logprob = None;
+        segment.no_speech_prob = None;
+        let json = serde_json::to_value(&segment).unwrap();
+        assert!(json.get("avg_logprob").is_none());
+        assert!(json.get("no_speech_prob").is_none());
+    }
 }
 
 /// Mock transcription engine for deterministic testing
-#[cfg(test)]
+#[cfg(any(test, feature = "mock-engine"))]
 pub struct MockTranscriptionEngine {
     responses: Vec<TranscriptionResult>,
     call_count: std::cell::RefCell<usize>,
+    max_segment_len: Option<u32>,
+    max_tokens: Option<u32>,
+    diarize: bool,
 }
 
-#[cfg(test)]
+#[cfg(any(test, feature = "mock-engine"))]
+impl Default for MockTranscriptionEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(any(test, feature = "mock-engine"))]
 impl MockTranscriptionEngine {
     pub fn new() -> Self {
         Self {
@@ -322,12 +843,19 @@ impl MockTranscriptionEngine {
                         start: Duration::from_millis(0),
                         end: Duration::from_millis(2000),
                         text: "This is a test transcription.".to_string(),
+                        avg_logprob: None,
+                        no_speech_prob: None,
+                        speaker: None,
                     }],
                     language: Some("en".to_string()),
                     processing_time: Duration::from_millis(50),
+                    partial: false,
                 },
             ],
             call_count: std::cell::RefCell::new(0),
+            max_segment_len: None,
+            max_tokens: None,
+            diarize: false,
         }
     }
 
@@ -335,18 +863,79 @@ impl MockTranscriptionEngine {
         Self {
             responses,
             call_count: std::cell::RefCell::new(0),
+            max_segment_len: None,
+            max_tokens: None,
+            diarize: false,
         }
     }
 
+    /// Wrap canned segments at word boundaries, as Whisper does with `max_len`
+    pub fn set_max_segment_len(&mut self, max_len: Option<u32>) {
+        self.max_segment_len = max_len;
+    }
+
+    /// Stop each canned segment after `max_tokens` words, as Whisper does
+    /// after that many tokens
+    pub fn set_max_tokens(&mut self, max_tokens: Option<u32>) {
+        self.max_tokens = max_tokens;
+    }
+
+    /// Tag canned segments with a running speaker index, as a tinydiarize
+    /// model would if it heard a turn after every segment
+    pub fn set_diarize(&mut self, diarize: bool) {
+        self.diarize = diarize;
+    }
+
     pub async fn transcribe(&self, _audio_data: &[f32]) -> Result<TranscriptionResult> {
-        let mut count = self.call_count.borrow_mut();
-        let response_index = *count % self.responses.len();
-        *count += 1;
+        let response_index = {
+            let mut count = self.call_count.borrow_mut();
+            let index = *count % self.responses.len();
+            *count += 1;
+            index
+        };
 
         // Simulate some processing time
         tokio::time::sleep(Duration::from_millis(10)).await;
 
-        Ok(self.responses[response_index].clone())
+        let mut result = self.responses[response_index].clone();
+        if let Some(max_tokens) = self.max_tokens {
+            for segment in &mut result.segments {
+                let words: Vec<&str> = segment.text.split_whitespace().take(max_tokens as usize).collect();
+                let space = if segment.text.starts_with(' ') { " " } else { "" };
+                segment.text = format!("{}{}", space, words.join(" "));
+            }
+            result.text = result.segments.iter().map(|s| s.text.as_str()).collect();
+        }
+        if self.diarize {
+            for (speaker, segment) in (0..).zip(&mut result.segments) {
+                segment.speaker = Some(speaker);
+            }
+        }
+        if let Some(max_len) = self.max_segment_len {
+            result.segments = result
+                .segments
+                .iter()
+                .flat_map(|segment| wrap_segment(segment, max_len as usize))
+                .collect();
+        }
+        Ok(result)
+    }
+
+    pub async fn transcribe_regions(
+        &self,
+        audio_data: &[f32],
+        regions: &[Range<usize>],
+    ) -> Result<TranscriptionResult> {
+        let mut parts = Vec::with_capacity(regions.len());
+        for region in regions {
+            let result = self.transcribe(&audio_data[region.clone()]).await?;
+            let partial = result.partial;
+            parts.push((sample_offset(region.start), result));
+            if partial {
+                break;
+            }
+        }
+        Ok(TranscriptionResult::concat(parts))
     }
 
     pub fn call_count(&self) -> usize {
@@ -354,10 +943,72 @@ impl MockTranscriptionEngine {
     }
 }
 
+/// Split `segment` before any word that would take it past `max_len`
+/// characters, sharing its time span out by character count
+#[cfg(any(test, feature = "mock-engine"))]
+fn wrap_segment(segment: &TranscriptionSegment, max_len: usize) -> Vec<TranscriptionSegment> {
+    // Whisper's tokens carry their leading space, so words do here too
+    let mut lines: Vec<String> = Vec::new();
+    for (index, word) in segment.text.split_whitespace().enumerate() {
+        let word = if index == 0 && !segment.text.starts_with(' ') {
+            word.to_string()
+        } else {
+            format!(" {}", word)
+        };
+        match lines.last_mut() {
+            Some(line) if line.len() + word.len() <= max_len => line.push_str(&word),
+            _ => lines.push(word),
+        }
+    }
+    if lines.len() <= 1 {
+        return vec![segment.clone()];
+    }
+
+    let total: usize = lines.iter().map(String::len).sum();
+    let span = segment.end.saturating_sub(segment.start);
+    let mut offset = 0;
+    lines
+        .into_iter()
+        .map(|text| {
+            let start = segment.start + span.mul_f64(offset as f64 / total as f64);
+            offset += text.len();
+            let end = segment.start + span.mul_f64(offset as f64 / total as f64);
+            TranscriptionSegment {
+                start,
+                end,
+                text,
+                ..segment.clone()
+            }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod mock_tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_max_segment_len_wraps_segments() {
+        let mut mock = MockTranscriptionEngine::new();
+        mock.set_max_segment_len(Some(10));
+
+        let result = mock.transcribe(&[0.0; 16]).await.unwrap();
+        let texts: Vec<&str> = result.segments.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, vec!["This is a", " test", " transcription."]);
+        // Only a single word longer than the limit may exceed it
+        for segment in &result.segments {
+            let words = segment.text.split_whitespace().count();
+            assert!(segment.text.len() <= 10 || words == 1, "{:?}", segment.text);
+        }
+
+        // The cues tile the original segment's time span
+        assert_eq!(result.segments[0].start, Duration::ZERO);
+        assert_eq!(result.segments[2].end, Duration::from_secs(2));
+        for pair in result.segments.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+    }
+
     #[tokio::test]
     async fn test_mock_transcription_engine() {
         let mock = MockTranscriptionEngine::new();
@@ -380,9 +1031,13 @@ mod mock_tests {
                     start: Duration::from_millis(0),
                     end: Duration::from_millis(1000),
                     text: "First response".to_string(),
+                    avg_logprob: None,
+                    no_speech_prob: None,
+                    speaker: None,
                 }],
                 language: Some("en".to_string()),
                 processing_time: Duration::from_millis(25),
+                partial: false,
             },
             TranscriptionResult {
                 text: "Second response".to_string(),
@@ -390,9 +1045,13 @@ mod mock_tests {
                     start: Duration::from_millis(0),
                     end: Duration::from_millis(1500),
                     text: "Second response".to_string(),
+                    avg_logprob: None,
+                    no_speech_prob: None,
+                    speaker: None,
                 }],
                 language: Some("en".to_string()),
                 processing_time: Duration::from_millis(30),
+                partial: false,
             },
         ];
 
@@ -414,6 +1073,45 @@ mod mock_tests {
         assert_eq!(mock.call_count(), 3);
     }
 
+    #[tokio::test]
+    async fn test_transcribe_regions_offsets_timestamps() {
+        let mock = MockTranscriptionEngine::new();
+        let audio_data = vec![0.0; 64000];
+
+        let result = mock
+            .transcribe_regions(&audio_data, &[0..16000, 40000..64000])
+            .await
+            .unwrap();
+
+        assert_eq!(mock.call_count(), 2);
+        assert_eq!(
+            result.text,
+            "This is a test transcription. This is a test transcription."
+        );
+        assert_eq!(result.segments.len(), 2);
+        assert_eq!(result.segments[0].start, Duration::ZERO);
+        assert_eq!(result.segments[1].start, Duration::from_millis(2500));
+        assert_eq!(result.segments[1].end, Duration::from_millis(4500));
+        assert_eq!(result.processing_time, Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_regions_stops_after_partial() {
+        let mut partial = MockTranscriptionEngine::new().responses[0].clone();
+        partial.partial = true;
+        let mock = MockTranscriptionEngine::with_responses(vec![partial]);
+        let audio_data = vec![0.0; 64000];
+
+        let result = mock
+            .transcribe_regions(&audio_data, &[0..16000, 40000..64000])
+            .await
+            .unwrap();
+
+        assert_eq!(mock.call_count(), 1);
+        assert!(result.partial);
+        assert_eq!(result.segments.len(), 1);
+    }
+
     #[tokio::test]
     async fn test_mock_response_cycling() {
         let responses = vec![
@@ -422,12 +1120,14 @@ mod mock_tests {
                 segments: vec![],
                 language: Some("en".to_string()),
                 processing_time: Duration::from_millis(10),
+                partial: false,
             },
             TranscriptionResult {
                 text: "Response B".to_string(),
                 segments: vec![],
                 language: Some("en".to_string()),
                 processing_time: Duration::from_millis(10),
+                partial: false,
             },
         ];
 
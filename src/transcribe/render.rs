@@ -0,0 +1,195 @@
+//! Subtitle and JSON rendering of transcription results.
+
+use std::time::Duration;
+
+use super::TranscriptionResult;
+
+impl TranscriptionResult {
+    /// Render segments as SubRip (`.srt`) cues, each starting with its
+    /// speaker when diarized
+    pub fn to_srt(&self) -> String {
+        let mut srt = String::new();
+        for (index, segment) in self.segments.iter().enumerate() {
+            let speaker = segment
+                .speaker
+                .map(|speaker| format!("[Speaker {}] ", speaker))
+                .unwrap_or_default();
+            srt.push_str(&format!(
+                "{}\n{} --> {}\n{}{}\n\n",
+                index + 1,
+                cue_timestamp(segment.start, ','),
+                cue_timestamp(segment.end, ','),
+                speaker,
+                segment.text.trim()
+            ));
+        }
+        srt
+    }
+
+    /// Render segments as a WebVTT (`.vtt`) document
+    pub fn to_vtt(&self) -> String {
+        let mut vtt = String::from("WEBVTT\n\n");
+        for segment in &self.segments {
+            vtt.push_str(&format!(
+                "{} --> {}\n{}\n\n",
+                cue_timestamp(segment.start, '.'),
+                cue_timestamp(segment.end, '.'),
+                segment.text.trim()
+            ));
+        }
+        vtt
+    }
+
+    /// Render segments as a Markdown list with the start time of each in bold,
+    /// for notes apps that render Markdown
+    pub fn to_markdown(&self) -> String {
+        if self.segments.is_empty() {
+            return self.text.clone();
+        }
+        let mut markdown = String::new();
+        for segment in &self.segments {
+            markdown.push_str(&format!(
+                "- **{}** {}\n",
+                clock_timestamp(segment.start),
+                segment.text.trim()
+            ));
+        }
+        markdown
+    }
+
+    /// Render the whole result, segments included, as pretty-printed JSON.
+    /// Times are in seconds.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("transcription results always serialize")
+    }
+}
+
+/// `HH:MM:SS<sep>mmm`, as used by SRT (`,`) and WebVTT (`.`)
+fn cue_timestamp(time: Duration, separator: char) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        separator,
+        millis % 1000
+    )
+}
+
+/// `M:SS`, or `H:MM:SS` from an hour on
+fn clock_timestamp(time: Duration) -> String {
+    let secs = time.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcribe::TranscriptionSegment;
+
+    fn segment(start_ms: u64, end_ms: u64, text: &str) -> TranscriptionSegment {
+        TranscriptionSegment {
+            start: Duration::from_millis(start_ms),
+            end: Duration::from_millis(end_ms),
+            text: text.to_string(),
+            avg_logprob: None,
+            no_speech_prob: None,
+            speaker: None,
+        }
+    }
+
+    fn multi_segment_result() -> TranscriptionResult {
+        TranscriptionResult {
+            text: "Hello there. General Kenobi.".to_string(),
+            segments: vec![
+                segment(0, 1500, " Hello there."),
+                segment(1500, 3_725_042, " General Kenobi."),
+            ],
+            language: Some("en".to_string()),
+            processing_time: Duration::from_millis(250),
+            partial: false,
+        }
+    }
+
+    #[test]
+    fn test_cue_timestamp() {
+        assert_eq!(cue_timestamp(Duration::ZERO, ','), "00:00:00,000");
+        assert_eq!(
+            cue_timestamp(Duration::from_millis(3_725_042), '.'),
+            "01:02:05.042"
+        );
+    }
+
+    #[test]
+    fn test_to_srt() {
+        assert_eq!(
+            multi_segment_result().to_srt(),
+            "1\n00:00:00,000 --> 00:00:01,500\nHello there.\n\n\
+             2\n00:00:01,500 --> 01:02:05,042\nGeneral Kenobi.\n\n"
+        );
+    }
+
+    #[test]
+    fn test_to_srt_with_speakers() {
+        let mut result = multi_segment_result();
+        result.segments[0].speaker = Some(0);
+        result.segments[1].speaker = Some(1);
+        assert_eq!(
+            result.to_srt(),
+            "1\n00:00:00,000 --> 00:00:01,500\n[Speaker 0] Hello there.\n\n\
+             2\n00:00:01,500 --> 01:02:05,042\n[Speaker 1] General Kenobi.\n\n"
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&result.to_json()).unwrap();
+        assert_eq!(json["segments"][1]["speaker"], 1);
+    }
+
+    #[test]
+    fn test_to_vtt() {
+        assert_eq!(
+            multi_segment_result().to_vtt(),
+            "WEBVTT\n\n\
+             00:00:00.000 --> 00:00:01.500\nHello there.\n\n\
+             00:00:01.500 --> 01:02:05.042\nGeneral Kenobi.\n\n"
+        );
+    }
+
+    #[test]
+    fn test_clock_timestamp() {
+        assert_eq!(clock_timestamp(Duration::from_millis(59_999)), "0:59");
+        assert_eq!(clock_timestamp(Duration::from_secs(754)), "12:34");
+        assert_eq!(clock_timestamp(Duration::from_millis(3_725_042)), "1:02:05");
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        assert_eq!(
+            multi_segment_result().to_markdown(),
+            "- **0:00** Hello there.\n- **0:01** General Kenobi.\n"
+        );
+
+        let mut result = multi_segment_result();
+        result.segments.clear();
+        assert_eq!(result.to_markdown(), "Hello there. General Kenobi.");
+    }
+
+    #[test]
+    fn test_to_json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&multi_segment_result().to_json()).unwrap();
+
+        assert_eq!(json["text"], "Hello there. General Kenobi.");
+        assert_eq!(json["language"], "en");
+        assert_eq!(json["processing_time"], 0.25);
+        assert_eq!(json["partial"], false);
+        assert_eq!(json["segments"].as_array().unwrap().len(), 2);
+        assert_eq!(json["segments"][1]["start"], 1.5);
+        assert_eq!(json["segments"][1]["end"], 3725.042);
+        assert_eq!(json["segments"][1]["text"], " General Kenobi.");
+    }
+}
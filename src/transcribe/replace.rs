@@ -0,0 +1,249 @@
+//! Rewriting words and phrases of a transcript into a canonical form, such as
+//! "github" into "GitHub".
+
+use std::collections::BTreeMap;
+
+use super::TranscriptionResult;
+
+impl TranscriptionResult {
+    /// Replace each whole-word match of a key of `replacements` with its value,
+    /// in the transcript and in each segment.
+    ///
+    /// Keys match regardless of case, and the words of a multi-word key match
+    /// across any run of whitespace. Where keys overlap the longest match wins,
+    /// so "github actions" takes precedence over "github". Replaced text isn't
+    /// matched again, and a phrase split between two segments is only
+    /// replaced in the transcript.
+    pub fn apply_replacements(&mut self, replacements: &BTreeMap<String, String>) {
+        if replacements.is_empty() {
+            return;
+        }
+        let patterns = patterns(replacements);
+
+        self.text = replace_words(&self.text, &patterns);
+        for segment in &mut self.segments {
+            segment.text = replace_words(&segment.text, &patterns);
+        }
+    }
+}
+
+/// Check that every key of `replacements` has a word to match and that no
+/// two keys match the same words
+pub fn validate_replacements(replacements: &BTreeMap<String, String>) -> Result<(), String> {
+    let mut seen: BTreeMap<Vec<String>, &str> = BTreeMap::new();
+    for key in replacements.keys() {
+        let words = lowercase_words(key);
+        if words.is_empty() {
+            return Err("replacements can't have an empty key".to_string());
+        }
+        if let Some(other) = seen.insert(words, key) {
+            return Err(format!(
+                "replacements '{}' and '{}' match the same words",
+                other, key
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// A key's lowercased words and the text that replaces them
+struct Pattern<'a> {
+    words: Vec<String>,
+    replacement: &'a str,
+}
+
+fn patterns(replacements: &BTreeMap<String, String>) -> Vec<Pattern<'_>> {
+    replacements
+        .iter()
+        .map(|(key, replacement)| Pattern {
+            words: lowercase_words(key),
+            replacement,
+        })
+        .filter(|pattern| !pattern.words.is_empty())
+        .collect()
+}
+
+fn lowercase_words(text: &str) -> Vec<String> {
+    text.split_whitespace().map(str::to_lowercase).collect()
+}
+
+fn replace_words(text: &str, patterns: &[Pattern]) -> String {
+    let mut replaced = String::with_capacity(text.len());
+    let mut position = 0;
+    while let Some(c) = text[position..].chars().next() {
+        let at_boundary = !text[..position]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric);
+        let longest = at_boundary
+            .then(|| {
+                patterns
+                    .iter()
+                    .filter_map(|pattern| {
+                        Some((match_end(text, position, &pattern.words)?, pattern))
+                    })
+                    .max_by_key(|&(end, _)| end)
+            })
+            .flatten();
+
+        match longest {
+            Some((end, pattern)) => {
+                replaced.push_str(pattern.replacement);
+                position = end;
+            }
+            None => {
+                replaced.push(c);
+                position += c.len_utf8();
+            }
+        }
+    }
+    replaced
+}
+
+/// Where a match of `words` starting at `start` ends, if there is one that
+/// ends at a word boundary
+fn match_end(text: &str, start: usize, words: &[String]) -> Option<usize> {
+    let mut position = start;
+    for (index, word) in words.iter().enumerate() {
+        if index > 0 {
+            let rest = &text[position..];
+            let trimmed = rest.trim_start();
+            if trimmed.len() == rest.len() {
+                return None;
+            }
+            position += rest.len() - trimmed.len();
+        }
+        position += match_word(&text[position..], word)?;
+    }
+
+    let at_boundary = !text[position..]
+        .chars()
+        .next()
+        .is_some_and(char::is_alphanumeric);
+    at_boundary.then_some(position)
+}
+
+/// Length of the start of `text` that matches the lowercase `word`,
+/// ignoring case
+fn match_word(text: &str, word: &str) -> Option<usize> {
+    let mut length = 0;
+    let mut expected = word.chars().peekable();
+    for c in text.chars() {
+        if expected.peek().is_none() {
+            break;
+        }
+        for lower in c.to_lowercase() {
+            if expected.next() != Some(lower) {
+                return None;
+            }
+        }
+        length += c.len_utf8();
+    }
+    expected.peek().is_none().then_some(length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcribe::TranscriptionSegment;
+    use std::time::Duration;
+
+    fn replacements(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|&(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn replace(text: &str, pairs: &[(&str, &str)]) -> String {
+        replace_words(text, &patterns(&replacements(pairs)))
+    }
+
+    #[test]
+    fn test_replaces_whole_words_ignoring_case() {
+        let pairs = [("github", "GitHub"), ("api", "API")];
+        assert_eq!(
+            replace("Push to github, then call the Api.", &pairs),
+            "Push to GitHub, then call the API."
+        );
+        assert_eq!(replace("GITHUB's api's", &pairs), "GitHub's API's");
+        // Only whole words
+        assert_eq!(replace("rapid apis github2", &pairs), "rapid apis github2");
+        assert_eq!(replace("", &pairs), "");
+    }
+
+    #[test]
+    fn test_replaces_multi_word_keys() {
+        let pairs = [("visual studio code", "VS Code"), ("pull request", "PR")];
+        assert_eq!(
+            replace(
+                "Open Visual  Studio\ncode and review the pull request.",
+                &pairs
+            ),
+            "Open VS Code and review the PR."
+        );
+        // Every word has to match
+        assert_eq!(
+            replace("visual studio and a pull", &pairs),
+            "visual studio and a pull"
+        );
+        assert_eq!(replace("pullrequest", &pairs), "pullrequest");
+    }
+
+    #[test]
+    fn test_longest_overlapping_key_wins() {
+        let pairs = [
+            ("github", "GitHub"),
+            ("github actions", "GitHub Actions"),
+            ("actions", "Actions"),
+            ("api", "API"),
+            ("api key", "API key"),
+        ];
+        assert_eq!(
+            replace("github actions and github use an api key", &pairs),
+            "GitHub Actions and GitHub use an API key"
+        );
+        // Replaced text isn't matched again
+        assert_eq!(
+            replace("ok then", &[("ok", "okay then"), ("then", "next")]),
+            "okay then next"
+        );
+    }
+
+    #[test]
+    fn test_validate_replacements() {
+        assert!(validate_replacements(&replacements(&[("github", "GitHub")])).is_ok());
+        assert!(validate_replacements(&replacements(&[(" ", "x")])).is_err());
+        let err = validate_replacements(&replacements(&[
+            ("Pull  Request", "PR"),
+            ("pull request", "PR"),
+        ]))
+        .unwrap_err();
+        assert!(err.contains("match the same words"), "{}", err);
+    }
+
+    #[test]
+    fn test_apply_replacements() {
+        let segment = |text: &str| TranscriptionSegment {
+            start: Duration::ZERO,
+            end: Duration::ZERO,
+            text: text.to_string(),
+            avg_logprob: None,
+            no_speech_prob: None,
+            speaker: None,
+        };
+        let mut result = TranscriptionResult {
+            text: " Open github. The api".to_string(),
+            segments: vec![segment(" Open github."), segment(" The api")],
+            language: Some("en".to_string()),
+            processing_time: Duration::ZERO,
+            partial: false,
+        };
+
+        result.apply_replacements(&replacements(&[("github", "GitHub"), ("api", "API")]));
+
+        assert_eq!(result.text, " Open GitHub. The API");
+        assert_eq!(result.segments[0].text, " Open GitHub.");
+        assert_eq!(result.segments[1].text, " The API");
+    }
+}
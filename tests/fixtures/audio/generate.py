@@ -0,0 +1,147 @@
+#!/usr/bin/env python3
+"""Regenerate the small audio fixtures used by the file-input tests.
+
+No encoder is needed: FLAC frames use verbatim subframes, the MP3 is a run of
+silent MPEG-1 Layer III frames, and the Opus file holds one silent 20 ms CELT
+packet behind a 312-sample pre-skip. The WAV
+files hold a few known samples in each subformat the WAV decoder must handle or
+reject.
+"""
+
+import math
+import struct
+from pathlib import Path
+
+HERE = Path(__file__).parent
+
+SAMPLE_RATE = 16000
+FRAMES = 1600
+TONE = [round(0.5 * 32767 * math.sin(2 * math.pi * 440 * n / SAMPLE_RATE)) for n in range(FRAMES)]
+
+
+class BitWriter:
+    def __init__(self):
+        self.bits = []
+
+    def write(self, value, width):
+        self.bits.extend((value >> (width - 1 - i)) & 1 for i in range(width))
+
+    def bytes(self):
+        assert len(self.bits) % 8 == 0
+        return bytes(
+            int("".join(map(str, self.bits[i : i + 8])), 2) for i in range(0, len(self.bits), 8)
+        )
+
+
+def crc8(data):
+    crc = 0
+    for byte in data:
+        crc ^= byte
+        for _ in range(8):
+            crc = ((crc << 1) ^ 0x07) & 0xFF if crc & 0x80 else (crc << 1) & 0xFF
+    return crc
+
+
+def crc16(data):
+    crc = 0
+    for byte in data:
+        crc ^= byte << 8
+        for _ in range(8):
+            crc = ((crc << 1) ^ 0x8005) & 0xFFFF if crc & 0x8000 else (crc << 1) & 0xFFFF
+    return crc
+
+
+def ogg_crc(data):
+    crc = 0
+    for byte in data:
+        crc ^= byte << 24
+        for _ in range(8):
+            crc = ((crc << 1) ^ 0x04C11DB7) & 0xFFFFFFFF if crc & 0x80000000 else (crc << 1) & 0xFFFFFFFF
+    return crc
+
+
+def flac_streaminfo():
+    w = BitWriter()
+    w.write(FRAMES, 16)  # min block size
+    w.write(FRAMES, 16)  # max block size
+    w.write(0, 24)  # min frame size (unknown)
+    w.write(0, 24)  # max frame size (unknown)
+    w.write(SAMPLE_RATE, 20)
+    w.write(0, 3)  # channels - 1
+    w.write(15, 5)  # bits per sample - 1
+    w.write(FRAMES, 36)
+    return w.bytes() + bytes(16)  # MD5 unset
+
+
+def flac_frame(samples):
+    header = bytes([0xFF, 0xF8, 0x75, 0x08, 0x00]) + struct.pack(">H", len(samples) - 1)
+    header += bytes([crc8(header)])
+    # Verbatim subframe, no wasted bits
+    frame = header + bytes([0x02]) + b"".join(struct.pack(">h", s) for s in samples)
+    return frame + struct.pack(">H", crc16(frame))
+
+
+def ogg_page(packet, granule, serial, sequence, flags):
+    lacing = [255] * (len(packet) // 255) + [len(packet) % 255]
+    header = b"OggS" + struct.pack(
+        "<BBqIIIB", 0, flags, granule, serial, sequence, 0, len(lacing)
+    ) + bytes(lacing)
+    page = bytearray(header + packet)
+    struct.pack_into("<I", page, 22, ogg_crc(page))
+    return bytes(page)
+
+
+def write_flac():
+    metadata = bytes([0x80, 0, 0, 34]) + flac_streaminfo()
+    (HERE / "tone.flac").write_bytes(b"fLaC" + metadata + flac_frame(TONE))
+
+
+def write_ogg_flac():
+    serial = 0x4D44
+    streaminfo = bytes([0x80, 0, 0, 34]) + flac_streaminfo()
+    first = b"\x7fFLAC" + bytes([1, 0]) + struct.pack(">H", 0) + b"fLaC" + streaminfo
+    pages = ogg_page(first, 0, serial, 0, 0x02)
+    pages += ogg_page(flac_frame(TONE), FRAMES, serial, 1, 0x04)
+    (HERE / "tone.ogg").write_bytes(pages)
+
+
+def write_mp3():
+    # MPEG-1 Layer III, 32 kbps, 32 kHz, mono: 144-byte frames of silence
+    frame = bytes([0xFF, 0xFB, 0x18, 0xC0]) + bytes(140)
+    (HERE / "silence.mp3").write_bytes(frame * 4)
+
+
+def write_opus():
+    serial = 0x4F50
+    head = b"OpusHead" + struct.pack("<BBHIhB", 1, 1, 312, 48000, 0, 0)
+    tags = b"OpusTags" + struct.pack("<I", 0) + struct.pack("<I", 0)
+    pages = ogg_page(head, 0, serial, 0, 0x02)
+    pages += ogg_page(tags, 0, serial, 1, 0x00)
+    # A single 20 ms CELT silence packet so the stream has audio to find
+    pages += ogg_page(bytes([0xF8, 0xFF, 0xFE]), 312 + 960, serial, 2, 0x04)
+    (HERE / "silence.opus").write_bytes(pages)
+
+
+def wav(format_tag, bits, data, fmt_extra=b"", block_align=None):
+    block_align = block_align or bits // 8
+    fmt = struct.pack("<HHIIHH", format_tag, 1, SAMPLE_RATE, SAMPLE_RATE * block_align, block_align, bits)
+    fmt += fmt_extra
+    body = b"WAVE" + b"fmt " + struct.pack("<I", len(fmt)) + fmt + b"data" + struct.pack("<I", len(data)) + data
+    return b"RIFF" + struct.pack("<I", len(body)) + body
+
+
+def write_wavs():
+    pcm24 = b"".join(s.to_bytes(3, "little", signed=True) for s in [0, 0x400000, -0x400000, 0x7FFFFF, -0x800000])
+    (HERE / "pcm24.wav").write_bytes(wav(1, 24, pcm24))
+    (HERE / "float32.wav").write_bytes(wav(3, 32, struct.pack("<4f", 0.0, 0.5, -0.25, 1.0)))
+    (HERE / "float64.wav").write_bytes(wav(3, 64, struct.pack("<2d", 0.0, 0.5)))
+    # Microsoft ADPCM: 4-bit samples with a codec-specific cbSize extension
+    (HERE / "adpcm.wav").write_bytes(wav(2, 4, bytes(256), struct.pack("<HH", 2, 500), 256))
+
+
+if __name__ == "__main__":
+    write_flac()
+    write_ogg_flac()
+    write_mp3()
+    write_opus()
+    write_wavs()
@@ -1,6 +1,8 @@
 use assert_cmd::Command;
 use predicates::prelude::*;
 use std::fs;
+#[cfg(feature = "mock-engine")]
+use std::path::Path;
 use tempfile::TempDir;
 
 #[test]
@@ -30,6 +32,20 @@ fn test_model_list_command() {
         .stdout(predicate::str::contains("Available models for download:"));
 }
 
+#[test]
+fn test_model_list_json_command() {
+    let temp_dir = TempDir::new().unwrap();
+
+    let mut cmd = Command::cargo_bin("microdrop").unwrap();
+    cmd.args(["model", "list", "--json"]);
+    cmd.env("MICRODROP_DATA_DIR", temp_dir.path());
+    let output = cmd.assert().success().get_output().stdout.clone();
+
+    let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(json["cached"], serde_json::json!([]));
+    assert!(!json["available"].as_array().unwrap().is_empty());
+}
+
 #[test]
 fn test_config_write_default_command() {
     let temp_dir = TempDir::new().unwrap();
@@ -61,13 +77,25 @@ fn test_config_write_default_without_force_fails_when_exists() {
 #[test]
 fn test_toggle_command_basic_functionality() {
     let mut cmd = Command::cargo_bin("microdrop").unwrap();
-    cmd.args(["toggle"]);
-    cmd.write_stdin(""); // Simulate immediate enter to stop capture
+    cmd.args(["toggle", "--duration", "1"]);
+    cmd.write_stdin("");
     cmd.assert()
-        .success() // This should succeed and capture/stop immediately
+        .success() // This should succeed and capture/stop after the duration
         .stdout(predicate::str::contains("Audio capture started"));
 }
 
+#[test]
+fn test_toggle_without_terminal_needs_duration() {
+    // Piped stdin has no Enter key to stop the recording
+    let mut cmd = Command::cargo_bin("microdrop").unwrap();
+    cmd.args(["toggle"]);
+    cmd.write_stdin("");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("Pass --duration"))
+        .stdout(predicate::str::contains("Audio capture started").not());
+}
+
 #[test]
 fn test_invalid_subcommand() {
     let mut cmd = Command::cargo_bin("microdrop").unwrap();
@@ -83,5 +111,113 @@ fn test_model_install_invalid_model() {
     cmd.args(["model", "install", "nonexistent-model"]);
     cmd.assert()
         .failure()
-        .stdout(predicate::str::contains("Model loading error"));
-}
\ No newline at end of file
+        .stdout(predicate::str::contains("Model registry error"));
+}
+
+/// Write one second of a 440 Hz tone to `path`, the same on every channel
+#[cfg(feature = "mock-engine")]
+fn write_tone_wav(path: &Path, sample_rate: u32, channels: u16) {
+    let spec = hound::WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec).unwrap();
+    for i in 0..sample_rate {
+        let t = i as f32 / sample_rate as f32;
+        let sample = ((t * 440.0 * 2.0 * std::f32::consts::PI).sin() * i16::MAX as f32) as i16;
+        for _ in 0..channels {
+            writer.write_sample(sample).unwrap();
+        }
+    }
+    writer.finalize().unwrap();
+}
+
+#[cfg(feature = "mock-engine")]
+#[test]
+fn test_toggle_mock_engine_end_to_end() {
+    let temp_dir = TempDir::new().unwrap();
+    let input = temp_dir.path().join("speech.wav");
+    let transcript = temp_dir.path().join("transcript.txt");
+    write_tone_wav(&input, 16000, 1);
+
+    let mut cmd = Command::cargo_bin("microdrop").unwrap();
+    cmd.args(["toggle", "--engine", "mock", "--no-clipboard", "--no-paste"]);
+    cmd.arg("--input").arg(&input);
+    cmd.arg("--append").arg(&transcript);
+    cmd.arg("--print-append-location");
+    cmd.env("HOME", temp_dir.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("This is a test transcription."))
+        .stderr(predicate::str::contains(format!("{}:1", transcript.display())));
+
+    let appended = fs::read_to_string(&transcript).unwrap();
+    assert!(appended.contains("This is a test transcription."));
+}
+
+#[cfg(feature = "mock-engine")]
+#[test]
+fn test_toggle_keep_partial_saves_undelivered_transcript() {
+    let temp_dir = TempDir::new().unwrap();
+    let input = temp_dir.path().join("speech.wav");
+    let data_dir = temp_dir.path().join("data");
+    write_tone_wav(&input, 16000, 1);
+
+    // Appending to a directory fails after transcription
+    let mut cmd = Command::cargo_bin("microdrop").unwrap();
+    cmd.args([
+        "toggle",
+        "--engine",
+        "mock",
+        "--no-clipboard",
+        "--no-paste",
+        "--keep-partial",
+    ]);
+    cmd.arg("--input").arg(&input);
+    cmd.arg("--append").arg(temp_dir.path());
+    cmd.arg("--data-dir").arg(&data_dir);
+    cmd.env("HOME", temp_dir.path());
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("Delivering the transcript failed (append: "));
+
+    let recovered: Vec<_> = fs::read_dir(data_dir.join("recovery")).unwrap().collect();
+    assert_eq!(recovered.len(), 1);
+    let text = fs::read_to_string(recovered[0].as_ref().unwrap().path()).unwrap();
+    assert!(text.contains("This is a test transcription."));
+}
+
+#[cfg(feature = "mock-engine")]
+#[test]
+fn test_toggle_save_audio_processed_or_raw() {
+    let temp_dir = TempDir::new().unwrap();
+    let input = temp_dir.path().join("stereo.wav");
+    write_tone_wav(&input, 44100, 2);
+
+    let save = |raw: bool| {
+        let saved = temp_dir.path().join(if raw { "raw.wav" } else { "processed.wav" });
+        let mut cmd = Command::cargo_bin("microdrop").unwrap();
+        cmd.args(["toggle", "--engine", "mock", "--no-clipboard", "--no-paste"]);
+        cmd.arg("--input").arg(&input);
+        cmd.arg("--save-audio").arg(&saved);
+        if raw {
+            cmd.arg("--save-audio-raw");
+        }
+        cmd.arg("--data-dir").arg(temp_dir.path().join("data"));
+        cmd.env("HOME", temp_dir.path());
+        cmd.assert().success();
+        hound::WavReader::open(&saved).unwrap()
+    };
+
+    let processed = save(false);
+    assert_eq!(processed.spec().sample_rate, 16000);
+    assert_eq!(processed.spec().channels, 1);
+    assert_eq!(processed.duration(), 16000);
+
+    let raw = save(true);
+    assert_eq!(raw.spec().sample_rate, 44100);
+    assert_eq!(raw.spec().channels, 2);
+    assert_eq!(raw.duration(), 44100);
+}